@@ -1,18 +1,25 @@
 mod boardstate;
+pub mod chess960;
 pub mod defs;
-mod fen;
+pub mod epd;
+pub mod fen;
 mod material;
+pub mod notation;
+mod playmove;
+pub mod zobrist;
 
 use std::fmt::Display;
 
 use defs::{
-    Coordinate, Piece, Pieces, Side, Square, BB_SQUARES, PIECE_CHAR_CAPS, PIECE_CHAR_SMALL,
-    PIECE_VALUES, SQUARE_NAME,
+    Coordinate, Piece, Pieces, Side, Square, BB_SQUARES, MAX_GAME_MOVES, PIECE_CHAR_CAPS,
+    PIECE_CHAR_SMALL, PIECE_VALUES, SQUARE_NAME,
 };
 
 use crate::{
     board::boardstate::BoardState,
     board::defs::{BitBoard, NrOf, Sides, EMPTY},
+    board::zobrist::ZOBRIST,
+    utils::bits,
 };
 
 #[derive(Clone)]
@@ -21,10 +28,12 @@ use crate::{
 /// * `bb_pieces`: Bitboard lists of each piece type, for each side.
 /// * `bb_side`: Bitboard of piece positions for each side.
 /// * `state`: The current board state.
+/// * `history`: The states before each move that was played, used to take moves back.
 pub struct Board {
     pub bb_pieces: [[BitBoard; NrOf::PIECE_TYPES]; Sides::BOTH],
     pub bb_side: [BitBoard; Sides::BOTH],
     pub state: BoardState,
+    pub history: Vec<BoardState>,
 }
 
 impl Board {
@@ -34,6 +43,7 @@ impl Board {
             bb_pieces: [[EMPTY; NrOf::PIECE_TYPES]; Sides::BOTH],
             bb_side: [EMPTY; Sides::BOTH],
             state: BoardState::new(),
+            history: Vec::with_capacity(MAX_GAME_MOVES as usize),
         }
     }
 
@@ -43,9 +53,11 @@ impl Board {
         self.bb_side[Sides::WHITE] = pieces_per_side_bitboards.0;
         self.bb_side[Sides::BLACK] = pieces_per_side_bitboards.1;
 
-        let material = material::count_material(&self);
+        let material = material::count_material(self);
         self.state.material[Sides::WHITE] = material.0;
         self.state.material[Sides::BLACK] = material.1;
+
+        self.state.zobrist_key = self.init_zobrist_key();
     }
 
     /// The side to play.
@@ -92,6 +104,15 @@ impl Board {
         Err(Pieces::NONE)
     }
 
+    /// The square the king of the given side is on.
+    ///
+    /// WARNING: The result is meaningless if the side has no king on the board.
+    ///
+    /// * `side`: The side to find the king for.
+    pub fn king_square(&self, side: Side) -> Square {
+        self.bb_pieces[side][Pieces::KING].trailing_zeros() as Square
+    }
+
     /// Retrieve the [`Coordinate`] for the given square.
     ///
     /// * `square`: The square to get a coordinate for.
@@ -117,7 +138,6 @@ impl Board {
                 PIECE_CHAR_CAPS[piece], SQUARE_NAME[from]
             );
         }
-        println!("{piece_exists}");
         self.remove_piece(side, piece, from);
         self.put_piece(side, piece, to);
     }
@@ -133,6 +153,7 @@ impl Board {
         self.bb_pieces[side][piece] |= BB_SQUARES[square];
         self.bb_side[side] |= BB_SQUARES[square];
         self.state.material[side] += PIECE_VALUES[piece];
+        self.state.zobrist_key ^= ZOBRIST.piece(side, piece, square);
     }
 
     /// Remove a piece from the board.
@@ -146,6 +167,57 @@ impl Board {
         self.bb_pieces[side][piece] ^= BB_SQUARES[square];
         self.bb_side[side] ^= BB_SQUARES[square];
         self.state.material[side] -= PIECE_VALUES[piece];
+        self.state.zobrist_key ^= ZOBRIST.piece(side, piece, square);
+    }
+
+    /// Determines if the current position occurred before, within the moves that can be undone
+    /// without resetting the fifty-move counter.
+    pub fn is_repetition(&self) -> bool {
+        let reversible = self.state.half_move_clock as usize;
+        self.history
+            .iter()
+            .rev()
+            .take(reversible)
+            .skip(1)
+            .step_by(2)
+            .any(|state| state.zobrist_key == self.state.zobrist_key)
+    }
+
+    /// Determines if neither side has enough material left to deliver checkmate: only the kings
+    /// are left, or the kings and a single bishop or knight.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minors = 0;
+        for side in [Sides::WHITE, Sides::BLACK] {
+            let pieces = &self.bb_pieces[side];
+            if pieces[Pieces::QUEEN] | pieces[Pieces::ROOK] | pieces[Pieces::PAWN] > 0 {
+                return false;
+            }
+            minors += (pieces[Pieces::BISHOP] | pieces[Pieces::KNIGHT]).count_ones();
+        }
+        minors <= 1
+    }
+
+    /// Calculates the Zobrist key of the position from scratch.
+    fn init_zobrist_key(&self) -> u64 {
+        let mut key = 0;
+
+        for (side, bb_pieces) in self.bb_pieces.iter().enumerate() {
+            for (piece, bb_piece) in bb_pieces.iter().enumerate() {
+                let mut bb = *bb_piece;
+                while bb > 0 {
+                    let square = bits::next(&mut bb);
+                    key ^= ZOBRIST.piece(side, piece, square);
+                }
+            }
+        }
+
+        key ^= ZOBRIST.castling(self.state.castling);
+        key ^= ZOBRIST.en_passant(self.state.en_passant);
+        if self.current_side() == Sides::BLACK {
+            key ^= ZOBRIST.side();
+        }
+
+        key
     }
 
     /// Generates two BitBoards ([`Sides::WHITE`], [`Sides::BLACK`]) that contain all of the piece
@@ -166,6 +238,12 @@ impl Board {
     }
 }
 
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "==========================")?;
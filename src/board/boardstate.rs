@@ -1,24 +1,34 @@
 use std::fmt::Display;
 
-use crate::board::defs::{Castling, Sides, SQUARE_NAME};
+use crate::{
+    board::defs::{Castling, Sides, SQUARE_NAME},
+    movegen::defs::Move,
+};
 
 #[derive(Clone, Copy)]
 /// Struct that represents the state of the game.
 ///
 /// * `active_side`: which [`Sides`] is next to move.
 /// * `castling`: the [`Castling`] permissions
+/// * `castling_rooks`: The file of the rook that each castling right castles with, indexed by
+///   [`Castling::index`]; the a- and h-files in standard chess, any file in Chess960.
 /// * `en_passant`: the square where an en-passant move can be played.
 /// * `half_move_clock`: Halfmove Clock for enforcing the fifty-move rule.
 /// * `full_move_number`: The total number of complete moves. (starts at 1, is incremented after
-///                       each move by [`Sides::BLACK`])
+///   each move by [`Sides::BLACK`])
 /// * `material`: The total material count for each side.
+/// * `zobrist_key`: The Zobrist hash key of the position.
+/// * `next_move`: The move that was played from this state; only set on states in the history.
 pub struct BoardState {
     pub active_side: u8,
     pub castling: u8,
+    pub castling_rooks: [u8; Castling::RIGHTS],
     pub en_passant: Option<u8>,
     pub half_move_clock: u8,
     pub full_move_number: u16,
     pub material: [u16; Sides::BOTH],
+    pub zobrist_key: u64,
+    pub next_move: Move,
 }
 
 impl BoardState {
@@ -26,10 +36,13 @@ impl BoardState {
         Self {
             active_side: 0,
             castling: 0,
+            castling_rooks: Castling::STANDARD_ROOKS,
             en_passant: None,
             half_move_clock: 0,
             full_move_number: 0,
             material: [0; Sides::BOTH],
+            zobrist_key: 0,
+            next_move: Move::new(0),
         }
     }
 
@@ -0,0 +1,101 @@
+/// The number of start positions of Chess960.
+pub const START_POSITIONS: usize = 960;
+
+/// The files of the two knights among the five squares left after placing the bishops and the
+/// queen, for each of the ten ways to place them.
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// The start position of Chess960 with the given number, as a FEN string. The positions are
+/// numbered 0 to 959 as proposed by Reinhard Scharnagl, so that position 518 is the start
+/// position of standard chess. Returns None for a number that is out of range.
+///
+/// * `index`: The number of the start position.
+pub fn start_position(index: usize) -> Option<String> {
+    if index >= START_POSITIONS {
+        return None;
+    }
+
+    let mut back_rank = [None; 8];
+    let mut n = index;
+
+    // The bishops go on squares of opposite colours: first the light one, then the dark one.
+    back_rank[2 * (n % 4) + 1] = Some('b');
+    n /= 4;
+    back_rank[2 * (n % 4)] = Some('b');
+    n /= 4;
+
+    place_on_empty(&mut back_rank, n % 6, 'q');
+    n /= 6;
+
+    // Place the second knight first, so the first one still counts the same empty squares.
+    let (first, second) = KNIGHT_PLACEMENTS[n];
+    place_on_empty(&mut back_rank, second, 'n');
+    place_on_empty(&mut back_rank, first, 'n');
+
+    // The king goes between the rooks on the three squares that are left.
+    for piece in ['r', 'k', 'r'] {
+        place_on_empty(&mut back_rank, 0, piece);
+    }
+
+    let black: String = back_rank.iter().flatten().collect();
+    let white = black.to_ascii_uppercase();
+
+    Some(format!(
+        "{black}/pppppppp/8/8/8/8/PPPPPPPP/{white} w KQkq - 0 1"
+    ))
+}
+
+/// Puts a piece on the n-th empty square of the back rank.
+///
+/// * `back_rank`: The pieces of the back rank, by file.
+/// * `n`: The number of empty squares to skip.
+/// * `piece`: The piece to put down.
+fn place_on_empty(back_rank: &mut [Option<char>; 8], n: usize, piece: char) {
+    if let Some(square) = back_rank
+        .iter_mut()
+        .filter(|square| square.is_none())
+        .nth(n)
+    {
+        *square = Some(piece);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{defs::Castling, Board};
+
+    use super::{start_position, START_POSITIONS};
+
+    #[test]
+    fn test_start_positions() {
+        assert_eq!(
+            start_position(518).unwrap(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        assert_eq!(
+            start_position(0).unwrap(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert_eq!(start_position(START_POSITIONS), None);
+
+        // Every position can be set up and keeps all castling rights.
+        for index in 0..START_POSITIONS {
+            let mut board = Board::new();
+            board
+                .fen_read(Some(&start_position(index).unwrap()))
+                .unwrap();
+            assert_eq!(board.state.castling, Castling::ALL, "position {index}");
+        }
+    }
+}
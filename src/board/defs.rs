@@ -1,4 +1,4 @@
-use std::{ops::RangeInclusive, u8, usize};
+use std::ops::RangeInclusive;
 
 pub type BitBoard = u64;
 pub type Piece = usize;
@@ -145,10 +145,10 @@ pub struct Files;
 impl Files {
     pub const A: usize = 0;
     pub const B: usize = 1;
-    pub const C: usize = 1;
-    pub const D: usize = 1;
-    pub const E: usize = 1;
-    pub const F: usize = 1;
+    pub const C: usize = 2;
+    pub const D: usize = 3;
+    pub const E: usize = 4;
+    pub const F: usize = 5;
     pub const G: usize = 6;
     pub const H: usize = 7;
 }
@@ -158,10 +158,10 @@ pub struct Ranks;
 impl Ranks {
     pub const R1: usize = 0;
     pub const R2: usize = 1;
-    pub const R3: usize = 1;
+    pub const R3: usize = 2;
     pub const R4: usize = 3;
     pub const R5: usize = 4;
-    pub const R6: usize = 4;
+    pub const R6: usize = 5;
     pub const R7: usize = 6;
     pub const R8: usize = 7;
 }
@@ -174,6 +174,44 @@ impl Castling {
     pub const BK: u8 = 4;
     pub const BQ: u8 = 8;
     pub const ALL: u8 = 15;
+
+    /// The number of castling rights.
+    pub const RIGHTS: usize = 4;
+
+    /// The files of the rooks each right castles with in standard chess.
+    pub const STANDARD_ROOKS: [u8; Castling::RIGHTS] = [
+        Files::H as u8,
+        Files::A as u8,
+        Files::H as u8,
+        Files::A as u8,
+    ];
+
+    /// The position of a single right (such as [`Castling::BK`]) in arrays indexed by right.
+    ///
+    /// * `right`: One of the castling rights.
+    pub const fn index(right: u8) -> usize {
+        right.trailing_zeros() as usize
+    }
+
+    /// The castling right of a side on one side of the board.
+    ///
+    /// * `side`: The side that castles.
+    /// * `kingside`: True for castling towards the h-file, false towards the a-file.
+    pub const fn right(side: Side, kingside: bool) -> u8 {
+        match (side == Sides::WHITE, kingside) {
+            (true, true) => Castling::WK,
+            (true, false) => Castling::WQ,
+            (false, true) => Castling::BK,
+            (false, false) => Castling::BQ,
+        }
+    }
+
+    /// Both castling rights of a side.
+    ///
+    /// * `side`: The side to get the rights of.
+    pub const fn rights(side: Side) -> u8 {
+        Castling::right(side, true) | Castling::right(side, false)
+    }
 }
 
 const fn init_bb_files() -> TBBFiles {
@@ -0,0 +1,322 @@
+use std::fmt::Display;
+
+use crate::{
+    board::{fen::FenError, notation::MoveParseError, Board},
+    movegen::{defs::Move, MoveGenerator},
+};
+
+/// The number of fields that describe the position in an EPD record.
+const EPD_POSITION_FIELDS: usize = 4;
+
+/// The errors that can occur when reading an EPD record.
+///
+/// * `MissingFields`: The record has fewer than the four position fields.
+/// * `Fen`: The position fields could not be read.
+/// * `Operation`: An operation is malformed, such as an operand with an unclosed quote.
+#[derive(Debug, PartialEq)]
+pub enum EpdError {
+    MissingFields,
+    Fen(FenError),
+    Operation(String),
+}
+
+impl Display for EpdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingFields => write!(f, "Error in EPD record: must have 4 position fields"),
+            Self::Fen(error) => write!(f, "{error}"),
+            Self::Operation(operation) => write!(f, "Error in EPD operation: {operation}"),
+        }
+    }
+}
+
+impl From<FenError> for EpdError {
+    fn from(error: FenError) -> Self {
+        Self::Fen(error)
+    }
+}
+
+/// An operation of an EPD record, such as `bm Nf3 Nc3;`.
+///
+/// * `opcode`: The name of the operation.
+/// * `operands`: The operands, without the quotes around strings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpdOperation {
+    pub opcode: String,
+    pub operands: Vec<String>,
+}
+
+/// A record of an EPD file: a position, and the operations that describe it.
+///
+/// * `position`: The four position fields, as in a FEN string without the move counters.
+/// * `operations`: The operations, in the order they appear.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Epd {
+    pub position: String,
+    pub operations: Vec<EpdOperation>,
+}
+
+impl Epd {
+    /// Reads an EPD record. The position is checked by reading it on a board.
+    ///
+    /// * `line`: The record to read.
+    pub fn parse(line: &str) -> Result<Self, EpdError> {
+        let line = line.trim();
+        let mut fields = Vec::new();
+        let mut rest = line;
+
+        while fields.len() < EPD_POSITION_FIELDS {
+            let Some((field, remainder)) = split_word(rest) else {
+                return Err(EpdError::MissingFields);
+            };
+            fields.push(field);
+            rest = remainder;
+        }
+
+        let epd = Self {
+            position: fields.join(" "),
+            operations: parse_operations(rest)?,
+        };
+        Board::new().fen_read(Some(&epd.fen()))?;
+
+        Ok(epd)
+    }
+
+    /// The position as a FEN string. The move counters are taken from the `hmvc` and `fmvn`
+    /// operations, or are 0 and 1 when those are missing.
+    pub fn fen(&self) -> String {
+        let half_move_clock = self.operand("hmvc").unwrap_or("0");
+        let full_move_number = self.operand("fmvn").unwrap_or("1");
+        format!("{} {half_move_clock} {full_move_number}", self.position)
+    }
+
+    /// The operands of the first operation with this opcode, if the record has it.
+    ///
+    /// * `opcode`: The opcode of the operation.
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|operation| operation.opcode == opcode)
+            .map(|operation| operation.operands.as_slice())
+    }
+
+    /// The first operand of the operation with this opcode.
+    ///
+    /// * `opcode`: The opcode of the operation.
+    fn operand(&self, opcode: &str) -> Option<&str> {
+        self.operation(opcode)?.first().map(String::as_str)
+    }
+
+    /// The name of the position (`id`).
+    pub fn id(&self) -> Option<&str> {
+        self.operand("id")
+    }
+
+    /// The first comment (`c0`).
+    pub fn comment(&self) -> Option<&str> {
+        self.operand("c0")
+    }
+
+    /// The evaluation in centipawns, from the side to move's point of view (`ce`).
+    pub fn centipawn_eval(&self) -> Option<i16> {
+        self.operand("ce")?.parse().ok()
+    }
+
+    /// The number of moves in which the side to move mates (`dm`).
+    pub fn direct_mate(&self) -> Option<u8> {
+        self.operand("dm")?.parse().ok()
+    }
+
+    /// The best moves (`bm`), read in the position of the record.
+    ///
+    /// * `board`: A board set up with the position of the record.
+    /// * `mg`: The move generator.
+    pub fn best_moves(
+        &self,
+        board: &mut Board,
+        mg: &MoveGenerator,
+    ) -> Result<Vec<Move>, MoveParseError> {
+        self.moves("bm", board, mg)
+    }
+
+    /// The moves to avoid (`am`), read in the position of the record.
+    ///
+    /// * `board`: A board set up with the position of the record.
+    /// * `mg`: The move generator.
+    pub fn avoid_moves(
+        &self,
+        board: &mut Board,
+        mg: &MoveGenerator,
+    ) -> Result<Vec<Move>, MoveParseError> {
+        self.moves("am", board, mg)
+    }
+
+    /// The operands of an operation read as moves in SAN.
+    ///
+    /// * `opcode`: The opcode of the operation.
+    /// * `board`: A board set up with the position of the record.
+    /// * `mg`: The move generator.
+    fn moves(
+        &self,
+        opcode: &str,
+        board: &mut Board,
+        mg: &MoveGenerator,
+    ) -> Result<Vec<Move>, MoveParseError> {
+        self.operation(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| board.parse_san(san, mg))
+            .collect()
+    }
+}
+
+/// Splits the first word off a text. Returns None if there are no words left.
+///
+/// * `text`: The text to split.
+fn split_word(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    if text.is_empty() {
+        return None;
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    Some((&text[..end], &text[end..]))
+}
+
+/// Reads the operations that follow the position fields. Each operation is an opcode followed
+/// by its operands, and ends with a semicolon; the semicolon of the last operation may be left
+/// out. Operands in double quotes may contain spaces and semicolons.
+///
+/// * `text`: The operations to read.
+fn parse_operations(text: &str) -> Result<Vec<EpdOperation>, EpdError> {
+    let mut operations = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    let mut finish = |words: &mut Vec<String>| {
+        if !words.is_empty() {
+            let opcode = words.remove(0);
+            operations.push(EpdOperation {
+                opcode,
+                operands: std::mem::take(words),
+            });
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => finish(&mut words),
+            '"' => {
+                let mut operand = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => operand.push(c),
+                        None => return Err(EpdError::Operation(format!("\"{operand}"))),
+                    }
+                }
+                if words.is_empty() {
+                    return Err(EpdError::Operation(format!("\"{operand}\"")));
+                }
+                words.push(operand);
+            }
+            c if c.is_whitespace() => (),
+            c => {
+                let mut word = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == ';' || next == '"' {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+    }
+    finish(&mut words);
+
+    Ok(operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{
+            fen::{FenError, FenErrorKind},
+            Board,
+        },
+        movegen::MoveGenerator,
+    };
+
+    use super::{Epd, EpdError, EpdOperation};
+
+    #[test]
+    fn test_parse_operations() {
+        let epd = Epd::parse(
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\"; \
+             c0 \"Qg6 mates; fxg6 is forced\"; ce 31000;",
+        )
+        .unwrap();
+
+        assert_eq!(
+            epd.position,
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - -"
+        );
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(epd.comment(), Some("Qg6 mates; fxg6 is forced"));
+        assert_eq!(epd.centipawn_eval(), Some(31000));
+        assert_eq!(epd.direct_mate(), None);
+        assert_eq!(epd.operations.len(), 4);
+        assert_eq!(
+            epd.fen(),
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+        );
+
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+        board.fen_read(Some(&epd.fen())).unwrap();
+        let best = epd.best_moves(&mut board, &mg).unwrap();
+        assert_eq!(best.len(), 1);
+        assert_eq!(best[0].to_uci(), "g3g6");
+        assert!(epd.avoid_moves(&mut board, &mg).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_move_counters_and_last_operation() {
+        let epd =
+            Epd::parse("4k3/8/8/8/8/8/8/R3K3 w Q - hmvc 12; fmvn 40; am Ra8+ Kd2; dm 1").unwrap();
+
+        assert_eq!(epd.fen(), "4k3/8/8/8/8/8/8/R3K3 w Q - 12 40");
+        assert_eq!(epd.direct_mate(), Some(1));
+        assert_eq!(
+            epd.operation("am"),
+            Some(&[String::from("Ra8+"), String::from("Kd2")][..])
+        );
+        assert_eq!(
+            epd.operations.last(),
+            Some(&EpdOperation {
+                opcode: String::from("dm"),
+                operands: vec![String::from("1")],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Epd::parse("4k3/8/8/8/8/8/8/4K3 w"),
+            Err(EpdError::MissingFields)
+        );
+        assert_eq!(
+            Epd::parse("4k3/8/8/8/8/8/8/4K3 x - - bm Kd2;"),
+            Err(EpdError::Fen(FenError {
+                position: Some(21),
+                kind: FenErrorKind::ColorSection,
+            }))
+        );
+        assert!(matches!(
+            Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - id \"open;"),
+            Err(EpdError::Operation(_))
+        ));
+    }
+}
@@ -2,11 +2,11 @@ use if_chain::if_chain;
 use std::{char, fmt::Display, ops::RangeInclusive};
 
 use crate::{
-    board::Board,
     board::defs::{
-        Castling, Files, Pieces, Ranks, Sides, Square, Squares, BB_SQUARES, MAX_GAME_MOVES,
-        MAX_MOVE_RULE, SQUARE_NAME,
+        BitBoard, Castling, Files, NrOf, Pieces, Ranks, Side, Sides, Square, Squares, BB_SQUARES,
+        MAX_GAME_MOVES, MAX_MOVE_RULE, SQUARE_NAME,
     },
+    board::Board,
 };
 
 const FEN_NR_OF_SECTIONS: usize = 6;
@@ -17,63 +17,166 @@ const WHITE_OR_BLACK: &str = "wb";
 const DELIMITER: char = '/';
 const DASH: char = '-';
 const EM_DASH: char = '–';
-const SPACE: char = ' ';
 const DEFAULT_FEN_STRING: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
+/// The most pieces a side can have, and the most pawns.
+const MAX_PIECES: u32 = 16;
+const MAX_PAWNS: u32 = 8;
+
+/// How strictly a FEN string is checked.
+///
+/// * `Lenient`: Only errors that make the position unplayable are reported. The move counters
+///   may be left out, and castling rights or an en-passant square that do not match the
+///   position are dropped.
+/// * `Strict`: The string must have all six fields, castling rights and the en-passant square
+///   must match the position, and neither side may have more pieces than a game can produce.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FenMode {
+    #[default]
+    Lenient,
+    Strict,
+}
+
+/// What is wrong with a FEN string.
 #[derive(Debug, PartialEq)]
-/// The possible errors that can be encountered during FEN parsing.
-pub enum FenError {
+pub enum FenErrorKind {
     IncorrectLength,
-    PieceSection,
+    InvalidPiece(char),
+    RankOverflow,
+    RankIncomplete,
+    RankCount,
+    PawnOnBackRank,
     ColorSection,
     CastlingSection,
     EnPassantSection,
     HalfMoveClockSection,
     FullMoveSection,
+    KingCount,
+    TooManyPieces,
+    OpponentInCheck,
+    CastlingRights,
+    EnPassantSquare,
 }
 
-impl Display for FenError {
+impl Display for FenErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let error = match self {
-            Self::IncorrectLength => "Error in FEN string: must be 6 parts",
-            Self::PieceSection => "Error in FEN Section: Pieces or Squares",
-            Self::ColorSection => "Error in FEN Section: Colors",
-            Self::CastlingSection => "Error in FEN Section: Castling rights",
-            Self::EnPassantSection => "Error in FEN Section: En passant field",
-            Self::HalfMoveClockSection => "Error in FEN Section: Half-move clock",
-            Self::FullMoveSection => "Error in FEN Section: Full-move number",
+            Self::IncorrectLength => "must be 6 parts, or 4 without the move counters",
+            Self::InvalidPiece(c) => return write!(f, "invalid piece '{c}'"),
+            Self::RankOverflow => "rank has more than 8 files",
+            Self::RankIncomplete => "rank has fewer than 8 files",
+            Self::RankCount => "must have 8 ranks",
+            Self::PawnOnBackRank => "pawn on the first or last rank",
+            Self::ColorSection => "side to move must be 'w' or 'b'",
+            Self::CastlingSection => "invalid castling rights",
+            Self::EnPassantSection => "invalid en-passant square",
+            Self::HalfMoveClockSection => "invalid half-move clock",
+            Self::FullMoveSection => "invalid full-move number",
+            Self::KingCount => "each side must have exactly one king",
+            Self::TooManyPieces => "too many pieces for one side",
+            Self::OpponentInCheck => "the side not to move is in check",
+            Self::CastlingRights => "castling right without the king and rook in place",
+            Self::EnPassantSquare => "en-passant square without a pawn that just moved",
         };
         write!(f, "{error}")
     }
 }
 
+/// The possible errors that can be encountered during FEN parsing.
+///
+/// * `position`: The position of the character where the error was found, starting at 1; None
+///   when the error is about the position as a whole, such as a missing king.
+/// * `kind`: What went wrong.
+#[derive(Debug, PartialEq)]
+pub struct FenError {
+    pub position: Option<usize>,
+    pub kind: FenErrorKind,
+}
+
+impl FenError {
+    /// An error at a character of the FEN string.
+    ///
+    /// * `position`: The position of the character, starting at 1.
+    /// * `kind`: What went wrong.
+    fn at(position: usize, kind: FenErrorKind) -> Self {
+        Self {
+            position: Some(position),
+            kind,
+        }
+    }
+
+    /// An error in the position as a whole.
+    ///
+    /// * `kind`: What went wrong.
+    fn position(kind: FenErrorKind) -> Self {
+        Self {
+            position: None,
+            kind,
+        }
+    }
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some(position) => write!(
+                f,
+                "Error in FEN string at character {position}: {}",
+                self.kind
+            ),
+            None => write!(f, "Error in FEN string: {}", self.kind),
+        }
+    }
+}
+
 /// The result of the FEN parsing operation.
 pub type FenResult = Result<(), FenError>;
 
 /// The result of the FEN string splitting operation.
-pub type SplitResult = Result<Vec<String>, FenError>;
+pub type SplitResult = Result<Vec<FenPart>, FenError>;
+
+/// A part of a FEN string.
+///
+/// * `text`: The text of the part.
+/// * `start`: The position of its first character in the FEN string, starting at 1.
+/// * `mode`: How strictly the part is checked.
+pub struct FenPart {
+    text: String,
+    start: usize,
+    mode: FenMode,
+}
 
 /// A parser for a FEN part, that applies the FEN data to the board.
-type FenPartParser = fn(board: &mut Board, part: &str) -> FenResult;
+type FenPartParser = fn(board: &mut Board, part: &FenPart) -> FenResult;
 
 impl Board {
     // This function reads a provided FEN-string or uses the default position
     ///
+    /// The string is checked leniently; see [`FenMode`].
+    ///
     /// * `fen_string`: A valid FEN-style string containing a chess position.
     pub fn fen_read(&mut self, fen_string: Option<&str>) -> FenResult {
-        // Split the string into parts, there should be 6 parts.
+        self.fen_read_with_mode(fen_string, FenMode::Lenient)
+    }
 
-        let fen_parts = split_fen_string(fen_string)?;
+    /// Reads a provided FEN-string or uses the default position, checking it as strictly as
+    /// the mode asks. The board is left unchanged when the string is rejected.
+    ///
+    /// * `fen_string`: A valid FEN-style string containing a chess position.
+    /// * `mode`: How strictly the string is checked.
+    pub fn fen_read_with_mode(&mut self, fen_string: Option<&str>, mode: FenMode) -> FenResult {
+        let fen_parts = split_fen_string(fen_string, mode)?;
 
         let fen_parsers = create_part_parsers();
 
-        let mut new_board = self.clone();
+        let mut new_board = Board::new();
 
         for (parser, part) in fen_parsers.iter().zip(fen_parts.iter()) {
             parser(&mut new_board, part)?;
         }
 
         new_board.init();
+        validate(&new_board, mode)?;
         *self = new_board;
 
         Ok(())
@@ -84,28 +187,50 @@ impl Board {
 ///
 /// It also does a bit of error handling:
 ///     such as replacing the EM-dash with a normal dash.
-///     If the FEN string is 4 parts long, the values 0 and 1 are assumed for the last two parts.
+///     If the FEN string is 4 parts long and the mode is lenient, the values 0 and 1 are assumed
+///     for the last two parts.
 ///
 /// * `fen_string`: a FEN-style string to split into its component parts.
-fn split_fen_string(fen_string: Option<&str>) -> SplitResult {
+/// * `mode`: How strictly the string is checked.
+fn split_fen_string(fen_string: Option<&str>, mode: FenMode) -> SplitResult {
     const SHORT_FEN_LENGTH: usize = 4;
 
     // If no FEN string was provided, use the default chess starting position.
-    let mut fen_sections: Vec<String> = match fen_string {
+    let fen = match fen_string {
         Some(fen) => fen,
         None => DEFAULT_FEN_STRING,
     }
-    .replace(EM_DASH, DASH.encode_utf8(&mut [0; 4]))
-    .split(SPACE)
-    .map(|s| s.to_string())
-    .collect();
+    .replace(EM_DASH, DASH.encode_utf8(&mut [0; 4]));
+
+    let mut fen_sections: Vec<FenPart> = Vec::new();
+    for (index, c) in fen.chars().enumerate() {
+        match fen_sections.last_mut() {
+            _ if c.is_whitespace() => (),
+            Some(part) if part.start + part.text.chars().count() == index + 1 => part.text.push(c),
+            _ => fen_sections.push(FenPart {
+                text: c.to_string(),
+                start: index + 1,
+                mode,
+            }),
+        }
+    }
 
-    if fen_sections.len() == SHORT_FEN_LENGTH {
-        fen_sections.append(&mut vec![String::from("0"), String::from("1")]);
+    if fen_sections.len() == SHORT_FEN_LENGTH && mode == FenMode::Lenient {
+        let end = fen.chars().count() + 1;
+        for counter in ["0", "1"] {
+            fen_sections.push(FenPart {
+                text: String::from(counter),
+                start: end,
+                mode,
+            });
+        }
     }
 
     if fen_sections.len() != FEN_NR_OF_SECTIONS {
-        return Err(FenError::IncorrectLength);
+        return Err(match fen_sections.get(FEN_NR_OF_SECTIONS) {
+            Some(extra) => FenError::at(extra.start, FenErrorKind::IncorrectLength),
+            None => FenError::position(FenErrorKind::IncorrectLength),
+        });
     }
 
     Ok(fen_sections)
@@ -125,60 +250,77 @@ fn create_part_parsers() -> [FenPartParser; FEN_NR_OF_SECTIONS] {
 
 /// Parses the pieces section of the FEN string to position the pieces on the board.
 ///
-/// Returns Err(FenError::PieceSection) when invalid characters in the part are detected, or a rank
-/// doesn't have 8 files. Each piece in the string is added to the corresponding sides piece
-/// bitboard.
+/// Returns an error when invalid characters in the part are detected, a rank doesn't have
+/// exactly 8 files, there are not exactly 8 ranks, or a pawn is on the first or last rank. Each
+/// piece in the string is added to the corresponding sides piece bitboard.
 ///
 /// * `board`: The board to add the pieces to.
-/// * `section`: Section 1 of the FEN strings that contains piece positions.
-fn pieces(board: &mut Board, section: &str) -> FenResult {
-    let mut rank = Ranks::R8 as u8;
-    let mut file = Files::A as u8;
+/// * `part`: Section 1 of the FEN strings that contains piece positions.
+fn pieces(board: &mut Board, part: &FenPart) -> FenResult {
+    const FILES: usize = 8;
+
+    let mut rank = Ranks::R8;
+    let mut file = Files::A;
 
     // Parse each character; it should be a piece, square count or DELIMITER
-    for c in section.chars() {
-        let square = ((rank * 8) + file) as usize;
-        match c {
-            'k' => board.bb_pieces[Sides::BLACK][Pieces::KING] |= BB_SQUARES[square],
-            'q' => board.bb_pieces[Sides::BLACK][Pieces::QUEEN] |= BB_SQUARES[square],
-            'r' => board.bb_pieces[Sides::BLACK][Pieces::ROOK] |= BB_SQUARES[square],
-            'b' => board.bb_pieces[Sides::BLACK][Pieces::BISHOP] |= BB_SQUARES[square],
-            'n' => board.bb_pieces[Sides::BLACK][Pieces::KNIGHT] |= BB_SQUARES[square],
-            'p' => board.bb_pieces[Sides::BLACK][Pieces::PAWN] |= BB_SQUARES[square],
-            'K' => board.bb_pieces[Sides::WHITE][Pieces::KING] |= BB_SQUARES[square],
-            'Q' => board.bb_pieces[Sides::WHITE][Pieces::QUEEN] |= BB_SQUARES[square],
-            'R' => board.bb_pieces[Sides::WHITE][Pieces::ROOK] |= BB_SQUARES[square],
-            'B' => board.bb_pieces[Sides::WHITE][Pieces::BISHOP] |= BB_SQUARES[square],
-            'N' => board.bb_pieces[Sides::WHITE][Pieces::KNIGHT] |= BB_SQUARES[square],
-            'P' => board.bb_pieces[Sides::WHITE][Pieces::PAWN] |= BB_SQUARES[square],
+    for (index, c) in part.text.chars().enumerate() {
+        let position = part.start + index;
 
+        match c {
             // Skip ahead N number of files
             '1'..='8' => {
-                if let Some(x) = c.to_digit(10) {
-                    file += x as u8;
+                file += c.to_digit(10).unwrap_or_default() as usize;
+                if file > FILES {
+                    return Err(FenError::at(position, FenErrorKind::RankOverflow));
                 }
             }
 
             // If we've reached a delimiter, check that we ended on the last file.
             DELIMITER => {
-                if file != 8 {
-                    return Err(FenError::PieceSection);
+                if file != FILES {
+                    return Err(FenError::at(position, FenErrorKind::RankIncomplete));
+                }
+                if rank == Ranks::R1 {
+                    return Err(FenError::at(position, FenErrorKind::RankCount));
                 }
 
                 // move to the next rank, and start over at file 0.
                 rank -= 1;
-                file = 0;
+                file = Files::A;
             }
-            _ => return Err(FenError::PieceSection),
-        }
 
-        // If a piece is found, advance to the next file.
-        // (So that this doesn't need to be done in each arm of the match.)
-        if LIST_OF_PIECES.contains(c) {
-            file += 1;
+            _ => {
+                let Some(index) = LIST_OF_PIECES.find(c) else {
+                    return Err(FenError::at(position, FenErrorKind::InvalidPiece(c)));
+                };
+                if file == FILES {
+                    return Err(FenError::at(position, FenErrorKind::RankOverflow));
+                }
+
+                let side = if index < NrOf::PIECE_TYPES {
+                    Sides::BLACK
+                } else {
+                    Sides::WHITE
+                };
+                let piece = index % NrOf::PIECE_TYPES;
+                if piece == Pieces::PAWN && (rank == Ranks::R1 || rank == Ranks::R8) {
+                    return Err(FenError::at(position, FenErrorKind::PawnOnBackRank));
+                }
+
+                board.bb_pieces[side][piece] |= BB_SQUARES[rank * 8 + file];
+                file += 1;
+            }
         }
     }
 
+    let end = part.start + part.text.chars().count();
+    if file != FILES {
+        return Err(FenError::at(end, FenErrorKind::RankIncomplete));
+    }
+    if rank != Ranks::R1 {
+        return Err(FenError::at(end, FenErrorKind::RankCount));
+    }
+
     Ok(())
 }
 
@@ -186,11 +328,11 @@ fn pieces(board: &mut Board, section: &str) -> FenResult {
 /// active color.
 ///
 /// * `board`: The board the game state will be updated on.
-/// * `section`: Section 2 of the FEN strings that contains the active color.
-fn color(board: &mut Board, section: &str) -> FenResult {
+/// * `part`: Section 2 of the FEN strings that contains the active color.
+fn color(board: &mut Board, part: &FenPart) -> FenResult {
     if_chain! {
-        if section.len() == 1;
-        if let Some(c) = section.chars().next();
+        if part.text.len() == 1;
+        if let Some(c) = part.text.chars().next();
         if WHITE_OR_BLACK.contains(c);
         then {
             match c {
@@ -201,79 +343,167 @@ fn color(board: &mut Board, section: &str) -> FenResult {
             return Ok(())
         }
     }
-    Err(FenError::ColorSection)
+    Err(FenError::at(part.start, FenErrorKind::ColorSection))
 }
 
 /// Parses the CastlingSection of the FEN string to determine which, if any castling rights remain
 /// for each color.
 ///
+/// Besides the standard `KQkq`, the X-FEN and Shredder-FEN notations of Chess960 are read: `K`
+/// and `Q` castle with the outermost rook on that side of the king, and a file letter (`A`-`H`
+/// for White, `a`-`h` for Black) names the rook to castle with. A castling right needs the king
+/// and the rook on the back rank. In lenient mode a right without them is dropped; in strict
+/// mode it is an error.
+///
 /// * `board`: The board the game state will be updated on.
-/// * `section`: Section 3 of the FEN strings that contains the castling rights.
-fn castling(board: &mut Board, section: &str) -> FenResult {
-    // There should be up a minimum of 1 and a maximum of 4 castling rights. If no player has castling
-    // rights, the character in the FEN string should be '-'.
-    if (1..=4).contains(&section.len()) {
-        for c in section.chars() {
-            match c {
-                // White
-                'K' => board.state.castling |= Castling::WK,
-                'Q' => board.state.castling |= Castling::WQ,
-                // Black
-                'k' => board.state.castling |= Castling::BK,
-                'q' => board.state.castling |= Castling::BQ,
-                // No castling rights
-                '-' => (),
-                // Any other character here is an error.
-                _ => return Err(FenError::CastlingSection),
+/// * `part`: Section 3 of the FEN strings that contains the castling rights.
+fn castling(board: &mut Board, part: &FenPart) -> FenResult {
+    // If no player has castling rights, the section should be '-'.
+    if part.text == DASH.to_string() {
+        return Ok(());
+    }
+
+    // There should be up a minimum of 1 and a maximum of 4 castling rights.
+    if !(1..=4).contains(&part.text.len()) {
+        return Err(FenError::at(part.start, FenErrorKind::CastlingSection));
+    }
+
+    let mut seen = 0;
+    for (index, c) in part.text.chars().enumerate() {
+        let position = part.start + index;
+
+        // Any other character here, or a right given twice, is an error.
+        if !matches!(c.to_ascii_lowercase(), 'k' | 'q' | 'a'..='h') {
+            return Err(FenError::at(position, FenErrorKind::CastlingSection));
+        }
+
+        let side = if c.is_ascii_uppercase() {
+            Sides::WHITE
+        } else {
+            Sides::BLACK
+        };
+        let rank_start = if side == Sides::WHITE { 0 } else { 56 };
+        let bb_king = board.bb_pieces[side][Pieces::KING];
+        let king_file = Some(bb_king.trailing_zeros() as usize)
+            .filter(|&square| bb_king.count_ones() == 1 && square / 8 == rank_start / 8)
+            .map(|square| square % 8);
+        let letter = c.to_ascii_lowercase();
+        let named_file = (letter as u8).wrapping_sub(b'a') as usize;
+
+        // A file letter only tells which side it castles to once the king is found.
+        let kingside = match (letter, king_file) {
+            ('k', _) => Some(true),
+            ('q', _) => Some(false),
+            (_, king_file) => king_file.map(|king_file| named_file > king_file),
+        };
+        if let Some(kingside) = kingside {
+            let right = Castling::right(side, kingside);
+            if seen & right != 0 {
+                return Err(FenError::at(position, FenErrorKind::CastlingSection));
             }
+            seen |= right;
+        }
+
+        let is_rook =
+            |file: &usize| board.bb_pieces[side][Pieces::ROOK] & BB_SQUARES[rank_start + file] != 0;
+        let rook_file = king_file.and_then(|king_file| match letter {
+            'k' => (king_file + 1..8).rev().find(is_rook),
+            'q' => (0..king_file).find(is_rook),
+            _ => Some(named_file).filter(is_rook),
+        });
+
+        match (kingside, rook_file) {
+            (Some(kingside), Some(rook_file)) => {
+                let right = Castling::right(side, kingside);
+                board.state.castling |= right;
+                board.state.castling_rooks[Castling::index(right)] = rook_file as u8;
+            }
+            _ => castling_not_in_place(part, position)?,
         }
     }
 
-    return Ok(());
+    Ok(())
+}
+
+/// Handles a castling right without its king and rook in place: it is dropped in lenient mode,
+/// and an error in strict mode.
+///
+/// * `part`: Section 3 of the FEN string.
+/// * `position`: The position of the castling right in the FEN string.
+fn castling_not_in_place(part: &FenPart, position: usize) -> FenResult {
+    match part.mode {
+        FenMode::Lenient => Ok(()),
+        FenMode::Strict => Err(FenError::at(position, FenErrorKind::CastlingRights)),
+    }
 }
 
 /// Parses the EnPassantSection of the FEN string to determine if an en passant move exists
 /// in the current position.
 ///
+/// The square must be behind a pawn of the side that just moved, which could have come from the
+/// empty square in front of it. In lenient mode a square that does not match the position is
+/// dropped; in strict mode it is an error.
+///
 /// * `board`: The board the game state will be updated on.
-/// * `section`: Section 4 of the FEN strings that contains the en passant square information.
-fn en_passant(board: &mut Board, section: &str) -> FenResult {
+/// * `part`: Section 4 of the FEN strings that contains the en passant square information.
+fn en_passant(board: &mut Board, part: &FenPart) -> FenResult {
     // No en-passant square if the length of the pat is 1. The character should be a DASH.
     if_chain! {
-       if section.len() == 1;
-       if let Some(x) = section.chars().next();
+       if part.text.len() == 1;
+       if let Some(x) = part.text.chars().next();
        if x == DASH;
        then {
             return Ok(());
        }
     }
 
-    if section.len() == 2 {
-        let square = SQUARE_NAME.iter().position(|&element| element == section);
-        match square {
-            Some(sq)
-                if ENPASSANT_SQUARES_WHITE.contains(&sq)
-                    || ENPASSANT_SQUARES_BLACK.contains(&sq) =>
-            {
-                board.state.en_passant = Some(sq as u8);
-                return Ok(());
-            }
-            _ => return Err(FenError::EnPassantSection),
+    let square = SQUARE_NAME.iter().position(|&element| element == part.text);
+    let square = match square {
+        Some(sq)
+            if ENPASSANT_SQUARES_WHITE.contains(&sq) || ENPASSANT_SQUARES_BLACK.contains(&sq) =>
+        {
+            sq
+        }
+        _ => return Err(FenError::at(part.start, FenErrorKind::EnPassantSection)),
+    };
+
+    match (en_passant_matches(board, square), part.mode) {
+        (true, _) => board.state.en_passant = Some(square as u8),
+        (false, FenMode::Lenient) => (),
+        (false, FenMode::Strict) => {
+            return Err(FenError::at(part.start, FenErrorKind::EnPassantSquare));
         }
     }
 
-    Err(FenError::EnPassantSection)
+    Ok(())
+}
+
+/// Checks that an en-passant square matches the position: the side to move can capture on it,
+/// and a pawn of the other side just moved two squares across it.
+///
+/// * `board`: The board, with the pieces and the side to move set up.
+/// * `square`: The en-passant square.
+fn en_passant_matches(board: &Board, square: Square) -> bool {
+    let (pawn, origin, rank) = match board.current_side() {
+        Sides::WHITE => (square - 8, square + 8, Ranks::R6),
+        _ => (square + 8, square - 8, Ranks::R3),
+    };
+    let bb_occupied = board.bb_pieces.iter().flatten().fold(0, |all, bb| all | bb);
+
+    square / 8 == rank
+        && board.bb_pieces[board.opponent()][Pieces::PAWN] & BB_SQUARES[pawn] != 0
+        && bb_occupied & (BB_SQUARES[square] | BB_SQUARES[origin]) == 0
 }
 
 /// Parses the HalfMoveClockSection of the FEN string to determine the state of the half move clock
 /// for determining drawn positions.
 ///
 /// * `board`: The board the game state will be updated on.
-/// * `section`: Section 5 of the FEN strings that contains the half move clock.
-fn half_move_clock(board: &mut Board, section: &str) -> FenResult {
+/// * `part`: Section 5 of the FEN strings that contains the half move clock.
+fn half_move_clock(board: &mut Board, part: &FenPart) -> FenResult {
     if_chain! {
-        if (1..=3).contains(&section.len());
-        if let Ok(x) = section.parse::<u8>();
+        if (1..=3).contains(&part.text.len());
+        if let Ok(x) = part.text.parse::<u8>();
         if x <= MAX_MOVE_RULE;
         then {
             board.state.half_move_clock = x;
@@ -282,7 +512,7 @@ fn half_move_clock(board: &mut Board, section: &str) -> FenResult {
 
     }
 
-    Err(FenError::HalfMoveClockSection)
+    Err(FenError::at(part.start, FenErrorKind::HalfMoveClockSection))
 }
 
 /// Parses the FullMoveSection of the FEN string to determine the total move counter.
@@ -290,12 +520,12 @@ fn half_move_clock(board: &mut Board, section: &str) -> FenResult {
 /// A full move is one move by white and one move by black. The counter begins at 1.
 ///
 /// * `board`: The board the game state will be updated on.
-/// * `section`: Section 6 of the FEN strings that contains the move counter.
-fn full_move_number(board: &mut Board, section: &str) -> FenResult {
+/// * `part`: Section 6 of the FEN strings that contains the move counter.
+fn full_move_number(board: &mut Board, part: &FenPart) -> FenResult {
     if_chain! {
-        if !section.is_empty() && section.len() <= 4;
-        if let Ok(x) = section.parse::<u16>();
-        if x <= (MAX_GAME_MOVES as u16);
+        if !part.text.is_empty() && part.text.len() <= 4;
+        if let Ok(x) = part.text.parse::<u16>();
+        if x <= MAX_GAME_MOVES;
         then {
             board.state.full_move_number = x;
             return Ok(());
@@ -303,7 +533,125 @@ fn full_move_number(board: &mut Board, section: &str) -> FenResult {
 
     }
 
-    Err(FenError::FullMoveSection)
+    Err(FenError::at(part.start, FenErrorKind::FullMoveSection))
+}
+
+/// Checks the position as a whole, once all parts are read: each side has exactly one king and
+/// the side not to move is not in check. In strict mode, neither side may have more pieces than
+/// a game can produce.
+///
+/// * `board`: The board with the position.
+/// * `mode`: How strictly the position is checked.
+fn validate(board: &Board, mode: FenMode) -> FenResult {
+    for side in [Sides::WHITE, Sides::BLACK] {
+        if board.bb_pieces[side][Pieces::KING].count_ones() != 1 {
+            return Err(FenError::position(FenErrorKind::KingCount));
+        }
+        if mode == FenMode::Strict && too_many_pieces(board, side) {
+            return Err(FenError::position(FenErrorKind::TooManyPieces));
+        }
+    }
+
+    let king = board.king_square(board.opponent());
+    if king_attacked(board, board.current_side(), king) {
+        return Err(FenError::position(FenErrorKind::OpponentInCheck));
+    }
+
+    Ok(())
+}
+
+/// Checks whether a side has more pieces than a game can produce: more than 16 pieces, more than
+/// 8 pawns, or more promoted pieces than pawns it is missing.
+///
+/// * `board`: The board with the position.
+/// * `side`: The side to check.
+fn too_many_pieces(board: &Board, side: Side) -> bool {
+    let count = |piece: usize| board.bb_pieces[side][piece].count_ones();
+    let pawns = count(Pieces::PAWN);
+    let promoted = count(Pieces::QUEEN).saturating_sub(1)
+        + count(Pieces::ROOK).saturating_sub(2)
+        + count(Pieces::BISHOP).saturating_sub(2)
+        + count(Pieces::KNIGHT).saturating_sub(2);
+    let total: u32 = board.bb_pieces[side].iter().map(|bb| bb.count_ones()).sum();
+
+    total > MAX_PIECES || pawns > MAX_PAWNS || promoted > MAX_PAWNS - pawns
+}
+
+/// Checks whether a king is attacked, by walking from its square in every direction. This is
+/// done without a move generator, so a FEN string can be checked on its own.
+///
+/// * `board`: The board with the position.
+/// * `attacker`: The side that may be attacking the king.
+/// * `king`: The square of the king.
+fn king_attacked(board: &Board, attacker: Side, king: Square) -> bool {
+    const KNIGHT_STEPS: [(i8, i8); 8] = [
+        (1, 2),
+        (2, 1),
+        (2, -1),
+        (1, -2),
+        (-1, -2),
+        (-2, -1),
+        (-2, 1),
+        (-1, 2),
+    ];
+    const KING_STEPS: [(i8, i8); 8] = [
+        (0, 1),
+        (1, 1),
+        (1, 0),
+        (1, -1),
+        (0, -1),
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+    ];
+
+    let pieces = &board.bb_pieces[attacker];
+    let bb_occupied = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
+    let (file, rank) = ((king % 8) as i8, (king / 8) as i8);
+    let at = |(df, dr): (i8, i8), distance: i8| -> Option<BitBoard> {
+        let (f, r) = (file + df * distance, rank + dr * distance);
+        ((0..8).contains(&f) && (0..8).contains(&r)).then(|| BB_SQUARES[(r * 8 + f) as usize])
+    };
+
+    // A pawn attacks the king from the rank behind it, seen from the pawn's side.
+    let pawn_rank = if attacker == Sides::WHITE { -1 } else { 1 };
+    let jumpers = [
+        (&KNIGHT_STEPS[..], pieces[Pieces::KNIGHT]),
+        (&KING_STEPS[..], pieces[Pieces::KING]),
+        (&[(-1, pawn_rank), (1, pawn_rank)][..], pieces[Pieces::PAWN]),
+    ];
+    for (steps, bb_attackers) in jumpers {
+        if steps
+            .iter()
+            .any(|&step| at(step, 1).is_some_and(|bb| bb & bb_attackers != 0))
+        {
+            return true;
+        }
+    }
+
+    // Rooks and queens attack along the ranks and files, bishops and queens along the diagonals.
+    for (index, &step) in KING_STEPS.iter().enumerate() {
+        let slider = if index % 2 == 0 {
+            Pieces::ROOK
+        } else {
+            Pieces::BISHOP
+        };
+        let bb_attackers = pieces[slider] | pieces[Pieces::QUEEN];
+
+        for distance in 1..8 {
+            let Some(bb_square) = at(step, distance) else {
+                break;
+            };
+            if bb_square & bb_attackers != 0 {
+                return true;
+            }
+            if bb_square & bb_occupied != 0 {
+                break;
+            }
+        }
+    }
+
+    false
 }
 
 #[cfg(test)]
@@ -372,7 +720,13 @@ mod tests {
             "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR a kq - 0 1",
         ));
         assert!(result.is_err());
-        assert_eq!(result.err(), Some(FenError::ColorSection))
+        assert_eq!(
+            result.err(),
+            Some(FenError {
+                position: Some(45),
+                kind: FenErrorKind::ColorSection,
+            })
+        )
     }
 
     #[test]
@@ -411,11 +765,39 @@ mod tests {
         assert_eq!(board.state.castling, Castling::WK | Castling::BQ);
     }
 
+    #[test]
+    fn test_fen_read_chess960_castling() {
+        // X-FEN: K and Q castle with the outermost rook; a file letter names an inner rook.
+        let board = read("rr2k1r1/8/8/8/8/8/8/1RK3RR w KQkb - 0 1", FenMode::Strict).unwrap();
+        assert_eq!(board.state.castling, Castling::ALL);
+        assert_eq!(board.castling_rook_square(Castling::WK), Squares::H1);
+        assert_eq!(board.castling_rook_square(Castling::WQ), Squares::B1);
+        assert_eq!(board.castling_rook_square(Castling::BK), Squares::G8);
+        assert_eq!(board.castling_rook_square(Castling::BQ), Squares::B8);
+
+        // Shredder-FEN names every rook by its file.
+        let board = read("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1", FenMode::Strict).unwrap();
+        assert_eq!(board.state.castling, Castling::ALL);
+        assert_eq!(board.castling_rook_square(Castling::WK), Squares::G1);
+        assert_eq!(board.castling_rook_square(Castling::BQ), Squares::B8);
+
+        // A file without a rook of the side is not a castling right.
+        let fen = "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w HB - 0 1";
+        assert_eq!(
+            read(fen, FenMode::Lenient).unwrap().state.castling,
+            Castling::WQ
+        );
+        assert_eq!(
+            error(fen, FenMode::Strict),
+            (Some(31), FenErrorKind::CastlingRights)
+        );
+    }
+
     #[test]
     fn test_fen_read_en_passant() {
         let mut board = Board::new();
         _ = board.fen_read(Some(
-            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b kq a3 0 1",
+            "rnbqkbnr/pppppppp/8/8/P7/8/1PPPPPPP/RNBQKBNR b kq a3 0 1",
         ));
         assert_eq!(board.state.en_passant, Some(Squares::A3 as u8));
     }
@@ -446,4 +828,118 @@ mod tests {
         ));
         assert_eq!(board.state.full_move_number, 37);
     }
+
+    fn read(fen: &str, mode: FenMode) -> Result<Board, FenError> {
+        let mut board = Board::new();
+        board.fen_read_with_mode(Some(fen), mode).map(|_| board)
+    }
+
+    fn error(fen: &str, mode: FenMode) -> (Option<usize>, FenErrorKind) {
+        let error = read(fen, mode).err().expect(fen);
+        (error.position, error.kind)
+    }
+
+    #[test]
+    fn test_fen_piece_section_errors() {
+        use FenErrorKind::*;
+        let lenient = FenMode::Lenient;
+
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3x w - -", lenient),
+            (Some(20), InvalidPiece('x'))
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K4 w - -", lenient),
+            (Some(19), RankOverflow)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K2R1 w - -", lenient),
+            (Some(21), RankOverflow)
+        );
+        assert_eq!(
+            error("4k3/7/8/8/8/8/8/4K3 w - -", lenient),
+            (Some(6), RankIncomplete)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/8/4K3 w - -", lenient),
+            (Some(18), RankCount)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/4K3 w - -", lenient),
+            (Some(18), RankCount)
+        );
+        assert_eq!(
+            error("4k2P/8/8/8/8/8/8/4K3 w - -", lenient),
+            (Some(4), PawnOnBackRank)
+        );
+        assert_eq!(error("8/8/8/8/8/8/8/4K3 w - -", lenient), (None, KingCount));
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/3KK3 w - -", lenient),
+            (None, KingCount)
+        );
+        assert_eq!(
+            error("4k3/4R3/8/8/8/8/8/4K3 w - -", lenient),
+            (None, OpponentInCheck)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w KQkqK -", lenient),
+            (Some(23), CastlingSection)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w KK -", lenient),
+            (Some(24), CastlingSection)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra", lenient),
+            (Some(31), IncorrectLength)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w", lenient),
+            (None, IncorrectLength)
+        );
+        assert!(read("4k3/8/8/8/8/8/8/4K3 w - -", lenient).is_ok());
+    }
+
+    #[test]
+    fn test_fen_lenient_and_strict_modes() {
+        use FenErrorKind::*;
+        let (lenient, strict) = (FenMode::Lenient, FenMode::Strict);
+
+        // Castling rights without the rook, and an en-passant square without a pawn.
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w KQ e6 0 1";
+        let board = read(fen, lenient).unwrap();
+        assert_eq!(board.state.castling, Castling::WQ);
+        assert_eq!(board.state.en_passant, None);
+        assert_eq!(error(fen, strict), (Some(24), CastlingRights));
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/R3K3 w Q e6 0 1", strict),
+            (Some(26), EnPassantSquare)
+        );
+
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2";
+        assert_eq!(
+            read(fen, strict).unwrap().state.en_passant,
+            Some(Squares::D6 as u8)
+        );
+
+        // Strict mode needs the move counters, and no more pieces than a game can produce.
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - -", strict),
+            (None, IncorrectLength)
+        );
+        let fen = "7k/8/8/QQQ5/8/8/PPPPPPP1/4K3 w - - 0 1";
+        assert!(read(fen, lenient).is_ok());
+        assert_eq!(error(fen, strict), (None, TooManyPieces));
+    }
+
+    #[test]
+    fn test_fen_error_display() {
+        let error = read("4k3/8/8/8/8/8/8/4K3 x - -", FenMode::Lenient)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Error in FEN string at character 21: side to move must be 'w' or 'b'"
+        );
+    }
 }
@@ -0,0 +1,501 @@
+use std::fmt::Display;
+
+use crate::{
+    board::{
+        defs::{Files, Pieces, PIECE_CHAR_CAPS, SQUARE_NAME},
+        Board,
+    },
+    movegen::{
+        defs::{Move, MoveType},
+        movelist::MoveList,
+        MoveGenerator,
+    },
+};
+
+/// The characters that may follow a move in SAN: check and mate marks, and annotations.
+const SAN_SUFFIXES: &[char] = &['+', '#', '!', '?'];
+
+#[derive(Debug, PartialEq)]
+/// The errors that can occur when reading a move.
+///
+/// * `Invalid`: The text is not a move in the notation.
+/// * `Illegal`: No legal move in the position matches the text.
+/// * `Ambiguous`: More than one legal move matches the text.
+pub enum MoveParseError {
+    Invalid(String),
+    Illegal(String),
+    Ambiguous(String),
+}
+
+impl Display for MoveParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(mv) => write!(f, "Invalid move: {mv}"),
+            Self::Illegal(mv) => write!(f, "Illegal move: {mv}"),
+            Self::Ambiguous(mv) => write!(f, "Ambiguous move: {mv}"),
+        }
+    }
+}
+
+/// A move in SAN, taken apart. The origin of the move is only known as far as the text
+/// disambiguates it.
+///
+/// * `piece`: The piece that moves.
+/// * `from_file`: The file the piece moves from, if given.
+/// * `from_rank`: The rank the piece moves from, if given.
+/// * `to`: The square the piece moves to.
+/// * `promoted`: The piece a pawn promotes to; [`Pieces::NONE`] when it does not promote.
+struct SanMove {
+    piece: usize,
+    from_file: Option<usize>,
+    from_rank: Option<usize>,
+    to: usize,
+    promoted: usize,
+}
+
+impl Board {
+    /// Formats a legal move in Standard Algebraic Notation (SAN), such as `Nbd7`, `exd6`,
+    /// `e8=Q+` or `O-O-O#`. The origin of a piece is only given when another piece of the same
+    /// kind can move to the same square: its file if that is enough, otherwise its rank, and
+    /// otherwise both.
+    ///
+    /// * `mv`: The move, which must be legal in the position.
+    /// * `mg`: The move generator, used to find the other legal moves and whether the move checks.
+    pub fn move_to_san(&mut self, mv: Move, mg: &MoveGenerator) -> String {
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(self, &mut legal, MoveType::All);
+
+        let mut san = if mv.castling() {
+            String::from(if mv.to() % 8 == Files::G {
+                "O-O"
+            } else {
+                "O-O-O"
+            })
+        } else {
+            let mut san = String::new();
+            let from = SQUARE_NAME[mv.from()];
+
+            if mv.piece() == Pieces::PAWN {
+                if mv.is_capture() {
+                    san.push_str(&from[..1]);
+                }
+            } else {
+                san.push_str(PIECE_CHAR_CAPS[mv.piece()]);
+
+                let rivals: Vec<&Move> = legal
+                    .iter()
+                    .filter(|other| {
+                        other.piece() == mv.piece() && other.to() == mv.to() && **other != mv
+                    })
+                    .collect();
+                if !rivals.is_empty() {
+                    if rivals.iter().all(|other| other.from() % 8 != mv.from() % 8) {
+                        san.push_str(&from[..1]);
+                    } else if rivals.iter().all(|other| other.from() / 8 != mv.from() / 8) {
+                        san.push_str(&from[1..]);
+                    } else {
+                        san.push_str(from);
+                    }
+                }
+            }
+
+            if mv.is_capture() {
+                san.push('x');
+            }
+            san.push_str(SQUARE_NAME[mv.to()]);
+
+            if mv.is_promotion() {
+                san.push('=');
+                san.push_str(PIECE_CHAR_CAPS[mv.promoted()]);
+            }
+            san
+        };
+
+        if self.make_move(mv, mg) {
+            if mg.in_check(self) {
+                let mut replies = MoveList::new();
+                mg.generate_legal_moves(self, &mut replies, MoveType::All);
+                san.push(if replies.is_empty() { '#' } else { '+' });
+            }
+            self.unmake_move();
+        }
+
+        san
+    }
+
+    /// Formats a move in the long algebraic notation used by UCI. In Chess960 castling is written
+    /// as the king capturing its own rook, such as `e1h1`, because the king's two-square move
+    /// does not tell which rook castles, or may be the same as a normal king move.
+    ///
+    /// * `mv`: The move to format.
+    /// * `chess960`: Write castling the Chess960 way.
+    pub fn move_to_uci(&self, mv: Move, chess960: bool) -> String {
+        if chess960 && mv.castling() {
+            let (rook_from, _) = self.castling_rook_squares(mv.to());
+            format!("{}{}", SQUARE_NAME[mv.from()], SQUARE_NAME[rook_from])
+        } else {
+            mv.to_uci()
+        }
+    }
+
+    /// Reads a move in the long algebraic notation used by UCI, such as `e2e4`, `e7e8q` or
+    /// `e1g1` for castling, and finds the legal move it stands for. Castling may also be given as
+    /// the king capturing its own rook, as in Chess960 (`e1h1`); the king's two-square move only
+    /// stands for castling when it cannot be mistaken for a normal king move.
+    ///
+    /// * `text`: The move in UCI notation.
+    /// * `mg`: The move generator, used to find the legal moves.
+    pub fn parse_uci_move(
+        &mut self,
+        text: &str,
+        mg: &MoveGenerator,
+    ) -> Result<Move, MoveParseError> {
+        let text = text.trim();
+        let chars: Vec<char> = text.chars().collect();
+        let valid = match chars[..] {
+            [from_file, from_rank, to_file, to_rank, ref promotion @ ..] => {
+                square(from_file, from_rank).is_some()
+                    && square(to_file, to_rank).is_some()
+                    && matches!(promotion, [] | ['q' | 'r' | 'b' | 'n'])
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(MoveParseError::Invalid(text.to_string()));
+        }
+
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(self, &mut legal, MoveType::All);
+        legal
+            .iter()
+            .find(|mv| match mv.castling() {
+                true => {
+                    self.move_to_uci(**mv, true) == text
+                        || (mv.from().abs_diff(mv.to()) == 2 && mv.to_uci() == text)
+                }
+                false => mv.to_uci() == text,
+            })
+            .copied()
+            .ok_or_else(|| MoveParseError::Illegal(text.to_string()))
+    }
+
+    /// Reads a move in Standard Algebraic Notation (SAN), and finds the legal move it stands for.
+    /// Common variants are accepted: castling with zeros (`0-0`), promotions without `=`
+    /// (`e8Q`), moves with or without check and mate marks or annotations (`Nf3!?`), and
+    /// moves that give the origin square in full (`Ng1f3`, `Ng1-f3`).
+    ///
+    /// * `san`: The move in SAN.
+    /// * `mg`: The move generator, used to find the legal moves.
+    pub fn parse_san(&mut self, san: &str, mg: &MoveGenerator) -> Result<Move, MoveParseError> {
+        let text = san.trim().trim_end_matches(SAN_SUFFIXES);
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(self, &mut legal, MoveType::All);
+
+        let candidates: Vec<Move> = match text {
+            "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+                let kingside = text.len() == 3;
+                legal
+                    .iter()
+                    .filter(|mv| mv.castling() && (mv.to() % 8 == Files::G) == kingside)
+                    .copied()
+                    .collect()
+            }
+            _ => {
+                let parsed =
+                    parse_san_move(text).ok_or_else(|| MoveParseError::Invalid(san.to_string()))?;
+                legal
+                    .iter()
+                    .filter(|mv| {
+                        mv.piece() == parsed.piece
+                            && mv.to() == parsed.to
+                            && mv.promoted() == parsed.promoted
+                            && parsed.from_file.is_none_or(|file| mv.from() % 8 == file)
+                            && parsed.from_rank.is_none_or(|rank| mv.from() / 8 == rank)
+                    })
+                    .copied()
+                    .collect()
+            }
+        };
+
+        match candidates[..] {
+            [mv] => Ok(mv),
+            [] => Err(MoveParseError::Illegal(san.to_string())),
+            _ => Err(MoveParseError::Ambiguous(san.to_string())),
+        }
+    }
+}
+
+/// Takes a move in SAN apart, without the check marks and annotations. Returns None if the text
+/// is not a move.
+///
+/// * `text`: The move to read.
+fn parse_san_move(text: &str) -> Option<SanMove> {
+    let mut chars: Vec<char> = text
+        .chars()
+        .filter(|c| !matches!(c, 'x' | '-' | ':'))
+        .collect();
+
+    let piece = match chars.first() {
+        Some(&c) if "KQRBN".contains(c) => {
+            chars.remove(0);
+            piece_from_char(c)?
+        }
+        _ => Pieces::PAWN,
+    };
+
+    // A promotion ends in the piece, with or without `=`, in either case.
+    let mut promoted = Pieces::NONE;
+    if let Some(&last) = chars.last() {
+        if piece == Pieces::PAWN && "QRBNqrbn".contains(last) {
+            promoted = piece_from_char(last.to_ascii_uppercase())?;
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+
+    let [origin @ .., file, rank] = &chars[..] else {
+        return None;
+    };
+    let to = square(*file, *rank)?;
+
+    let (from_file, from_rank) = match origin {
+        [] => (None, None),
+        [c] if c.is_ascii_digit() => (None, Some(rank_index(*c)?)),
+        [c] => (Some(file_index(*c)?), None),
+        [file, rank] => (Some(file_index(*file)?), Some(rank_index(*rank)?)),
+        _ => return None,
+    };
+
+    Some(SanMove {
+        piece,
+        from_file,
+        from_rank,
+        to,
+        promoted,
+    })
+}
+
+/// The piece of an uppercase SAN piece letter.
+fn piece_from_char(c: char) -> Option<usize> {
+    PIECE_CHAR_CAPS
+        .iter()
+        .take(Pieces::PAWN)
+        .position(|p| p.starts_with(c))
+}
+
+/// The index of a file letter, `a` to `h`.
+fn file_index(c: char) -> Option<usize> {
+    ('a'..='h').contains(&c).then(|| c as usize - 'a' as usize)
+}
+
+/// The index of a rank digit, `1` to `8`.
+fn rank_index(c: char) -> Option<usize> {
+    ('1'..='8').contains(&c).then(|| c as usize - '1' as usize)
+}
+
+/// The square of a file letter and a rank digit.
+fn square(file: char, rank: char) -> Option<usize> {
+    Some(rank_index(rank)? * 8 + file_index(file)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{
+            defs::{Pieces, Squares},
+            Board,
+        },
+        movegen::{
+            defs::{Move, MoveType},
+            movelist::MoveList,
+            MoveGenerator,
+        },
+    };
+
+    use super::MoveParseError;
+
+    fn board(fen: &str) -> Board {
+        let mut board = Board::new();
+        board.fen_read(Some(fen)).unwrap();
+        board
+    }
+
+    fn find(
+        board: &mut Board,
+        mg: &MoveGenerator,
+        from: usize,
+        to: usize,
+        promoted: usize,
+    ) -> Move {
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(board, &mut legal, MoveType::All);
+        *legal
+            .iter()
+            .find(|mv| mv.from() == from && mv.to() == to && mv.promoted() == promoted)
+            .unwrap()
+    }
+
+    /// Checks that the move formats as the expected SAN, and that the SAN reads back as the move.
+    fn assert_san(board: &mut Board, mg: &MoveGenerator, mv: Move, expected: &str) {
+        assert_eq!(board.move_to_san(mv, mg), expected);
+        assert_eq!(board.parse_san(expected, mg), Ok(mv));
+    }
+
+    #[test]
+    fn test_san_of_simple_moves() {
+        let mg = MoveGenerator::new();
+        let mut start = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+        let e4 = find(&mut start, &mg, Squares::E2, Squares::E4, Pieces::NONE);
+        assert_san(&mut start, &mg, e4, "e4");
+        let nf3 = find(&mut start, &mg, Squares::G1, Squares::F3, Pieces::NONE);
+        assert_san(&mut start, &mg, nf3, "Nf3");
+    }
+
+    #[test]
+    fn test_san_disambiguation_and_captures() {
+        let mg = MoveGenerator::new();
+        let mut position = board("4k3/8/8/3p4/8/2N1N3/8/R4RK1 w - - 0 1");
+
+        let by_file = find(&mut position, &mg, Squares::C3, Squares::D5, Pieces::NONE);
+        assert_san(&mut position, &mg, by_file, "Ncxd5");
+        let rook = find(&mut position, &mg, Squares::A1, Squares::D1, Pieces::NONE);
+        assert_san(&mut position, &mg, rook, "Rad1");
+
+        let mut ranks = board("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1");
+        let upper = find(&mut ranks, &mg, Squares::A5, Squares::A3, Pieces::NONE);
+        assert_san(&mut ranks, &mg, upper, "R5a3");
+        let lower = find(&mut ranks, &mg, Squares::A1, Squares::A3, Pieces::NONE);
+        assert_san(&mut ranks, &mg, lower, "R1a3");
+        let unique = find(&mut ranks, &mg, Squares::A1, Squares::B1, Pieces::NONE);
+        assert_san(&mut ranks, &mg, unique, "Rb1");
+
+        let mut queens = board("7k/8/8/8/Q1Q5/8/Q7/4K3 w - - 0 1");
+        let full = find(&mut queens, &mg, Squares::A4, Squares::B3, Pieces::NONE);
+        assert_san(&mut queens, &mg, full, "Qa4b3");
+    }
+
+    #[test]
+    fn test_san_of_castling_promotion_and_checks() {
+        let mg = MoveGenerator::new();
+
+        let mut castling = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let short = find(&mut castling, &mg, Squares::E1, Squares::G1, Pieces::NONE);
+        assert_san(&mut castling, &mg, short, "O-O");
+        let long = find(&mut castling, &mg, Squares::E1, Squares::C1, Pieces::NONE);
+        assert_san(&mut castling, &mg, long, "O-O-O");
+
+        let mut promotion = board("3r2k1/4P3/6K1/8/8/8/8/8 w - - 0 1");
+        let capture = find(&mut promotion, &mg, Squares::E7, Squares::D8, Pieces::QUEEN);
+        assert_san(&mut promotion, &mg, capture, "exd8=Q#");
+        let under = find(
+            &mut promotion,
+            &mg,
+            Squares::E7,
+            Squares::E8,
+            Pieces::KNIGHT,
+        );
+        assert_san(&mut promotion, &mg, under, "e8=N");
+
+        let mut mate = board("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let back_rank = find(&mut mate, &mg, Squares::A1, Squares::A8, Pieces::NONE);
+        assert_san(&mut mate, &mg, back_rank, "Ra8#");
+    }
+
+    #[test]
+    fn test_parse_san_variants() {
+        let mg = MoveGenerator::new();
+
+        let mut castling = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let short = find(&mut castling, &mg, Squares::E1, Squares::G1, Pieces::NONE);
+        assert_eq!(castling.parse_san("0-0", &mg), Ok(short));
+        assert_eq!(castling.parse_san("O-O+", &mg), Ok(short));
+
+        let mut promotion = board("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+        let b8q = find(&mut promotion, &mg, Squares::B7, Squares::B8, Pieces::QUEEN);
+        assert_eq!(promotion.parse_san("b8Q", &mg), Ok(b8q));
+        assert_eq!(promotion.parse_san("b8=q+", &mg), Ok(b8q));
+
+        let mut start = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let nf3 = find(&mut start, &mg, Squares::G1, Squares::F3, Pieces::NONE);
+        assert_eq!(start.parse_san("Nf3!?", &mg), Ok(nf3));
+        assert_eq!(start.parse_san("Ng1-f3", &mg), Ok(nf3));
+    }
+
+    #[test]
+    fn test_uci_moves() {
+        let mg = MoveGenerator::new();
+        let mut position = board("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let castle = find(&mut position, &mg, Squares::E1, Squares::G1, Pieces::NONE);
+        let promotion = find(&mut position, &mg, Squares::B7, Squares::A8, Pieces::KNIGHT);
+
+        assert_eq!(castle.to_uci(), "e1g1");
+        assert_eq!(promotion.to_uci(), "b7a8n");
+        assert_eq!(position.parse_uci_move("e1g1", &mg), Ok(castle));
+        assert_eq!(position.parse_uci_move(" b7a8n", &mg), Ok(promotion));
+
+        assert_eq!(
+            position.parse_uci_move("b7a8", &mg),
+            Err(MoveParseError::Illegal(String::from("b7a8")))
+        );
+        assert_eq!(
+            position.parse_uci_move("e1e3", &mg),
+            Err(MoveParseError::Illegal(String::from("e1e3")))
+        );
+        for invalid in ["e1", "e1g9", "b7a8k", "Nf3", "0000"] {
+            assert_eq!(
+                position.parse_uci_move(invalid, &mg),
+                Err(MoveParseError::Invalid(String::from(invalid)))
+            );
+        }
+    }
+
+    #[test]
+    fn test_chess960_castling_notation() {
+        let mg = MoveGenerator::new();
+        let mut position = board("1r2k2r/8/8/8/8/8/8/1R3KR1 w GBhb - 0 1");
+        let short = find(&mut position, &mg, Squares::F1, Squares::G1, Pieces::NONE);
+        let long = find(&mut position, &mg, Squares::F1, Squares::C1, Pieces::NONE);
+
+        assert!(short.castling() && long.castling());
+        assert_san(&mut position, &mg, short, "O-O");
+        assert_san(&mut position, &mg, long, "O-O-O");
+        assert_eq!(position.move_to_uci(short, true), "f1g1");
+        assert_eq!(position.move_to_uci(long, true), "f1b1");
+        assert_eq!(position.move_to_uci(long, false), "f1c1");
+
+        // The king's move to g1 takes the rook, so f1g1 can only be read as castling; f1c1 is
+        // not a two-square king move and is not read as castling.
+        assert_eq!(position.parse_uci_move("f1g1", &mg), Ok(short));
+        assert_eq!(position.parse_uci_move("f1b1", &mg), Ok(long));
+        assert_eq!(
+            position.parse_uci_move("f1c1", &mg),
+            Err(MoveParseError::Illegal(String::from("f1c1")))
+        );
+    }
+
+    #[test]
+    fn test_parse_san_errors() {
+        let mg = MoveGenerator::new();
+        let mut position = board("4k3/8/8/3p4/8/2N1N3/8/R3K2R w KQ - 0 1");
+
+        assert_eq!(
+            position.parse_san("Nxd5", &mg),
+            Err(MoveParseError::Ambiguous(String::from("Nxd5")))
+        );
+        assert_eq!(
+            position.parse_san("Qd1", &mg),
+            Err(MoveParseError::Illegal(String::from("Qd1")))
+        );
+        assert_eq!(
+            position.parse_san("Zz9", &mg),
+            Err(MoveParseError::Invalid(String::from("Zz9")))
+        );
+        assert_eq!(
+            position.parse_san("b8", &mg),
+            Err(MoveParseError::Illegal(String::from("b8")))
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use crate::{
+    board::{
+        defs::{Castling, Files, Piece, Pieces, Side, Sides, Square},
+        zobrist::ZOBRIST,
+        Board,
+    },
+    movegen::{defs::Move, MoveGenerator},
+};
+
+impl Board {
+    /// Plays a move on the board.
+    ///
+    /// The move must be pseudo-legal for the current position. If the move leaves the king of the
+    /// side that moved in check, it is taken back and false is returned; otherwise true is
+    /// returned and the move can later be taken back with [`Board::unmake_move`].
+    ///
+    /// * `mv`: The move to play.
+    /// * `mg`: The move generator, used to determine if the king is left in check.
+    pub fn make_move(&mut self, mv: Move, mg: &MoveGenerator) -> bool {
+        let mut previous = self.state;
+        previous.next_move = mv;
+        self.history.push(previous);
+
+        let us = self.current_side();
+        let opponent = self.opponent();
+        let piece = mv.piece();
+        let from = mv.from();
+        let to = mv.to();
+
+        self.state.half_move_clock = self.state.half_move_clock.saturating_add(1);
+
+        if mv.is_capture() {
+            let captured_square = if mv.en_passant() {
+                Board::en_passant_victim(us, to)
+            } else {
+                to
+            };
+            self.remove_piece(opponent, mv.captured(), captured_square);
+            self.state.half_move_clock = 0;
+        }
+
+        if piece == Pieces::PAWN {
+            self.state.half_move_clock = 0;
+        }
+
+        if mv.castling() {
+            // In Chess960 the king may land on the rook's square or the other way around, so
+            // both pieces are lifted before either is put down.
+            let (rook_from, rook_to) = self.castling_rook_squares(to);
+            self.remove_piece(us, piece, from);
+            self.remove_piece(us, Pieces::ROOK, rook_from);
+            self.put_piece(us, piece, to);
+            self.put_piece(us, Pieces::ROOK, rook_to);
+        } else {
+            self.remove_piece(us, piece, from);
+            if mv.is_promotion() {
+                self.put_piece(us, mv.promoted(), to);
+            } else {
+                self.put_piece(us, piece, to);
+            }
+        }
+
+        self.state.zobrist_key ^= ZOBRIST.en_passant(self.state.en_passant);
+        self.state.en_passant = if mv.double_step() {
+            Some(((from + to) / 2) as u8)
+        } else {
+            None
+        };
+        self.state.zobrist_key ^= ZOBRIST.en_passant(self.state.en_passant);
+
+        self.state.zobrist_key ^= ZOBRIST.castling(self.state.castling);
+        self.state.castling = self.castling_permissions(us, piece, from, to);
+        self.state.zobrist_key ^= ZOBRIST.castling(self.state.castling);
+
+        if us == Sides::BLACK {
+            self.state.full_move_number += 1;
+        }
+
+        self.state.active_side ^= 1;
+        self.state.zobrist_key ^= ZOBRIST.side();
+
+        // A move is only legal if it does not leave the own king in check.
+        let king_square = self.king_square(us);
+        if mg.square_attacked(self, opponent, king_square) {
+            self.unmake_move();
+            return false;
+        }
+
+        true
+    }
+
+    /// Takes back the last move that was played with [`Board::make_move`].
+    ///
+    /// WARNING: This function will panic if no move has been played.
+    pub fn unmake_move(&mut self) {
+        let previous = self.history.pop().expect("No move to take back.");
+        let mv = previous.next_move;
+
+        let us = previous.active_side as usize;
+        let opponent = us ^ 1;
+        let piece = mv.piece();
+        let from = mv.from();
+        let to = mv.to();
+
+        if mv.castling() {
+            let (rook_from, rook_to) = self.castling_rook_squares(to);
+            self.remove_piece(us, piece, to);
+            self.remove_piece(us, Pieces::ROOK, rook_to);
+            self.put_piece(us, Pieces::ROOK, rook_from);
+        } else if mv.is_promotion() {
+            self.remove_piece(us, mv.promoted(), to);
+        } else {
+            self.remove_piece(us, piece, to);
+        }
+        self.put_piece(us, piece, from);
+
+        if mv.is_capture() {
+            let captured_square = if mv.en_passant() {
+                Board::en_passant_victim(us, to)
+            } else {
+                to
+            };
+            self.put_piece(opponent, mv.captured(), captured_square);
+        }
+
+        // Restoring the state also restores the material count that was changed above.
+        self.state = previous;
+    }
+
+    /// The square of the pawn that is captured by an en-passant capture.
+    ///
+    /// * `side`: The side making the capture.
+    /// * `to`: The en-passant square the capturing pawn moves to.
+    fn en_passant_victim(side: usize, to: Square) -> Square {
+        if side == Sides::WHITE {
+            to - 8
+        } else {
+            to + 8
+        }
+    }
+
+    /// The square of the rook a castling right castles with.
+    ///
+    /// * `right`: One of the castling rights, such as [`Castling::WK`].
+    pub fn castling_rook_square(&self, right: u8) -> Square {
+        let rank = if right & Castling::rights(Sides::WHITE) > 0 {
+            0
+        } else {
+            7
+        };
+        rank * 8 + self.state.castling_rooks[Castling::index(right)] as usize
+    }
+
+    /// The squares a rook moves from and to when castling, given the king's destination. The
+    /// king always ends on the g-file or the c-file, and the rook next to it on the f-file or
+    /// the d-file, wherever they started.
+    ///
+    /// * `king_to`: The square the king castles to.
+    pub fn castling_rook_squares(&self, king_to: Square) -> (Square, Square) {
+        let side = if king_to < 8 {
+            Sides::WHITE
+        } else {
+            Sides::BLACK
+        };
+        let kingside = king_to % 8 == Files::G;
+        let rank_start = king_to - king_to % 8;
+        let rook_to = rank_start + if kingside { Files::F } else { Files::D };
+
+        (
+            self.castling_rook_square(Castling::right(side, kingside)),
+            rook_to,
+        )
+    }
+
+    /// The castling permissions that remain after a move. Moving the king removes both
+    /// permissions for its side, and moving a rook from (or capturing a rook on) its starting
+    /// square removes the permission for that rook.
+    ///
+    /// * `side`: The side that moves.
+    /// * `piece`: The piece that moves.
+    /// * `from`: The square the piece moves from.
+    /// * `to`: The square the piece moves to.
+    fn castling_permissions(&self, side: Side, piece: Piece, from: Square, to: Square) -> u8 {
+        let mut castling = self.state.castling;
+        if piece == Pieces::KING {
+            castling &= !Castling::rights(side);
+        }
+
+        for right in [Castling::WK, Castling::WQ, Castling::BK, Castling::BQ] {
+            let rook = self.castling_rook_square(right);
+            if castling & right > 0 && (rook == from || rook == to) {
+                castling &= !right;
+            }
+        }
+        castling
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{
+            defs::{Castling, Pieces, Sides, Squares, BB_SQUARES},
+            Board,
+        },
+        movegen::{movelist::MoveList, MoveGenerator},
+    };
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn perft(board: &mut Board, mg: &MoveGenerator, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let mut move_list = MoveList::new();
+        mg.generate_moves(board, &mut move_list);
+
+        let mut nodes = 0;
+        for mv in move_list.iter() {
+            if board.make_move(*mv, mg) {
+                nodes += perft(board, mg, depth - 1);
+                board.unmake_move();
+            }
+        }
+        nodes
+    }
+
+    fn find_move(
+        board: &Board,
+        mg: &MoveGenerator,
+        from: usize,
+        to: usize,
+    ) -> crate::movegen::defs::Move {
+        let mut move_list = MoveList::new();
+        mg.generate_moves(board, &mut move_list);
+        *move_list
+            .iter()
+            .find(|mv| mv.from() == from && mv.to() == to)
+            .expect("move not generated")
+    }
+
+    #[test]
+    fn test_perft_starting_position() {
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let mg = MoveGenerator::new();
+        assert_eq!(perft(&mut board, &mg, 1), 20);
+        assert_eq!(perft(&mut board, &mg, 2), 400);
+        assert_eq!(perft(&mut board, &mg, 3), 8_902);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        let mut board = Board::new();
+        board.fen_read(Some(KIWIPETE)).unwrap();
+        let mg = MoveGenerator::new();
+        assert_eq!(perft(&mut board, &mg, 1), 48);
+        assert_eq!(perft(&mut board, &mg, 2), 2_039);
+        assert_eq!(perft(&mut board, &mg, 3), 97_862);
+    }
+
+    #[test]
+    fn test_perft_en_passant_and_promotions() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+        assert_eq!(perft(&mut board, &mg, 3), 2_812);
+
+        board
+            .fen_read(Some("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"))
+            .unwrap();
+        assert_eq!(perft(&mut board, &mg, 3), 9_483);
+    }
+
+    #[test]
+    fn test_make_unmake_restores_board() {
+        let mut board = Board::new();
+        board.fen_read(Some(KIWIPETE)).unwrap();
+        let mg = MoveGenerator::new();
+        let original = board.clone();
+
+        let mut move_list = MoveList::new();
+        mg.generate_moves(&board, &mut move_list);
+
+        for mv in move_list.iter() {
+            if board.make_move(*mv, &mg) {
+                board.unmake_move();
+            }
+            assert_eq!(board.bb_pieces, original.bb_pieces);
+            assert_eq!(board.bb_side, original.bb_side);
+            assert_eq!(board.state.castling, original.state.castling);
+            assert_eq!(board.state.material, original.state.material);
+            assert_eq!(board.state.zobrist_key, original.state.zobrist_key);
+            assert!(board.history.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_make_move_updates_zobrist_key_incrementally() {
+        let mut board = Board::new();
+        board.fen_read(Some(KIWIPETE)).unwrap();
+        let mg = MoveGenerator::new();
+
+        let mut move_list = MoveList::new();
+        mg.generate_moves(&board, &mut move_list);
+
+        for mv in move_list.iter() {
+            if board.make_move(*mv, &mg) {
+                let mut fresh = board.clone();
+                fresh.init();
+                assert_eq!(board.state.zobrist_key, fresh.state.zobrist_key, "{mv}");
+                board.unmake_move();
+            }
+        }
+    }
+
+    #[test]
+    fn test_repetition_after_knight_moves() {
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let mg = MoveGenerator::new();
+
+        let moves = [
+            (Squares::G1, Squares::F3),
+            (Squares::G8, Squares::F6),
+            (Squares::F3, Squares::G1),
+            (Squares::F6, Squares::G8),
+        ];
+
+        for (from, to) in moves {
+            assert!(!board.is_repetition());
+            let mv = find_move(&board, &mg, from, to);
+            assert!(board.make_move(mv, &mg));
+        }
+
+        assert!(board.is_repetition());
+    }
+
+    #[test]
+    fn test_make_move_castling_moves_rook_and_clears_permissions() {
+        let mut board = Board::new();
+        board.fen_read(Some(KIWIPETE)).unwrap();
+        let mg = MoveGenerator::new();
+
+        let castle = find_move(&board, &mg, Squares::E1, Squares::G1);
+        assert!(castle.castling());
+        assert!(board.make_move(castle, &mg));
+
+        assert!(board.bb_pieces[Sides::WHITE][Pieces::ROOK] & BB_SQUARES[Squares::F1] > 0);
+        assert!(board.bb_pieces[Sides::WHITE][Pieces::ROOK] & BB_SQUARES[Squares::H1] == 0);
+        assert_eq!(board.state.castling, Castling::BK | Castling::BQ);
+        assert_eq!(board.current_side(), Sides::BLACK);
+    }
+
+    #[test]
+    fn test_perft_chess960() {
+        let mg = MoveGenerator::new();
+        let positions = [
+            (
+                "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+                [21, 528, 12189],
+            ),
+            (
+                "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+                [21, 807, 18002],
+            ),
+        ];
+
+        for (fen, expected) in positions {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+            for (depth, nodes) in expected.into_iter().enumerate() {
+                assert_eq!(perft(&mut board, &mg, depth as u8 + 1), nodes, "{fen}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_move_chess960_castling() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("1r4kr/8/8/8/8/8/8/1R4KR w KQkq - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+        let original = board.clone();
+
+        // Castling short leaves the king where it is and only moves the rook.
+        let short = find_move(&board, &mg, Squares::G1, Squares::G1);
+        assert!(short.castling());
+        assert!(board.make_move(short, &mg));
+        assert!(board.bb_pieces[Sides::WHITE][Pieces::KING] & BB_SQUARES[Squares::G1] > 0);
+        assert!(board.bb_pieces[Sides::WHITE][Pieces::ROOK] & BB_SQUARES[Squares::F1] > 0);
+        assert_eq!(board.state.castling, Castling::BK | Castling::BQ);
+        board.unmake_move();
+        assert_eq!(board.bb_pieces, original.bb_pieces);
+
+        // Castling long moves the king to c1 and the rook from b1 to d1.
+        let long = find_move(&board, &mg, Squares::G1, Squares::C1);
+        assert!(board.make_move(long, &mg));
+        assert!(board.bb_pieces[Sides::WHITE][Pieces::ROOK] & BB_SQUARES[Squares::D1] > 0);
+        assert!(board.bb_pieces[Sides::WHITE][Pieces::ROOK] & BB_SQUARES[Squares::B1] == 0);
+        assert_eq!(board.state.zobrist_key, board.init_zobrist_key());
+    }
+
+    #[test]
+    fn test_make_move_double_step_sets_en_passant() {
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let mg = MoveGenerator::new();
+
+        let mv = find_move(&board, &mg, Squares::E2, Squares::E4);
+        assert!(mv.double_step());
+        assert!(board.make_move(mv, &mg));
+        assert_eq!(board.state.en_passant, Some(Squares::E3 as u8));
+    }
+
+    #[test]
+    fn test_make_move_rejects_move_into_check() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+
+        let mv = find_move(&board, &mg, Squares::E1, Squares::E2);
+        let original = board.clone();
+        assert!(!board.make_move(mv, &mg));
+        assert_eq!(board.bb_pieces, original.bb_pieces);
+        assert_eq!(board.current_side(), Sides::WHITE);
+    }
+}
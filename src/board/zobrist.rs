@@ -0,0 +1,102 @@
+use crate::board::defs::{NrOf, Piece, Side, Sides, Square};
+
+/// The random numbers used to create a Zobrist hash key of a position.
+///
+/// Every (side, piece, square) combination, every set of castling permissions, every en-passant
+/// square and the side to move get their own random number. The key of a position is the XOR of
+/// all numbers that apply to it, so it can be updated incrementally when a move is played.
+///
+/// * `pieces`: One number per side, piece and square.
+/// * `castling`: One number per combination of castling permissions.
+/// * `side`: XOR-ed into the key when black is to move.
+/// * `en_passant`: One number per en-passant square.
+pub struct ZobristKeys {
+    pieces: [[[u64; NrOf::SQUARES]; NrOf::PIECE_TYPES]; Sides::BOTH],
+    castling: [u64; 16],
+    side: u64,
+    en_passant: [u64; NrOf::SQUARES],
+}
+
+/// The Zobrist keys shared by every board. They are generated at compile time from a fixed seed,
+/// so the keys (and thus the hash of a position) are the same in every run.
+pub static ZOBRIST: ZobristKeys = ZobristKeys::new(0x6C61_726B_5A4F_4252);
+
+impl ZobristKeys {
+    /// Generates all keys from the given seed.
+    ///
+    /// * `seed`: The seed for the pseudo-random number generator.
+    const fn new(seed: u64) -> Self {
+        let mut state = seed;
+        let mut keys = Self {
+            pieces: [[[0; NrOf::SQUARES]; NrOf::PIECE_TYPES]; Sides::BOTH],
+            castling: [0; 16],
+            side: 0,
+            en_passant: [0; NrOf::SQUARES],
+        };
+
+        let mut side = 0;
+        while side < Sides::BOTH {
+            let mut piece = 0;
+            while piece < NrOf::PIECE_TYPES {
+                let mut square = 0;
+                while square < NrOf::SQUARES {
+                    state = splitmix64(state);
+                    keys.pieces[side][piece][square] = state;
+                    square += 1;
+                }
+                piece += 1;
+            }
+            side += 1;
+        }
+
+        let mut i = 0;
+        while i < 16 {
+            state = splitmix64(state);
+            keys.castling[i] = state;
+            i += 1;
+        }
+
+        let mut square = 0;
+        while square < NrOf::SQUARES {
+            state = splitmix64(state);
+            keys.en_passant[square] = state;
+            square += 1;
+        }
+
+        keys.side = splitmix64(state);
+        keys
+    }
+
+    /// The key for a piece of the given side on the given square.
+    pub fn piece(&self, side: Side, piece: Piece, square: Square) -> u64 {
+        self.pieces[side][piece][square]
+    }
+
+    /// The key for a set of castling permissions.
+    pub fn castling(&self, permissions: u8) -> u64 {
+        self.castling[permissions as usize]
+    }
+
+    /// The key for the side to move, only used when black is to move.
+    pub fn side(&self) -> u64 {
+        self.side
+    }
+
+    /// The key for an en-passant square; no key is used when there is no en-passant square.
+    pub fn en_passant(&self, en_passant: Option<u8>) -> u64 {
+        match en_passant {
+            Some(square) => self.en_passant[square as usize],
+            None => 0,
+        }
+    }
+}
+
+/// A small, fast pseudo-random number generator that can be evaluated at compile time.
+///
+/// See: https://prng.di.unimi.it/splitmix64.c
+pub const fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,328 @@
+pub mod builder;
+mod keys;
+
+use std::{fs, io, path::Path};
+
+use rand::Rng;
+
+use crate::{
+    board::{
+        defs::{Pieces, SQUARE_NAME},
+        Board,
+    },
+    movegen::{
+        defs::{Move, MoveType},
+        movelist::MoveList,
+        MoveGenerator,
+    },
+};
+
+/// The size of an entry in a Polyglot book file, in bytes.
+pub const ENTRY_SIZE: usize = 16;
+
+/// The promotion pieces in the order of their Polyglot numbers, starting at 1.
+const PROMOTIONS: [usize; 4] = [Pieces::KNIGHT, Pieces::BISHOP, Pieces::ROOK, Pieces::QUEEN];
+
+/// How a move is chosen when the book has several moves for a position.
+///
+/// * `Best`: Always the move with the highest weight.
+/// * `Weighted`: A random move, with a chance in proportion to its weight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BookSelection {
+    Best,
+    #[default]
+    Weighted,
+}
+
+/// An entry of a Polyglot book: a move that can be played in a position. Entries are stored as
+/// 16 bytes, big-endian.
+///
+/// * `key`: The Polyglot key of the position, see [`Board::polyglot_key`].
+/// * `mv`: The move, encoded as in [`encode_move`].
+/// * `weight`: How good the move is, compared to the other moves of the position.
+/// * `learn`: Used by some programs to learn from their games; not used here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    /// Reads an entry as it is stored in a book file.
+    ///
+    /// * `bytes`: The 16 bytes of the entry.
+    pub fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> Self {
+        let mut key = [0; 8];
+        key.copy_from_slice(&bytes[0..8]);
+        Self {
+            key: u64::from_be_bytes(key),
+            mv: u16::from_be_bytes([bytes[8], bytes[9]]),
+            weight: u16::from_be_bytes([bytes[10], bytes[11]]),
+            learn: u32::from_be_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+        }
+    }
+
+    /// The entry as it is stored in a book file.
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// An opening book in the Polyglot format, which is supported by many engines and GUIs.
+///
+/// * `entries`: The entries, sorted by key, and by weight from high to low for the same key.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    /// Creates a book from its entries, in any order.
+    ///
+    /// * `entries`: The entries of the book.
+    pub fn new(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)));
+        Self { entries }
+    }
+
+    /// Reads a book file.
+    ///
+    /// * `path`: The path of the file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_bytes(&fs::read(path)?))
+    }
+
+    /// Reads a book from the contents of a book file. Bytes at the end that do not make up a
+    /// whole entry are ignored.
+    ///
+    /// * `bytes`: The contents of the file.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .filter_map(|chunk| chunk.try_into().ok())
+            .map(BookEntry::from_bytes)
+            .collect();
+        Self::new(entries)
+    }
+
+    /// Writes the book in the Polyglot format.
+    ///
+    /// * `writer`: Where to write the book.
+    pub fn write(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        for entry in &self.entries {
+            writer.write_all(&entry.to_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// The number of entries in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// True when the book has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries for a position, found by binary search.
+    ///
+    /// * `key`: The Polyglot key of the position.
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = start + self.entries[start..].partition_point(|entry| entry.key == key);
+        &self.entries[start..end]
+    }
+
+    /// The book moves of the position with their weights, best first. Entries with a move that
+    /// is not legal in the position are left out.
+    ///
+    /// * `board`: The position.
+    /// * `mg`: The move generator, used to find the legal moves.
+    pub fn moves(&self, board: &mut Board, mg: &MoveGenerator) -> Vec<(Move, u16)> {
+        let entries = self.entries(board.polyglot_key());
+        if entries.is_empty() {
+            return Vec::new();
+        }
+
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(board, &mut legal, MoveType::All);
+        entries
+            .iter()
+            .filter_map(|entry| {
+                legal
+                    .iter()
+                    .find(|mv| encode_move(board, **mv) == entry.mv)
+                    .map(|mv| (*mv, entry.weight))
+            })
+            .collect()
+    }
+
+    /// Chooses a book move for the position. Returns None if the book has no move for it. With
+    /// weighted selection, moves with weight 0 are only played when all moves have weight 0.
+    ///
+    /// * `board`: The position.
+    /// * `mg`: The move generator.
+    /// * `selection`: How to choose between several moves.
+    /// * `rng`: The random number generator for weighted selection.
+    pub fn choose(
+        &self,
+        board: &mut Board,
+        mg: &MoveGenerator,
+        selection: BookSelection,
+        rng: &mut impl Rng,
+    ) -> Option<Move> {
+        let moves = self.moves(board, mg);
+        let (best, _) = *moves.first()?;
+        let total: u32 = moves.iter().map(|(_, weight)| u32::from(*weight)).sum();
+
+        if selection == BookSelection::Best || total == 0 {
+            return Some(best);
+        }
+
+        let mut pick = rng.gen_range(0..total);
+        for (mv, weight) in moves {
+            match pick.checked_sub(u32::from(weight)) {
+                Some(rest) => pick = rest,
+                None => return Some(mv),
+            }
+        }
+        Some(best)
+    }
+}
+
+/// Encodes a move as in a Polyglot book: the destination square in bits 0-5, the origin square
+/// in bits 6-11, and the promotion piece in bits 12-14 (1 for a knight up to 4 for a queen).
+/// Castling is encoded as the king moving to the square of its rook, such as `e1h1`.
+///
+/// * `board`: The position the move is played in, for the square of the castling rook.
+/// * `mv`: The move to encode.
+pub fn encode_move(board: &Board, mv: Move) -> u16 {
+    let to = if mv.castling() {
+        board.castling_rook_squares(mv.to()).0
+    } else {
+        mv.to()
+    };
+    let promotion = PROMOTIONS
+        .iter()
+        .position(|&piece| mv.is_promotion() && piece == mv.promoted())
+        .map_or(0, |index| index + 1);
+
+    (to | mv.from() << 6 | promotion << 12) as u16
+}
+
+/// Formats a move of a book entry in long algebraic notation, such as `e1h1` or `e7e8q`, for
+/// when it can not be matched to a legal move.
+///
+/// * `mv`: The encoded move.
+pub fn format_move(mv: u16) -> String {
+    let mv = usize::from(mv);
+    let promotion = match mv >> 12 & 7 {
+        1 => "n",
+        2 => "b",
+        3 => "r",
+        4 => "q",
+        _ => "",
+    };
+    format!(
+        "{}{}{promotion}",
+        SQUARE_NAME[mv >> 6 & 63],
+        SQUARE_NAME[mv & 63]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use crate::{board::Board, movegen::MoveGenerator};
+
+    use super::{encode_move, format_move, Book, BookEntry, BookSelection, ENTRY_SIZE};
+
+    const START_KEY: u64 = 0x463b96181691fc9c;
+
+    fn entry(key: u64, mv: u16, weight: u16) -> BookEntry {
+        BookEntry {
+            key,
+            mv,
+            weight,
+            learn: 0,
+        }
+    }
+
+    #[test]
+    fn test_encode_moves() {
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+        board
+            .fen_read(Some("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1"))
+            .unwrap();
+
+        for text in ["e1g1", "e1c1", "b7a8q", "b7b8n", "a1a7"] {
+            let mv = board.parse_uci_move(text, &mg).unwrap();
+            let expected = match text {
+                "e1g1" => "e1h1",
+                "e1c1" => "e1a1",
+                _ => text,
+            };
+            assert_eq!(format_move(encode_move(&board, mv)), expected);
+        }
+
+        let bytes = entry(START_KEY, 0x031c, 7).to_bytes();
+        assert_eq!(bytes.len(), ENTRY_SIZE);
+        assert_eq!(
+            &bytes[..10],
+            &[0x46, 0x3b, 0x96, 0x18, 0x16, 0x91, 0xfc, 0x9c, 3, 0x1c]
+        );
+        assert_eq!(BookEntry::from_bytes(&bytes), entry(START_KEY, 0x031c, 7));
+    }
+
+    #[test]
+    fn test_probe_and_choose() {
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let e4 = board.parse_uci_move("e2e4", &mg).unwrap();
+        let d4 = board.parse_uci_move("d2d4", &mg).unwrap();
+
+        // An illegal move (e2e5) is skipped; entries of other positions are not found.
+        let book = Book::new(vec![
+            entry(START_KEY + 1, encode_move(&board, e4), 50),
+            entry(START_KEY, encode_move(&board, d4), 10),
+            entry(START_KEY, 0x0324, 90),
+            entry(START_KEY, encode_move(&board, e4), 30),
+            entry(START_KEY - 1, encode_move(&board, d4), 50),
+        ]);
+        let mut bytes = Vec::new();
+        book.write(&mut bytes).unwrap();
+        let book = Book::from_bytes(&bytes);
+
+        assert_eq!(book.len(), 5);
+        assert_eq!(book.entries(START_KEY).len(), 3);
+        assert_eq!(book.moves(&mut board, &mg), vec![(e4, 30), (d4, 10)]);
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        let best = book.choose(&mut board, &mg, BookSelection::Best, &mut rng);
+        assert_eq!(best, Some(e4));
+
+        let picks: Vec<_> = (0..200)
+            .filter_map(|_| book.choose(&mut board, &mg, BookSelection::Weighted, &mut rng))
+            .collect();
+        let e4_count = picks.iter().filter(|&&mv| mv == e4).count();
+        assert_eq!(picks.len(), 200);
+        assert!((110..190).contains(&e4_count), "{e4_count}");
+
+        board.make_move(e4, &mg);
+        assert_eq!(
+            book.choose(&mut board, &mg, BookSelection::Best, &mut rng),
+            None
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::{
+    board::{defs::Sides, fen::FenError, Board},
+    movegen::MoveGenerator,
+    pgn::defs::{Game, GameResult},
+};
+
+use super::{encode_move, Book, BookEntry};
+
+/// How often a move was played in a position, and how well it did.
+///
+/// * `games`: The number of times the move was played.
+/// * `score`: Two points for each win of the side that played the move, and one for each draw
+///   or game without a result.
+#[derive(Clone, Copy, Debug, Default)]
+struct MoveStats {
+    games: u32,
+    score: u32,
+}
+
+/// Collects the moves of a set of games into a Polyglot book.
+///
+/// * `max_ply`: Only the first this many half-moves of each game are added.
+/// * `min_games`: A move must have been played at least this often to be in the book.
+/// * `moves`: The statistics of each move, by position key and encoded move.
+pub struct BookBuilder {
+    max_ply: usize,
+    min_games: u32,
+    moves: HashMap<(u64, u16), MoveStats>,
+}
+
+impl BookBuilder {
+    /// Creates an empty builder.
+    ///
+    /// * `max_ply`: Only the first this many half-moves of each game are added.
+    /// * `min_games`: A move must have been played at least this often to be in the book.
+    pub fn new(max_ply: usize, min_games: u32) -> Self {
+        Self {
+            max_ply,
+            min_games,
+            moves: HashMap::new(),
+        }
+    }
+
+    /// Adds the moves of the main line of a game.
+    ///
+    /// * `game`: The game to add.
+    /// * `mg`: The move generator, used to replay the game.
+    pub fn add_game(&mut self, game: &Game, mg: &MoveGenerator) -> Result<(), FenError> {
+        let mut board = Board::new();
+        board.fen_read(game.fen())?;
+
+        for pgn_move in game.moves.iter().take(self.max_ply) {
+            let key = board.polyglot_key();
+            let encoded = encode_move(&board, pgn_move.mv);
+            let score = match (game.result, board.current_side()) {
+                (GameResult::WhiteWins, Sides::WHITE) | (GameResult::BlackWins, Sides::BLACK) => 2,
+                (GameResult::Draw | GameResult::Unknown, _) => 1,
+                _ => 0,
+            };
+
+            let stats = self.moves.entry((key, encoded)).or_default();
+            stats.games += 1;
+            stats.score += score;
+
+            if !board.make_move(pgn_move.mv, mg) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the book. The weight of a move is its score, scaled down for all moves if the
+    /// highest score does not fit. Moves that were played too rarely, or that only lost, are
+    /// left out.
+    pub fn build(self) -> Book {
+        let kept: Vec<((u64, u16), MoveStats)> = self
+            .moves
+            .into_iter()
+            .filter(|(_, stats)| stats.games >= self.min_games && stats.score > 0)
+            .collect();
+        let max_score = kept.iter().map(|(_, stats)| stats.score).max().unwrap_or(0);
+        let scale = |score: u32| match max_score > u32::from(u16::MAX) {
+            true => (u64::from(score) * u64::from(u16::MAX) / u64::from(max_score)).max(1) as u16,
+            false => score as u16,
+        };
+
+        let entries = kept
+            .into_iter()
+            .map(|((key, mv), stats)| BookEntry {
+                key,
+                mv,
+                weight: scale(stats.score),
+                learn: 0,
+            })
+            .collect();
+        Book::new(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::Board, movegen::MoveGenerator, pgn::reader::PgnReader};
+
+    use super::BookBuilder;
+
+    #[test]
+    fn test_build_book() {
+        let pgn = "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n\n\
+                   [Result \"0-1\"]\n\n1. e4 c5 2. Nf3 d6 0-1\n\n\
+                   [Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2\n\n\
+                   [Result \"0-1\"]\n\n1. e4 e5 2. Bc4 0-1\n";
+        let mg = MoveGenerator::new();
+        let mut builder = BookBuilder::new(3, 2);
+        for game in PgnReader::new(pgn.as_bytes()) {
+            builder.add_game(&game.unwrap(), &mg).unwrap();
+        }
+        let book = builder.build();
+
+        // 1. e4 was played in three games and won one, 1... e5 in two games and won one. All
+        // other moves were played only once, or after the third half-move.
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let e4 = board.parse_uci_move("e2e4", &mg).unwrap();
+        assert_eq!(book.moves(&mut board, &mg), vec![(e4, 2)]);
+
+        board.make_move(e4, &mg);
+        let e5 = board.parse_uci_move("e7e5", &mg).unwrap();
+        assert_eq!(book.moves(&mut board, &mg), vec![(e5, 2)]);
+        assert_eq!(book.len(), 2);
+    }
+}
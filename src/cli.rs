@@ -0,0 +1,69 @@
+mod bench;
+mod book;
+mod epd;
+mod solve;
+mod tbgen;
+mod wdlfit;
+
+use std::fmt::Display;
+
+use crate::{board::fen::FenError, engine::Engine};
+
+/// The errors that can occur when running a subcommand.
+///
+/// * `UnknownCommand`: There is no subcommand with this name.
+/// * `MissingArgument`: A required argument was not given.
+/// * `InvalidArgument`: An argument could not be read.
+/// * `Io`: A file could not be read.
+/// * `Fen`: The FEN string could not be read.
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    InvalidArgument(String),
+    Io(String),
+    Fen(FenError),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownCommand(command) => write!(f, "Unknown command: {command}"),
+            Self::MissingArgument(argument) => write!(f, "Missing argument: {argument}"),
+            Self::InvalidArgument(argument) => write!(f, "Invalid argument: {argument}"),
+            Self::Io(error) => write!(f, "Read error: {error}"),
+            Self::Fen(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl From<FenError> for CliError {
+    fn from(error: FenError) -> Self {
+        Self::Fen(error)
+    }
+}
+
+/// Runs the subcommand given on the command line. Without a subcommand the engine starts in UCI
+/// mode instead, so this is not called. The `xboard` subcommand starts the engine in XBoard
+/// mode.
+///
+/// * `args`: The command line arguments, without the name of the program.
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    let Some((command, args)) = args.split_first() else {
+        return Err(CliError::MissingArgument("command"));
+    };
+
+    match command.as_str() {
+        "bench" => bench::run(args),
+        "book" => book::run(args),
+        "epd" => epd::run(args),
+        "solve" => solve::run(args),
+        "tbgen" => tbgen::run(args),
+        "wdlfit" => wdlfit::run(args),
+        "xboard" => {
+            Engine::new().run_xboard();
+            Ok(())
+        }
+        _ => Err(CliError::UnknownCommand(command.clone())),
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+};
+
+use crate::{book::builder::BookBuilder, movegen::MoveGenerator, pgn::reader::PgnReader};
+
+use super::CliError;
+
+/// The number of half-moves of each game that are added to a book, unless `--depth` is given.
+const DEFAULT_BOOK_DEPTH: usize = 20;
+
+/// How often a move must have been played to be added to a book, unless `--min-games` is given.
+const DEFAULT_MIN_GAMES: u32 = 3;
+
+/// The options of `lark book build`.
+///
+/// * `depth`: The number of half-moves of each game that are added.
+/// * `min_games`: How often a move must have been played to be added.
+#[derive(Debug, PartialEq, Eq)]
+struct BuildOptions {
+    depth: usize,
+    min_games: u32,
+}
+
+/// `lark book build <pgn> <out.bin> [--depth <plies>] [--min-games <n>]`: creates a Polyglot
+/// opening book from the games of a PGN file. Games that can not be read are skipped.
+///
+/// * `args`: The arguments after `book`.
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    match args.split_first() {
+        Some((command, args)) if command == "build" => build(args),
+        Some((command, _)) => Err(CliError::UnknownCommand(format!("book {command}"))),
+        None => Err(CliError::MissingArgument("book command")),
+    }
+}
+
+/// Builds the book, and reports how many games and entries it has.
+///
+/// * `args`: The arguments after `build`.
+fn build(args: &[String]) -> Result<(), CliError> {
+    let [pgn_path, book_path, options @ ..] = args else {
+        return Err(CliError::MissingArgument("file"));
+    };
+    let options = parse_options(options)?;
+    let io_error = |error: std::io::Error| CliError::Io(error.to_string());
+
+    let mg = MoveGenerator::new();
+    let mut builder = BookBuilder::new(options.depth, options.min_games);
+    let (mut games, mut skipped) = (0, 0);
+
+    let reader = PgnReader::new(BufReader::new(File::open(pgn_path).map_err(io_error)?));
+    for game in reader {
+        match game {
+            Ok(game) => {
+                builder.add_game(&game, &mg)?;
+                games += 1;
+            }
+            Err(error) => {
+                println!("{error}");
+                skipped += 1;
+            }
+        }
+    }
+
+    let book = builder.build();
+    let mut writer = BufWriter::new(File::create(book_path).map_err(io_error)?);
+    book.write(&mut writer).map_err(io_error)?;
+    writer.flush().map_err(io_error)?;
+
+    println!(
+        "Added {games} games ({skipped} skipped), wrote {} entries to {book_path}",
+        book.len()
+    );
+    Ok(())
+}
+
+/// Reads the options after the file names.
+///
+/// * `options`: The options, as `--depth <plies>` and `--min-games <n>`.
+fn parse_options(options: &[String]) -> Result<BuildOptions, CliError> {
+    let mut build_options = BuildOptions {
+        depth: DEFAULT_BOOK_DEPTH,
+        min_games: DEFAULT_MIN_GAMES,
+    };
+    let mut options = options.iter();
+
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or(CliError::MissingArgument("option value"))?;
+        let invalid = || CliError::InvalidArgument(value.clone());
+
+        match option.as_str() {
+            "--depth" => build_options.depth = value.parse().map_err(|_| invalid())?,
+            "--min-games" => {
+                build_options.min_games =
+                    value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?
+            }
+            _ => return Err(CliError::InvalidArgument(option.clone())),
+        }
+    }
+
+    Ok(build_options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_options, run, BuildOptions, DEFAULT_BOOK_DEPTH, DEFAULT_MIN_GAMES};
+    use crate::cli::CliError;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(
+            parse_options(&[]),
+            Ok(BuildOptions {
+                depth: DEFAULT_BOOK_DEPTH,
+                min_games: DEFAULT_MIN_GAMES,
+            })
+        );
+        assert_eq!(
+            parse_options(&args("--min-games 5 --depth 12")),
+            Ok(BuildOptions {
+                depth: 12,
+                min_games: 5,
+            })
+        );
+        assert!(parse_options(&args("--min-games 0")).is_err());
+        assert!(parse_options(&args("--depth")).is_err());
+        assert_eq!(
+            run(&args("probe book.bin")),
+            Err(CliError::UnknownCommand(String::from("book probe")))
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    fs,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use crate::{
+    board::{epd::Epd, Board},
+    movegen::{defs::Move, MoveGenerator},
+    search::{
+        defs::{moves_to_mate, SearchLimits, SearchOptions},
+        transposition::{TranspositionTable, DEFAULT_HASH_SIZE},
+        Search, SearchShared,
+    },
+};
+
+use super::CliError;
+
+/// The time each position is searched when neither a time nor a depth is given.
+const DEFAULT_EPD_TIME: Duration = Duration::from_secs(1);
+
+/// The outcome of a position of a test suite.
+///
+/// * `Solved`: The engine found a best move, avoided the moves to avoid, or found the mate.
+/// * `Failed`: The engine did not.
+/// * `Untested`: The record has no `bm`, `am` or `dm` operation to check the engine against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Verdict {
+    Solved,
+    Failed,
+    Untested,
+}
+
+/// `lark epd <file> [--time <t>] [--depth <d>]`: searches each position of an EPD test suite,
+/// such as WAC or STS, and reports how many were solved. The time may be given as `1s`,
+/// `500ms` or a number of seconds; without a time or depth every position gets one second.
+///
+/// * `args`: The arguments after `epd`.
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    let Some((path, options)) = args.split_first() else {
+        return Err(CliError::MissingArgument("file"));
+    };
+    let limits = parse_limits(options)?;
+    let text = fs::read_to_string(path).map_err(|error| CliError::Io(error.to_string()))?;
+
+    let shared = SearchShared::new(
+        Arc::new(MoveGenerator::new()),
+        Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE)),
+        Arc::new(AtomicBool::new(false)),
+    );
+    let (mut solved, mut failed) = (0, 0);
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let epd = match Epd::parse(line) {
+            Ok(epd) => epd,
+            Err(error) => {
+                println!("Line {}: {error}", index + 1);
+                failed += 1;
+                continue;
+            }
+        };
+        let name = epd
+            .id()
+            .map_or_else(|| format!("line {}", index + 1), String::from);
+
+        let mut board = Board::new();
+        board.fen_read(Some(&epd.fen()))?;
+        shared.tt.clear();
+        let mut search = Search::new(
+            board.clone(),
+            &shared,
+            limits.clone(),
+            SearchOptions {
+                threads: 1,
+                multipv: 1,
+                ..Default::default()
+            },
+        );
+        let result = search.run(&mut |_| {});
+
+        let played = match result.best_move {
+            Some(mv) => board.move_to_san(mv, &shared.mg),
+            None => String::from("(none)"),
+        };
+        let verdict = match judge(
+            &epd,
+            &mut board,
+            &shared.mg,
+            result.best_move,
+            result.summary.score,
+        ) {
+            Ok(verdict) => verdict,
+            Err(error) => {
+                println!("{name}: {error}");
+                failed += 1;
+                continue;
+            }
+        };
+
+        match verdict {
+            Verdict::Solved => solved += 1,
+            Verdict::Failed => failed += 1,
+            Verdict::Untested => (),
+        }
+        println!(
+            "{name}: {verdict:?} ({played}, expected {})",
+            expectation(&epd)
+        );
+    }
+
+    println!("===========================");
+    println!("Solved {solved} of {}", solved + failed);
+
+    Ok(())
+}
+
+/// Reads the search limits from the options after the file name.
+///
+/// * `options`: The options, as `--time <t>` and `--depth <d>`.
+fn parse_limits(options: &[String]) -> Result<SearchLimits, CliError> {
+    let mut limits = SearchLimits::default();
+    let mut options = options.iter();
+
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or(CliError::MissingArgument("option value"))?;
+        let invalid = || CliError::InvalidArgument(value.clone());
+
+        match option.as_str() {
+            "--time" => limits.move_time = Some(parse_duration(value).ok_or_else(invalid)?),
+            "--depth" => {
+                limits.depth = Some(value.parse().ok().filter(|&d| d > 0).ok_or_else(invalid)?)
+            }
+            _ => return Err(CliError::InvalidArgument(option.clone())),
+        }
+    }
+
+    if limits.move_time.is_none() && limits.depth.is_none() {
+        limits.move_time = Some(DEFAULT_EPD_TIME);
+    }
+    Ok(limits)
+}
+
+/// Reads a duration such as `1s`, `2.5s` or `500ms`; a plain number is a number of seconds.
+///
+/// * `text`: The duration to read.
+fn parse_duration(text: &str) -> Option<Duration> {
+    if let Some(millis) = text.strip_suffix("ms") {
+        return millis.parse().ok().map(Duration::from_millis);
+    }
+    let seconds: f64 = text.strip_suffix('s').unwrap_or(text).parse().ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Checks the outcome of a search against the operations of the record: the best move must be
+/// one of the `bm` moves and none of the `am` moves, and the score must be a mate in the number
+/// of moves of `dm`.
+///
+/// * `epd`: The record of the position.
+/// * `board`: A board set up with the position.
+/// * `mg`: The move generator.
+/// * `best_move`: The move the engine chose.
+/// * `score`: The score of the move.
+fn judge(
+    epd: &Epd,
+    board: &mut Board,
+    mg: &MoveGenerator,
+    best_move: Option<Move>,
+    score: i16,
+) -> Result<Verdict, CliError> {
+    let best_moves = epd
+        .best_moves(board, mg)
+        .map_err(|error| CliError::InvalidArgument(error.to_string()))?;
+    let avoid_moves = epd
+        .avoid_moves(board, mg)
+        .map_err(|error| CliError::InvalidArgument(error.to_string()))?;
+    let direct_mate = epd.direct_mate();
+
+    if best_moves.is_empty() && avoid_moves.is_empty() && direct_mate.is_none() {
+        return Ok(Verdict::Untested);
+    }
+
+    let is_best = best_moves.is_empty() || best_move.is_some_and(|mv| best_moves.contains(&mv));
+    let is_avoided = best_move.is_none_or(|mv| !avoid_moves.contains(&mv));
+    let is_mate = direct_mate.is_none_or(|moves| moves_to_mate(score) == Some(moves as i16));
+
+    Ok(match is_best && is_avoided && is_mate {
+        true => Verdict::Solved,
+        false => Verdict::Failed,
+    })
+}
+
+/// Describes what the record expects, for the report of a position.
+///
+/// * `epd`: The record of the position.
+fn expectation(epd: &Epd) -> String {
+    let mut parts = Vec::new();
+    for opcode in ["bm", "am", "dm"] {
+        if let Some(operands) = epd.operation(opcode) {
+            parts.push(format!("{opcode} {}", operands.join(" ")));
+        }
+    }
+    match parts.is_empty() {
+        true => String::from("nothing"),
+        false => parts.join(", "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        board::{epd::Epd, Board},
+        movegen::MoveGenerator,
+        search::defs::CHECKMATE,
+    };
+
+    use super::{judge, parse_duration, parse_limits, Verdict};
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(parse_duration("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2.5"), Some(Duration::from_millis(2500)));
+        assert_eq!(parse_duration("soon"), None);
+
+        let limits = parse_limits(&[]).unwrap();
+        assert_eq!(limits.move_time, Some(Duration::from_secs(1)));
+
+        let args = [String::from("--depth"), String::from("6")];
+        let limits = parse_limits(&args).unwrap();
+        assert_eq!((limits.depth, limits.move_time), (Some(6), None));
+
+        assert!(parse_limits(&[String::from("--time")]).is_err());
+    }
+
+    #[test]
+    fn test_judge() {
+        let mg = MoveGenerator::new();
+        let epd = Epd::parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; am Ra7; dm 1;").unwrap();
+        let mut board = Board::new();
+        board.fen_read(Some(&epd.fen())).unwrap();
+
+        let mate = board.parse_san("Ra8", &mg).unwrap();
+        let other = board.parse_san("Ra7", &mg).unwrap();
+        let verdict = |mv, score, board: &mut Board| judge(&epd, board, &mg, Some(mv), score);
+
+        assert_eq!(
+            verdict(mate, CHECKMATE - 1, &mut board).unwrap(),
+            Verdict::Solved
+        );
+        assert_eq!(verdict(mate, 500, &mut board).unwrap(), Verdict::Failed);
+        assert_eq!(
+            verdict(other, CHECKMATE - 1, &mut board).unwrap(),
+            Verdict::Failed
+        );
+
+        let untested = Epd::parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - id \"x\";").unwrap();
+        assert_eq!(
+            judge(&untested, &mut board, &mg, Some(mate), 0).unwrap(),
+            Verdict::Untested
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{
+    board::Board,
+    movegen::{defs::Move, MoveGenerator},
+    search::mate::{MateOutcome, MateSearch, MAX_MATE_MOVES},
+};
+
+use super::CliError;
+
+/// `lark solve <fen> <n>`: searches for a forced mate in at most `n` moves and prints the
+/// mating line. The FEN string may be passed as one argument or as separate words.
+///
+/// * `args`: The arguments after `solve`.
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    let (moves, fen) = match args.split_last() {
+        Some((moves, fen)) if !fen.is_empty() => (moves, fen.join(" ")),
+        Some(_) => return Err(CliError::MissingArgument("n")),
+        None => return Err(CliError::MissingArgument("fen")),
+    };
+
+    let moves = match moves.parse::<u8>() {
+        Ok(moves) if (1..=MAX_MATE_MOVES).contains(&moves) => moves,
+        _ => return Err(CliError::InvalidArgument(moves.clone())),
+    };
+
+    let mut board = Board::new();
+    board.fen_read(Some(&fen))?;
+    let mg = MoveGenerator::new();
+
+    let mut search = MateSearch::new(board, &mg);
+    match search.solve(moves) {
+        MateOutcome::Found(line) => {
+            let line: Vec<String> = line.iter().map(Move::to_uci).collect();
+            println!("mate in {}: {}", line.len().div_ceil(2), line.join(" "));
+        }
+        MateOutcome::Refuted | MateOutcome::Stopped => println!("no mate in {moves}"),
+    }
+    println!(
+        "nodes {} time {}",
+        search.nodes(),
+        search.elapsed().as_millis()
+    );
+
+    Ok(())
+}
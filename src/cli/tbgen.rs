@@ -0,0 +1,137 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::{
+    dtm::{generator::Generator, DtmTable, Material, DTM_EXTENSION},
+    movegen::MoveGenerator,
+};
+
+use super::CliError;
+
+/// `lark tbgen <material> [--out <dir>]`: generates the DTM table of an endgame, such as `KRvK`
+/// or `KRPvKR`, and the tables of the endgames it turns into by captures and promotions. Tables
+/// that are already in the directory are read instead. Without `--out` the tables are written
+/// to the current directory.
+///
+/// * `args`: The arguments after `tbgen`.
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    let [name, options @ ..] = args else {
+        return Err(CliError::MissingArgument("material"));
+    };
+    let material = Material::parse(name)
+        .ok_or_else(|| CliError::InvalidArgument(name.clone()))?
+        .canonical();
+    let dir = parse_options(options)?;
+
+    let mg = MoveGenerator::new();
+    let mut generator = Generator::new(&mg);
+    build(&mut generator, &material, &dir)
+}
+
+/// Makes sure the generator has the table of a material, and the tables it depends on: they are
+/// read from their files, or generated and written.
+///
+/// * `generator`: The generator, with the tables it has so far.
+/// * `material`: The pieces of the table.
+/// * `dir`: The directory of the table files.
+fn build(generator: &mut Generator, material: &Material, dir: &Path) -> Result<(), CliError> {
+    if generator.contains(material) {
+        return Ok(());
+    }
+
+    let path = dir.join(format!("{material}.{DTM_EXTENSION}"));
+    let io_error =
+        |error: &dyn std::fmt::Display| CliError::Io(format!("{}: {error}", path.display()));
+    if path.exists() {
+        generator.add(DtmTable::open(&path).map_err(|error| io_error(&error))?);
+        println!("Read {}", path.display());
+        return Ok(());
+    }
+
+    for dependency in Generator::dependencies(material) {
+        build(generator, &dependency, dir)?;
+    }
+
+    let start = Instant::now();
+    let (table, summary) = generator
+        .generate(material)
+        .map_err(|error| CliError::InvalidArgument(format!("{material}: {error}")))?;
+
+    let mut writer = BufWriter::new(File::create(&path).map_err(|error| io_error(&error))?);
+    table.write(&mut writer).map_err(|error| io_error(&error))?;
+    writer.flush().map_err(|error| io_error(&error))?;
+
+    println!(
+        "Wrote {} in {:.1}s: {} positions, {} won and {} lost by the side to move, longest mate \
+         in {} moves, {} bytes",
+        path.display(),
+        start.elapsed().as_secs_f64(),
+        summary.positions,
+        summary.wins,
+        summary.losses,
+        summary.longest.div_ceil(2),
+        table.compressed_len()
+    );
+    generator.add(table);
+    Ok(())
+}
+
+/// Reads the options after the material.
+///
+/// * `options`: The options, as `--out <dir>`.
+fn parse_options(options: &[String]) -> Result<PathBuf, CliError> {
+    let mut dir = PathBuf::from(".");
+    let mut options = options.iter();
+
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or(CliError::MissingArgument("option value"))?;
+
+        match option.as_str() {
+            "--out" => dir = PathBuf::from(value),
+            _ => return Err(CliError::InvalidArgument(option.clone())),
+        }
+    }
+
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::run;
+    use crate::{cli::CliError, dtm::DtmTables};
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_tbgen() {
+        assert_eq!(run(&[]), Err(CliError::MissingArgument("material")));
+        assert_eq!(
+            run(&args("KQQQvKR")),
+            Err(CliError::InvalidArgument(String::from("KQQQvKR")))
+        );
+        assert_eq!(
+            run(&args("KvK --depth 3")),
+            Err(CliError::InvalidArgument(String::from("--depth")))
+        );
+
+        let dir = env::temp_dir().join(format!("lark-tbgen-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let out = format!("KvKN --out {}", dir.display());
+        assert_eq!(run(&args(&out)), Ok(()));
+        assert_eq!(run(&args(&out)), Ok(()));
+
+        let tables = DtmTables::open(dir.to_str().unwrap());
+        assert_eq!((tables.len(), tables.max_pieces()), (2, 3));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
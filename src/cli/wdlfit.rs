@@ -0,0 +1,366 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    board::{defs::MAX_MOVE_RULE, zobrist::splitmix64, Board},
+    evaluation::wdl::{self, MATERIAL_MAX, MATERIAL_MIN, MATERIAL_NORMALIZE},
+    movegen::{defs::MoveType, movelist::MoveList, MoveGenerator},
+    search::{
+        defs::{moves_to_mate, SearchLimits, SearchOptions},
+        transposition::{TranspositionTable, DEFAULT_HASH_SIZE},
+        Search, SearchShared,
+    },
+};
+
+use super::CliError;
+
+/// The number of games that are played when `--games` is not given.
+const DEFAULT_GAMES: usize = 5000;
+
+/// The number of nodes searched per move when `--nodes` is not given.
+const DEFAULT_NODES: u64 = 4000;
+
+/// Each game starts with this many random moves, so that the games are different.
+const RANDOM_PLIES: usize = 8;
+
+/// A game that is still going after this many plies counts as a draw.
+const MAX_PLIES: usize = 400;
+
+/// A material count with fewer positions than this is left out of the fit.
+const MIN_POSITIONS: u32 = 200;
+
+/// Scores are clamped to this range before fitting; beyond it the outcome hardly changes.
+const MAX_SCORE: i16 = 2000;
+
+/// `lark wdlfit [--games <n>] [--nodes <n>] [--seed <n>]`: plays games of the engine against
+/// itself, and fits the model of [`wdl::score_to_wdl`] to the scores of the positions and the
+/// outcomes of the games. The coefficients are printed in the form of `evaluation/wdl.rs`.
+///
+/// Each game starts with a few random moves and is then played with a fixed number of nodes
+/// per move. For each amount of material the parameters of the model are fitted by maximum
+/// likelihood; the polynomials are then fitted to those parameters by least squares.
+///
+/// * `args`: The arguments after `wdlfit`.
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    let (games, nodes, seed) = parse_options(args)?;
+
+    let shared = SearchShared::new(
+        Arc::new(MoveGenerator::new()),
+        Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE)),
+        Arc::new(AtomicBool::new(false)),
+    );
+    let mut state = seed;
+    let mut positions = Positions::new();
+    let mut count = 0;
+
+    for game in 1..=games {
+        count += play_game(&shared, nodes, &mut state, &mut positions);
+        if game % 100 == 0 || game == games {
+            println!("Played {game}/{games} games, {count} positions");
+        }
+    }
+
+    let (a, b) = fit(&positions).ok_or(CliError::InvalidArgument(String::from("--games")))?;
+    let format = |coefficients: [f64; 4]| {
+        let coefficients: Vec<String> = coefficients.iter().map(|c| format!("{c:.3}")).collect();
+        coefficients.join(", ")
+    };
+    println!("const WIN_RATE_A: [f64; 4] = [{}];", format(a));
+    println!("const WIN_RATE_B: [f64; 4] = [{}];", format(b));
+
+    Ok(())
+}
+
+/// The positions of the games, by material count and score: how many of them were won, drawn
+/// and lost by the side to move.
+type Positions = BTreeMap<u32, BTreeMap<i16, [u32; 3]>>;
+
+/// Plays one game and adds its positions, from the end of the random opening on, to
+/// `positions`. Positions with a mate score are left out, as the model does not cover them.
+/// Returns the number of positions that were added.
+///
+/// * `shared`: The move generator and transposition table.
+/// * `nodes`: The number of nodes searched per move.
+/// * `state`: The state of the random number generator.
+/// * `positions`: Where the positions are added.
+fn play_game(
+    shared: &SearchShared,
+    nodes: u64,
+    state: &mut u64,
+    positions: &mut Positions,
+) -> usize {
+    let mg = &shared.mg;
+    let limits = SearchLimits {
+        nodes: Some(nodes),
+        ..Default::default()
+    };
+    let mut board = Board::new();
+    board
+        .fen_read(None)
+        .expect("The starting position is valid.");
+    shared.tt.clear();
+
+    // The side to move, material and score of each searched position.
+    let mut scored = Vec::new();
+    let mut ply = 0;
+    let winner = loop {
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(&mut board, &mut legal, MoveType::All);
+        if legal.is_empty() {
+            let side = board.current_side();
+            break mg.in_check(&board).then_some(side ^ 1);
+        }
+        if ply >= MAX_PLIES
+            || board.state.half_move_clock >= MAX_MOVE_RULE
+            || board.is_repetition()
+            || board.is_insufficient_material()
+        {
+            break None;
+        }
+
+        let mv = if ply < RANDOM_PLIES {
+            *state = splitmix64(*state);
+            legal[(*state % legal.len() as u64) as usize]
+        } else {
+            shared.tt.new_search();
+            shared.nodes.store(0, Ordering::Relaxed);
+            let mut search = Search::new(
+                board.clone(),
+                shared,
+                limits.clone(),
+                SearchOptions::default(),
+            );
+            let result = search.run(&mut |_| {});
+            let score = result.summary.score;
+            if moves_to_mate(score).is_none() {
+                scored.push((board.current_side(), wdl::material_count(&board), score));
+            }
+            result.best_move.expect("The position has legal moves.")
+        };
+
+        board.make_move(mv, mg);
+        ply += 1;
+    };
+
+    let count = scored.len();
+    for (side, material, score) in scored {
+        let outcome = match winner {
+            Some(winner) if winner == side => 0,
+            Some(_) => 2,
+            None => 1,
+        };
+        let material = material.clamp(MATERIAL_MIN, MATERIAL_MAX);
+        let score = score.clamp(-MAX_SCORE, MAX_SCORE);
+        positions
+            .entry(material)
+            .or_default()
+            .entry(score)
+            .or_default()[outcome] += 1;
+    }
+    count
+}
+
+/// Fits the coefficients of the polynomials of the model to the positions, highest power first.
+/// Returns None when there are not enough positions.
+///
+/// * `positions`: The positions of the games.
+fn fit(positions: &Positions) -> Option<([f64; 4], [f64; 4])> {
+    let mut points = Vec::new();
+    for (&material, scores) in positions {
+        let count: u32 = scores.values().flatten().sum();
+        if count >= MIN_POSITIONS {
+            let (a, b) = fit_parameters(scores);
+            let x = f64::from(material) / MATERIAL_NORMALIZE;
+            points.push((x, a, b, f64::from(count)));
+        }
+    }
+
+    let a: Vec<_> = points.iter().map(|&(x, a, _, w)| (x, a, w)).collect();
+    let b: Vec<_> = points.iter().map(|&(x, _, b, w)| (x, b, w)).collect();
+    Some((fit_polynomial(&a)?, fit_polynomial(&b)?))
+}
+
+/// Finds the parameters `a` and `b` of the model for one material count that make the outcomes
+/// of the positions the most likely. A coarse search of a grid is refined around its best point.
+///
+/// * `scores`: The number of positions won, drawn and lost for each score.
+fn fit_parameters(scores: &BTreeMap<i16, [u32; 3]>) -> (f64, f64) {
+    let log_likelihood = |a: f64, b: f64| {
+        scores
+            .iter()
+            .map(|(&score, counts)| {
+                let wdl = wdl::model_wdl(score, a, b);
+                counts
+                    .iter()
+                    .zip(wdl)
+                    .map(|(&n, p)| f64::from(n) * p.max(1e-9).ln())
+                    .sum::<f64>()
+            })
+            .sum::<f64>()
+    };
+    let best = |candidates: Vec<(f64, f64)>| {
+        candidates
+            .into_iter()
+            .map(|(a, b)| (log_likelihood(a, b), a, b))
+            .max_by(|x, y| x.0.total_cmp(&y.0))
+            .map(|(_, a, b)| (a, b))
+            .expect("The grid is not empty.")
+    };
+
+    let grid = |center: (f64, f64), step: f64| {
+        let mut candidates = Vec::new();
+        for i in -10..=10 {
+            for j in -10..=10 {
+                let (a, b) = (
+                    center.0 + step * f64::from(i),
+                    center.1 + step * f64::from(j),
+                );
+                if a > 0.0 && b > 0.0 {
+                    candidates.push((a, b));
+                }
+            }
+        }
+        candidates
+    };
+
+    let mut center = (200.0, 100.0);
+    for step in [20.0, 4.0, 1.0, 0.2] {
+        center = best(grid(center, step));
+    }
+    center
+}
+
+/// Fits a polynomial of degree 3 to weighted points by least squares, and returns its
+/// coefficients, highest power first. Returns None without enough points.
+///
+/// * `points`: The points, as x, y and weight.
+fn fit_polynomial(points: &[(f64, f64, f64)]) -> Option<[f64; 4]> {
+    if points.len() < 4 {
+        return None;
+    }
+
+    // The normal equations, with the powers of x from 3 down to 0.
+    let mut matrix = [[0.0; 5]; 4];
+    for &(x, y, w) in points {
+        let powers = [x * x * x, x * x, x, 1.0];
+        for row in 0..4 {
+            for column in 0..4 {
+                matrix[row][column] += w * powers[row] * powers[column];
+            }
+            matrix[row][4] += w * powers[row] * y;
+        }
+    }
+
+    // Gaussian elimination with partial pivoting.
+    for column in 0..4 {
+        let pivot = (column..4)
+            .max_by(|&i, &j| matrix[i][column].abs().total_cmp(&matrix[j][column].abs()))?;
+        matrix.swap(column, pivot);
+        if matrix[column][column].abs() < 1e-12 {
+            return None;
+        }
+        let pivot = matrix[column];
+        for (row, values) in matrix.iter_mut().enumerate() {
+            if row != column {
+                let factor = values[column] / pivot[column];
+                for (value, p) in values.iter_mut().zip(pivot) {
+                    *value -= factor * p;
+                }
+            }
+        }
+    }
+
+    Some(std::array::from_fn(|i| matrix[i][4] / matrix[i][i]))
+}
+
+/// Reads the options of the command: the number of games, the nodes per move and the seed of
+/// the random openings.
+///
+/// * `options`: The options, as `--games <n>`, `--nodes <n>` and `--seed <n>`.
+fn parse_options(options: &[String]) -> Result<(usize, u64, u64), CliError> {
+    let (mut games, mut nodes, mut seed) = (DEFAULT_GAMES, DEFAULT_NODES, 1);
+    let mut options = options.iter();
+
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or(CliError::MissingArgument("option value"))?;
+        let invalid = || CliError::InvalidArgument(value.clone());
+
+        match option.as_str() {
+            "--games" => games = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+            "--nodes" => nodes = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+            "--seed" => seed = value.parse().map_err(|_| invalid())?,
+            _ => return Err(CliError::InvalidArgument(option.clone())),
+        }
+    }
+
+    Ok((games, nodes, seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{cli::CliError, evaluation::wdl};
+
+    use super::{fit_parameters, fit_polynomial, parse_options, DEFAULT_GAMES, DEFAULT_NODES};
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(parse_options(&[]), Ok((DEFAULT_GAMES, DEFAULT_NODES, 1)));
+        assert_eq!(
+            parse_options(&args("--nodes 500 --games 3 --seed 7")),
+            Ok((3, 500, 7))
+        );
+        assert_eq!(
+            parse_options(&args("--games 0")),
+            Err(CliError::InvalidArgument(String::from("0")))
+        );
+        assert_eq!(
+            parse_options(&args("--depth 3")),
+            Err(CliError::InvalidArgument(String::from("--depth")))
+        );
+        assert_eq!(
+            parse_options(&args("--games")),
+            Err(CliError::MissingArgument("option value"))
+        );
+    }
+
+    #[test]
+    fn test_fit_finds_the_parameters_of_the_outcomes() {
+        // The expected outcomes of a model with a = 150 and b = 70, in a large number of games.
+        let scores: BTreeMap<i16, [u32; 3]> = (-40..=40)
+            .map(|i| {
+                let score = i * 25;
+                let wdl = wdl::model_wdl(score, 150.0, 70.0);
+                (score, wdl.map(|p| (p * 100_000.0).round() as u32))
+            })
+            .collect();
+
+        let (a, b) = fit_parameters(&scores);
+        assert!((a - 150.0).abs() <= 0.5, "{a}");
+        assert!((b - 70.0).abs() <= 0.5, "{b}");
+
+        let points: Vec<_> = (0..10)
+            .map(|i| {
+                let x = f64::from(i) / 5.0;
+                (x, 2.0 * x * x * x - x + 3.0, 1.0 + f64::from(i))
+            })
+            .collect();
+        let coefficients = fit_polynomial(&points).unwrap();
+        for (c, expected) in coefficients.iter().zip([2.0, 0.0, -1.0, 3.0]) {
+            assert!((c - expected).abs() < 1e-6, "{coefficients:?}");
+        }
+        assert_eq!(fit_polynomial(&points[..3]), None);
+    }
+}
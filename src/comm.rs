@@ -0,0 +1,52 @@
+pub mod uci;
+pub mod xboard;
+
+use std::{
+    io::{self, BufRead},
+    sync::mpsc::Sender,
+    thread::{self, JoinHandle},
+};
+
+use crate::engine::defs::Information;
+
+use uci::UciCommand;
+use xboard::XBoardCommand;
+
+/// Reads commands from standard input on a separate thread, and sends them to the engine. The
+/// commands are read as UCI commands until the GUI sends `xboard`; from then on they are read as
+/// XBoard commands. When the input is closed, the engine is told to quit.
+///
+/// * `tx`: The channel to send the commands over.
+/// * `xboard`: Read XBoard commands from the start.
+pub fn read_commands(tx: Sender<Information>, mut xboard: bool) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            let information = if xboard {
+                Information::XBoard(xboard::parse(&line))
+            } else if line.trim() == "xboard" {
+                xboard = true;
+                Information::XBoard(XBoardCommand::XBoard)
+            } else {
+                Information::Comm(uci::parse(&line))
+            };
+            let quit = matches!(
+                information,
+                Information::Comm(UciCommand::Quit) | Information::XBoard(XBoardCommand::Quit)
+            );
+
+            if tx.send(information).is_err() || quit {
+                return;
+            }
+        }
+
+        _ = tx.send(match xboard {
+            true => Information::XBoard(XBoardCommand::Quit),
+            false => Information::Comm(UciCommand::Quit),
+        });
+    })
+}
@@ -0,0 +1,480 @@
+use std::time::Duration;
+
+use crate::{
+    movegen::defs::Move,
+    search::{
+        defs::{moves_to_mate, CurrentMove, GameTime, SearchLimits, SearchResult, SearchSummary},
+        transposition::Bound,
+    },
+};
+
+/// The commands the GUI can send to the engine.
+///
+/// * `Uci`: Identify the engine and list its options.
+/// * `IsReady`: Reply with `readyok` once all earlier commands are processed.
+/// * `UciNewGame`: The next position is from a new game.
+/// * `Position`: Set up a position from a FEN string (or the starting position) and moves.
+/// * `Go`: Start searching the current position within the given limits, considering only the
+///   given moves at the root (`searchmoves`), or all moves when there are none.
+/// * `Stop`: Stop searching as soon as possible, and report the best move.
+/// * `PonderHit`: The opponent played the expected move; the ponder search continues as a
+///   normal search.
+/// * `Quit`: Quit the engine.
+/// * `SetOption`: Change the value of an engine option.
+/// * `Unknown`: A command that is not understood, which is ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UciCommand {
+    Uci,
+    IsReady,
+    UciNewGame,
+    Position {
+        fen: Option<String>,
+        moves: Vec<String>,
+    },
+    Go {
+        limits: SearchLimits,
+        search_moves: Vec<String>,
+    },
+    Stop,
+    PonderHit,
+    Quit,
+    SetOption {
+        name: String,
+        value: Option<String>,
+    },
+    Unknown(String),
+}
+
+/// Parses a line sent by the GUI into a [`UciCommand`].
+///
+/// * `line`: The line to parse.
+pub fn parse(line: &str) -> UciCommand {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+
+    match tokens.first() {
+        Some(&"uci") => UciCommand::Uci,
+        Some(&"isready") => UciCommand::IsReady,
+        Some(&"ucinewgame") => UciCommand::UciNewGame,
+        Some(&"position") => parse_position(&tokens[1..]),
+        Some(&"go") => parse_go(&tokens[1..]),
+        Some(&"stop") => UciCommand::Stop,
+        Some(&"ponderhit") => UciCommand::PonderHit,
+        Some(&"quit") => UciCommand::Quit,
+        Some(&"setoption") => parse_setoption(&tokens[1..]),
+        _ => UciCommand::Unknown(line.trim().to_string()),
+    }
+}
+
+/// The keywords that can follow `go`.
+const GO_KEYWORDS: [&str; 12] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
+
+/// Parses `position [startpos | fen <fen>] [moves <move> ...]`.
+///
+/// * `tokens`: The tokens after `position`.
+fn parse_position(tokens: &[&str]) -> UciCommand {
+    let moves_index = tokens.iter().position(|t| *t == "moves");
+    let (setup, moves) = match moves_index {
+        Some(i) => (&tokens[..i], &tokens[i + 1..]),
+        None => (tokens, &[][..]),
+    };
+
+    let fen = match setup.first() {
+        Some(&"fen") => Some(setup[1..].join(" ")),
+        _ => None,
+    };
+
+    UciCommand::Position {
+        fen,
+        moves: moves.iter().map(|mv| mv.to_string()).collect(),
+    }
+}
+
+/// Parses the limits of `go`. Values that can not be read are ignored; negative times (sent by
+/// some GUIs when the clock has run out) are treated as zero. The moves after `searchmoves` run
+/// until the next keyword.
+///
+/// * `tokens`: The tokens after `go`.
+fn parse_go(tokens: &[&str]) -> UciCommand {
+    let mut limits = SearchLimits::default();
+    let mut search_moves = Vec::new();
+    let mut game_time = GameTime::default();
+    let mut has_game_time = false;
+
+    let millis = |value: Option<&&str>| -> Option<Duration> {
+        let value = value?.parse::<i64>().ok()?;
+        Some(Duration::from_millis(value.max(0) as u64))
+    };
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let value = tokens.get(i + 1);
+        match tokens[i] {
+            "infinite" | "ponder" => {
+                match tokens[i] {
+                    "infinite" => limits.infinite = true,
+                    _ => limits.ponder = true,
+                }
+                i += 1;
+                continue;
+            }
+            "searchmoves" => {
+                let moves: Vec<String> = tokens[i + 1..]
+                    .iter()
+                    .take_while(|token| !GO_KEYWORDS.contains(token))
+                    .map(|mv| mv.to_string())
+                    .collect();
+                i += 1 + moves.len();
+                search_moves.extend(moves);
+                continue;
+            }
+            "depth" => limits.depth = value.and_then(|v| v.parse().ok()),
+            "nodes" => limits.nodes = value.and_then(|v| v.parse().ok()),
+            "mate" => limits.mate = value.and_then(|v| v.parse().ok()),
+            "movetime" => limits.move_time = millis(value),
+            "wtime" | "btime" | "winc" | "binc" => {
+                if let Some(time) = millis(value) {
+                    has_game_time = true;
+                    match tokens[i] {
+                        "wtime" => game_time.wtime = time,
+                        "btime" => game_time.btime = time,
+                        "winc" => game_time.winc = time,
+                        _ => game_time.binc = time,
+                    }
+                }
+            }
+            "movestogo" => {
+                game_time.moves_to_go = value.and_then(|v| v.parse().ok());
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+
+    if has_game_time {
+        limits.game_time = Some(game_time);
+    }
+
+    UciCommand::Go {
+        limits,
+        search_moves,
+    }
+}
+
+/// Parses `setoption name <name> [value <value>]`. Both the name and the value may contain
+/// spaces.
+///
+/// * `tokens`: The tokens after `setoption`.
+fn parse_setoption(tokens: &[&str]) -> UciCommand {
+    let value_index = tokens.iter().position(|t| *t == "value");
+    let name_end = value_index.unwrap_or(tokens.len());
+    let name_start = usize::from(tokens.first() == Some(&"name"));
+
+    let name = tokens[name_start.min(name_end)..name_end].join(" ");
+    let value = value_index.map(|i| tokens[i + 1..].join(" "));
+
+    UciCommand::SetOption { name, value }
+}
+
+/// Formats a score as `cp <centipawns>` or `mate <moves>`.
+///
+/// * `score`: The score, from the side to move's point of view.
+pub fn score(score: i16) -> String {
+    match moves_to_mate(score) {
+        Some(moves) => format!("mate {moves}"),
+        None => format!("cp {score}"),
+    }
+}
+
+/// Formats the `info` line for a completed iteration, or for a search of an aspiration window
+/// that failed, in which case the score is marked as a bound.
+///
+/// * `summary`: The outcome of the iteration.
+/// * `notation`: Formats a move, such as [`Move::to_uci`].
+pub fn info(summary: &SearchSummary, notation: &dyn Fn(&Move) -> String) -> String {
+    let pv: Vec<String> = summary.pv.iter().map(notation).collect();
+    let bound = match summary.bound {
+        Bound::Exact => "",
+        Bound::Lower => " lowerbound",
+        Bound::Upper => " upperbound",
+    };
+    let wdl = summary.wdl.map_or(String::new(), |wdl| {
+        format!(" wdl {} {} {}", wdl.win, wdl.draw, wdl.loss)
+    });
+
+    format!(
+        "info depth {} seldepth {} multipv {} score {}{}{} nodes {} nps {} hashfull {} tbhits {} \
+         time {} pv {}",
+        summary.depth,
+        summary.seldepth,
+        summary.multipv,
+        score(summary.score),
+        bound,
+        wdl,
+        summary.nodes,
+        summary.nps(),
+        summary.hashfull,
+        summary.tbhits,
+        summary.time.as_millis(),
+        pv.join(" ")
+    )
+}
+
+/// Formats the `info` line with the move the root search is working on.
+///
+/// * `current`: The move that is being searched.
+/// * `notation`: Formats a move, such as [`Move::to_uci`].
+pub fn currmove(current: &CurrentMove, notation: &dyn Fn(&Move) -> String) -> String {
+    format!(
+        "info depth {} currmove {} currmovenumber {}",
+        current.depth,
+        notation(&current.mv),
+        current.number
+    )
+}
+
+/// Formats the `bestmove` line. A null move (`0000`) is sent when there is no legal move.
+///
+/// * `result`: The result of the search.
+/// * `notation`: Formats a move, such as [`Move::to_uci`].
+pub fn bestmove(result: &SearchResult, notation: &dyn Fn(&Move) -> String) -> String {
+    let best = result
+        .best_move
+        .map_or(String::from("0000"), |mv| notation(&mv));
+    match result.ponder_move {
+        Some(ponder) => format!("bestmove {best} ponder {}", notation(&ponder)),
+        None => format!("bestmove {best}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        board::defs::{Pieces, Squares},
+        movegen::defs::{Move, Shift},
+        search::{
+            defs::{CurrentMove, GameTime, SearchLimits, SearchSummary, Wdl, CHECKMATE},
+            transposition::Bound,
+        },
+    };
+
+    use super::{currmove, info, parse, score, UciCommand};
+
+    fn go(limits: SearchLimits) -> UciCommand {
+        UciCommand::Go {
+            limits,
+            search_moves: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_simple_commands() {
+        assert_eq!(parse("uci"), UciCommand::Uci);
+        assert_eq!(parse("  isready "), UciCommand::IsReady);
+        assert_eq!(parse("ucinewgame"), UciCommand::UciNewGame);
+        assert_eq!(parse("stop"), UciCommand::Stop);
+        assert_eq!(parse("ponderhit"), UciCommand::PonderHit);
+        assert_eq!(parse("quit"), UciCommand::Quit);
+        assert_eq!(
+            parse("debug on"),
+            UciCommand::Unknown(String::from("debug on"))
+        );
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(
+            parse("position startpos"),
+            UciCommand::Position {
+                fen: None,
+                moves: vec![],
+            }
+        );
+        assert_eq!(
+            parse("position startpos moves e2e4 e7e5"),
+            UciCommand::Position {
+                fen: None,
+                moves: vec![String::from("e2e4"), String::from("e7e5")],
+            }
+        );
+        assert_eq!(
+            parse("position fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4"),
+            UciCommand::Position {
+                fen: Some(String::from("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1")),
+                moves: vec![String::from("e2e4")],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_go_time_control() {
+        let expected = SearchLimits {
+            game_time: Some(GameTime {
+                wtime: Duration::from_millis(60_000),
+                btime: Duration::from_millis(0),
+                winc: Duration::from_millis(1_000),
+                binc: Duration::from_millis(1_000),
+                moves_to_go: Some(20),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parse("go wtime 60000 btime -25 winc 1000 binc 1000 movestogo 20"),
+            go(expected)
+        );
+    }
+
+    #[test]
+    fn test_parse_go_other_limits() {
+        assert_eq!(
+            parse("go infinite"),
+            go(SearchLimits {
+                infinite: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse("go mate 3"),
+            go(SearchLimits {
+                mate: Some(3),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse("go depth 6 movetime 2500"),
+            go(SearchLimits {
+                depth: Some(6),
+                move_time: Some(Duration::from_millis(2500)),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse("go ponder movetime 1000"),
+            go(SearchLimits {
+                ponder: true,
+                move_time: Some(Duration::from_millis(1000)),
+                ..Default::default()
+            })
+        );
+        assert_eq!(parse("go"), go(SearchLimits::default()));
+    }
+
+    #[test]
+    fn test_parse_go_searchmoves_and_nodes() {
+        assert_eq!(
+            parse("go searchmoves e2e4 d2d4 nodes 5000"),
+            UciCommand::Go {
+                limits: SearchLimits {
+                    nodes: Some(5000),
+                    ..Default::default()
+                },
+                search_moves: vec![String::from("e2e4"), String::from("d2d4")],
+            }
+        );
+        assert_eq!(
+            parse("go depth 3 searchmoves g1f3"),
+            UciCommand::Go {
+                limits: SearchLimits {
+                    depth: Some(3),
+                    ..Default::default()
+                },
+                search_moves: vec![String::from("g1f3")],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_setoption() {
+        assert_eq!(
+            parse("setoption name Move Overhead value 100"),
+            UciCommand::SetOption {
+                name: String::from("Move Overhead"),
+                value: Some(String::from("100")),
+            }
+        );
+        assert_eq!(
+            parse("setoption name Clear Hash"),
+            UciCommand::SetOption {
+                name: String::from("Clear Hash"),
+                value: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_score_formats_mates() {
+        assert_eq!(score(35), "cp 35");
+        assert_eq!(score(CHECKMATE - 3), "mate 2");
+        assert_eq!(score(-CHECKMATE + 2), "mate -1");
+    }
+
+    fn e2e4() -> Move {
+        let data = Pieces::PAWN
+            | Squares::E2 << Shift::FROM_SQ
+            | Squares::E4 << Shift::TO_SQ
+            | Pieces::NONE << Shift::CAPTURE
+            | Pieces::NONE << Shift::PROMOTION;
+        Move::new(data)
+    }
+
+    #[test]
+    fn test_info_line() {
+        let mut summary = SearchSummary {
+            multipv: 1,
+            depth: 9,
+            seldepth: 14,
+            score: 25,
+            bound: Bound::Lower,
+            wdl: None,
+            nodes: 200_000,
+            hashfull: 37,
+            tbhits: 0,
+            time: Duration::from_millis(100),
+            pv: vec![e2e4()],
+        };
+        assert_eq!(
+            info(&summary, &Move::to_uci),
+            "info depth 9 seldepth 14 multipv 1 score cp 25 lowerbound nodes 200000 nps 2000000 \
+             hashfull 37 tbhits 0 time 100 pv e2e4"
+        );
+
+        summary.bound = Bound::Exact;
+        summary.wdl = Some(Wdl {
+            win: 80,
+            draw: 900,
+            loss: 20,
+        });
+        assert!(info(&summary, &Move::to_uci).contains(" score cp 25 wdl 80 900 20 nodes "));
+    }
+
+    #[test]
+    fn test_currmove_line() {
+        let current = CurrentMove {
+            depth: 12,
+            mv: e2e4(),
+            number: 3,
+        };
+        assert_eq!(
+            currmove(&current, &Move::to_uci),
+            "info depth 12 currmove e2e4 currmovenumber 3"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use std::time::Duration;
+
+use crate::{
+    movegen::defs::Move,
+    search::defs::{moves_to_mate, SearchSummary},
+};
+
+/// The score XBoard expects for a mate, to which the number of moves to mate is added.
+const MATE_SCORE: i32 = 100_000;
+
+/// The commands of the XBoard protocol (also known as the Chess Engine Communication Protocol)
+/// the engine understands.
+///
+/// * `XBoard`: The GUI speaks the XBoard protocol.
+/// * `ProtoVer`: The version of the protocol; the engine replies with its features.
+/// * `New`: Start a new game from the starting position, with the engine playing Black.
+/// * `SetBoard`: Set up a position from a FEN string.
+/// * `UserMove`: The opponent played a move, in coordinate notation.
+/// * `Go`: The engine plays the side to move, and starts thinking.
+/// * `Force`: Stop thinking, and only play the moves that are received.
+/// * `MoveNow`: Stop thinking, and play the best move found so far (`?`).
+/// * `Level`: A time control of a number of moves per session (0 for the whole game), a base
+///   time and an increment per move.
+/// * `SetTime`: Think exactly this long per move (`st`).
+/// * `SetDepth`: Think at most this many plies deep (`sd`).
+/// * `Time`: The time left on the engine's clock.
+/// * `OTim`: The time left on the opponent's clock.
+/// * `Undo`: Take back one move.
+/// * `Remove`: Take back the last two moves.
+/// * `Post`: Print thinking output.
+/// * `NoPost`: Do not print thinking output.
+/// * `Result`: The game is over, with this result.
+/// * `Ping`: Reply with `pong` and the same number once all earlier commands are processed.
+/// * `Memory`: The size of the hash table, in megabytes.
+/// * `Cores`: The number of threads to search with.
+/// * `Quit`: Quit the engine.
+/// * `Ignored`: A known command without effect for this engine.
+/// * `Invalid`: A known command with arguments that can not be read.
+/// * `Unknown`: A command that is not understood.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum XBoardCommand {
+    XBoard,
+    ProtoVer(u32),
+    New,
+    SetBoard(String),
+    UserMove(String),
+    Go,
+    Force,
+    MoveNow,
+    Level {
+        moves_per_session: u32,
+        base: Duration,
+        increment: Duration,
+    },
+    SetTime(Duration),
+    SetDepth(u8),
+    Time(Duration),
+    OTim(Duration),
+    Undo,
+    Remove,
+    Post,
+    NoPost,
+    Result(String),
+    Ping(u32),
+    Memory(usize),
+    Cores(usize),
+    Quit,
+    Ignored(String),
+    Invalid(String),
+    Unknown(String),
+}
+
+/// The commands that are accepted, but have no effect.
+const IGNORED_COMMANDS: [&str; 12] = [
+    "accepted", "rejected", "random", "computer", "easy", "hard", "name", "rating", "ics", "draw",
+    "hint", "bk",
+];
+
+/// Parses a line sent by the GUI into an [`XBoardCommand`].
+///
+/// * `line`: The line to parse.
+pub fn parse(line: &str) -> XBoardCommand {
+    let line = line.trim();
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let Some((&command, args)) = tokens.split_first() else {
+        return XBoardCommand::Unknown(String::new());
+    };
+    let arg = args.first().copied().unwrap_or_default();
+    let rest = args.join(" ");
+
+    let parsed = match command {
+        "xboard" => Some(XBoardCommand::XBoard),
+        "protover" => arg.parse().ok().map(XBoardCommand::ProtoVer),
+        "new" => Some(XBoardCommand::New),
+        "setboard" => (!args.is_empty()).then_some(XBoardCommand::SetBoard(rest)),
+        "usermove" => (!args.is_empty()).then(|| XBoardCommand::UserMove(arg.to_string())),
+        "go" => Some(XBoardCommand::Go),
+        "force" => Some(XBoardCommand::Force),
+        "?" => Some(XBoardCommand::MoveNow),
+        "level" => parse_level(args),
+        "st" => seconds(arg).map(XBoardCommand::SetTime),
+        "sd" => arg.parse().ok().map(XBoardCommand::SetDepth),
+        "time" | "otim" => centiseconds(arg).map(|time| match command {
+            "time" => XBoardCommand::Time(time),
+            _ => XBoardCommand::OTim(time),
+        }),
+        "undo" => Some(XBoardCommand::Undo),
+        "remove" => Some(XBoardCommand::Remove),
+        "post" => Some(XBoardCommand::Post),
+        "nopost" => Some(XBoardCommand::NoPost),
+        "result" => Some(XBoardCommand::Result(rest)),
+        "ping" => arg.parse().ok().map(XBoardCommand::Ping),
+        "memory" => arg.parse().ok().map(XBoardCommand::Memory),
+        "cores" => arg.parse().ok().map(XBoardCommand::Cores),
+        "quit" => Some(XBoardCommand::Quit),
+        _ if IGNORED_COMMANDS.contains(&command) => Some(XBoardCommand::Ignored(line.to_string())),
+        _ => return XBoardCommand::Unknown(line.to_string()),
+    };
+
+    parsed.unwrap_or_else(|| XBoardCommand::Invalid(line.to_string()))
+}
+
+/// Parses `level <moves per session> <base> <increment>`. The base time is in minutes, or in
+/// minutes and seconds as `5:30`; the increment is in seconds, and may have a fraction.
+///
+/// * `args`: The tokens after `level`.
+fn parse_level(args: &[&str]) -> Option<XBoardCommand> {
+    let [moves_per_session, base, increment] = args else {
+        return None;
+    };
+
+    let base = match base.split_once(':') {
+        Some((minutes, secs)) => {
+            Duration::from_secs(minutes.parse::<u64>().ok()? * 60 + secs.parse::<u64>().ok()?)
+        }
+        None => Duration::from_secs(base.parse::<u64>().ok()? * 60),
+    };
+
+    Some(XBoardCommand::Level {
+        moves_per_session: moves_per_session.parse().ok()?,
+        base,
+        increment: seconds(increment)?,
+    })
+}
+
+/// Reads a number of seconds, which may have a fraction.
+fn seconds(value: &str) -> Option<Duration> {
+    let seconds = value.parse::<f64>().ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Reads a number of centiseconds, as sent by `time` and `otim`. A negative time (the clock has
+/// run out) is treated as zero.
+fn centiseconds(value: &str) -> Option<Duration> {
+    let centiseconds = value.parse::<i64>().ok()?;
+    Some(Duration::from_millis(centiseconds.max(0) as u64 * 10))
+}
+
+/// The `feature` lines sent in reply to `protover`.
+///
+/// * `name`: The name of the engine, including its version.
+pub fn features(name: &str) -> Vec<String> {
+    vec![
+        String::from("feature done=0"),
+        format!(
+            "feature myname=\"{name}\" ping=1 setboard=1 usermove=1 playother=0 san=0 time=1 \
+             draw=0 sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 memory=1 smp=1"
+        ),
+        String::from("feature done=1"),
+    ]
+}
+
+/// Converts a score into the score shown by XBoard: centipawns, or a mate as 100000 plus the
+/// number of moves to mate (negative when the engine is mated).
+///
+/// * `score`: The score, from the engine's point of view.
+pub fn score(score: i16) -> i32 {
+    match moves_to_mate(score) {
+        Some(moves) if moves > 0 => MATE_SCORE + moves as i32,
+        Some(moves) => -MATE_SCORE + moves as i32,
+        None => score as i32,
+    }
+}
+
+/// Formats a line of thinking output: the depth, the score, the time in centiseconds, the number
+/// of nodes and the principal variation.
+///
+/// * `summary`: The outcome of an iteration.
+pub fn thinking(summary: &SearchSummary) -> String {
+    let pv: Vec<String> = summary.pv.iter().map(Move::to_uci).collect();
+
+    let line = format!(
+        "{} {} {} {} {}",
+        summary.depth,
+        score(summary.score),
+        summary.time.as_millis() / 10,
+        summary.nodes,
+        pv.join(" ")
+    );
+    line.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::search::{
+        defs::{SearchSummary, CHECKMATE},
+        transposition::Bound,
+    };
+
+    use super::{parse, score, thinking, XBoardCommand};
+
+    #[test]
+    fn test_parse_simple_commands() {
+        assert_eq!(parse("xboard"), XBoardCommand::XBoard);
+        assert_eq!(parse("protover 2"), XBoardCommand::ProtoVer(2));
+        assert_eq!(parse("new"), XBoardCommand::New);
+        assert_eq!(parse("force"), XBoardCommand::Force);
+        assert_eq!(parse("?"), XBoardCommand::MoveNow);
+        assert_eq!(parse("undo"), XBoardCommand::Undo);
+        assert_eq!(parse("ping 7"), XBoardCommand::Ping(7));
+        assert_eq!(parse("memory 64"), XBoardCommand::Memory(64));
+        assert_eq!(
+            parse("usermove e7e8q"),
+            XBoardCommand::UserMove(String::from("e7e8q"))
+        );
+        assert_eq!(
+            parse("setboard 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"),
+            XBoardCommand::SetBoard(String::from("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"))
+        );
+        assert_eq!(
+            parse("result 1-0 {White mates}"),
+            XBoardCommand::Result(String::from("1-0 {White mates}"))
+        );
+    }
+
+    #[test]
+    fn test_parse_time_controls() {
+        assert_eq!(
+            parse("level 40 5 0"),
+            XBoardCommand::Level {
+                moves_per_session: 40,
+                base: Duration::from_secs(300),
+                increment: Duration::ZERO,
+            }
+        );
+        assert_eq!(
+            parse("level 0 2:30 0.5"),
+            XBoardCommand::Level {
+                moves_per_session: 0,
+                base: Duration::from_secs(150),
+                increment: Duration::from_millis(500),
+            }
+        );
+        assert_eq!(
+            parse("st 10"),
+            XBoardCommand::SetTime(Duration::from_secs(10))
+        );
+        assert_eq!(parse("sd 6"), XBoardCommand::SetDepth(6));
+        assert_eq!(
+            parse("time 12345"),
+            XBoardCommand::Time(Duration::from_millis(123_450))
+        );
+        assert_eq!(parse("otim -3"), XBoardCommand::OTim(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("level 40 five 0"),
+            XBoardCommand::Invalid(String::from("level 40 five 0"))
+        );
+        assert_eq!(parse("sd"), XBoardCommand::Invalid(String::from("sd")));
+        assert_eq!(
+            parse("analyze"),
+            XBoardCommand::Unknown(String::from("analyze"))
+        );
+        assert_eq!(
+            parse("accepted usermove"),
+            XBoardCommand::Ignored(String::from("accepted usermove"))
+        );
+    }
+
+    #[test]
+    fn test_thinking_output() {
+        assert_eq!(score(-42), -42);
+        assert_eq!(score(CHECKMATE - 5), 100_003);
+        assert_eq!(score(-CHECKMATE + 2), -100_001);
+
+        let summary = SearchSummary {
+            multipv: 1,
+            depth: 7,
+            seldepth: 12,
+            score: 31,
+            bound: Bound::Exact,
+            wdl: None,
+            nodes: 54_321,
+            hashfull: 0,
+            tbhits: 0,
+            time: Duration::from_millis(1_234),
+            pv: Vec::new(),
+        };
+        assert_eq!(thinking(&summary), "7 31 123 54321");
+    }
+}
@@ -0,0 +1,402 @@
+pub mod generator;
+mod material;
+mod table;
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{
+    board::{
+        defs::{Files, Pieces, Sides, BB_FILES, BB_SQUARES},
+        Board,
+    },
+    movegen::{defs::Move, MoveGenerator},
+};
+
+pub use material::{Material, MAX_PIECES};
+pub use table::{DtmTable, TableError};
+
+/// The extension of table files.
+pub const DTM_EXTENSION: &str = "ldtm";
+
+/// The rank of a root move that mates at once; see [`DtmTables::rank_root_moves`].
+pub const MAX_RANK: i32 = 1 << 10;
+
+/// The distance to mate of a position with perfect play, in plies, from the side to move's point
+/// of view. A position where the side to move is checkmated is lost in 0 plies. The fifty-move
+/// rule is not taken into account.
+///
+/// In a table, a draw is stored as 0, a win in `n` plies (always odd) as `n` and a loss in `n`
+/// plies (always even) as `n + 2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dtm {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+impl Dtm {
+    /// The longest distance to mate that can be stored.
+    pub const MAX_PLIES: usize = 253;
+
+    /// Converts a value as stored in a table.
+    ///
+    /// * `value`: The value.
+    pub fn from_value(value: u8) -> Self {
+        match value {
+            0 => Self::Draw,
+            value if value % 2 == 1 => Self::Win(value),
+            value => Self::Loss(value - 2),
+        }
+    }
+
+    /// The value as stored in a table.
+    pub fn value(self) -> u8 {
+        match self {
+            Self::Win(plies) => plies,
+            Self::Loss(plies) => plies + 2,
+            Self::Draw => 0,
+        }
+    }
+
+    /// The distance to mate before the move that led to this position, for the side that
+    /// played it.
+    pub fn before_move(self) -> Self {
+        match self {
+            Self::Win(plies) => Self::Loss(plies + 1),
+            Self::Loss(plies) => Self::Win(plies + 1),
+            Self::Draw => Self::Draw,
+        }
+    }
+}
+
+/// A table file that was found, and is read when it is first probed.
+///
+/// * `path`: The path of the file.
+/// * `table`: The table, once read; None if it could not be read.
+struct TableEntry {
+    path: PathBuf,
+    table: OnceLock<Option<DtmTable>>,
+}
+
+impl TableEntry {
+    /// The table, which is read from its file on first use.
+    fn table(&self) -> Option<&DtmTable> {
+        self.table
+            .get_or_init(|| DtmTable::open(&self.path).ok())
+            .as_ref()
+    }
+}
+
+/// The DTM tables found in a set of directories, as written by `lark tbgen`. They give the
+/// distance to mate of every position of an endgame, so the engine can play it perfectly.
+///
+/// Positions with castling rights or an en-passant capture can not be probed, as the tables do
+/// not have them.
+///
+/// * `tables`: The tables, by name (such as `KRvK`).
+/// * `max_pieces`: The number of pieces of the largest table.
+#[derive(Default)]
+pub struct DtmTables {
+    tables: HashMap<String, TableEntry>,
+    max_pieces: usize,
+}
+
+impl DtmTables {
+    /// Finds the table files in a list of directories, such as the value of the `DtmPath`
+    /// option. The files are only read when they are first probed.
+    ///
+    /// * `paths`: The directories, separated as in the `PATH` environment variable (`:` on Unix,
+    ///   `;` on Windows).
+    pub fn open(paths: &str) -> Self {
+        let mut tables = Self::default();
+
+        for dir in env::split_paths(paths).filter(|dir| !dir.as_os_str().is_empty()) {
+            let Ok(files) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for path in files.flatten().map(|file| file.path()) {
+                tables.add(&path);
+            }
+        }
+
+        tables
+    }
+
+    /// Adds a file if it is a table. When a table is found in several directories, the first one
+    /// is used.
+    ///
+    /// * `path`: The path of the file.
+    fn add(&mut self, path: &Path) {
+        let (Some(name), Some(extension)) = (path.file_stem(), path.extension()) else {
+            return;
+        };
+        let Some(material) = name.to_str().and_then(Material::parse) else {
+            return;
+        };
+        if extension.to_str() != Some(DTM_EXTENSION) {
+            return;
+        }
+
+        self.max_pieces = self.max_pieces.max(material.pieces().len());
+        self.tables
+            .entry(material.to_string())
+            .or_insert_with(|| TableEntry {
+                path: path.to_path_buf(),
+                table: OnceLock::new(),
+            });
+    }
+
+    /// The number of tables that were found.
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// True when no tables were found.
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// The number of pieces, including the kings, of the largest table; 0 without tables.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// True when the position may be in the tables: it has few enough pieces, no castling
+    /// rights and no en-passant capture. The table for its pieces may still be missing.
+    ///
+    /// * `board`: The position.
+    pub fn can_probe(&self, board: &Board) -> bool {
+        let pieces = (board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK]).count_ones();
+        board.state.castling == 0
+            && !can_capture_en_passant(board)
+            && pieces as usize <= self.max_pieces
+    }
+
+    /// The distance to mate of a position, if the tables have it.
+    ///
+    /// * `board`: The position.
+    pub fn probe(&self, board: &Board) -> Option<Dtm> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        lookup(board, |name| {
+            self.tables.get(name).and_then(|entry| entry.table())
+        })
+    }
+
+    /// Ranks the moves of the root position by the distance to mate after them, so the engine
+    /// can keep to the best ones: a move that mates in `n` plies ranks `MAX_RANK - n`, a draw 0
+    /// and a move after which the side to move is mated in `n` plies `-MAX_RANK + n`. Returns
+    /// None if a position is not in the tables.
+    ///
+    /// * `board`: The root position.
+    /// * `mg`: The move generator.
+    /// * `moves`: The legal moves of the root position.
+    pub fn rank_root_moves(
+        &self,
+        board: &mut Board,
+        mg: &MoveGenerator,
+        moves: &[Move],
+    ) -> Option<Vec<i32>> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        moves
+            .iter()
+            .map(|&mv| {
+                board.make_move(mv, mg);
+                let dtm = self.probe(board);
+                board.unmake_move();
+
+                Some(match dtm?.before_move() {
+                    Dtm::Win(plies) => MAX_RANK - i32::from(plies),
+                    Dtm::Loss(plies) => -MAX_RANK + i32::from(plies),
+                    Dtm::Draw => 0,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The distance to mate of a position in the table for its pieces, if there is one. Tables have
+/// the stronger side as White, so the colours of the position are swapped if needed. Positions
+/// where neither side can mate are draws even without a table.
+///
+/// * `board`: The position.
+/// * `table`: Finds the table of a material signature.
+fn lookup<'a>(board: &Board, table: impl Fn(&str) -> Option<&'a DtmTable>) -> Option<Dtm> {
+    let found = [false, true].into_iter().find_map(|flip| {
+        let table = table(&Material::of_board(board, flip).to_string())?;
+        Some((table, flip))
+    });
+
+    let Some((table, flip)) = found else {
+        return board.is_insufficient_material().then_some(Dtm::Draw);
+    };
+    let idx = table.material().board_index(board, flip)?;
+    Some(Dtm::from_value(table.get(idx)))
+}
+
+/// Determines if a pawn of the side to move stands next to a pawn that just made a double step,
+/// so that it may be able to capture it en passant.
+///
+/// * `board`: The position.
+fn can_capture_en_passant(board: &Board) -> bool {
+    let Some(square) = board.state.en_passant.map(usize::from) else {
+        return false;
+    };
+    let victim = if board.current_side() == Sides::WHITE {
+        square - 8
+    } else {
+        square + 8
+    };
+    let neighbours = BB_SQUARES[victim] << 1 & !BB_FILES[Files::A]
+        | BB_SQUARES[victim] >> 1 & !BB_FILES[Files::H];
+    board.bb_pieces[board.current_side()][Pieces::PAWN] & neighbours > 0
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        env, fs,
+        sync::{Arc, OnceLock},
+    };
+
+    use super::{generator::Generator, Dtm, DtmTables, Material, DTM_EXTENSION, MAX_RANK};
+    use crate::{
+        board::{
+            defs::{Pieces, Sides},
+            Board,
+        },
+        evaluation::kpk,
+        movegen::{defs::MoveType, movelist::MoveList, MoveGenerator},
+    };
+
+    /// The tables of king and pawn against king and the endings it turns into, generated once
+    /// and written to a temporary directory. They are all read before the directory is removed.
+    pub fn dtm_tables() -> Arc<DtmTables> {
+        static TABLES: OnceLock<Arc<DtmTables>> = OnceLock::new();
+        let tables = TABLES.get_or_init(|| {
+            let mg = MoveGenerator::new();
+            let mut generator = Generator::new(&mg);
+            let dir = env::temp_dir().join(format!("lark-dtm-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+
+            for name in ["KvK", "KQvK", "KRvK", "KBvK", "KNvK", "KPvK"] {
+                let material = Material::parse(name).unwrap();
+                let (table, _) = generator.generate(&material).unwrap();
+                let mut file = fs::File::create(dir.join(format!("{name}.{DTM_EXTENSION}")));
+                table.write(file.as_mut().unwrap()).unwrap();
+                generator.add(table);
+            }
+
+            let tables = DtmTables::open(dir.to_str().unwrap());
+            for entry in tables.tables.values() {
+                assert!(entry.table().is_some());
+            }
+            fs::remove_dir_all(&dir).unwrap();
+            Arc::new(tables)
+        });
+        Arc::clone(tables)
+    }
+
+    fn probe(fen: &str) -> Option<Dtm> {
+        let mut board = Board::new();
+        board.fen_read(Some(fen)).unwrap();
+        dtm_tables().probe(&board)
+    }
+
+    #[test]
+    fn test_dtm_probe() {
+        let tables = dtm_tables();
+        assert_eq!((tables.len(), tables.max_pieces()), (6, 3));
+
+        assert_eq!(probe("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1"), Some(Dtm::Win(1)));
+        assert_eq!(probe("7k/Q7/6K1/8/8/8/8/8 b - - 0 1"), Some(Dtm::Loss(2)));
+        assert_eq!(probe("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1"), Some(Dtm::Loss(0)));
+        assert_eq!(probe("k7/8/1QK5/8/8/8/8/8 b - - 0 1"), Some(Dtm::Draw));
+        assert_eq!(
+            probe("8/8/8/8/8/2K5/8/kq6 b - - 0 1"),
+            probe("8/8/8/8/8/2k5/8/KQ6 w - - 0 1")
+        );
+        assert_eq!(probe("8/8/8/3k4/8/8/8/4K3 w - - 0 1"), Some(Dtm::Draw));
+        assert_eq!(probe("8/8/8/3k4/8/8/8/R3K3 w Q - 0 1"), None);
+        assert_eq!(probe("8/8/8/3k4/8/8/8/RQ2K3 w - - 0 1"), None);
+
+        // The longest mates take 10 moves with a queen and 16 with a rook.
+        let longest = |name: &str| {
+            let material = Material::parse(name).unwrap();
+            let table = &tables.tables[name].table().unwrap();
+            (0..2 * material.size())
+                .map(|idx| table.get(idx))
+                .filter(|value| value % 2 == 1)
+                .max()
+        };
+        assert_eq!(longest("KQvK"), Some(19));
+        assert_eq!(longest("KRvK"), Some(31));
+        assert_eq!(longest("KBvK"), None);
+    }
+
+    #[test]
+    fn test_dtm_agrees_with_kpk_bitbase() {
+        let tables = dtm_tables();
+        let material = Material::parse("KPvK").unwrap();
+        let table = tables.tables["KPvK"].table().unwrap();
+
+        let mg = MoveGenerator::new();
+
+        for idx in 0..2 * material.size() {
+            let (squares, stm) = material.position(idx);
+            if material.index(&squares, stm) != Some(idx) {
+                continue;
+            }
+            let mut board = Board::new();
+            board.put_piece(Sides::WHITE, Pieces::KING, squares[0]);
+            board.put_piece(Sides::WHITE, Pieces::PAWN, squares[1]);
+            board.put_piece(Sides::BLACK, Pieces::KING, squares[2]);
+            board.state.active_side = stm as u8 ^ 1;
+            if mg.in_check(&board) {
+                continue;
+            }
+
+            let wins = kpk::probe(Sides::WHITE, squares[0], squares[1], squares[2], stm);
+            let dtm = Dtm::from_value(table.get(idx));
+            let expected = match (wins, stm) {
+                (false, _) => matches!(dtm, Dtm::Draw),
+                (true, Sides::WHITE) => matches!(dtm, Dtm::Win(_)),
+                (true, _) => matches!(dtm, Dtm::Loss(_)),
+            };
+            assert!(expected, "{idx} {squares:?} {stm} {dtm:?}");
+        }
+    }
+
+    #[test]
+    fn test_rank_root_moves() {
+        let tables = dtm_tables();
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+        board
+            .fen_read(Some("7k/8/5K2/8/8/8/8/Q7 w - - 0 1"))
+            .unwrap();
+        let mut list = MoveList::new();
+        mg.generate_legal_moves(&mut board, &mut list, MoveType::All);
+        let moves: Vec<_> = list.iter().copied().collect();
+
+        let ranks = tables.rank_root_moves(&mut board, &mg, &moves).unwrap();
+        let best = ranks.iter().copied().max().unwrap();
+        assert_eq!(best, MAX_RANK - 3);
+        let best_moves: Vec<_> = moves
+            .iter()
+            .zip(&ranks)
+            .filter(|&(_, &rank)| rank == best)
+            .map(|(mv, _)| mv.to_uci())
+            .collect();
+        assert!(best_moves.contains(&String::from("f6g6")));
+    }
+}
@@ -0,0 +1,460 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    board::{
+        defs::{NrOf, Pieces, Side, Sides, Square, EMPTY},
+        Board,
+    },
+    movegen::{
+        defs::{Move, MoveType},
+        movelist::MoveList,
+        MoveGenerator,
+    },
+};
+
+use super::{lookup, material::Material, table::DtmTable, Dtm};
+
+/// The move count of a position that can not occur: a piece on the square of another, or the
+/// side that is not to move in check.
+const ILLEGAL: u8 = u8::MAX;
+
+/// The errors that can occur when generating a table.
+///
+/// * `MissingTable`: The table of an ending the position can turn into by a capture or
+///   promotion has not been generated yet.
+/// * `TooLong`: A mate takes longer than a table can store; see [`Dtm::MAX_PLIES`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum GenerateError {
+    MissingTable(String),
+    TooLong,
+}
+
+impl Display for GenerateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingTable(name) => write!(f, "The table {name} is needed first"),
+            Self::TooLong => write!(f, "A mate takes more than {} plies", Dtm::MAX_PLIES),
+        }
+    }
+}
+
+/// What a generated table holds, counting each position with both sides to move.
+///
+/// * `positions`: The number of legal positions.
+/// * `wins`: The positions the side to move wins.
+/// * `losses`: The positions the side to move loses.
+/// * `longest`: The longest mate, in plies.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub positions: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub longest: u8,
+}
+
+/// Generates DTM tables by retrograde analysis. The mates are found first; then, one ply at a
+/// time, a position is won when a move leads to a position lost in the ply before, and lost when
+/// all moves lead to won positions. The positions before are found with
+/// [`MoveGenerator::generate_unmoves`]. Positions that are never found to be won or lost are
+/// draws.
+///
+/// Captures and promotions lead to other endings, which are looked up in their tables: those
+/// have to be generated first, see [`Generator::dependencies`]. En-passant captures are not
+/// taken into account.
+///
+/// * `mg`: The move generator.
+/// * `tables`: The tables generated so far, by name.
+pub struct Generator<'a> {
+    mg: &'a MoveGenerator,
+    tables: HashMap<String, DtmTable>,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(mg: &'a MoveGenerator) -> Self {
+        Self {
+            mg,
+            tables: HashMap::new(),
+        }
+    }
+
+    /// Adds a table, so that tables that depend on it can be generated.
+    ///
+    /// * `table`: The table, generated or read from a file.
+    pub fn add(&mut self, table: DtmTable) {
+        self.tables.insert(table.material().to_string(), table);
+    }
+
+    /// Determines if the table of the material was added.
+    ///
+    /// * `material`: The pieces of the table.
+    pub fn contains(&self, material: &Material) -> bool {
+        self.tables.contains_key(&material.to_string())
+    }
+
+    /// The endings a position with this material can turn into with one move: by capturing a
+    /// piece, by promoting a pawn, or both. They are stored with the stronger side as White.
+    ///
+    /// * `material`: The pieces of the table.
+    pub fn dependencies(material: &Material) -> Vec<Material> {
+        let pieces = material.pieces();
+        let without = |pieces: &[(Side, usize)], i: usize| {
+            let mut pieces = pieces.to_vec();
+            pieces.remove(i);
+            pieces
+        };
+        let mut dependencies = Vec::new();
+
+        for (i, &(side, piece)) in pieces.iter().enumerate() {
+            if piece == Pieces::KING {
+                continue;
+            }
+            dependencies.push(without(pieces, i));
+
+            if piece != Pieces::PAWN {
+                continue;
+            }
+            for promotion in [Pieces::QUEEN, Pieces::ROOK, Pieces::BISHOP, Pieces::KNIGHT] {
+                let mut promoted = pieces.to_vec();
+                promoted[i] = (side, promotion);
+                for (j, &(other, captured)) in pieces.iter().enumerate() {
+                    if other != side && captured != Pieces::KING && captured != Pieces::PAWN {
+                        dependencies.push(without(&promoted, j));
+                    }
+                }
+                dependencies.push(promoted);
+            }
+        }
+
+        let mut dependencies: Vec<_> = dependencies
+            .into_iter()
+            .map(|pieces| Material::new(pieces).canonical())
+            .collect();
+        dependencies.sort_by_key(|material| material.to_string());
+        dependencies.dedup();
+        dependencies
+    }
+
+    /// Generates the table of a material. The tables it depends on must have been added.
+    ///
+    /// * `material`: The pieces of the table.
+    pub fn generate(&self, material: &Material) -> Result<(DtmTable, Summary), GenerateError> {
+        if let Some(missing) = Self::dependencies(material)
+            .iter()
+            .find(|dependency| !self.contains(dependency))
+        {
+            return Err(GenerateError::MissingTable(missing.to_string()));
+        }
+
+        let mut generation = Generation::new(self, material);
+        generation.initialize()?;
+        generation.retrograde()?;
+        Ok(generation.finish())
+    }
+}
+
+/// The state of the generation of one table.
+///
+/// * `generator`: The generator, with the tables of the other endings.
+/// * `material`: The pieces of the table.
+/// * `values`: The value of each position so far, as in [`Dtm::value`]; 0 until it is known.
+/// * `counts`: The number of moves of each position that may not lose; [`ILLEGAL`] for
+///   positions that can not occur.
+/// * `plies`: The positions that are won or lost in each number of plies. They may be found
+///   again later, with a longer distance, which is then ignored.
+/// * `board`: The board the positions are set up on.
+struct Generation<'g, 'a> {
+    generator: &'g Generator<'a>,
+    material: &'g Material,
+    values: Vec<u8>,
+    counts: Vec<u8>,
+    plies: Vec<Vec<u32>>,
+    board: Board,
+}
+
+impl<'g, 'a> Generation<'g, 'a> {
+    fn new(generator: &'g Generator<'a>, material: &'g Material) -> Self {
+        let len = 2 * material.size();
+        Self {
+            generator,
+            material,
+            values: vec![0; len],
+            counts: vec![ILLEGAL; len],
+            plies: Vec::new(),
+            board: Board::new(),
+        }
+    }
+
+    /// Finds the legal positions and counts their moves. Positions where the side to move is
+    /// mated, or where a capture or promotion wins, are added to [`Generation::plies`], as are
+    /// positions where all moves are captures or promotions that lose.
+    fn initialize(&mut self) -> Result<(), GenerateError> {
+        let mg = self.generator.mg;
+        let mut moves = MoveList::new();
+        let mut children = Vec::new();
+
+        for idx in 0..self.values.len() {
+            let (squares, stm) = self.material.position(idx);
+            if self.material.index(&squares, stm) != Some(idx) {
+                continue;
+            }
+            self.set_up(&squares, stm);
+            if mg.square_attacked(&self.board, stm, self.board.king_square(stm ^ 1)) {
+                continue;
+            }
+
+            moves.clear();
+            mg.generate_legal_moves(&mut self.board, &mut moves, MoveType::All);
+            if moves.is_empty() {
+                self.counts[idx] = 0;
+                if mg.in_check(&self.board) {
+                    self.add(0, idx);
+                }
+                continue;
+            }
+
+            // Moves within the table count once for each position they lead to.
+            children.clear();
+            let (mut count, mut longest_loss) = (0, 0);
+            for &mv in moves.iter() {
+                if !is_exit(mv) {
+                    let child = moved(&squares, mv.from(), mv.to());
+                    children.extend(self.material.index(&child, stm ^ 1));
+                    continue;
+                }
+
+                match self.probe_exit(mv)?.before_move() {
+                    Dtm::Win(plies) => {
+                        self.add(plies as usize, idx);
+                        count += 1;
+                    }
+                    Dtm::Loss(plies) => longest_loss = longest_loss.max(plies as usize),
+                    Dtm::Draw => count += 1,
+                }
+            }
+            children.sort_unstable();
+            children.dedup();
+
+            self.counts[idx] = (count + children.len()) as u8;
+            if self.counts[idx] == 0 {
+                self.add(longest_loss, idx);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds the won and lost positions, one ply at a time. The positions before a lost position
+    /// are won in one ply more. The positions before a won position have one move less that may
+    /// not lose; when none are left, they are lost.
+    fn retrograde(&mut self) -> Result<(), GenerateError> {
+        let mut unmoves = MoveList::new();
+        let mut parents = Vec::new();
+
+        let mut ply = 0;
+        while ply < self.plies.len() {
+            for idx in std::mem::take(&mut self.plies[ply]) {
+                let idx = idx as usize;
+                if self.values[idx] != 0 {
+                    continue;
+                }
+                if ply > Dtm::MAX_PLIES {
+                    return Err(GenerateError::TooLong);
+                }
+                let dtm = if ply % 2 == 1 {
+                    Dtm::Win(ply as u8)
+                } else {
+                    Dtm::Loss(ply as u8)
+                };
+                self.values[idx] = dtm.value();
+
+                let (squares, stm) = self.material.position(idx);
+                self.set_up(&squares, stm);
+                unmoves.clear();
+                self.generator
+                    .mg
+                    .generate_unmoves(&self.board, &mut unmoves);
+
+                parents.clear();
+                for mv in unmoves.iter() {
+                    let parent = moved(&squares, mv.to(), mv.from());
+                    if let Some(parent) = self.material.index(&parent, stm ^ 1) {
+                        if self.counts[parent] != ILLEGAL && self.values[parent] == 0 {
+                            parents.push(parent);
+                        }
+                    }
+                }
+                parents.sort_unstable();
+                parents.dedup();
+
+                for &parent in &parents {
+                    if let Dtm::Loss(_) = dtm {
+                        self.add(ply + 1, parent);
+                        continue;
+                    }
+                    self.counts[parent] -= 1;
+                    if self.counts[parent] == 0 {
+                        let plies = self.longest_loss(parent)?;
+                        self.add(plies, parent);
+                    }
+                }
+            }
+            ply += 1;
+        }
+
+        Ok(())
+    }
+
+    /// The number of plies a position is lost in, when all its moves lead to won positions: one
+    /// more than the longest win after a move.
+    ///
+    /// * `idx`: The index of the position.
+    fn longest_loss(&mut self, idx: usize) -> Result<usize, GenerateError> {
+        let (squares, stm) = self.material.position(idx);
+        self.set_up(&squares, stm);
+        let mut moves = MoveList::new();
+        self.generator
+            .mg
+            .generate_legal_moves(&mut self.board, &mut moves, MoveType::All);
+
+        let mut longest = 0;
+        for &mv in moves.iter() {
+            let dtm = if is_exit(mv) {
+                self.probe_exit(mv)?
+            } else {
+                let child = moved(&squares, mv.from(), mv.to());
+                let child = self.material.index(&child, stm ^ 1);
+                child.map_or(Dtm::Draw, |child| Dtm::from_value(self.values[child]))
+            };
+            if let Dtm::Loss(plies) = dtm.before_move() {
+                longest = longest.max(plies as usize);
+            }
+        }
+
+        Ok(longest)
+    }
+
+    /// The table with the values of all positions, and what it holds. Positions that can not
+    /// occur are never probed, so they get the value before them, which makes the runs of the
+    /// same value longer.
+    fn finish(mut self) -> (DtmTable, Summary) {
+        let mut summary = Summary::default();
+        let mut last = 0;
+        for (value, &count) in self.values.iter_mut().zip(&self.counts) {
+            if count == ILLEGAL {
+                *value = last;
+                continue;
+            }
+            last = *value;
+            summary.positions += 1;
+            match Dtm::from_value(*value) {
+                Dtm::Win(plies) => {
+                    summary.wins += 1;
+                    summary.longest = summary.longest.max(plies);
+                }
+                Dtm::Loss(_) => summary.losses += 1,
+                Dtm::Draw => (),
+            }
+        }
+
+        let table = DtmTable::compress(self.material.clone(), &self.values);
+        (table, summary)
+    }
+
+    /// Adds a position that is won or lost in a number of plies.
+    fn add(&mut self, plies: usize, idx: usize) {
+        if self.plies.len() <= plies {
+            self.plies.resize_with(plies + 1, Vec::new);
+        }
+        self.plies[plies].push(idx as u32);
+    }
+
+    /// The distance to mate after a capture or promotion, from the table of the ending it leads
+    /// to.
+    fn probe_exit(&mut self, mv: Move) -> Result<Dtm, GenerateError> {
+        self.board.make_move(mv, self.generator.mg);
+        let dtm = lookup(&self.board, |name| self.generator.tables.get(name));
+        let missing = || {
+            Material::of_board(&self.board, false)
+                .canonical()
+                .to_string()
+        };
+        let dtm = dtm.ok_or_else(|| GenerateError::MissingTable(missing()));
+        self.board.unmake_move();
+        dtm
+    }
+
+    /// Sets up a position on the board.
+    fn set_up(&mut self, squares: &[Square], stm: Side) {
+        let board = &mut self.board;
+        board.bb_pieces = [[EMPTY; NrOf::PIECE_TYPES]; Sides::BOTH];
+        board.bb_side = [EMPTY; Sides::BOTH];
+        board.state.material = [0; Sides::BOTH];
+        board.state.active_side = stm as u8;
+        board.state.en_passant = None;
+        board.state.castling = 0;
+        board.history.clear();
+
+        for (&(side, piece), &square) in self.material.pieces().iter().zip(squares) {
+            board.put_piece(side, piece, square);
+        }
+    }
+}
+
+/// Determines if a move leaves the table: a capture or a promotion.
+fn is_exit(mv: Move) -> bool {
+    mv.is_capture() || mv.is_promotion()
+}
+
+/// The squares of the pieces after the piece on `from` moves to `to`.
+fn moved(squares: &[Square], from: Square, to: Square) -> Vec<Square> {
+    squares
+        .iter()
+        .map(|&square| if square == from { to } else { square })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GenerateError, Generator, Summary};
+    use crate::{dtm::Material, movegen::MoveGenerator};
+
+    fn material(name: &str) -> Material {
+        Material::parse(name).unwrap()
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let names = |name: &str| {
+            Generator::dependencies(&material(name))
+                .iter()
+                .map(|material| material.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("KQvK"), ["KvK"]);
+        assert_eq!(names("KPvK"), ["KBvK", "KNvK", "KQvK", "KRvK", "KvK"]);
+        assert_eq!(
+            names("KPvKR"),
+            ["KBvK", "KNvK", "KPvK", "KQvK", "KQvKR", "KRvK", "KRvKB", "KRvKN", "KRvKR"]
+        );
+    }
+
+    #[test]
+    fn test_generate_needs_dependencies() {
+        let mg = MoveGenerator::new();
+        let mut generator = Generator::new(&mg);
+        assert_eq!(
+            generator.generate(&material("KRvK")).err(),
+            Some(GenerateError::MissingTable(String::from("KvK")))
+        );
+
+        let (table, summary) = generator.generate(&material("KvK")).unwrap();
+        assert_eq!((summary.wins, summary.losses, summary.longest), (0, 0, 0));
+        generator.add(table);
+
+        let (_, summary) = generator.generate(&material("KRvK")).unwrap();
+        assert_eq!(summary.longest, 31);
+        assert!(summary.wins > 0 && summary.losses > 0);
+        assert_ne!(summary, Summary::default());
+    }
+}
@@ -0,0 +1,322 @@
+use std::fmt::Display;
+
+use crate::{
+    board::{
+        defs::{Piece, Pieces, Side, Sides, Square, PIECE_CHAR_CAPS, PIECE_VALUES},
+        Board,
+    },
+    utils::bits,
+};
+
+/// The maximum number of pieces in a table, including the kings.
+pub const MAX_PIECES: usize = 5;
+
+/// The squares of the a1-d1-d4 triangle, where the white king stands in tables without pawns.
+const TRIANGLE: [Square; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+/// The pieces of a table: the white pieces, then the black ones, each starting with the king.
+/// Pieces of the same kind are told apart by their order, so a position with two of them has
+/// two indices.
+///
+/// The index of a position is made of the squares of the pieces, after mirroring the board so
+/// that the white king is in a small part of it: the a1-d1-d4 triangle without pawns, and the
+/// files a to d with pawns, which can only be mirrored from left to right. Pawns can only be on
+/// the 48 squares of the ranks 2 to 7.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Material {
+    pieces: Vec<(Side, Piece)>,
+    has_pawns: bool,
+}
+
+impl Material {
+    /// Reads a material signature such as `KRPvKR`, with the white pieces first.
+    ///
+    /// * `name`: The signature.
+    pub fn parse(name: &str) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let mut pieces = Vec::new();
+
+        for (side, group) in [(Sides::WHITE, white), (Sides::BLACK, black)] {
+            let group: Vec<Piece> = group
+                .chars()
+                .map(|c| {
+                    PIECE_CHAR_CAPS[..=Pieces::PAWN]
+                        .iter()
+                        .position(|&p| p == c.to_string())
+                })
+                .collect::<Option<_>>()?;
+            if group.iter().filter(|&&piece| piece == Pieces::KING).count() != 1 {
+                return None;
+            }
+            pieces.extend(group.into_iter().map(|piece| (side, piece)));
+        }
+
+        if pieces.len() > MAX_PIECES {
+            return None;
+        }
+        Some(Self::new(pieces))
+    }
+
+    /// The material of the pieces on a board, with the colours swapped if `flip` is set.
+    ///
+    /// * `board`: The position.
+    /// * `flip`: Swap the colours.
+    pub fn of_board(board: &Board, flip: bool) -> Self {
+        let mut pieces = Vec::new();
+        for side in [Sides::WHITE, Sides::BLACK] {
+            for piece in Pieces::KING..=Pieces::PAWN {
+                let count = board.bb_pieces[side ^ flip as usize][piece].count_ones();
+                pieces.extend((0..count).map(|_| (side, piece)));
+            }
+        }
+        Self::new(pieces)
+    }
+
+    /// The material of a list of pieces, in any order.
+    ///
+    /// * `pieces`: The pieces.
+    pub(super) fn new(mut pieces: Vec<(Side, Piece)>) -> Self {
+        pieces.sort_unstable();
+        let has_pawns = pieces.iter().any(|&(_, piece)| piece == Pieces::PAWN);
+        Self { pieces, has_pawns }
+    }
+
+    /// The same material with the colours swapped.
+    pub fn flipped(&self) -> Self {
+        Self::new(
+            self.pieces
+                .iter()
+                .map(|&(side, piece)| (side ^ 1, piece))
+                .collect(),
+        )
+    }
+
+    /// The material with the stronger side as White, which is how tables are stored. The
+    /// stronger side has more material, or with equal material the most pieces.
+    pub fn canonical(&self) -> Self {
+        let strength = |side: Side| {
+            let pieces = self.pieces.iter().filter(|&&(s, _)| s == side);
+            let value: u16 = pieces.clone().map(|&(_, piece)| PIECE_VALUES[piece]).sum();
+            (value, pieces.count())
+        };
+
+        if strength(Sides::BLACK) > strength(Sides::WHITE) {
+            self.flipped()
+        } else {
+            self.clone()
+        }
+    }
+
+    /// The pieces, in the order of the squares of a position.
+    pub fn pieces(&self) -> &[(Side, Piece)] {
+        &self.pieces
+    }
+
+    /// The number of positions with each side to move.
+    pub fn size(&self) -> usize {
+        let king = if self.has_pawns { 32 } else { TRIANGLE.len() };
+        self.pieces[1..]
+            .iter()
+            .fold(king, |size, &(_, piece)| size * squares_of(piece))
+    }
+
+    /// The index of a position, or None if it can not be stored: two pieces on the same square
+    /// or a pawn on the first or last rank.
+    ///
+    /// * `squares`: The squares of the pieces.
+    /// * `stm`: The side to move.
+    pub fn index(&self, squares: &[Square], stm: Side) -> Option<usize> {
+        if squares
+            .iter()
+            .enumerate()
+            .any(|(i, square)| squares[..i].contains(square))
+        {
+            return None;
+        }
+
+        let flip = self.mirror(squares);
+        let king = squares[0] ^ flip.0;
+        let king = if self.has_pawns {
+            king / 8 * 4 + king % 8
+        } else {
+            TRIANGLE
+                .iter()
+                .position(|&square| square == flip_diagonal(king, flip.1))?
+        };
+
+        let mut idx = king;
+        for (&(_, piece), &square) in self.pieces[1..].iter().zip(&squares[1..]) {
+            let square = flip_diagonal(square ^ flip.0, flip.1);
+            let square = if piece == Pieces::PAWN {
+                square.checked_sub(8).filter(|&square| square < 48)?
+            } else {
+                square
+            };
+            idx = idx * squares_of(piece) + square;
+        }
+
+        Some(stm * self.size() + idx)
+    }
+
+    /// The position of an index: the squares of the pieces and the side to move.
+    ///
+    /// * `idx`: The index.
+    pub fn position(&self, idx: usize) -> (Vec<Square>, Side) {
+        let size = self.size();
+        let (stm, mut idx) = (idx / size, idx % size);
+        let mut squares = vec![0; self.pieces.len()];
+
+        for (i, &(_, piece)) in self.pieces.iter().enumerate().skip(1).rev() {
+            let square = idx % squares_of(piece);
+            idx /= squares_of(piece);
+            squares[i] = if piece == Pieces::PAWN {
+                square + 8
+            } else {
+                square
+            };
+        }
+        squares[0] = if self.has_pawns {
+            idx / 4 * 8 + idx % 4
+        } else {
+            TRIANGLE[idx]
+        };
+
+        (squares, stm)
+    }
+
+    /// The index of the position on a board, with the colours swapped if `flip` is set. Pieces of
+    /// the same kind are taken in the order of their squares.
+    ///
+    /// * `board`: The position, which must have this material.
+    /// * `flip`: Swap the colours.
+    pub fn board_index(&self, board: &Board, flip: bool) -> Option<usize> {
+        let flip_side = flip as usize;
+        let flip_square = if flip { 56 } else { 0 };
+        let mut squares = Vec::with_capacity(self.pieces.len());
+        let mut last = None;
+
+        for &(side, piece) in &self.pieces {
+            if last != Some((side, piece)) {
+                let mut bb = board.bb_pieces[side ^ flip_side][piece];
+                while bb > 0 {
+                    squares.push(bits::next(&mut bb) ^ flip_square);
+                }
+                last = Some((side, piece));
+            }
+        }
+
+        self.index(&squares, board.current_side() ^ flip_side)
+    }
+
+    /// How to mirror a position so that the white king is in its part of the board: the squares
+    /// are XORed with the first value, then mirrored in the a1-h8 diagonal if the second is set.
+    /// When the king ends up on the diagonal, the first piece that is not on it is put below it,
+    /// so that each position has one index.
+    fn mirror(&self, squares: &[Square]) -> (Square, bool) {
+        let king = squares[0];
+        let mut flip = if king % 8 >= 4 { 7 } else { 0 };
+        if self.has_pawns {
+            return (flip, false);
+        }
+        if king / 8 >= 4 {
+            flip ^= 56;
+        }
+
+        let king = king ^ flip;
+        if king / 8 > king % 8 {
+            return (flip, true);
+        }
+        if king / 8 == king % 8 {
+            let off_diagonal = squares[1..]
+                .iter()
+                .map(|&square| square ^ flip)
+                .find(|&square| square / 8 != square % 8);
+            if off_diagonal.is_some_and(|square| square / 8 > square % 8) {
+                return (flip, true);
+            }
+        }
+        (flip, false)
+    }
+}
+
+impl Display for Material {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for &(side, piece) in &self.pieces {
+            if side == Sides::BLACK && piece == Pieces::KING {
+                write!(f, "v")?;
+            }
+            write!(f, "{}", PIECE_CHAR_CAPS[piece])?;
+        }
+        Ok(())
+    }
+}
+
+/// Mirrors a square in the a1-h8 diagonal, if `flip` is set.
+fn flip_diagonal(square: Square, flip: bool) -> Square {
+    if flip {
+        (square % 8) * 8 + square / 8
+    } else {
+        square
+    }
+}
+
+/// The number of squares a piece can be on in a position.
+fn squares_of(piece: Piece) -> usize {
+    if piece == Pieces::PAWN {
+        48
+    } else {
+        64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Material;
+    use crate::board::{defs::Sides, Board};
+
+    #[test]
+    fn test_material_index() {
+        let material = Material::parse("KRPvKR").unwrap();
+        assert_eq!(material.to_string(), "KRPvKR");
+        assert_eq!(material.size(), 32 * 64 * 48 * 64 * 64);
+        assert_eq!(Material::parse("KPRvKR"), Some(material.clone()));
+        assert_eq!(
+            Material::parse("KQvKRR").unwrap().canonical().to_string(),
+            "KRRvKQ"
+        );
+        assert_eq!(
+            Material::parse("KvKP").unwrap().canonical().to_string(),
+            "KPvK"
+        );
+        assert!(Material::parse("KQQQvKR").is_none());
+        assert!(Material::parse("KQvR").is_none());
+        assert!(Material::parse("KXvK").is_none());
+
+        // Positions that are mirrors of each other have the same index.
+        let index = |fen: &str, material: &Material, flip| {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+            material.board_index(&board, flip).unwrap()
+        };
+        let kqk = Material::parse("KQvK").unwrap();
+        let a = index("8/8/8/8/8/2k5/8/KQ6 w - - 0 1", &kqk, false);
+        assert_eq!(index("8/8/8/8/8/5k2/8/6QK w - - 0 1", &kqk, false), a);
+        assert_eq!(index("6QK/8/5k2/8/8/8/8/8 w - - 0 1", &kqk, false), a);
+        assert_eq!(index("8/8/8/8/8/2K5/8/kq6 b - - 0 1", &kqk, true), a);
+        assert_ne!(index("8/8/8/8/8/2k5/8/KQ6 b - - 0 1", &kqk, false), a);
+
+        // With the king on the diagonal, mirroring in the diagonal gives the same index too.
+        let b = index("8/8/8/8/2k5/8/1K6/Q7 w - - 0 1", &kqk, false);
+        assert_eq!(index("8/8/8/8/8/3k4/1K6/Q7 w - - 0 1", &kqk, false), b);
+        assert_ne!(index("8/8/8/4k3/8/8/1K6/Q7 w - - 0 1", &kqk, false), b);
+
+        for idx in [0, a, b, kqk.size() + 1234] {
+            let (squares, stm) = kqk.position(idx);
+            if let Some(back) = kqk.index(&squares, stm) {
+                assert_eq!(back, idx);
+            }
+        }
+        let (squares, stm) = material.position(material.size() * 2 - 1);
+        assert_eq!((squares, stm), (vec![59, 63, 55, 63, 63], Sides::BLACK));
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use super::material::Material;
+
+/// The first bytes of a table file.
+const MAGIC: &[u8; 4] = b"LDTM";
+
+/// The version of the file format.
+const VERSION: u8 = 1;
+
+/// The number of values in a block. A probe decodes one block.
+const BLOCK_SIZE: usize = 256;
+
+/// The errors that can occur when reading a table file.
+///
+/// * `Io`: The file could not be read.
+/// * `Format`: The file is not a table, or it is damaged.
+#[derive(Debug)]
+pub enum TableError {
+    Io(io::Error),
+    Format,
+}
+
+impl Display for TableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Format => write!(f, "Not a DTM table"),
+        }
+    }
+}
+
+impl From<io::Error> for TableError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// A DTM table: one byte for each position, as described in [`super::Dtm`], compressed in
+/// blocks of [`BLOCK_SIZE`] values. Each block is a list of runs of the same value: the length
+/// of the run as a LEB128 number, then the value.
+///
+/// The file starts with [`MAGIC`], the version, the length and name of the material and the
+/// number of values as a 64-bit number. Then come the offsets of the blocks in the data as
+/// 32-bit numbers, with the end of the data last, and the data. All numbers are little-endian.
+///
+/// * `material`: The pieces of the table.
+/// * `len`: The number of values.
+/// * `offsets`: Where each block starts in `data`, and where the last block ends.
+/// * `data`: The blocks.
+pub struct DtmTable {
+    material: Material,
+    len: usize,
+    offsets: Vec<u32>,
+    data: Vec<u8>,
+}
+
+impl DtmTable {
+    /// Compresses the values of a table.
+    ///
+    /// * `material`: The pieces of the table.
+    /// * `values`: The value of each position.
+    pub fn compress(material: Material, values: &[u8]) -> Self {
+        let mut offsets = Vec::with_capacity(values.len() / BLOCK_SIZE + 2);
+        let mut data = Vec::new();
+
+        for block in values.chunks(BLOCK_SIZE) {
+            offsets.push(data.len() as u32);
+            for run in block.chunk_by(|a, b| a == b) {
+                write_leb128(&mut data, run.len());
+                data.push(run[0]);
+            }
+        }
+        offsets.push(data.len() as u32);
+
+        Self {
+            material,
+            len: values.len(),
+            offsets,
+            data,
+        }
+    }
+
+    /// Reads a table file.
+    ///
+    /// * `path`: The path of the file.
+    pub fn open(path: &Path) -> Result<Self, TableError> {
+        Self::read(&fs::read(path)?)
+    }
+
+    /// Reads a table from the contents of a file.
+    ///
+    /// * `bytes`: The contents.
+    pub fn read(bytes: &[u8]) -> Result<Self, TableError> {
+        let header = bytes.get(..6).ok_or(TableError::Format)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(TableError::Format);
+        }
+
+        let name_end = 6 + header[5] as usize;
+        let name = bytes.get(6..name_end).ok_or(TableError::Format)?;
+        let material = std::str::from_utf8(name)
+            .ok()
+            .and_then(Material::parse)
+            .ok_or(TableError::Format)?;
+
+        let len = bytes
+            .get(name_end..name_end + 8)
+            .and_then(|len| len.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(TableError::Format)? as usize;
+        if len != 2 * material.size() {
+            return Err(TableError::Format);
+        }
+
+        let offsets_start = name_end + 8;
+        let data_start = offsets_start + 4 * (len.div_ceil(BLOCK_SIZE) + 1);
+        let offsets: Vec<u32> = bytes
+            .get(offsets_start..data_start)
+            .ok_or(TableError::Format)?
+            .chunks_exact(4)
+            .map(|offset| u32::from_le_bytes([offset[0], offset[1], offset[2], offset[3]]))
+            .collect();
+        let data = bytes[data_start..].to_vec();
+        if offsets.last() != Some(&(data.len() as u32)) {
+            return Err(TableError::Format);
+        }
+
+        Ok(Self {
+            material,
+            len,
+            offsets,
+            data,
+        })
+    }
+
+    /// Writes the table in the format of a table file.
+    ///
+    /// * `writer`: Where to write the table.
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let name = self.material.to_string();
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, name.len() as u8])?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(&(self.len as u64).to_le_bytes())?;
+        for offset in &self.offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        writer.write_all(&self.data)
+    }
+
+    /// The pieces of the table.
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// The size of the compressed values, in bytes.
+    pub fn compressed_len(&self) -> usize {
+        self.data.len() + 4 * self.offsets.len()
+    }
+
+    /// The value of a position. Indices beyond the table, which a valid table can not have,
+    /// count as draws.
+    ///
+    /// * `idx`: The index of the position.
+    pub fn get(&self, idx: usize) -> u8 {
+        let block = idx / BLOCK_SIZE;
+        let (Some(&start), Some(&end)) = (self.offsets.get(block), self.offsets.get(block + 1))
+        else {
+            return 0;
+        };
+
+        let mut data = &self.data[start as usize..end as usize];
+        let mut skip = idx % BLOCK_SIZE;
+        while let Some((run, rest)) = read_leb128(data) {
+            let Some((&value, rest)) = rest.split_first() else {
+                break;
+            };
+            if skip < run {
+                return value;
+            }
+            skip -= run;
+            data = rest;
+        }
+        0
+    }
+}
+
+/// Appends a number in the LEB128 format: 7 bits per byte, starting with the lowest, with the
+/// top bit set on all bytes but the last.
+fn write_leb128(data: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        data.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+/// Reads a number in the LEB128 format, and returns it with the bytes after it.
+fn read_leb128(data: &[u8]) -> Option<(usize, &[u8])> {
+    let mut value = 0;
+    for (i, &byte) in data.iter().enumerate().take(4) {
+        value |= ((byte & 0x7F) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DtmTable, TableError, BLOCK_SIZE};
+    use crate::dtm::material::Material;
+
+    #[test]
+    fn test_table_round_trip() {
+        let material = Material::parse("KvK").unwrap();
+        let len = 2 * material.size();
+        let values: Vec<u8> = (0..len)
+            .map(|idx| (idx / 50 % 5 + idx / 700) as u8)
+            .collect();
+
+        let table = DtmTable::compress(material.clone(), &values);
+        assert!(table.compressed_len() < len);
+        let mut bytes = Vec::new();
+        table.write(&mut bytes).unwrap();
+
+        let table = DtmTable::read(&bytes).unwrap();
+        assert_eq!(table.material(), &material);
+        assert!((0..len).all(|idx| table.get(idx) == values[idx]));
+        assert_eq!(table.get(len + BLOCK_SIZE), 0);
+
+        assert!(matches!(
+            DtmTable::read(&bytes[..bytes.len() - 1]),
+            Err(TableError::Format)
+        ));
+        assert!(matches!(DtmTable::read(b"LDTM"), Err(TableError::Format)));
+    }
+}
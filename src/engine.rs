@@ -1,9 +1,703 @@
+pub mod defs;
+mod xboard;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    board::Board,
+    book::{Book, BookSelection},
+    comm::{
+        self,
+        uci::{self, UciCommand},
+        xboard::XBoardCommand,
+    },
+    dtm::DtmTables,
+    evaluation::kpk,
+    movegen::{defs::Move, MoveGenerator},
+    search::{
+        defs::{
+            SearchInfo, SearchLimits, SearchOptions, SearchResult, DEFAULT_MOVE_OVERHEAD,
+            DEFAULT_MULTIPV, MULTIPV_MAX, MULTIPV_MIN,
+        },
+        mate,
+        threads::{self, DEFAULT_THREADS, THREADS_MAX, THREADS_MIN},
+        transposition::{TranspositionTable, DEFAULT_HASH_SIZE, HASH_SIZE_MAX, HASH_SIZE_MIN},
+        SearchShared,
+    },
+    syzygy::Tablebases,
+};
+
+use xboard::XBoardState;
+
+use defs::{
+    Information, PositionError, SearchReport, ENGINE_AUTHOR, ENGINE_NAME, MOVE_OVERHEAD_MAX,
+    MOVE_OVERHEAD_MIN,
+};
+
+/// A search that is running in its own thread.
+///
+/// * `handle`: The thread the search runs in.
+/// * `stop`: Set to stop the search.
+/// * `ponder`: Set while the search is pondering; cleared on `ponderhit`.
+struct SearchThread {
+    handle: JoinHandle<()>,
+    stop: Arc<AtomicBool>,
+    ponder: Arc<AtomicBool>,
+}
+
 /// This struct holds the chess engine and its functions, so they are not all
 /// separate entities in the global space. This is the high level engine "API".
-pub struct Engine { }
+///
+/// * `board`: The current position, including the moves that led to it.
+/// * `mg`: The move generator, shared with the search threads.
+/// * `tt`: The transposition table, shared with the search threads.
+/// * `options`: The settings used for each search.
+/// * `chess960`: Castling moves are sent and read as in Chess960 (UCI_Chess960).
+/// * `book`: The opening book loaded from the BookFile option, if any.
+/// * `own_book`: Moves are played from the book when it has one (OwnBook).
+/// * `book_selection`: How a move is chosen from the book (BookSelection).
+/// * `tablebases`: The Syzygy tablebases found in the directories of SyzygyPath.
+/// * `dtm`: The DTM tables found in the directories of DtmPath.
+/// * `search`: The search that is currently running, if any.
+/// * `xboard`: The state of the game when the GUI speaks the XBoard protocol; None for UCI.
+/// * `quit`: Set when the engine should shut down.
+pub struct Engine {
+    board: Board,
+    mg: Arc<MoveGenerator>,
+    tt: Arc<TranspositionTable>,
+    options: SearchOptions,
+    chess960: bool,
+    book: Option<Book>,
+    own_book: bool,
+    book_selection: BookSelection,
+    tablebases: Arc<Tablebases>,
+    dtm: Arc<DtmTables>,
+    search: Option<SearchThread>,
+    xboard: Option<XBoardState>,
+    quit: bool,
+}
 
 impl Engine {
     pub fn new() -> Self {
-        Self {  }
+        let mut board = Board::new();
+        _ = board.fen_read(None);
+
+        // The endgame bitbase takes a moment to generate, which should not delay the first search.
+        kpk::init();
+
+        Self {
+            board,
+            mg: Arc::new(MoveGenerator::new()),
+            tt: Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE)),
+            options: SearchOptions::default(),
+            chess960: false,
+            book: None,
+            own_book: false,
+            book_selection: BookSelection::default(),
+            tablebases: Arc::new(Tablebases::default()),
+            dtm: Arc::new(DtmTables::default()),
+            search: None,
+            xboard: None,
+            quit: false,
+        }
+    }
+
+    /// Runs the engine using the UCI protocol on standard input and output, until the GUI sends
+    /// `quit` or closes the input. When the GUI sends `xboard` instead, the engine switches to
+    /// the XBoard protocol.
+    pub fn run(&mut self) {
+        self.main_loop(false);
+    }
+
+    /// Runs the engine using the XBoard protocol on standard input and output, until the GUI
+    /// sends `quit` or closes the input.
+    pub fn run_xboard(&mut self) {
+        self.main_loop(true);
+    }
+
+    /// Handles the commands of the GUI and the reports of the search thread, until the engine
+    /// should shut down.
+    ///
+    /// * `xboard`: The GUI speaks the XBoard protocol from the start.
+    fn main_loop(&mut self, xboard: bool) {
+        let (tx, rx) = mpsc::channel();
+        let _reader = comm::read_commands(tx.clone(), xboard);
+        if xboard {
+            self.handle_xboard_command(XBoardCommand::XBoard, &tx);
+        }
+
+        while !self.quit {
+            match rx.recv() {
+                Ok(Information::Comm(command)) => self.handle_command(command, &tx),
+                Ok(Information::XBoard(command)) => self.handle_xboard_command(command, &tx),
+                Ok(Information::Search(report)) if self.xboard.is_some() => {
+                    self.handle_xboard_report(report);
+                }
+                Ok(Information::Search(report)) => self.handle_search_report(report),
+                Err(_) => self.quit = true,
+            }
+        }
+
+        self.stop_search();
+    }
+
+    /// Searches the current position on the calling thread, and returns the result when the
+    /// search is done. A running search is stopped first.
+    ///
+    /// * `limits`: The limits of the search; without any, the search never ends.
+    /// * `on_info`: Called with the progress of the search.
+    pub fn search(
+        &mut self,
+        limits: SearchLimits,
+        on_info: &mut dyn FnMut(&SearchInfo),
+    ) -> SearchResult {
+        self.stop_search();
+        let shared = SearchShared::new(
+            Arc::clone(&self.mg),
+            Arc::clone(&self.tt),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .with_tablebases(Arc::clone(&self.tablebases))
+        .with_dtm_tables(Arc::clone(&self.dtm));
+
+        Engine::run_search(&self.board, &shared, limits, self.options, on_info)
+    }
+
+    /// Starts searching the current position in a separate thread, and returns the channel the
+    /// progress of the search is sent over. The last report is always
+    /// [`SearchReport::Finished`]. A running search is stopped first.
+    ///
+    /// * `limits`: The limits of the search; without any, it runs until [`Engine::stop_search`].
+    pub fn search_async(&mut self, limits: SearchLimits) -> Receiver<SearchReport> {
+        self.stop_search();
+        let (tx, rx) = mpsc::channel();
+        self.start_search(limits, move |report| _ = tx.send(report));
+        rx
+    }
+
+    /// The current position.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The settings used for each search.
+    pub fn options(&self) -> &SearchOptions {
+        &self.options
+    }
+
+    /// Sets up a position from a FEN string (or the starting position when None) and plays the
+    /// given moves, which are in UCI notation. The position is not changed when an error occurs.
+    ///
+    /// * `fen`: The FEN string of the position.
+    /// * `moves`: The moves to play from that position.
+    pub fn set_position(
+        &mut self,
+        fen: Option<&str>,
+        moves: &[String],
+    ) -> Result<(), PositionError> {
+        let mut board = Board::new();
+        board.fen_read(fen)?;
+
+        for mv in moves {
+            let found = board.parse_uci_move(mv, &self.mg)?;
+            board.make_move(found, &self.mg);
+        }
+
+        self.board = board;
+        Ok(())
+    }
+
+    /// Handles a command from the GUI.
+    ///
+    /// * `command`: The command to handle.
+    /// * `tx`: The channel the search thread reports over.
+    fn handle_command(&mut self, command: UciCommand, tx: &Sender<Information>) {
+        match command {
+            UciCommand::Uci => {
+                println!("id name {ENGINE_NAME} {}", env!("CARGO_PKG_VERSION"));
+                println!("id author {ENGINE_AUTHOR}");
+                println!(
+                    "option name Hash type spin default {DEFAULT_HASH_SIZE} min {HASH_SIZE_MIN} \
+                     max {HASH_SIZE_MAX}"
+                );
+                println!(
+                    "option name Threads type spin default {DEFAULT_THREADS} min {THREADS_MIN} \
+                     max {THREADS_MAX}"
+                );
+                println!(
+                    "option name MultiPV type spin default {DEFAULT_MULTIPV} min {MULTIPV_MIN} \
+                     max {MULTIPV_MAX}"
+                );
+                println!("option name Ponder type check default false");
+                println!(
+                    "option name Move Overhead type spin default {DEFAULT_MOVE_OVERHEAD} min \
+                     {MOVE_OVERHEAD_MIN} max {MOVE_OVERHEAD_MAX}"
+                );
+                println!("option name UCI_ShowWDL type check default false");
+                println!("option name UCI_Chess960 type check default false");
+                println!("option name NormalizeScore type check default false");
+                println!("option name OwnBook type check default false");
+                println!("option name BookFile type string default <empty>");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("option name DtmPath type string default <empty>");
+                println!(
+                    "option name BookSelection type combo default Weighted var Best var Weighted"
+                );
+                println!("uciok");
+            }
+            UciCommand::IsReady => println!("readyok"),
+            UciCommand::UciNewGame => {
+                self.stop_search();
+                self.tt.clear();
+                _ = self.board.fen_read(None);
+            }
+            UciCommand::Position { fen, moves } => {
+                self.stop_search();
+                if let Err(error) = self.set_position(fen.as_deref(), &moves) {
+                    println!("info string {error}");
+                }
+            }
+            UciCommand::Go {
+                mut limits,
+                search_moves,
+            } => {
+                self.stop_search();
+                for mv in &search_moves {
+                    match self.board.parse_uci_move(mv, &self.mg) {
+                        Ok(found) => limits.search_moves.push(found),
+                        Err(error) => println!("info string {error}"),
+                    }
+                }
+                if let Some(mv) = self.book_move(&limits) {
+                    println!("bestmove {}", self.board.move_to_uci(mv, self.chess960));
+                    return;
+                }
+                let tx = tx.clone();
+                self.start_search(limits, move |report| {
+                    _ = tx.send(Information::Search(report));
+                });
+            }
+            UciCommand::Stop => {
+                if let Some(search) = &self.search {
+                    search.stop.store(true, Ordering::Relaxed);
+                }
+            }
+            UciCommand::PonderHit => {
+                if let Some(search) = &self.search {
+                    search.ponder.store(false, Ordering::Relaxed);
+                }
+            }
+            UciCommand::Quit => self.quit = true,
+            UciCommand::SetOption { name, value } => {
+                self.stop_search();
+                self.set_option(&name, value.as_deref());
+            }
+            UciCommand::Unknown(_) => (),
+        }
+    }
+
+    /// Changes the value of an engine option, as with the UCI command `setoption`. Unknown
+    /// options and invalid values are ignored.
+    ///
+    /// * `name`: The name of the option, which is not case sensitive.
+    /// * `value`: The new value.
+    pub fn set_option(&mut self, name: &str, value: Option<&str>) {
+        // An empty BookFile, which some GUIs send without a value, unloads the book.
+        if name.eq_ignore_ascii_case("BookFile") {
+            self.load_book(value.map_or("", str::trim));
+            return;
+        }
+
+        // Likewise an empty SyzygyPath or DtmPath stops the use of the tablebases.
+        if name.eq_ignore_ascii_case("SyzygyPath") {
+            self.load_tablebases(value.map_or("", str::trim));
+            return;
+        }
+        if name.eq_ignore_ascii_case("DtmPath") {
+            self.load_dtm_tables(value.map_or("", str::trim));
+            return;
+        }
+
+        let Some(value) = value.map(str::trim) else {
+            return;
+        };
+
+        if name.eq_ignore_ascii_case("BookSelection") {
+            if value.eq_ignore_ascii_case("Best") {
+                self.book_selection = BookSelection::Best;
+            } else if value.eq_ignore_ascii_case("Weighted") {
+                self.book_selection = BookSelection::Weighted;
+            }
+            return;
+        }
+
+        if let Ok(enabled) = value.to_ascii_lowercase().parse::<bool>() {
+            if name.eq_ignore_ascii_case("UCI_ShowWDL") {
+                self.options.show_wdl = enabled;
+            } else if name.eq_ignore_ascii_case("UCI_Chess960") {
+                self.chess960 = enabled;
+            } else if name.eq_ignore_ascii_case("NormalizeScore") {
+                self.options.normalize_score = enabled;
+            } else if name.eq_ignore_ascii_case("OwnBook") {
+                self.own_book = enabled;
+            }
+            return;
+        }
+
+        let Ok(value) = value.parse::<u64>() else {
+            return;
+        };
+
+        if name.eq_ignore_ascii_case("Hash") {
+            let megabytes = (value as usize).clamp(HASH_SIZE_MIN, HASH_SIZE_MAX);
+            self.tt = Arc::new(TranspositionTable::new(megabytes));
+        } else if name.eq_ignore_ascii_case("Threads") {
+            self.options.threads = (value as usize).clamp(THREADS_MIN, THREADS_MAX);
+        } else if name.eq_ignore_ascii_case("MultiPV") {
+            self.options.multipv = (value as usize).clamp(MULTIPV_MIN, MULTIPV_MAX);
+        } else if name.eq_ignore_ascii_case("Move Overhead") {
+            let millis = value.clamp(MOVE_OVERHEAD_MIN, MOVE_OVERHEAD_MAX);
+            self.options.move_overhead = Duration::from_millis(millis);
+        }
+    }
+
+    /// Loads the opening book, or unloads it when the path is empty or `<empty>`. When the file
+    /// can not be read, the GUI is told and no book is used.
+    ///
+    /// * `path`: The path of the book file.
+    fn load_book(&mut self, path: &str) {
+        self.book = None;
+        if path.is_empty() || path == "<empty>" {
+            return;
+        }
+
+        match Book::open(path) {
+            Ok(book) => self.book = Some(book),
+            Err(error) => println!("info string Can not read book {path}: {error}"),
+        }
+    }
+
+    /// Finds the Syzygy tablebases in the directories of the path, or stops using them when the
+    /// path is empty or `<empty>`. The GUI is told how many tables were found.
+    ///
+    /// * `paths`: The directories, separated by `:` (`;` on Windows).
+    fn load_tablebases(&mut self, paths: &str) {
+        self.tablebases = Arc::new(Tablebases::default());
+        if paths.is_empty() || paths == "<empty>" {
+            return;
+        }
+
+        let tablebases = Tablebases::open(paths);
+        let (wdl, dtz) = tablebases.counts();
+        println!(
+            "info string Found {wdl} WDL and {dtz} DTZ tablebase files (up to {}-man)",
+            tablebases.max_pieces()
+        );
+        self.tablebases = Arc::new(tablebases);
+    }
+
+    /// Finds the DTM tables written by `lark tbgen` in the directories of the path, or stops
+    /// using them when the path is empty or `<empty>`. The GUI is told how many tables were
+    /// found.
+    ///
+    /// * `paths`: The directories, separated by `:` (`;` on Windows).
+    fn load_dtm_tables(&mut self, paths: &str) {
+        self.dtm = Arc::new(DtmTables::default());
+        if paths.is_empty() || paths == "<empty>" {
+            return;
+        }
+
+        let dtm = DtmTables::open(paths);
+        println!(
+            "info string Found {} DTM tablebase files (up to {}-man)",
+            dtm.len(),
+            dtm.max_pieces()
+        );
+        self.dtm = Arc::new(dtm);
+    }
+
+    /// A move from the opening book for the current position, if OwnBook is on and the search
+    /// may be skipped: not when analysing, pondering, looking for a mate or restricted to some
+    /// moves.
+    ///
+    /// * `limits`: The limits of the search that would be started.
+    fn book_move(&mut self, limits: &SearchLimits) -> Option<Move> {
+        let book = self.book.as_ref().filter(|_| self.own_book)?;
+        if limits.infinite || limits.ponder || limits.mate.is_some() {
+            return None;
+        }
+        if !limits.search_moves.is_empty() {
+            return None;
+        }
+
+        let selection = self.book_selection;
+        book.choose(
+            &mut self.board,
+            &self.mg,
+            selection,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Handles a report from the search thread.
+    ///
+    /// * `report`: The report to handle.
+    fn handle_search_report(&mut self, report: SearchReport) {
+        // Castling is written with the rook squares of the root position, which do not change
+        // during the game.
+        let notation = |mv: &Move| self.board.move_to_uci(*mv, self.chess960);
+
+        match report {
+            SearchReport::Info(SearchInfo::Summary(summary)) => {
+                println!("{}", uci::info(&summary, &notation));
+            }
+            SearchReport::Info(SearchInfo::CurrentMove(current)) => {
+                println!("{}", uci::currmove(&current, &notation));
+            }
+            SearchReport::Finished(result) => {
+                // The result may be of a search that was already stopped and joined, while a new
+                // search is running; that one must not be touched.
+                let finished = self.search.as_ref().is_some_and(|s| s.handle.is_finished());
+                if let Some(search) = self.search.take_if(|_| finished) {
+                    _ = search.handle.join();
+                }
+                println!("{}", uci::bestmove(&result, &notation));
+            }
+        }
+    }
+
+    /// Starts searching the current position in a separate thread.
+    ///
+    /// * `limits`: The limits of the search.
+    /// * `send`: Sends the progress and result of the search to where they are handled.
+    fn start_search(&mut self, limits: SearchLimits, send: impl Fn(SearchReport) + Send + 'static) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let board = self.board.clone();
+        let options = self.options;
+        let shared = SearchShared::new(
+            Arc::clone(&self.mg),
+            Arc::clone(&self.tt),
+            Arc::clone(&stop),
+        )
+        .with_tablebases(Arc::clone(&self.tablebases))
+        .with_dtm_tables(Arc::clone(&self.dtm));
+        shared.ponder.store(limits.ponder, Ordering::Relaxed);
+        let ponder = Arc::clone(&shared.ponder);
+
+        let handle = thread::spawn(move || {
+            let mut report = |info: &SearchInfo| send(SearchReport::Info(info.clone()));
+            let result = Engine::run_search(&board, &shared, limits, options, &mut report);
+            send(SearchReport::Finished(result));
+        });
+
+        self.search = Some(SearchThread {
+            handle,
+            stop,
+            ponder,
+        });
+    }
+
+    /// Runs a search: the mate search for `go mate`, and the normal search otherwise.
+    ///
+    /// * `board`: The position to search.
+    /// * `shared`: The data shared by the search threads.
+    /// * `limits`: The limits of the search.
+    /// * `options`: The settings of the search.
+    /// * `report`: Called with the progress of the search.
+    fn run_search(
+        board: &Board,
+        shared: &SearchShared,
+        limits: SearchLimits,
+        options: SearchOptions,
+        report: &mut dyn FnMut(&SearchInfo),
+    ) -> SearchResult {
+        match limits.mate {
+            Some(moves) => mate::run_mate_search(board, shared, moves, &limits, options, report),
+            None => threads::run_threads(board, shared, limits, options, report),
+        }
+    }
+
+    /// Stops the running search, if any, and waits for it to finish. Its result is still
+    /// reported, as required by the protocol.
+    pub fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            _ = search.handle.join();
+        }
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        board::{
+            defs::{Pieces, Sides, Squares, BB_SQUARES},
+            notation::MoveParseError,
+        },
+        book::{encode_move, Book, BookEntry},
+        search::defs::{SearchInfo, SearchLimits},
+    };
+
+    use super::{
+        defs::{PositionError, SearchReport},
+        Engine,
+    };
+
+    #[test]
+    fn test_set_position_plays_moves() {
+        let mut engine = Engine::new();
+        let moves = vec![String::from("e2e4"), String::from("e7e5")];
+        engine.set_position(None, &moves).unwrap();
+
+        let board = engine.board();
+        assert!(board.bb_pieces[Sides::WHITE][Pieces::PAWN] & BB_SQUARES[Squares::E4] > 0);
+        assert!(board.bb_pieces[Sides::BLACK][Pieces::PAWN] & BB_SQUARES[Squares::E5] > 0);
+        assert_eq!(board.history.len(), 2);
+    }
+
+    #[test]
+    fn test_set_position_keeps_position_on_error() {
+        let mut engine = Engine::new();
+        let moves = vec![String::from("e2e4"), String::from("e2e4")];
+
+        assert_eq!(
+            engine.set_position(None, &moves),
+            Err(PositionError::Move(MoveParseError::Illegal(String::from(
+                "e2e4"
+            ))))
+        );
+        assert!(engine.board().history.is_empty());
+    }
+
+    #[test]
+    fn test_set_option_move_overhead() {
+        let mut engine = Engine::new();
+        engine.set_option("move overhead", Some("250"));
+        assert_eq!(engine.options().move_overhead, Duration::from_millis(250));
+
+        engine.set_option("Move Overhead", Some("100000"));
+        assert_eq!(engine.options().move_overhead, Duration::from_millis(5000));
+
+        engine.set_option("Move Overhead", Some("fast"));
+        assert_eq!(engine.options().move_overhead, Duration::from_millis(5000));
+    }
+
+    #[test]
+    fn test_set_option_threads() {
+        let mut engine = Engine::new();
+        engine.set_option("Threads", Some("8"));
+        assert_eq!(engine.options().threads, 8);
+
+        engine.set_option("threads", Some("0"));
+        assert_eq!(engine.options().threads, 1);
+
+        engine.set_option("MultiPV", Some("3"));
+        assert_eq!(engine.options().multipv, 3);
+    }
+
+    #[test]
+    fn test_set_option_wdl() {
+        let mut engine = Engine::new();
+        engine.set_option("UCI_ShowWDL", Some("true"));
+        engine.set_option("normalizescore", Some("True"));
+        assert!(engine.options().show_wdl && engine.options().normalize_score);
+
+        engine.set_option("UCI_ShowWDL", Some("false"));
+        engine.set_option("NormalizeScore", Some("yes"));
+        assert!(!engine.options().show_wdl && engine.options().normalize_score);
+
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..Default::default()
+        };
+        engine.set_option("UCI_ShowWDL", Some("true"));
+        let result = engine.search(limits, &mut |_| {});
+        let wdl = result.summary.wdl.unwrap();
+        assert_eq!(wdl.win + wdl.draw + wdl.loss, 1000);
+    }
+
+    #[test]
+    fn test_book_options() {
+        let mut engine = Engine::new();
+        let e4 = engine.board.parse_uci_move("e2e4", &engine.mg).unwrap();
+        let book = Book::new(vec![BookEntry {
+            key: engine.board.polyglot_key(),
+            mv: encode_move(&engine.board, e4),
+            weight: 1,
+            learn: 0,
+        }]);
+        let path = std::env::temp_dir().join(format!("lark-book-{}.bin", std::process::id()));
+        let mut bytes = Vec::new();
+        book.write(&mut bytes).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+
+        let limits = SearchLimits::default();
+        engine.set_option("BookFile", Some(path.to_str().unwrap()));
+        engine.set_option("BookSelection", Some("best"));
+        assert_eq!(engine.book_move(&limits), None);
+
+        engine.set_option("OwnBook", Some("true"));
+        assert_eq!(engine.book_move(&limits), Some(e4));
+        let infinite = SearchLimits {
+            infinite: true,
+            ..Default::default()
+        };
+        assert_eq!(engine.book_move(&infinite), None);
+
+        engine.set_option("BookFile", None);
+        assert_eq!(engine.book_move(&limits), None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_search_reports_every_depth() {
+        let mut engine = Engine::new();
+        engine.set_option("Threads", Some("1"));
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..Default::default()
+        };
+
+        let mut depths = Vec::new();
+        let result = engine.search(limits, &mut |info| {
+            if let SearchInfo::Summary(summary) = info {
+                depths.push(summary.depth);
+            }
+        });
+
+        assert_eq!(depths, [1, 2, 3, 4]);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_search_async_ends_with_result() {
+        let mut engine = Engine::new();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..Default::default()
+        };
+
+        let reports: Vec<_> = engine.search_async(limits).iter().collect();
+
+        assert!(matches!(reports.last(), Some(SearchReport::Finished(_))));
+        assert!(reports[..reports.len() - 1]
+            .iter()
+            .all(|report| matches!(report, SearchReport::Info(_))));
     }
 }
@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+use crate::{
+    board::{fen::FenError, notation::MoveParseError},
+    comm::{uci::UciCommand, xboard::XBoardCommand},
+    search::defs::{SearchInfo, SearchResult},
+};
+
+/// The name the engine reports to the GUI.
+pub const ENGINE_NAME: &str = "Lark";
+
+/// The author the engine reports to the GUI.
+pub const ENGINE_AUTHOR: &str = "Tyler Saunders";
+
+/// The limits of the `Move Overhead` option, in milliseconds.
+pub const MOVE_OVERHEAD_MIN: u64 = 0;
+pub const MOVE_OVERHEAD_MAX: u64 = 5000;
+
+/// The messages the engine's main loop receives, from the GUI and from the search thread.
+///
+/// * `Comm`: A UCI command sent by the GUI.
+/// * `XBoard`: An XBoard command sent by the GUI.
+/// * `Search`: A report from the search thread.
+pub enum Information {
+    Comm(UciCommand),
+    XBoard(XBoardCommand),
+    Search(SearchReport),
+}
+
+/// The reports a search running in its own thread sends, to the engine's main loop or to a
+/// library user of [`Engine::search_async`](super::Engine::search_async).
+///
+/// * `Info`: The search made progress.
+/// * `Finished`: The search is done, and this is its result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchReport {
+    Info(SearchInfo),
+    Finished(SearchResult),
+}
+
+#[derive(Debug, PartialEq)]
+/// The errors that can occur when setting up a position.
+///
+/// * `Fen`: The FEN string could not be read.
+/// * `Move`: One of the moves can not be played in the position.
+pub enum PositionError {
+    Fen(FenError),
+    Move(MoveParseError),
+}
+
+impl Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fen(error) => write!(f, "{error}"),
+            Self::Move(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl From<FenError> for PositionError {
+    fn from(error: FenError) -> Self {
+        Self::Fen(error)
+    }
+}
+
+impl From<MoveParseError> for PositionError {
+    fn from(error: MoveParseError) -> Self {
+        Self::Move(error)
+    }
+}
@@ -0,0 +1,409 @@
+use std::{
+    sync::{atomic::Ordering, mpsc::Sender},
+    time::Duration,
+};
+
+use crate::{
+    board::defs::{Side, Sides, MAX_MOVE_RULE},
+    comm::xboard::{self, XBoardCommand},
+    movegen::{defs::MoveType, movelist::MoveList},
+    search::defs::{GameTime, SearchInfo, SearchLimits},
+};
+
+use super::{
+    defs::{Information, SearchReport, ENGINE_NAME},
+    Engine,
+};
+
+/// The base time of the time control until the GUI sends `level`.
+const DEFAULT_BASE_TIME: Duration = Duration::from_secs(300);
+
+/// The state of a game played over the XBoard protocol. Unlike with UCI, the engine keeps track
+/// of the game itself, and decides when to think and move.
+///
+/// * `force`: In force mode the engine only plays the moves it receives, and does not think.
+/// * `engine_side`: The side the engine plays.
+/// * `post`: Print thinking output while searching.
+/// * `moves_per_session`: The number of moves per time control of `level`; 0 when the base time
+///   is for the whole game.
+/// * `increment`: The time added to the clock after each move.
+/// * `move_time`: The exact time to think per move, set by `st`.
+/// * `depth`: The maximum depth to think, set by `sd`.
+/// * `engine_time`: The time left on the engine's clock.
+/// * `opponent_time`: The time left on the opponent's clock.
+/// * `stale`: The number of stopped searches whose results have not arrived yet. Their moves must
+///   not be played.
+pub struct XBoardState {
+    force: bool,
+    engine_side: Side,
+    post: bool,
+    moves_per_session: u32,
+    increment: Duration,
+    move_time: Option<Duration>,
+    depth: Option<u8>,
+    engine_time: Duration,
+    opponent_time: Duration,
+    stale: usize,
+}
+
+impl XBoardState {
+    /// The limits of a search of the engine's move.
+    ///
+    /// * `full_move_number`: The number of the move in the game.
+    fn limits(&self, full_move_number: u16) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.depth,
+            ..Default::default()
+        };
+
+        if self.move_time.is_some() {
+            limits.move_time = self.move_time;
+            return limits;
+        }
+
+        let (wtime, btime) = match self.engine_side {
+            Sides::WHITE => (self.engine_time, self.opponent_time),
+            _ => (self.opponent_time, self.engine_time),
+        };
+        let played = u32::from(full_move_number.saturating_sub(1));
+        let moves_to_go = (self.moves_per_session > 0)
+            .then(|| self.moves_per_session - played % self.moves_per_session);
+
+        limits.game_time = Some(GameTime {
+            wtime,
+            btime,
+            winc: self.increment,
+            binc: self.increment,
+            moves_to_go,
+        });
+        limits
+    }
+}
+
+impl Default for XBoardState {
+    fn default() -> Self {
+        Self {
+            force: false,
+            engine_side: Sides::BLACK,
+            post: false,
+            moves_per_session: 0,
+            increment: Duration::ZERO,
+            move_time: None,
+            depth: None,
+            engine_time: DEFAULT_BASE_TIME,
+            opponent_time: DEFAULT_BASE_TIME,
+            stale: 0,
+        }
+    }
+}
+
+impl Engine {
+    /// Handles a command of the XBoard protocol.
+    ///
+    /// * `command`: The command to handle.
+    /// * `tx`: The channel the search thread reports over.
+    pub(super) fn handle_xboard_command(
+        &mut self,
+        command: XBoardCommand,
+        tx: &Sender<Information>,
+    ) {
+        // From now on the reports of the search are handled the XBoard way.
+        self.xboard.get_or_insert_with(XBoardState::default);
+
+        match command {
+            XBoardCommand::XBoard | XBoardCommand::Ignored(_) => (),
+            XBoardCommand::ProtoVer(_) => {
+                let name = format!("{ENGINE_NAME} {}", env!("CARGO_PKG_VERSION"));
+                for line in xboard::features(&name) {
+                    println!("{line}");
+                }
+            }
+            XBoardCommand::New => {
+                self.discard_search();
+                self.tt.clear();
+                _ = self.board.fen_read(None);
+
+                let state = self.xboard_state();
+                state.force = false;
+                state.engine_side = Sides::BLACK;
+                state.depth = None;
+            }
+            XBoardCommand::SetBoard(fen) => {
+                self.discard_search();
+                if self.set_position(Some(&fen), &[]).is_err() {
+                    println!("tellusererror Illegal position");
+                }
+            }
+            XBoardCommand::UserMove(mv) => {
+                self.discard_search();
+                match self.board.parse_uci_move(&mv, &self.mg) {
+                    Ok(found) => {
+                        self.board.make_move(found, &self.mg);
+                        self.think_if_engine_to_move(tx);
+                    }
+                    Err(_) => println!("Illegal move: {mv}"),
+                }
+            }
+            XBoardCommand::Go => {
+                self.discard_search();
+                let side = self.board.current_side();
+                let state = self.xboard_state();
+                state.force = false;
+                state.engine_side = side;
+                self.think_if_engine_to_move(tx);
+            }
+            XBoardCommand::Force => {
+                self.discard_search();
+                self.xboard_state().force = true;
+            }
+            XBoardCommand::MoveNow => self.stop_thinking(),
+            XBoardCommand::Level {
+                moves_per_session,
+                base,
+                increment,
+            } => {
+                let state = self.xboard_state();
+                state.moves_per_session = moves_per_session;
+                state.increment = increment;
+                state.move_time = None;
+                state.engine_time = base;
+                state.opponent_time = base;
+            }
+            XBoardCommand::SetTime(time) => self.xboard_state().move_time = Some(time),
+            XBoardCommand::SetDepth(depth) => self.xboard_state().depth = Some(depth),
+            XBoardCommand::Time(time) => self.xboard_state().engine_time = time,
+            XBoardCommand::OTim(time) => self.xboard_state().opponent_time = time,
+            XBoardCommand::Undo | XBoardCommand::Remove => {
+                self.discard_search();
+                let count = if command == XBoardCommand::Undo { 1 } else { 2 };
+                for _ in 0..count.min(self.board.history.len()) {
+                    self.board.unmake_move();
+                }
+            }
+            XBoardCommand::Post => self.xboard_state().post = true,
+            XBoardCommand::NoPost => self.xboard_state().post = false,
+            XBoardCommand::Result(_) => {
+                self.discard_search();
+                self.xboard_state().force = true;
+            }
+            XBoardCommand::Ping(number) => println!("pong {number}"),
+            XBoardCommand::Memory(megabytes) => {
+                self.discard_search();
+                self.set_option("Hash", Some(&megabytes.to_string()));
+            }
+            XBoardCommand::Cores(threads) => {
+                self.discard_search();
+                self.set_option("Threads", Some(&threads.to_string()));
+            }
+            XBoardCommand::Quit => self.quit = true,
+            XBoardCommand::Invalid(line) => println!("Error (invalid arguments): {line}"),
+            XBoardCommand::Unknown(line) => println!("Error (unknown command): {line}"),
+        }
+    }
+
+    /// Handles a report from the search thread in XBoard mode: prints the thinking output, and
+    /// plays the move that was found.
+    ///
+    /// * `report`: The report to handle.
+    pub(super) fn handle_xboard_report(&mut self, report: SearchReport) {
+        let state = self.xboard_state();
+
+        match report {
+            SearchReport::Info(SearchInfo::Summary(summary)) => {
+                if state.post && state.stale == 0 && summary.multipv == 1 {
+                    println!("{}", xboard::thinking(&summary));
+                }
+            }
+            SearchReport::Info(SearchInfo::CurrentMove(_)) => (),
+            SearchReport::Finished(result) => {
+                if state.stale > 0 {
+                    state.stale -= 1;
+                    return;
+                }
+
+                // This is the result of the running search, which is about to end.
+                if let Some(search) = self.search.take() {
+                    _ = search.handle.join();
+                }
+                if let Some(mv) = result.best_move {
+                    self.board.make_move(mv, &self.mg);
+                    println!("move {}", mv.to_uci());
+                    self.report_game_end();
+                }
+            }
+        }
+    }
+
+    /// The state of the XBoard game.
+    fn xboard_state(&mut self) -> &mut XBoardState {
+        self.xboard.get_or_insert_with(XBoardState::default)
+    }
+
+    /// Starts thinking about the engine's move, unless the engine is in force mode, it is the
+    /// opponent's turn or the game is over.
+    ///
+    /// * `tx`: The channel the search thread reports over.
+    fn think_if_engine_to_move(&mut self, tx: &Sender<Information>) {
+        if self.report_game_end() {
+            return;
+        }
+
+        let side = self.board.current_side();
+        let full_move_number = self.board.state.full_move_number;
+        let state = self.xboard_state();
+        if state.force || state.engine_side != side {
+            return;
+        }
+
+        let limits = state.limits(full_move_number);
+        let tx = tx.clone();
+        self.start_search(limits, move |report| {
+            _ = tx.send(Information::Search(report));
+        });
+    }
+
+    /// Stops the running search, so it reports its result and the engine plays its move.
+    fn stop_thinking(&mut self) {
+        if let Some(search) = &self.search {
+            search.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Stops the running search, if any, without playing the move it finds.
+    fn discard_search(&mut self) {
+        if self.search.is_some() {
+            self.xboard_state().stale += 1;
+        }
+        self.stop_search();
+    }
+
+    /// Prints the result if the game is over by the rules: checkmate, stalemate, the fifty-move
+    /// rule or insufficient material. Returns true if it is.
+    fn report_game_end(&mut self) -> bool {
+        let mut legal = MoveList::new();
+        self.mg
+            .generate_legal_moves(&mut self.board, &mut legal, MoveType::All);
+
+        let result = if legal.is_empty() {
+            match (self.mg.in_check(&self.board), self.board.current_side()) {
+                (true, Sides::WHITE) => "0-1 {Black mates}",
+                (true, _) => "1-0 {White mates}",
+                (false, _) => "1/2-1/2 {Stalemate}",
+            }
+        } else if self.board.state.half_move_clock >= MAX_MOVE_RULE {
+            "1/2-1/2 {Fifty move rule}"
+        } else if self.board.is_insufficient_material() {
+            "1/2-1/2 {Insufficient material}"
+        } else {
+            return false;
+        };
+
+        println!("{result}");
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Duration};
+
+    use crate::{
+        board::defs::Sides,
+        comm::xboard::{parse, XBoardCommand},
+        engine::{defs::Information, Engine},
+        search::defs::GameTime,
+    };
+
+    use super::XBoardState;
+
+    #[test]
+    fn test_limits_from_time_control() {
+        let state = XBoardState {
+            engine_side: Sides::WHITE,
+            moves_per_session: 40,
+            increment: Duration::from_secs(2),
+            engine_time: Duration::from_secs(100),
+            opponent_time: Duration::from_secs(90),
+            ..Default::default()
+        };
+
+        let limits = state.limits(45);
+        assert_eq!(
+            limits.game_time,
+            Some(GameTime {
+                wtime: Duration::from_secs(100),
+                btime: Duration::from_secs(90),
+                winc: Duration::from_secs(2),
+                binc: Duration::from_secs(2),
+                moves_to_go: Some(36),
+            })
+        );
+
+        let fixed = XBoardState {
+            move_time: Some(Duration::from_secs(5)),
+            depth: Some(8),
+            ..Default::default()
+        };
+        let limits = fixed.limits(1);
+        assert_eq!(limits.move_time, Some(Duration::from_secs(5)));
+        assert_eq!(limits.depth, Some(8));
+        assert_eq!(limits.game_time, None);
+    }
+
+    #[test]
+    fn test_force_mode_moves_and_undo() {
+        let mut engine = Engine::new();
+        let (tx, _rx) = mpsc::channel();
+        for command in [
+            "new",
+            "force",
+            "usermove e2e4",
+            "usermove e7e5",
+            "usermove g1f3",
+        ] {
+            engine.handle_xboard_command(parse(command), &tx);
+        }
+        assert_eq!(engine.board().history.len(), 3);
+        assert!(engine.search.is_none());
+
+        engine.handle_xboard_command(XBoardCommand::Remove, &tx);
+        assert_eq!(engine.board().history.len(), 1);
+        engine.handle_xboard_command(XBoardCommand::Undo, &tx);
+        assert!(engine.board().history.is_empty());
+    }
+
+    #[test]
+    fn test_engine_replies_to_user_move() {
+        let mut engine = Engine::new();
+        let (tx, rx) = mpsc::channel();
+        for command in ["new", "sd 2", "usermove e2e4"] {
+            engine.handle_xboard_command(parse(command), &tx);
+        }
+
+        while engine.search.is_some() {
+            match rx.recv().unwrap() {
+                Information::Search(report) => engine.handle_xboard_report(report),
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(engine.board().history.len(), 2);
+        assert_eq!(engine.board().current_side(), Sides::WHITE);
+    }
+
+    #[test]
+    fn test_discarded_search_does_not_move() {
+        let mut engine = Engine::new();
+        let (tx, rx) = mpsc::channel();
+        engine.handle_xboard_command(XBoardCommand::New, &tx);
+        engine.handle_xboard_command(XBoardCommand::Go, &tx);
+        engine.handle_xboard_command(XBoardCommand::Force, &tx);
+
+        for information in rx.try_iter() {
+            if let Information::Search(report) = information {
+                engine.handle_xboard_report(report);
+            }
+        }
+        assert!(engine.board().history.is_empty());
+        assert_eq!(engine.xboard.as_ref().unwrap().stale, 0);
+    }
+}
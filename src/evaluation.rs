@@ -1,20 +1,32 @@
+pub mod endgame;
+pub mod kpk;
+pub mod wdl;
+
 use crate::board::{defs::Sides, Board};
 
 /// Calculates an evaluation of the position from the current side to move's point of view. A
 /// positive value indicates that the current side to move is better, a negative value that the
 /// opponent's side is better.
 ///
-/// Currently this is just a simple count of all the material on the board.
+/// Currently this is a count of all the material on the board, with some knowledge of endgames:
+/// see [`endgame::evaluate`] and [`endgame::scale_factor`].
 ///
 /// * `board`: The board to evaluate.
 pub fn evaluate_position(board: &Board) -> i16 {
     let side = board.state.active_side as usize;
 
-    // Start by calculating the evaluation from White's point of view.
-    let mut value: i16 = ((board.state.material[Sides::WHITE]) as i16
-        - (board.state.material[Sides::BLACK]) as i16)
-        .try_into()
-        .unwrap();
+    // Start by calculating the evaluation from White's point of view. Known endgames have their
+    // own evaluation; otherwise drawish material counts for less.
+    let mut value = endgame::evaluate(board).unwrap_or_else(|| {
+        let material = (board.state.material[Sides::WHITE]) as i32
+            - (board.state.material[Sides::BLACK]) as i32;
+        let strong = if material >= 0 {
+            Sides::WHITE
+        } else {
+            Sides::BLACK
+        };
+        (material * endgame::scale_factor(board, strong) / endgame::SCALE_NORMAL) as i16
+    });
 
     // If it is black to move, flip the value before it is returned.
     value = if side == Sides::BLACK { -value } else { value };
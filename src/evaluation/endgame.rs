@@ -0,0 +1,264 @@
+use crate::board::{
+    defs::{BitBoard, Files, Pieces, Side, Sides, Square, BB_FILES, PIECE_VALUES},
+    Board,
+};
+
+use super::kpk::{self, distance};
+
+/// The bonus for an endgame that is known to be won, on top of the material. It makes the search
+/// steer for such an endgame, and not give it up for more material.
+pub const KNOWN_WIN: i16 = 1000;
+
+/// The scale factor that leaves the material count as it is; see [`scale_factor`].
+pub const SCALE_NORMAL: i32 = 64;
+
+/// The scale factors of drawish material.
+const SCALE_OPPOSITE_BISHOPS: i32 = 22;
+const SCALE_ROOK_VERSUS_BISHOP: i32 = 8;
+const SCALE_DRAW: i32 = 0;
+
+/// The dark squares, starting with a1.
+const DARK_SQUARES: BitBoard = 0xAA55_AA55_AA55_AA55;
+
+/// The weights of the mop-up evaluation: per square the losing king is away from the centre, per
+/// square the winning king is closer to it, and per square it is closer to the corner where a
+/// bishop and knight can mate.
+const PUSH_TO_EDGE: i16 = 20;
+const PUSH_CLOSE: i16 = 20;
+const PUSH_TO_CORNER: i16 = 40;
+
+/// Evaluates the endgames against a lone king that the engine knows, from White's point of view:
+/// king and pawn against king by the [`kpk`] bitbase, and the endgames where the strong side can
+/// force mate by a mop-up evaluation. Returns None for all other positions.
+///
+/// * `board`: The position to evaluate.
+pub fn evaluate(board: &Board) -> Option<i16> {
+    let weak = [Sides::WHITE, Sides::BLACK]
+        .into_iter()
+        .find(|&side| board.bb_side[side] == board.bb_pieces[side][Pieces::KING])?;
+    let strong = weak ^ 1;
+    let pieces = &board.bb_pieces[strong];
+
+    let only_pawn = board.bb_side[strong] == pieces[Pieces::KING] | pieces[Pieces::PAWN];
+    let value = if only_pawn && pieces[Pieces::PAWN].count_ones() == 1 {
+        kpk_value(board, strong)
+    } else if can_force_mate(board, strong) {
+        mop_up(board, strong)
+    } else {
+        return None;
+    };
+
+    Some(if strong == Sides::WHITE {
+        value
+    } else {
+        -value
+    })
+}
+
+/// How much of the material advantage of the strong side counts, out of [`SCALE_NORMAL`]. It is
+/// less for material that is hard to win with, even with an extra pawn or two:
+///
+/// * Opposite-coloured bishops, when the bishops are the only pieces.
+/// * A bishop that does not control the promotion square of rook pawns, when the other king
+///   stands in front of them. This is a draw.
+/// * A rook against a bishop, without pawns.
+///
+/// * `board`: The position to evaluate.
+/// * `strong`: The side that is ahead in material.
+pub fn scale_factor(board: &Board, strong: Side) -> i32 {
+    let weak = strong ^ 1;
+    let pieces = |side: Side, piece: usize| board.bb_pieces[side][piece];
+    let non_pawn = |side: Side| {
+        board.bb_side[side] & !pieces(side, Pieces::KING) & !pieces(side, Pieces::PAWN)
+    };
+    let only = |side: Side, piece: usize| {
+        non_pawn(side) == pieces(side, piece) && pieces(side, piece).count_ones() == 1
+    };
+    let dark = |bb: BitBoard| bb & DARK_SQUARES > 0;
+
+    if only(strong, Pieces::BISHOP) && non_pawn(weak) == 0 {
+        let pawns = pieces(strong, Pieces::PAWN);
+        let file = [Files::A, Files::H]
+            .into_iter()
+            .find(|&file| pawns > 0 && pawns & !BB_FILES[file] == 0);
+
+        if let Some(file) = file {
+            let promotion = if strong == Sides::WHITE {
+                56 + file
+            } else {
+                file
+            };
+            let weak_king = square(pieces(weak, Pieces::KING));
+            let wrong_bishop = dark(pieces(strong, Pieces::BISHOP)) != dark(1 << promotion);
+            if wrong_bishop && distance(weak_king, promotion) <= 1 {
+                return SCALE_DRAW;
+            }
+        }
+    }
+
+    if only(strong, Pieces::BISHOP)
+        && only(weak, Pieces::BISHOP)
+        && dark(pieces(strong, Pieces::BISHOP)) != dark(pieces(weak, Pieces::BISHOP))
+    {
+        return SCALE_OPPOSITE_BISHOPS;
+    }
+
+    let no_pawns = pieces(strong, Pieces::PAWN) | pieces(weak, Pieces::PAWN) == 0;
+    if no_pawns && only(strong, Pieces::ROOK) && only(weak, Pieces::BISHOP) {
+        return SCALE_ROOK_VERSUS_BISHOP;
+    }
+
+    SCALE_NORMAL
+}
+
+/// The value of king and pawn against king for the side with the pawn: a draw, or a known win
+/// that is higher the further the pawn has advanced.
+fn kpk_value(board: &Board, strong: Side) -> i16 {
+    let king = square(board.bb_pieces[strong][Pieces::KING]);
+    let pawn = square(board.bb_pieces[strong][Pieces::PAWN]);
+    let weak_king = square(board.bb_pieces[strong ^ 1][Pieces::KING]);
+
+    if !kpk::probe(strong, king, pawn, weak_king, board.current_side()) {
+        return 0;
+    }
+
+    let rank = if strong == Sides::WHITE {
+        pawn / 8
+    } else {
+        7 - pawn / 8
+    };
+    KNOWN_WIN + PIECE_VALUES[Pieces::PAWN] as i16 + 10 * rank as i16
+}
+
+/// Determines if a side has the pieces to mate a lone king: a queen, a rook, a bishop and a
+/// knight, or bishops on both colours.
+fn can_force_mate(board: &Board, strong: Side) -> bool {
+    let pieces = &board.bb_pieces[strong];
+    let bishops = pieces[Pieces::BISHOP];
+
+    pieces[Pieces::QUEEN] | pieces[Pieces::ROOK] > 0
+        || (bishops > 0 && pieces[Pieces::KNIGHT] > 0)
+        || (bishops & DARK_SQUARES > 0 && bishops & !DARK_SQUARES > 0)
+}
+
+/// The mop-up evaluation of a won endgame against a lone king. The losing king has to be driven
+/// to the edge, and the winning king has to come close to help mate it. With only a bishop and
+/// knight, the mate can only be forced in a corner of the bishop's colour, so the king is driven
+/// there.
+fn mop_up(board: &Board, strong: Side) -> i16 {
+    let pieces = &board.bb_pieces[strong];
+    let king = square(pieces[Pieces::KING]);
+    let weak_king = square(board.bb_pieces[strong ^ 1][Pieces::KING]);
+    let material = board.state.material[strong] as i16;
+    let close = PUSH_CLOSE * (7 - distance(king, weak_king) as i16);
+
+    let bishop_and_knight = board.bb_side[strong]
+        == pieces[Pieces::KING] | pieces[Pieces::BISHOP] | pieces[Pieces::KNIGHT]
+        && pieces[Pieces::BISHOP].count_ones() == 1
+        && pieces[Pieces::KNIGHT].count_ones() == 1;
+
+    let push = if bishop_and_knight {
+        let corners = if pieces[Pieces::BISHOP] & DARK_SQUARES > 0 {
+            [0, 63]
+        } else {
+            [7, 56]
+        };
+        let corner = corners
+            .map(|corner| distance(weak_king, corner))
+            .into_iter()
+            .min();
+        PUSH_TO_CORNER * (7 - corner.unwrap_or(7) as i16)
+    } else {
+        PUSH_TO_EDGE * centre_distance(weak_king)
+    };
+
+    material + KNOWN_WIN + close + push
+}
+
+/// How far a square is from the four centre squares, counting files and ranks: 0 in the centre
+/// and 6 in the corners.
+fn centre_distance(square: Square) -> i16 {
+    let from_centre = |line: usize| (2 * line as i16 - 7).abs() / 2;
+    from_centre(square % 8) + from_centre(square / 8)
+}
+
+/// The square of the only piece on a bitboard.
+fn square(bb: BitBoard) -> Square {
+    bb.trailing_zeros() as Square
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, scale_factor, KNOWN_WIN, SCALE_NORMAL};
+    use crate::board::{defs::Sides, Board};
+
+    fn board(fen: &str) -> Board {
+        let mut board = Board::new();
+        board.fen_read(Some(fen)).unwrap();
+        board
+    }
+
+    fn value(fen: &str) -> Option<i16> {
+        evaluate(&board(fen))
+    }
+
+    #[test]
+    fn test_kpk_evaluation() {
+        let win = value("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1").unwrap();
+        assert!(win > KNOWN_WIN);
+        assert_eq!(value("4k3/8/4P3/4K3/8/8/8/8 w - - 0 1"), Some(0));
+        assert_eq!(value("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1"), Some(-win));
+        assert_eq!(value("k7/8/8/P7/8/8/8/K7 w - - 0 1"), Some(0));
+    }
+
+    #[test]
+    fn test_mop_up_drives_king_to_edge() {
+        let centre = value("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+        let edge = value("k7/8/8/8/8/8/8/1R2K3 w - - 0 1").unwrap();
+        let close = value("3k4/8/3K4/8/8/8/8/R7 w - - 0 1").unwrap();
+        assert!(KNOWN_WIN < centre && centre < edge && edge < close);
+        assert_eq!(value("r7/8/8/8/8/3k4/8/3K4 b - - 0 1"), Some(-close));
+
+        // A dark-squared bishop mates in a1 or h8, not in a8.
+        let right = value("7k/8/5K2/8/8/8/8/2B1N3 w - - 0 1").unwrap();
+        let wrong = value("k7/8/2K5/8/8/8/8/2B1N3 w - - 0 1").unwrap();
+        assert!(right > wrong);
+
+        assert_eq!(value("4k3/8/8/8/8/8/8/2N1K1N1 w - - 0 1"), None);
+        assert_eq!(value("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), None);
+        assert_eq!(value("4k3/pp6/8/8/8/8/8/R3K3 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn test_scale_factor() {
+        let white = Sides::WHITE;
+        let scale = |fen: &str, strong| scale_factor(&board(fen), strong);
+
+        // Opposite-coloured bishops, but not bishops of the same colour.
+        assert!(scale("4k3/8/5b2/8/3P4/2P5/2B5/4K3 w - - 0 1", white) < SCALE_NORMAL);
+        let same = "4k3/5b2/8/8/3P4/2P5/2B5/4K3 w - - 0 1";
+        assert_eq!(scale(same, white), SCALE_NORMAL);
+
+        // A light-squared bishop can not help an h-pawn promote on h8, a dark-squared one can.
+        assert_eq!(scale("7k/8/8/7P/8/8/8/1B2K3 w - - 0 1", white), 0);
+        assert_eq!(
+            scale("7k/8/8/7P/8/8/8/2B1K3 w - - 0 1", white),
+            SCALE_NORMAL
+        );
+        assert_eq!(
+            scale("8/8/5k2/7P/8/8/8/1B2K3 w - - 0 1", white),
+            SCALE_NORMAL
+        );
+        assert_eq!(
+            scale("8/8/8/8/8/8/p7/3bK1k1 b - - 0 1", Sides::BLACK),
+            SCALE_NORMAL
+        );
+        assert_eq!(scale("8/8/8/8/8/8/p7/K2bk3 b - - 0 1", Sides::BLACK), 0);
+
+        assert!(scale("4k3/8/3b4/8/8/8/8/R3K3 w - - 0 1", white) < SCALE_NORMAL);
+        assert_eq!(
+            scale("4k3/p7/3b4/8/8/8/8/R3K3 w - - 0 1", white),
+            SCALE_NORMAL
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::sync::OnceLock;
+
+use crate::board::defs::{Ranks, Side, Sides, Square};
+
+/// The number of positions in the bitbase: both sides to move, the 24 squares of the pawn on
+/// the files a to d and ranks 2 to 7, and the squares of both kings.
+const MAX_INDEX: usize = 2 * 24 * 64 * 64;
+
+/// What is known about a position during the generation of the bitbase. The values are bits, so
+/// the outcomes of the positions after all moves can be combined with `|`.
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+/// One bit for each position, set when White wins; see [`index`].
+static BITBASE: OnceLock<Vec<u64>> = OnceLock::new();
+
+/// Generates the bitbase, unless that was already done. It is also generated on the first probe,
+/// but that would delay the search that does it.
+pub fn init() {
+    bitbase();
+}
+
+/// Determines if the side with the pawn wins a king and pawn versus king position.
+///
+/// * `strong_side`: The side with the pawn.
+/// * `strong_king`: The square of the king of the side with the pawn.
+/// * `pawn`: The square of the pawn.
+/// * `weak_king`: The square of the other king.
+/// * `side_to_move`: The side to move.
+pub fn probe(
+    strong_side: Side,
+    strong_king: Square,
+    pawn: Square,
+    weak_king: Square,
+    side_to_move: Side,
+) -> bool {
+    // The bitbase has White with the pawn, on the files a to d.
+    let mut flip = if strong_side == Sides::BLACK { 56 } else { 0 };
+    if pawn % 8 >= 4 {
+        flip ^= 7;
+    }
+    let stm = if side_to_move == strong_side {
+        Sides::WHITE
+    } else {
+        Sides::BLACK
+    };
+
+    let idx = index(stm, strong_king ^ flip, weak_king ^ flip, pawn ^ flip);
+    bitbase()[idx / 64] >> (idx % 64) & 1 == 1
+}
+
+/// The bitbase, which is generated on first use.
+fn bitbase() -> &'static [u64] {
+    BITBASE.get_or_init(generate)
+}
+
+/// The index of a position in the bitbase, with White having the pawn on the files a to d.
+fn index(stm: Side, white_king: Square, black_king: Square, pawn: Square) -> usize {
+    white_king | black_king << 6 | stm << 12 | (pawn % 8) << 13 | (Ranks::R7 - pawn / 8) << 15
+}
+
+/// The position of an index: the side to move and the squares of the white king, the black king
+/// and the pawn.
+fn position(idx: usize) -> (Side, Square, Square, Square) {
+    let pawn = (Ranks::R7 - (idx >> 15)) * 8 + (idx >> 13 & 3);
+    (idx >> 12 & 1, idx & 63, idx >> 6 & 63, pawn)
+}
+
+/// Generates the bitbase by retrograde analysis. Positions where the pawn promotes safely are
+/// won, and positions where Black is stalemated or takes the pawn are drawn. Then a position
+/// with White to move is won when a move leads to a won position, and one with Black to move
+/// when all moves do, until no more positions are found. The positions that are left are draws.
+fn generate() -> Vec<u64> {
+    let mut db: Vec<u8> = (0..MAX_INDEX).map(initial).collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in 0..MAX_INDEX {
+            if db[idx] == UNKNOWN {
+                db[idx] = classify(&db, idx);
+                changed |= db[idx] != UNKNOWN;
+            }
+        }
+    }
+
+    let mut bitbase = vec![0; MAX_INDEX / 64];
+    for (idx, _) in db.iter().enumerate().filter(|(_, &result)| result == WIN) {
+        bitbase[idx / 64] |= 1 << (idx % 64);
+    }
+    bitbase
+}
+
+/// The outcome of a position that is known without looking at the positions after its moves.
+fn initial(idx: usize) -> u8 {
+    let (stm, white_king, black_king, pawn) = position(idx);
+    let promotion = pawn + 8;
+
+    if distance(white_king, black_king) <= 1
+        || white_king == pawn
+        || black_king == pawn
+        || (stm == Sides::WHITE && pawn_attacks(pawn, black_king))
+    {
+        return INVALID;
+    }
+
+    if stm == Sides::WHITE
+        && pawn / 8 == Ranks::R7
+        && white_king != promotion
+        && (distance(black_king, promotion) > 1 || distance(white_king, promotion) == 1)
+    {
+        return WIN;
+    }
+
+    if stm == Sides::BLACK {
+        let safe = |square: Square| distance(white_king, square) > 1 && !pawn_attacks(pawn, square);
+        let stalemate = !king_moves(black_king).any(safe);
+        let takes_pawn = distance(black_king, pawn) == 1 && distance(white_king, pawn) > 1;
+        if stalemate || takes_pawn {
+            return DRAW;
+        }
+    }
+
+    UNKNOWN
+}
+
+/// The outcome of a position, from the outcomes of the positions after its moves.
+fn classify(db: &[u8], idx: usize) -> u8 {
+    let (stm, white_king, black_king, pawn) = position(idx);
+    let (good, bad) = if stm == Sides::WHITE {
+        (WIN, DRAW)
+    } else {
+        (DRAW, WIN)
+    };
+
+    // Illegal moves lead to invalid positions, which add nothing.
+    let mut result = INVALID;
+    if stm == Sides::WHITE {
+        for square in king_moves(white_king) {
+            result |= db[index(Sides::BLACK, square, black_king, pawn)];
+        }
+        if pawn / 8 < Ranks::R7 {
+            result |= db[index(Sides::BLACK, white_king, black_king, pawn + 8)];
+        }
+        let blocked = pawn + 8 == white_king || pawn + 8 == black_king;
+        if pawn / 8 == Ranks::R2 && !blocked {
+            result |= db[index(Sides::BLACK, white_king, black_king, pawn + 16)];
+        }
+    } else {
+        for square in king_moves(black_king) {
+            result |= db[index(Sides::WHITE, white_king, square, pawn)];
+        }
+    }
+
+    if result & good > 0 {
+        good
+    } else if result & UNKNOWN > 0 {
+        UNKNOWN
+    } else {
+        bad
+    }
+}
+
+/// The number of king moves between two squares.
+pub(super) fn distance(a: Square, b: Square) -> usize {
+    (a % 8).abs_diff(b % 8).max((a / 8).abs_diff(b / 8))
+}
+
+/// The squares a king can move to.
+fn king_moves(square: Square) -> impl Iterator<Item = Square> {
+    (0..64).filter(move |&to| distance(square, to) == 1)
+}
+
+/// Determines if a white pawn attacks a square.
+fn pawn_attacks(pawn: Square, square: Square) -> bool {
+    square / 8 == pawn / 8 + 1 && (square % 8).abs_diff(pawn % 8) == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::probe;
+    use crate::board::defs::{Sides, Squares};
+
+    #[test]
+    fn test_kpk_bitbase() {
+        let (white, black) = (Sides::WHITE, Sides::BLACK);
+
+        // The king in front of its pawn on the sixth rank wins with either side to move.
+        assert!(probe(white, Squares::E6, Squares::E5, Squares::E8, white));
+        assert!(probe(white, Squares::E6, Squares::E5, Squares::E8, black));
+
+        // Behind the pawn, it only wins when the other king is not in front of the pawn.
+        assert!(!probe(white, Squares::E5, Squares::E6, Squares::E8, white));
+        assert!(!probe(white, Squares::E5, Squares::E6, Squares::E8, black));
+        assert!(probe(white, Squares::E5, Squares::E6, Squares::F8, white));
+
+        // The same for Black, on the other side of the board.
+        assert!(!probe(black, Squares::E4, Squares::E3, Squares::E1, black));
+        assert!(probe(black, Squares::E4, Squares::E3, Squares::F1, black));
+
+        // A king in the corner stops a rook pawn; a king outside the square of the pawn does not.
+        assert!(!probe(white, Squares::A1, Squares::A5, Squares::A8, white));
+        assert!(probe(white, Squares::A1, Squares::A4, Squares::H2, white));
+        assert!(!probe(white, Squares::A1, Squares::A4, Squares::E4, black));
+        assert!(probe(white, Squares::A1, Squares::A4, Squares::G4, black));
+
+        // Black takes the pawn.
+        assert!(!probe(white, Squares::H1, Squares::C4, Squares::D5, black));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    board::{defs::Sides, Board},
+    search::defs::{moves_to_mate, Wdl},
+};
+
+/// The coefficients of the polynomials that give the parameters of the model for an amount of
+/// material on the board; see [`score_to_wdl`]. Highest power first.
+///
+/// Fitted with `lark wdlfit` and its defaults: 5000 self-play games at 4000 nodes per move,
+/// each from 8 random plies, which gave 298239 positions with their score and the outcome of
+/// their game. Run it again after changes to the evaluation that change its scale.
+const WIN_RATE_A: [f64; 4] = [-513.061, 1174.781, -806.443, 608.969];
+const WIN_RATE_B: [f64; 4] = [-1262.318, 2967.052, -2116.983, 735.312];
+
+/// The material of the model is clamped to this range, as there is too little data about
+/// positions outside it. The starting position has 78.
+pub const MATERIAL_MIN: u32 = 17;
+pub const MATERIAL_MAX: u32 = 78;
+
+/// The polynomials of the model take the material divided by this value.
+pub const MATERIAL_NORMALIZE: f64 = 58.0;
+
+/// Counts the material on the board for the model, in pawns: a pawn is 1, a knight or bishop 3,
+/// a rook 5 and a queen 9.
+///
+/// * `board`: The board to count the material on.
+pub fn material_count(board: &Board) -> u32 {
+    let centipawns = board.state.material[Sides::WHITE] + board.state.material[Sides::BLACK];
+    u32::from(centipawns / 100)
+}
+
+/// The parameters of the model for an amount of material: `a` is the score at which the side to
+/// move wins half of the games, `b` how quickly the win rate rises around it.
+///
+/// * `material`: The material on the board; see [`material_count`].
+fn parameters(material: u32) -> (f64, f64) {
+    let x = f64::from(material.clamp(MATERIAL_MIN, MATERIAL_MAX)) / MATERIAL_NORMALIZE;
+    let polynomial = |coefficients: [f64; 4]| coefficients.iter().fold(0.0, |v, c| v * x + c);
+
+    (polynomial(WIN_RATE_A), polynomial(WIN_RATE_B))
+}
+
+/// The chance of winning for the side to move, in permille.
+///
+/// * `score`: The score of the position, from the side to move's point of view.
+/// * `material`: The material on the board; see [`material_count`].
+fn win_rate(score: i16, material: u32) -> f64 {
+    let (a, b) = parameters(material);
+    1000.0 * logistic(score, a, b)
+}
+
+/// The chance of winning for the side to move, between 0 and 1, for the given parameters of
+/// the model; see [`parameters`].
+fn logistic(score: i16, a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + ((a - f64::from(score)) / b).exp())
+}
+
+/// The chances of winning, drawing and losing of the side to move, between 0 and 1, for the
+/// given parameters of the model instead of those of the material. Used to fit the model.
+///
+/// * `score`: The score of the position, from the side to move's point of view.
+/// * `a`: The score at which the side to move wins half of the games.
+/// * `b`: How quickly the win rate rises around `a`.
+pub fn model_wdl(score: i16, a: f64, b: f64) -> [f64; 3] {
+    let win = logistic(score, a, b);
+    let loss = logistic(-score, a, b);
+    [win, 1.0 - win - loss, loss]
+}
+
+/// Converts a score into the expected outcome of the game. The chance of winning is a logistic
+/// function of the score, whose midpoint and slope depend on the material on the board: with
+/// less material, an advantage is more likely to be converted. The chance of losing is the
+/// chance of winning for the opponent, and the rest is the chance of a draw. A mate score is a
+/// certain win or loss.
+///
+/// * `score`: The score of the position, from the side to move's point of view.
+/// * `material`: The material on the board; see [`material_count`].
+pub fn score_to_wdl(score: i16, material: u32) -> Wdl {
+    match moves_to_mate(score) {
+        Some(moves) if moves > 0 => Wdl {
+            win: 1000,
+            ..Default::default()
+        },
+        Some(_) => Wdl {
+            loss: 1000,
+            ..Default::default()
+        },
+        None => {
+            let win = win_rate(score, material).round() as u16;
+            let loss = win_rate(-score, material).round() as u16;
+            let draw = 1000u16.saturating_sub(win + loss);
+
+            Wdl { win, draw, loss }
+        }
+    }
+}
+
+/// Scales a score so that 100 centipawns always means the same chance of winning, 50%, whatever
+/// the material on the board. Mate scores are not changed.
+///
+/// * `score`: The score of the position, from the side to move's point of view.
+/// * `material`: The material on the board; see [`material_count`].
+pub fn normalize_score(score: i16, material: u32) -> i16 {
+    if moves_to_mate(score).is_some() {
+        return score;
+    }
+
+    let (a, _) = parameters(material);
+    (f64::from(score) * 100.0 / a).round() as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::Board,
+        search::defs::{Wdl, CHECKMATE},
+    };
+
+    use super::{material_count, normalize_score, score_to_wdl, MATERIAL_MAX, MATERIAL_MIN};
+
+    #[test]
+    fn test_material_count() {
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        assert_eq!(material_count(&board), 78);
+
+        board
+            .fen_read(Some("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1"))
+            .unwrap();
+        assert_eq!(material_count(&board), 6);
+    }
+
+    #[test]
+    fn test_wdl_is_symmetric_and_complete() {
+        for material in [MATERIAL_MIN, 40, MATERIAL_MAX] {
+            let equal = score_to_wdl(0, material);
+            assert_eq!(equal.win, equal.loss);
+
+            for score in [-600, -150, -20, 35, 300, 1200] {
+                let wdl = score_to_wdl(score, material);
+                let flipped = score_to_wdl(-score, material);
+                assert_eq!(wdl.win + wdl.draw + wdl.loss, 1000);
+                assert_eq!((wdl.win, wdl.loss), (flipped.loss, flipped.win));
+            }
+        }
+    }
+
+    #[test]
+    fn test_wdl_grows_with_score_and_less_material() {
+        let small = score_to_wdl(150, 78);
+        let large = score_to_wdl(300, 78);
+        assert!(large.win > small.win && large.loss <= small.loss);
+
+        let endgame = score_to_wdl(150, 20);
+        assert!(endgame.win > small.win);
+    }
+
+    #[test]
+    fn test_wdl_of_mate_scores() {
+        let win = Wdl {
+            win: 1000,
+            ..Default::default()
+        };
+        let loss = Wdl {
+            loss: 1000,
+            ..Default::default()
+        };
+        assert_eq!(score_to_wdl(CHECKMATE - 5, 30), win);
+        assert_eq!(score_to_wdl(-CHECKMATE + 4, 30), loss);
+    }
+
+    #[test]
+    fn test_normalized_pawn_is_half_a_win() {
+        for material in MATERIAL_MIN..=MATERIAL_MAX {
+            let score = (1..1000)
+                .find(|&s| normalize_score(s, material) >= 100)
+                .unwrap();
+            let wdl = score_to_wdl(score, material);
+            assert!((495..=510).contains(&wdl.win), "{material}: {wdl:?}");
+        }
+        assert_eq!(normalize_score(CHECKMATE - 3, 40), CHECKMATE - 3);
+    }
+}
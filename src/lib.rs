@@ -0,0 +1,12 @@
+pub mod board;
+pub mod book;
+pub mod cli;
+pub mod comm;
+pub mod dtm;
+pub mod engine;
+pub mod evaluation;
+pub mod movegen;
+pub mod pgn;
+pub mod search;
+pub mod syzygy;
+pub mod utils;
@@ -1,30 +1,15 @@
-mod board;
-mod engine;
-mod evaluation;
-mod movegen;
-mod utils;
+use std::{env, process};
 
-use movegen::{defs::Move, MoveGenerator};
-
-use crate::board::Board;
+use lark::{cli, engine::Engine};
 
 fn main() {
-    let mut board = Board::new();
-
-    _ = board.fen_read(None);
-
-    println!("{board}");
-    println!("   {}", board.state);
-    println!("   evaluation {}", evaluation::evaluate_position(&board));
-
-    let mut move_list: Vec<Move> = Vec::new();
-    let move_gen = MoveGenerator::new();
-    move_gen.generate_moves(&board, &mut move_list);
-
-    println!("Possible Moves for White:");
-
-    for mv in move_list.iter() {
-        println!();
-        print!("{mv}");
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.is_empty() {
+        let mut engine = Engine::new();
+        engine.run();
+    } else if let Err(error) = cli::run(&args) {
+        eprintln!("{error}");
+        process::exit(1);
     }
 }
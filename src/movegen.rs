@@ -1,17 +1,24 @@
 use std::vec;
+mod checks;
+pub mod history;
 mod init;
 pub mod magics;
+pub mod movelist;
+pub mod picker;
+mod see;
+mod unmoves;
 
-use defs::{Move, Shift};
+use defs::{Move, MoveType, Shift};
 use magics::{
     Magic, BISHOP_TABLE_SIZE, PRECALC_BISHOP_MAGIC_NUMBERS, PRECALC_ROOK_MAGIC_NUMBERS,
     ROOK_TABLE_SIZE,
 };
+use movelist::MoveList;
 
 use crate::{
     board::{
         defs::{
-            BitBoard, Castling, NrOf, Piece, Pieces, Ranks, Side, Sides, Square, Squares, BB_RANKS,
+            BitBoard, Castling, Files, NrOf, Piece, Pieces, Ranks, Side, Sides, Square, BB_RANKS,
             BB_SQUARES, EMPTY,
         },
         Board,
@@ -34,6 +41,7 @@ const PROMOTION_PIECES: [usize; 4] = [Pieces::QUEEN, Pieces::ROOK, Pieces::BISHO
 /// * `bishop`: The bishop's attack table.
 /// * `rook_magics`: The per square Rook Magic numbers.
 /// * `bishop_magics`: The per square Bishop Magic numbers.
+/// * `between`: The squares strictly between two squares on a line, indexed by both squares.
 pub struct MoveGenerator {
     king: [BitBoard; NrOf::SQUARES],
     knight: [BitBoard; NrOf::SQUARES],
@@ -42,6 +50,7 @@ pub struct MoveGenerator {
     bishop: Vec<BitBoard>,
     rook_magics: [Magic; NrOf::SQUARES],
     bishop_magics: [Magic; NrOf::SQUARES],
+    between: Vec<[BitBoard; NrOf::SQUARES]>,
 }
 
 impl MoveGenerator {
@@ -57,11 +66,13 @@ impl MoveGenerator {
             bishop: vec![EMPTY; BISHOP_TABLE_SIZE],
             rook_magics: [Magic::default(); NrOf::SQUARES],
             bishop_magics: [Magic::default(); NrOf::SQUARES],
+            between: vec![[EMPTY; NrOf::SQUARES]; NrOf::SQUARES],
         };
         mg.init_king();
         mg.init_knight();
         mg.init_pawns();
         mg.init_magics_with_precalc(PRECALC_ROOK_MAGIC_NUMBERS, PRECALC_BISHOP_MAGIC_NUMBERS);
+        mg.init_between();
         mg
     }
 
@@ -77,11 +88,13 @@ impl MoveGenerator {
             bishop: vec![EMPTY; BISHOP_TABLE_SIZE],
             rook_magics: [Magic::default(); NrOf::SQUARES],
             bishop_magics: [Magic::default(); NrOf::SQUARES],
+            between: vec![[EMPTY; NrOf::SQUARES]; NrOf::SQUARES],
         };
         mg.init_king();
         mg.init_knight();
         mg.init_pawns();
         mg.init_magics();
+        mg.init_between();
         mg
     }
 
@@ -89,14 +102,68 @@ impl MoveGenerator {
     ///
     /// * `board`: The current board to generate moves for
     /// * `move_list`: A list where the generated moves will be appended.
-    pub fn generate_moves(&self, board: &Board, move_list: &mut Vec<Move>) {
-        self.piece(board, Pieces::KING, move_list);
-        self.piece(board, Pieces::KNIGHT, move_list);
-        self.piece(board, Pieces::QUEEN, move_list);
-        self.piece(board, Pieces::ROOK, move_list);
-        self.piece(board, Pieces::BISHOP, move_list);
-        self.pawns(board, move_list);
-        self.castling(board, move_list);
+    pub fn generate_moves(&self, board: &Board, move_list: &mut MoveList) {
+        self.generate(board, move_list, MoveType::All);
+    }
+
+    /// Generates only the captures and promotions for the side that is to move.
+    ///
+    /// * `board`: The current board to generate moves for
+    /// * `move_list`: A list where the generated moves will be appended.
+    pub fn generate_captures(&self, board: &Board, move_list: &mut MoveList) {
+        self.generate(board, move_list, MoveType::Captures);
+    }
+
+    /// Generates only the quiet moves (no captures or promotions) for the side that is to move.
+    ///
+    /// * `board`: The current board to generate moves for
+    /// * `move_list`: A list where the generated moves will be appended.
+    pub fn generate_quiets(&self, board: &Board, move_list: &mut MoveList) {
+        self.generate(board, move_list, MoveType::Quiets);
+    }
+
+    /// Generates the moves of the given [`MoveType`] for the side that is to move.
+    ///
+    /// * `board`: The current board to generate moves for
+    /// * `move_list`: A list where the generated moves will be appended.
+    /// * `move_type`: Which kind of moves to generate.
+    fn generate(&self, board: &Board, move_list: &mut MoveList, move_type: MoveType) {
+        self.piece(board, Pieces::KING, move_list, move_type);
+        self.piece(board, Pieces::KNIGHT, move_list, move_type);
+        self.piece(board, Pieces::QUEEN, move_list, move_type);
+        self.piece(board, Pieces::ROOK, move_list, move_type);
+        self.piece(board, Pieces::BISHOP, move_list, move_type);
+        self.pawns(board, move_list, move_type);
+        if move_type != MoveType::Captures {
+            self.castling(board, move_list);
+        }
+    }
+
+    /// Determines if the move could have been generated in the current position, i.e. that it is
+    /// pseudo-legal. This is used to verify moves that do not come from the move generator, such
+    /// as a move stored during an earlier search.
+    ///
+    /// * `board`: The current board.
+    /// * `mv`: The move to verify.
+    pub fn is_pseudo_legal(&self, board: &Board, mv: Move) -> bool {
+        let player = board.current_side();
+        let piece = mv.piece();
+
+        if piece >= Pieces::NONE || board.bb_pieces[player][piece] & BB_SQUARES[mv.from()] == 0 {
+            return false;
+        }
+
+        let mut list = MoveList::new();
+        match piece {
+            Pieces::PAWN => self.pawns(board, &mut list, MoveType::All),
+            Pieces::KING => {
+                self.piece(board, piece, &mut list, MoveType::All);
+                self.castling(board, &mut list);
+            }
+            _ => self.piece(board, piece, &mut list, MoveType::All),
+        }
+
+        list.contains(&mv)
     }
 
     /// Generate all pseudo-legal moves for the particular piece type. This generates
@@ -107,12 +174,42 @@ impl MoveGenerator {
     /// * `board`: The current board
     /// * `piece`: the [`Pieces`] to generate moves for.
     /// * `list`: the move list to append all pseudo-legal moves.
-    pub fn piece(&self, board: &Board, piece: Piece, list: &mut Vec<Move>) {
+    /// * `move_type`: Which kind of moves to generate.
+    pub fn piece(&self, board: &Board, piece: Piece, list: &mut MoveList, move_type: MoveType) {
         let player = board.current_side();
         let bb_occupied = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
         let bb_own_pieces = board.bb_side[player];
+        let bb_opponent_pieces = board.bb_side[board.opponent()];
+
+        let bb_allowed = match move_type {
+            MoveType::All => !bb_own_pieces,
+            MoveType::Captures => bb_opponent_pieces,
+            MoveType::Quiets => !bb_occupied,
+        };
+
+        let bb_pieces = board.bb_pieces[player][piece];
+        self.add_piece_moves(board, piece, bb_pieces, bb_allowed, list);
+    }
 
-        let mut bb_pieces = board.bb_pieces[player][piece];
+    /// Generate the pseudo-legal moves of the given pieces, restricted to the allowed destination
+    /// squares.
+    ///
+    /// * `board`: The current board
+    /// * `piece`: the [`Pieces`] type of the pieces; must not be a PAWN.
+    /// * `bb_pieces`: The pieces of the side to move to generate moves for.
+    /// * `bb_allowed`: The squares the pieces are allowed to move to.
+    /// * `list`: the move list to append all pseudo-legal moves.
+    fn add_piece_moves(
+        &self,
+        board: &Board,
+        piece: Piece,
+        bb_pieces: BitBoard,
+        bb_allowed: BitBoard,
+        list: &mut MoveList,
+    ) {
+        let bb_occupied = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
+        let bb_own_pieces = board.bb_side[board.current_side()];
+        let mut bb_pieces = bb_pieces;
 
         while bb_pieces > 0 {
             let from = bits::next(&mut bb_pieces);
@@ -124,108 +221,116 @@ impl MoveGenerator {
                 _ => panic!("Not a piece: {piece}"),
             };
 
-            let bb_moves = bb_target & !bb_own_pieces;
+            let bb_moves = bb_target & bb_allowed & !bb_own_pieces;
             self.add_moves(board, piece, from, bb_moves, list);
         }
     }
 
     /// Generate all castling moves for the current side.
     ///
+    /// This works for any placement of the king and the rooks, as in Chess960: the king ends on
+    /// the g-file or the c-file and the rook next to it, all squares both pieces pass must be
+    /// empty (apart from the king and the rook themselves), and the king may not start in or
+    /// pass through check. Whether the king lands in check is tested when the move is played.
+    ///
     /// * `board`: The current board.
     /// * `list`: The current move list.
-    pub fn castling(&self, board: &Board, list: &mut Vec<Move>) {
+    pub fn castling(&self, board: &Board, list: &mut MoveList) {
         let player = board.current_side();
         let opponent = board.opponent();
-
-        let castle_permissions_white = (board.state.castling & (Castling::WK | Castling::WQ)) > 0;
-        let castle_permissions_black = (board.state.castling & (Castling::BK | Castling::BQ)) > 0;
-
-        let bb_occupancy = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
-        let mut bb_king = board.bb_pieces[player][Pieces::KING];
+        let rights = board.state.castling & Castling::rights(player);
 
         // If there is no king on the board, don't proceed.
         // This is not really legal state, but some tests don't always put a king piece on the
         // board.
-        if bb_king == 0 {
-            return
+        if rights == 0 || board.bb_pieces[player][Pieces::KING] == 0 {
+            return;
         }
 
-        let from = bits::next(&mut bb_king);
-
-        // Generate castling moves for white.
-        if player == Sides::WHITE && castle_permissions_white {
-            // King side
-            if board.state.castling & Castling::WK > 0 {
-                let bb_kingside_blockers = BB_SQUARES[Squares::F1] | BB_SQUARES[Squares::G1];
-                let is_kingside_blocked = (bb_occupancy & bb_kingside_blockers) > 0;
-
-                if !is_kingside_blocked
-                    && !self.square_attacked(board, opponent, Squares::F1)
-                    && !self.square_attacked(board, opponent, Squares::E1)
-                {
-                    let to = BB_SQUARES[from] << 2;
-                    self.add_moves(board, Pieces::KING, from, to, list)
-                }
+        let bb_occupancy = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
+        let from = board.king_square(player);
+        let rank_start = from - from % 8;
+        let span = |a: Square, b: Square| self.between[a][b] | BB_SQUARES[a] | BB_SQUARES[b];
+
+        for kingside in [true, false] {
+            let right = Castling::right(player, kingside);
+            let rook_from = board.castling_rook_square(right);
+            if rights & right == 0
+                || board.bb_pieces[player][Pieces::ROOK] & BB_SQUARES[rook_from] == 0
+            {
+                continue;
             }
 
-            // Queen side
-            if board.state.castling & Castling::WQ > 0 {
-                let bb_queenside_blockers =
-                    BB_SQUARES[Squares::B1] | BB_SQUARES[Squares::C1] | BB_SQUARES[Squares::D1];
-                let is_queenside_blocked = (bb_occupancy & bb_queenside_blockers) > 0;
-
-                if !is_queenside_blocked
-                    && !self.square_attacked(board, opponent, Squares::E1)
-                    && !self.square_attacked(board, opponent, Squares::D1)
-                {
-                    let to = BB_SQUARES[from] >> 2;
-                    self.add_moves(board, Pieces::KING, from, to, list);
-                }
+            let (to, rook_to) = if kingside {
+                (rank_start + Files::G, rank_start + Files::F)
+            } else {
+                (rank_start + Files::C, rank_start + Files::D)
+            };
+
+            let bb_pieces = BB_SQUARES[from] | BB_SQUARES[rook_from];
+            let bb_path = (span(from, to) | span(rook_from, rook_to)) & !bb_pieces;
+            if bb_occupancy & bb_path > 0 {
+                continue;
             }
-        }
 
-        // Generate castling moves for black.
-        if player == Sides::BLACK && castle_permissions_black {
-            // King side
-            if board.state.castling & Castling::BK > 0 {
-                let bb_kingside_blockers = BB_SQUARES[Squares::F8] | BB_SQUARES[Squares::G8];
-                let is_kingside_blocked = (bb_occupancy & bb_kingside_blockers) > 0;
-
-                if !is_kingside_blocked
-                    && !self.square_attacked(board, opponent, Squares::F8)
-                    && !self.square_attacked(board, opponent, Squares::E8)
-                {
-                    let to = BB_SQUARES[from] << 2;
-                    self.add_moves(board, Pieces::KING, from, to, list)
-                }
+            let mut bb_squares = self.between[from][to] | BB_SQUARES[from];
+            let mut in_check = false;
+            while bb_squares > 0 && !in_check {
+                in_check = self.square_attacked(board, opponent, bits::next(&mut bb_squares));
             }
 
-            // Queen side
-            if board.state.castling & Castling::BQ > 0 {
-                let bb_queenside_blockers =
-                    BB_SQUARES[Squares::B8] | BB_SQUARES[Squares::C8] | BB_SQUARES[Squares::D8];
-                let is_queenside_blocked = (bb_occupancy & bb_queenside_blockers) > 0;
-
-                if !is_queenside_blocked
-                    && !self.square_attacked(board, opponent, Squares::E8)
-                    && !self.square_attacked(board, opponent, Squares::D8)
-                {
-                    let to = BB_SQUARES[from] >> 2;
-                    self.add_moves(board, Pieces::KING, from, to, list);
-                }
+            if !in_check {
+                let move_data = Pieces::KING
+                    | from << Shift::FROM_SQ
+                    | to << Shift::TO_SQ
+                    | Pieces::NONE << Shift::CAPTURE
+                    | 1 << Shift::CASTLING
+                    | Pieces::NONE << Shift::PROMOTION;
+                list.push(Move::new(move_data));
             }
         }
     }
 
     /// Generates all pseudo-legal pawn moves.
     ///
-    /// This does consider possible en-passant captures.
+    /// This does consider possible en-passant captures. Pushes to the last rank are considered
+    /// noisy moves, so they are generated together with the captures.
     ///
     /// NOTE: Not all moves are actually legal; they do not consider things such as pins.
     ///
     /// * `board`: The current board
     /// * `list`: the move list to append all pseudo-legal pawn moves.
-    pub fn pawns(&self, board: &Board, list: &mut Vec<Move>) {
+    /// * `move_type`: Which kind of moves to generate.
+    pub fn pawns(&self, board: &Board, list: &mut MoveList, move_type: MoveType) {
+        let bb_promotion = BB_RANKS[Ranks::R1] | BB_RANKS[Ranks::R8];
+
+        let (bb_push_mask, bb_capture_mask) = match move_type {
+            MoveType::All => (!EMPTY, !EMPTY),
+            MoveType::Captures => (bb_promotion, !EMPTY),
+            MoveType::Quiets => (!bb_promotion, EMPTY),
+        };
+
+        let bb_pawns = board.bb_pieces[board.current_side()][Pieces::PAWN];
+        self.add_pawn_moves(board, bb_pawns, bb_push_mask, bb_capture_mask, list);
+    }
+
+    /// Generate the pseudo-legal moves of the given pawns, restricted to the allowed destination
+    /// squares for pushes and captures.
+    ///
+    /// * `board`: The current board
+    /// * `bb_pawns`: The pawns of the side to move to generate moves for.
+    /// * `bb_push_mask`: The squares the pawns are allowed to push to.
+    /// * `bb_capture_mask`: The squares the pawns are allowed to capture on. For an en-passant
+    ///   capture this is the en-passant square itself.
+    /// * `list`: the move list to append all pseudo-legal pawn moves.
+    fn add_pawn_moves(
+        &self,
+        board: &Board,
+        bb_pawns: BitBoard,
+        bb_push_mask: BitBoard,
+        bb_capture_mask: BitBoard,
+        list: &mut MoveList,
+    ) {
         const NORTH: i8 = 8;
         const SOUTH: i8 = -8;
 
@@ -246,28 +351,27 @@ impl MoveGenerator {
         };
 
         let rotation_count = (NrOf::SQUARES as i8 + direction) as u32;
-        let mut bb_pawns = board.bb_pieces[player][Pieces::PAWN];
+        let mut bb_pawns = bb_pawns;
 
         while bb_pawns > 0 {
             let from = bits::next(&mut bb_pawns);
             let to = (from as i8 + direction) as usize;
-            let mut bb_moves = 0;
 
             // Generate pawn pushes
             let bb_push = BB_SQUARES[to];
             let bb_one_step = bb_push & bb_empty;
             let bb_two_step = bb_one_step.rotate_left(rotation_count) & bb_empty & bb_fourth;
-            bb_moves |= bb_one_step | bb_two_step;
+            let bb_pushes = bb_one_step | bb_two_step;
 
             // Generate pawn captures
             let bb_targets = self.pawns[player][from];
-            let bb_captures = bb_targets & bb_opponent_pieces;
             let bb_ep_capture = match board.state.en_passant {
                 Some(ep) => bb_targets & BB_SQUARES[ep as usize],
                 None => 0,
             };
+            let bb_captures = (bb_targets & bb_opponent_pieces) | bb_ep_capture;
 
-            bb_moves |= bb_captures | bb_ep_capture;
+            let bb_moves = (bb_pushes & bb_push_mask) | (bb_captures & bb_capture_mask);
 
             self.add_moves(board, Pieces::PAWN, from, bb_moves, list);
         }
@@ -276,7 +380,7 @@ impl MoveGenerator {
     /// Creates and adds new [`Move`]s to the provided move list.
     ///
     /// This will iterate the Bitboard provided in `to` and create new moves for each target square
-    /// in the Bitboard.
+    /// in the Bitboard. A pawn move to the last rank is added once for every promotion piece.
     ///
     /// * `board`: The current board
     /// * `piece`: The piece this move is for.
@@ -289,33 +393,37 @@ impl MoveGenerator {
         piece: Piece,
         from: Square,
         to: BitBoard,
-        move_list: &mut Vec<Move>,
+        move_list: &mut MoveList,
     ) {
         let mut bb_to = to;
 
         let is_pawn = piece == Pieces::PAWN;
+        let bb_promotion = BB_RANKS[Ranks::R1] | BB_RANKS[Ranks::R8];
 
         while bb_to > 0 {
             let to_square = bits::next(&mut bb_to);
-            let capture = 0;
             let en_passant = match board.state.en_passant {
                 Some(square) => is_pawn && (square as usize == to_square),
                 None => false,
             };
-            let promotion = false;
-            let double_step = false;
-            let castling = false;
+            let capture = match board.get_piece_on_square(to_square) {
+                Ok((captured, _)) => captured,
+                Err(_) if en_passant => Pieces::PAWN,
+                Err(none) => none,
+            };
+            let distance = (to_square as i8 - from as i8).abs();
+            let promotion = is_pawn && (BB_SQUARES[to_square] & bb_promotion > 0);
+            let double_step = is_pawn && distance == 16;
 
             let move_data = (piece)
                 | from << Shift::FROM_SQ
                 | to_square << Shift::TO_SQ
                 | capture << Shift::CAPTURE
                 | (en_passant as usize) << Shift::EN_PASSANT
-                | (double_step as usize) << Shift::DOUBLE_STEP
-                | (castling as usize) << Shift::CASTLING;
+                | (double_step as usize) << Shift::DOUBLE_STEP;
 
             if !promotion {
-                move_list.push(Move::new(move_data));
+                move_list.push(Move::new(move_data | Pieces::NONE << Shift::PROMOTION));
             } else {
                 PROMOTION_PIECES.iter().for_each(|piece| {
                     let promotion_piece = *piece << Shift::PROMOTION;
@@ -392,6 +500,12 @@ impl MoveGenerator {
     }
 }
 
+impl Default for MoveGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::board::{
@@ -399,7 +513,7 @@ mod tests {
         Board,
     };
 
-    use super::{defs::Move, MoveGenerator};
+    use super::{movelist::MoveList, MoveGenerator};
 
     /// Parameterize a set of test cases for a particular side
     ///
@@ -464,7 +578,7 @@ mod tests {
         board.state.active_side = Sides::WHITE as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([Squares::D3, Squares::D4, Squares::E5, Squares::D5]);
@@ -495,7 +609,7 @@ mod tests {
         board.state.active_side = Sides::BLACK as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([Squares::E6, Squares::E5, Squares::D4, Squares::E4]);
@@ -527,7 +641,7 @@ mod tests {
         board.state.active_side = Sides::WHITE as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -550,7 +664,7 @@ mod tests {
         board.state.active_side = Sides::WHITE as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -574,7 +688,7 @@ mod tests {
         board.state.active_side = Sides::WHITE as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -598,7 +712,7 @@ mod tests {
         board.state.active_side = Sides::WHITE as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -622,7 +736,7 @@ mod tests {
         board.state.active_side = Sides::WHITE as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -645,7 +759,7 @@ mod tests {
         board.state.active_side = Sides::BLACK as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -669,7 +783,7 @@ mod tests {
         board.state.active_side = Sides::BLACK as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -693,7 +807,7 @@ mod tests {
         board.state.active_side = Sides::BLACK as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -717,7 +831,7 @@ mod tests {
         board.state.active_side = Sides::BLACK as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -740,7 +854,7 @@ mod tests {
         board.state.active_side = Sides::BLACK as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         // Discard all non-king moves.
@@ -756,7 +870,7 @@ mod tests {
         board.put_piece(side, Pieces::KING, Squares::D4);
         board.state.active_side = side as u8;
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -790,7 +904,7 @@ mod tests {
         board.put_piece(side, Pieces::ROOK, Squares::B2);
         board.state.active_side = side as u8;
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -832,7 +946,7 @@ mod tests {
         board.put_piece(side, Pieces::KNIGHT, Squares::C1);
         board.state.active_side = side as u8;
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -871,7 +985,7 @@ mod tests {
         board.put_piece(board.opponent(), Pieces::KNIGHT, Squares::C1);
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -907,7 +1021,7 @@ mod tests {
         board.put_piece(side, Pieces::BISHOP, Squares::B2);
         board.state.active_side = side as u8;
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -944,7 +1058,7 @@ mod tests {
         board.put_piece(side, Pieces::KNIGHT, Squares::B6);
         board.state.active_side = side as u8;
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -986,7 +1100,7 @@ mod tests {
         board.put_piece(board.opponent(), Pieces::KING, Squares::F6);
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -1020,7 +1134,7 @@ mod tests {
         board.put_piece(side, Pieces::QUEEN, Squares::B2);
         board.state.active_side = side as u8;
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -1076,7 +1190,7 @@ mod tests {
         board.state.active_side = side as u8;
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -1131,7 +1245,7 @@ mod tests {
         board.put_piece(board.opponent(), Pieces::KING, Squares::F6);
 
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -1183,7 +1297,7 @@ mod tests {
         board.put_piece(side, Pieces::KING, Squares::A1);
         board.state.active_side = side as u8;
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([Squares::A2, Squares::B1, Squares::B2]);
@@ -1208,7 +1322,7 @@ mod tests {
         board.put_piece(side, Pieces::KNIGHT, Squares::D4);
         board.state.active_side = side as u8;
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([
@@ -1242,7 +1356,7 @@ mod tests {
         board.put_piece(side, Pieces::KNIGHT, Squares::A1);
         board.state.active_side = side as u8;
         let mg = MoveGenerator::new();
-        let mut move_list: Vec<Move> = Vec::new();
+        let mut move_list = MoveList::new();
         mg.generate_moves(&board, &mut move_list);
 
         let mut expected_sq = Vec::from([Squares::C2, Squares::B3]);
@@ -0,0 +1,448 @@
+use crate::{
+    board::{
+        defs::{BitBoard, Pieces, Ranks, Sides, BB_RANKS, BB_SQUARES, EMPTY},
+        Board,
+    },
+    utils::bits,
+};
+
+use super::{
+    defs::{Move, MoveType},
+    movelist::MoveList,
+    MoveGenerator,
+};
+
+impl MoveGenerator {
+    /// Determines if the king of the side to move is in check.
+    ///
+    /// * `board`: The current board.
+    pub fn in_check(&self, board: &Board) -> bool {
+        self.square_attacked(
+            board,
+            board.opponent(),
+            board.king_square(board.current_side()),
+        )
+    }
+
+    /// Returns the pieces of the opponent that give check to the king of the side to move.
+    ///
+    /// * `board`: The current board.
+    pub fn checkers(&self, board: &Board) -> BitBoard {
+        let bb_occupied = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
+        let king_square = board.king_square(board.current_side());
+        self.attackers_to(board, king_square, bb_occupied) & board.bb_side[board.opponent()]
+    }
+
+    /// Determines if the (pseudo-legal) move gives check to the opponent's king, either directly
+    /// or by uncovering a sliding piece (discovered check).
+    ///
+    /// * `board`: The current board, before the move is played.
+    /// * `mv`: The move to test.
+    pub fn gives_check(&self, board: &Board, mv: Move) -> bool {
+        let us = board.current_side();
+        let them = board.opponent();
+        let own = &board.bb_pieces[us];
+
+        if board.bb_pieces[them][Pieces::KING] == 0 {
+            return false;
+        }
+
+        let king_square = board.king_square(them);
+        let bb_from = BB_SQUARES[mv.from()];
+        let bb_to = BB_SQUARES[mv.to()];
+        let piece = if mv.is_promotion() {
+            mv.promoted()
+        } else {
+            mv.piece()
+        };
+
+        // Direct checks by pieces that do not slide.
+        match piece {
+            Pieces::KNIGHT if self.knight[mv.to()] & BB_SQUARES[king_square] > 0 => return true,
+            Pieces::PAWN if self.pawns[us][mv.to()] & BB_SQUARES[king_square] > 0 => return true,
+            _ => (),
+        }
+
+        // Build the occupancy and the own sliders as they are after the move.
+        let mut bb_occupied =
+            (board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK]) & !bb_from;
+        bb_occupied |= bb_to;
+        let mut bb_rooks = (own[Pieces::ROOK] | own[Pieces::QUEEN]) & !bb_from;
+        let mut bb_bishops = (own[Pieces::BISHOP] | own[Pieces::QUEEN]) & !bb_from;
+
+        match piece {
+            Pieces::ROOK => bb_rooks |= bb_to,
+            Pieces::BISHOP => bb_bishops |= bb_to,
+            Pieces::QUEEN => {
+                bb_rooks |= bb_to;
+                bb_bishops |= bb_to;
+            }
+            _ => (),
+        }
+
+        if mv.en_passant() {
+            let victim = if us == Sides::WHITE {
+                mv.to() - 8
+            } else {
+                mv.to() + 8
+            };
+            bb_occupied &= !BB_SQUARES[victim];
+        }
+
+        if mv.castling() {
+            // In Chess960 the king may land on the square the rook started on.
+            let (rook_from, rook_to) = board.castling_rook_squares(mv.to());
+            bb_occupied = (bb_occupied & !BB_SQUARES[rook_from]) | BB_SQUARES[rook_to] | bb_to;
+            bb_rooks = (bb_rooks & !BB_SQUARES[rook_from]) | BB_SQUARES[rook_to];
+        }
+
+        (self.get_slider_attacks(Pieces::ROOK, king_square, bb_occupied) & bb_rooks > 0)
+            || (self.get_slider_attacks(Pieces::BISHOP, king_square, bb_occupied) & bb_bishops > 0)
+    }
+
+    /// Generates the pseudo-legal moves that can get the side to move out of check: king moves,
+    /// captures of the checking piece and moves that block the line between a sliding checker and
+    /// the king. In double check only king moves are generated.
+    ///
+    /// Should only be used when the side to move is in check; when it is not, nothing is generated.
+    ///
+    /// * `board`: The current board.
+    /// * `move_list`: A list where the generated moves will be appended.
+    pub fn generate_evasions(&self, board: &Board, move_list: &mut MoveList) {
+        let player = board.current_side();
+        let bb_checkers = self.checkers(board);
+
+        if bb_checkers == 0 {
+            return;
+        }
+
+        let king_square = board.king_square(player);
+        self.add_piece_moves(
+            board,
+            Pieces::KING,
+            board.bb_pieces[player][Pieces::KING],
+            !EMPTY,
+            move_list,
+        );
+
+        // In a double check, the king has to move.
+        if bb_checkers.count_ones() > 1 {
+            return;
+        }
+
+        let checker = bb_checkers.trailing_zeros() as usize;
+        let bb_block = self.between[king_square][checker];
+        let bb_targets = bb_block | bb_checkers;
+
+        for piece in [Pieces::QUEEN, Pieces::ROOK, Pieces::BISHOP, Pieces::KNIGHT] {
+            let bb_pieces = board.bb_pieces[player][piece];
+            self.add_piece_moves(board, piece, bb_pieces, bb_targets, move_list);
+        }
+
+        // A pawn that just made a double step and gives check can be captured en-passant.
+        let mut bb_capture_mask = bb_checkers;
+        if let Some(ep) = board.state.en_passant {
+            let bb_double_stepped = if player == Sides::WHITE {
+                BB_SQUARES[ep as usize] >> 8
+            } else {
+                BB_SQUARES[ep as usize] << 8
+            };
+            if bb_double_stepped == bb_checkers {
+                bb_capture_mask |= BB_SQUARES[ep as usize];
+            }
+        }
+
+        let bb_pawns = board.bb_pieces[player][Pieces::PAWN];
+        self.add_pawn_moves(board, bb_pawns, bb_block, bb_capture_mask, move_list);
+    }
+
+    /// Generates the pseudo-legal quiet moves (no captures or promotions) that give check. This is
+    /// used on the first ply of the quiescence search.
+    ///
+    /// Pieces that can give a discovered check have all their quiet moves tested, the other
+    /// pieces only generate moves to the squares from which they attack the opponent's king.
+    ///
+    /// * `board`: The current board.
+    /// * `move_list`: A list where the generated moves will be appended.
+    pub fn generate_quiet_checks(&self, board: &Board, move_list: &mut MoveList) {
+        let player = board.current_side();
+        let them = board.opponent();
+
+        if board.bb_pieces[them][Pieces::KING] == 0 {
+            return;
+        }
+
+        let king_square = board.king_square(them);
+        let bb_occupied = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
+        let bb_empty = !bb_occupied;
+        let bb_promotion = BB_RANKS[Ranks::R1] | BB_RANKS[Ranks::R8];
+
+        // The squares from which each piece type would attack the king.
+        let bb_rook_checks = self.get_slider_attacks(Pieces::ROOK, king_square, bb_occupied);
+        let bb_bishop_checks = self.get_slider_attacks(Pieces::BISHOP, king_square, bb_occupied);
+        let bb_check_squares = |piece| match piece {
+            Pieces::QUEEN => bb_rook_checks | bb_bishop_checks,
+            Pieces::ROOK => bb_rook_checks,
+            Pieces::BISHOP => bb_bishop_checks,
+            Pieces::KNIGHT => self.knight[king_square],
+            Pieces::PAWN => self.pawns[them][king_square],
+            _ => EMPTY,
+        };
+
+        let bb_discoverers = self.discovered_check_candidates(board);
+        let mut candidates = MoveList::new();
+
+        for piece in [
+            Pieces::KING,
+            Pieces::QUEEN,
+            Pieces::ROOK,
+            Pieces::BISHOP,
+            Pieces::KNIGHT,
+        ] {
+            let bb_pieces = board.bb_pieces[player][piece];
+            let bb_direct = bb_pieces & !bb_discoverers;
+            let bb_targets = bb_check_squares(piece) & bb_empty;
+            self.add_piece_moves(board, piece, bb_direct, bb_targets, move_list);
+            self.add_piece_moves(
+                board,
+                piece,
+                bb_pieces & bb_discoverers,
+                bb_empty,
+                &mut candidates,
+            );
+        }
+
+        let bb_pawns = board.bb_pieces[player][Pieces::PAWN];
+        let bb_targets = bb_check_squares(Pieces::PAWN) & !bb_promotion;
+        self.add_pawn_moves(
+            board,
+            bb_pawns & !bb_discoverers,
+            bb_targets,
+            EMPTY,
+            move_list,
+        );
+        self.add_pawn_moves(
+            board,
+            bb_pawns & bb_discoverers,
+            !bb_promotion,
+            EMPTY,
+            &mut candidates,
+        );
+
+        // Castling can give check with the rook.
+        self.castling(board, &mut candidates);
+
+        for mv in candidates.iter() {
+            if self.gives_check(board, *mv) {
+                move_list.push(*mv);
+            }
+        }
+    }
+
+    /// Pieces of the side to move that stand alone between one of its own sliding pieces and the
+    /// opponent's king; moving such a piece off the line gives a discovered check.
+    ///
+    /// * `board`: The current board.
+    fn discovered_check_candidates(&self, board: &Board) -> BitBoard {
+        let player = board.current_side();
+        let own = &board.bb_pieces[player];
+        let king_square = board.king_square(board.opponent());
+        let bb_occupied = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
+
+        // Sliders that would attack the king on an empty board.
+        let mut bb_snipers = (self.get_slider_attacks(Pieces::ROOK, king_square, EMPTY)
+            & (own[Pieces::ROOK] | own[Pieces::QUEEN]))
+            | (self.get_slider_attacks(Pieces::BISHOP, king_square, EMPTY)
+                & (own[Pieces::BISHOP] | own[Pieces::QUEEN]));
+
+        let mut bb_candidates = EMPTY;
+        while bb_snipers > 0 {
+            let sniper = bits::next(&mut bb_snipers);
+            let bb_blockers = self.between[king_square][sniper] & bb_occupied;
+            if bb_blockers.count_ones() == 1 {
+                bb_candidates |= bb_blockers & board.bb_side[player];
+            }
+        }
+
+        bb_candidates
+    }
+
+    /// Generates the moves of the given [`MoveType`] and keeps only those that are legal.
+    ///
+    /// This is slower than generating pseudo-legal moves, as every move is played on the board and
+    /// taken back again. It is meant for the root of the search and for move parsing.
+    ///
+    /// * `board`: The current board.
+    /// * `move_list`: A list where the legal moves will be appended.
+    /// * `move_type`: Which kind of moves to generate.
+    pub fn generate_legal_moves(
+        &self,
+        board: &mut Board,
+        move_list: &mut MoveList,
+        move_type: MoveType,
+    ) {
+        let mut pseudo_legal = MoveList::new();
+        match move_type {
+            MoveType::All => self.generate_moves(board, &mut pseudo_legal),
+            MoveType::Captures => self.generate_captures(board, &mut pseudo_legal),
+            MoveType::Quiets => self.generate_quiets(board, &mut pseudo_legal),
+        }
+
+        for mv in pseudo_legal.iter() {
+            if board.make_move(*mv, self) {
+                board.unmake_move();
+                move_list.push(*mv);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{defs::Pieces, Board},
+        movegen::{
+            defs::{Move, MoveType},
+            movelist::MoveList,
+            MoveGenerator,
+        },
+    };
+
+    /// Positions where the side to move is in check, covering slider, knight and pawn checks,
+    /// double checks and an en-passant capture of the checking pawn.
+    const IN_CHECK: [&str; 8] = [
+        "4k3/8/8/8/1b6/8/8/1N2K3 w - - 0 1",
+        "4k3/8/8/8/8/5n2/8/R3K2R w KQ - 0 1",
+        "4k3/8/8/8/4r3/8/8/R1B1K2R w KQ - 0 1",
+        "4k3/8/8/8/1b2r3/8/8/4K3 w - - 0 1",
+        "4k3/8/8/2Pp4/2K5/8/8/8 w - d6 0 1",
+        "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        "r3k2r/p1ppqpb1/bn1Npnp1/3P4/1p2P3/5Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+        "rnb1kbnr/pppp1ppp/8/4p3/5PPq/8/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+    ];
+
+    /// Positions with many ways to give check, including discovered checks, pawn checks and a
+    /// castling move that gives check.
+    const CHECKING: [&str; 5] = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+        "3k4/8/3N4/8/3R4/8/2P1P3/4K3 w - - 0 1",
+        "7k/8/8/4P3/3Q4/8/1N6/R3K3 w Q - 0 1",
+        "4k3/8/3P1P2/8/8/2B5/1N6/4K2R w K - 0 1",
+    ];
+
+    fn legal(board: &mut Board, mg: &MoveGenerator, list: &MoveList) -> Vec<Move> {
+        let mut legal = Vec::new();
+        for mv in list.iter() {
+            if board.make_move(*mv, mg) {
+                board.unmake_move();
+                legal.push(*mv);
+            }
+        }
+        legal
+    }
+
+    fn assert_same_moves(mut expected: Vec<Move>, actual: Vec<Move>) {
+        assert_eq!(expected.len(), actual.len());
+        for mv in actual.iter() {
+            let index = expected
+                .iter()
+                .position(|m| m == mv)
+                .expect("unexpected move");
+            expected.swap_remove(index);
+        }
+        assert!(expected.is_empty());
+    }
+
+    #[test]
+    fn test_evasions_match_legal_moves() {
+        let mg = MoveGenerator::new();
+        for fen in IN_CHECK {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+            assert!(mg.in_check(&board), "{fen}");
+
+            let mut all = MoveList::new();
+            mg.generate_legal_moves(&mut board, &mut all, MoveType::All);
+
+            let mut evasions = MoveList::new();
+            mg.generate_evasions(&board, &mut evasions);
+
+            let expected: Vec<Move> = all.iter().copied().collect();
+            assert_same_moves(expected, legal(&mut board, &mg, &evasions));
+        }
+    }
+
+    #[test]
+    fn test_evasions_double_check_only_king_moves() {
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+        board
+            .fen_read(Some("4k3/8/8/8/1b6/8/8/R3K2r w - - 0 1"))
+            .unwrap();
+        assert_eq!(mg.checkers(&board).count_ones(), 2);
+
+        let mut evasions = MoveList::new();
+        mg.generate_evasions(&board, &mut evasions);
+        assert!(!evasions.is_empty());
+        assert!(evasions.iter().all(|mv| mv.piece() == Pieces::KING));
+    }
+
+    #[test]
+    fn test_evasions_empty_when_not_in_check() {
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+
+        let mut evasions = MoveList::new();
+        mg.generate_evasions(&board, &mut evasions);
+        assert!(evasions.is_empty());
+    }
+
+    #[test]
+    fn test_quiet_checks_match_legal_moves() {
+        let mg = MoveGenerator::new();
+        for fen in CHECKING.iter().chain(IN_CHECK.iter()) {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+
+            // Filter the full legal move list: quiet moves after which the opponent is in check.
+            let mut all = MoveList::new();
+            mg.generate_legal_moves(&mut board, &mut all, MoveType::All);
+            let mut expected = Vec::new();
+            for mv in all.iter() {
+                if mv.is_capture() || mv.is_promotion() {
+                    continue;
+                }
+                assert!(board.make_move(*mv, &mg));
+                if mg.in_check(&board) {
+                    expected.push(*mv);
+                }
+                board.unmake_move();
+            }
+
+            let mut checks = MoveList::new();
+            mg.generate_quiet_checks(&board, &mut checks);
+
+            assert_same_moves(expected, legal(&mut board, &mg, &checks));
+        }
+    }
+
+    #[test]
+    fn test_gives_check_matches_playing_the_move() {
+        let mg = MoveGenerator::new();
+        for fen in CHECKING.iter().chain(IN_CHECK.iter()) {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+
+            let mut all = MoveList::new();
+            mg.generate_legal_moves(&mut board, &mut all, MoveType::All);
+            for mv in all.iter() {
+                let gives_check = mg.gives_check(&board, *mv);
+                assert!(board.make_move(*mv, &mg));
+                assert_eq!(gives_check, mg.in_check(&board), "{fen} {mv}");
+                board.unmake_move();
+            }
+        }
+    }
+}
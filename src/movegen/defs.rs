@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use crate::board::defs::{BitBoard, Piece, Square, PIECE_CHAR_SMALL, SQUARE_NAME};
+use crate::board::defs::{BitBoard, Piece, Pieces, Square, PIECE_CHAR_SMALL, SQUARE_NAME};
 
 // A list of BitBoard that represent possible attacks.
 pub type AttackBoards = Vec<BitBoard>;
@@ -18,8 +18,8 @@ Field       :   bits     Decimal values
 PIECE       :   3        0-7 (use only 0-6)
 FROM        :   6        0-63
 TO          :   6        0-63
-CAPTURE     :   3        0-7 (captured piece)
-PROMOTION   :   3        0-7 (piece promoted to)
+CAPTURE     :   3        0-7 (captured piece, NONE if not a capture)
+PROMOTION   :   3        0-7 (piece promoted to, NONE if not a promotion)
 ENPASSANT   :   1        0-1
 DOUBLESTEP  :   1        0-1
 CASTLING    :   1        0-1
@@ -62,11 +62,27 @@ impl Shift {
     pub const SORTSCORE: usize = 24;
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// The kind of moves a generator function should produce.
+///
+/// * `All`: every pseudo-legal move.
+/// * `Captures`: captures (including en-passant) and promotions; the "noisy" moves.
+/// * `Quiets`: every move that is not in `Captures`, including castling.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveType {
+    All,
+    Captures,
+    Quiets,
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct Move {
     data: usize,
 }
 
+/// Mask of the bits in a [`Move`] that describe the move itself, i.e. everything except the sort
+/// score.
+const MOVE_ONLY: usize = (1 << Shift::SORTSCORE) - 1;
+
 impl Move {
     pub fn new(data: usize) -> Self {
         Self { data }
@@ -112,6 +128,32 @@ impl Move {
         ((self.data >> Shift::CASTLING as u64) & 0b1) as u8 == 1
     }
 
+    /// Returns true if this move captures a piece, including en-passant captures.
+    pub fn is_capture(&self) -> bool {
+        self.captured() != Pieces::NONE
+    }
+
+    /// Returns true if this move promotes a pawn.
+    pub fn is_promotion(&self) -> bool {
+        self.promoted() != Pieces::NONE
+    }
+
+    /// Formats the move in the long algebraic notation used by UCI: the origin and destination
+    /// squares, and the piece a pawn promotes to, such as `e2e4` or `e7e8q`. Castling is the
+    /// two-square move of the king, such as `e1g1`.
+    pub fn to_uci(&self) -> String {
+        let mut uci = format!("{}{}", SQUARE_NAME[self.from()], SQUARE_NAME[self.to()]);
+        if self.is_promotion() {
+            uci.push_str(PIECE_CHAR_SMALL[self.promoted()]);
+        }
+        uci
+    }
+
+    /// The move without its sort score, 24 bits. Used to store the move compactly.
+    pub fn get_move(&self) -> u32 {
+        (self.data & MOVE_ONLY) as u32
+    }
+
     /// 32 bits
     pub fn get_sort_score(&self) -> u32 {
         ((self.data >> Shift::SORTSCORE as u64) & 0xFFFFFFFF) as u32
@@ -124,6 +166,16 @@ impl Move {
     }
 }
 
+/// Two moves are equal when they describe the same move; the sort score is ignored so that a move
+/// from a previous search can be matched against freshly generated moves.
+impl PartialEq for Move {
+    fn eq(&self, other: &Self) -> bool {
+        self.data & MOVE_ONLY == other.data & MOVE_ONLY
+    }
+}
+
+impl Eq for Move {}
+
 impl Display for Move {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -155,7 +207,6 @@ pub enum Direction {
 /// Additionally, it also contains compass directions for Knight moves.
 pub struct Compass;
 impl Compass {
-
     pub fn northwest(bb: BitBoard) -> BitBoard {
         bb << 7
     }
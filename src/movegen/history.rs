@@ -0,0 +1,68 @@
+use crate::board::defs::{NrOf, Sides};
+
+use super::defs::Move;
+
+/// When a history score reaches this value, all scores are halved. This keeps the scores within
+/// range and lets recent cut-offs weigh more than old ones.
+const MAX_HISTORY: u32 = 1 << 20;
+
+/// Counts how often quiet moves caused a beta cut-off, indexed by side, piece and destination.
+/// Quiet moves that often cause cut-offs are tried first.
+///
+/// Every search thread has its own table, so threads do not slow each other down and end up
+/// searching different parts of the tree.
+#[derive(Clone)]
+pub struct HistoryTable {
+    table: [[[u32; NrOf::SQUARES]; NrOf::PIECE_TYPES]; Sides::BOTH],
+}
+
+impl HistoryTable {
+    pub fn new() -> Self {
+        Self {
+            table: [[[0; NrOf::SQUARES]; NrOf::PIECE_TYPES]; Sides::BOTH],
+        }
+    }
+
+    /// The history score of a quiet move.
+    ///
+    /// * `side`: The side playing the move.
+    /// * `mv`: The move.
+    pub fn get(&self, side: usize, mv: Move) -> u32 {
+        self.table[side][mv.piece()][mv.to()]
+    }
+
+    /// Rewards a quiet move that caused a beta cut-off. Deeper cut-offs get a larger bonus.
+    ///
+    /// * `side`: The side playing the move.
+    /// * `mv`: The move.
+    /// * `depth`: The remaining depth of the node where the cut-off happened.
+    pub fn reward(&mut self, side: usize, mv: Move, depth: i8) {
+        let depth = depth.max(1) as u32;
+        let score = &mut self.table[side][mv.piece()][mv.to()];
+        *score += depth * depth;
+
+        if *score >= MAX_HISTORY {
+            self.age();
+        }
+    }
+
+    /// Halves all scores.
+    pub fn age(&mut self) {
+        self.table
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .for_each(|score| *score /= 2);
+    }
+
+    /// Resets all scores to zero.
+    pub fn clear(&mut self) {
+        *self = HistoryTable::new();
+    }
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
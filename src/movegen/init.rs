@@ -3,7 +3,7 @@ use crate::board::defs::{
 };
 
 use super::{
-    defs::Compass,
+    defs::{Compass, Direction},
     magics::{find_magics, Magic, BISHOP_TABLE_SIZE, ROOK_TABLE_SIZE},
     MoveGenerator,
 };
@@ -77,6 +77,36 @@ impl MoveGenerator {
         }
     }
 
+    /// Generates the squares between each pair of squares that share a rank, file or diagonal.
+    ///
+    /// The table is empty for squares that are not on a common line, or that are adjacent.
+    pub fn init_between(&mut self) {
+        const DIRECTIONS: [Direction; 8] = [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+            Direction::NorthWest,
+            Direction::NorthEast,
+            Direction::SouthEast,
+            Direction::SouthWest,
+        ];
+
+        for from in RangeOf::SQUARES {
+            for to in RangeOf::SQUARES {
+                let bb_to = BB_SQUARES[to];
+                for direction in DIRECTIONS {
+                    // Cast a ray that stops at the target square; if it was reached, everything
+                    // before it is in between.
+                    let bb_ray = MoveGenerator::bb_ray(bb_to, from, direction);
+                    if bb_ray & bb_to > 0 {
+                        self.between[from][to] = bb_ray & !bb_to;
+                    }
+                }
+            }
+        }
+    }
+
     /// Initializes the sliding piece attack tables by generating a new set of magic numbers.
     /// This is slow, but guarantees to find a set of magic numbers.
     pub fn init_magics(&mut self) {
@@ -116,8 +116,6 @@ pub const PRECALC_BISHOP_MAGIC_NUMBERS: [u64; NrOf::SQUARES] = [
 ];
 
 impl MoveGenerator {
-
-
     /// Generates a rook mask for a rook on the given square.
     ///
     /// These are squares the rook could potentially "see".
@@ -318,7 +316,6 @@ impl MoveGenerator {
     }
 }
 
-
 /// Generates magic numbers & attack tables for valid pieces. (Rooks, Bishops)
 ///
 /// This looks for a suitable number for a "perfect hash" to store attack tables for the given
@@ -0,0 +1,137 @@
+use std::ops::Index;
+
+use super::defs::Move;
+
+/// The maximum number of moves that can be stored in a [`MoveList`]. The highest known number of
+/// legal moves in a chess position is 218, so this leaves a little room for pseudo-legal moves.
+pub const MAX_LEGAL_MOVES: usize = 255;
+
+/// A fixed-capacity list of moves that lives on the stack.
+///
+/// Move generation happens at every node of the search, so allocating a `Vec` each time is
+/// wasteful. The list is backed by an array of [`MAX_LEGAL_MOVES`] entries and a counter that keeps
+/// track of how many of those entries are in use.
+///
+/// * `list`: The backing array of moves.
+/// * `count`: The number of moves currently stored in the list.
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    list: [Move; MAX_LEGAL_MOVES],
+    count: usize,
+}
+
+impl MoveList {
+    /// Creates a new, empty [`MoveList`].
+    pub fn new() -> Self {
+        Self {
+            list: [Move::new(0); MAX_LEGAL_MOVES],
+            count: 0,
+        }
+    }
+
+    /// Appends a move to the end of the list.
+    ///
+    /// WARNING: This function will panic if the list is already full.
+    ///
+    /// * `mv`: The move to append.
+    pub fn push(&mut self, mv: Move) {
+        self.list[self.count] = mv;
+        self.count += 1;
+    }
+
+    /// The number of moves in the list.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns true if there are no moves in the list.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Removes all moves from the list.
+    pub fn clear(&mut self) {
+        self.count = 0;
+    }
+
+    /// Get the move at the given index.
+    ///
+    /// * `index`: The index of the move, must be less than [`MoveList::len`].
+    pub fn get(&self, index: usize) -> Move {
+        self.list[..self.count][index]
+    }
+
+    /// Get a mutable reference to the move at the given index.
+    ///
+    /// * `index`: The index of the move, must be less than [`MoveList::len`].
+    pub fn get_mut(&mut self, index: usize) -> &mut Move {
+        &mut self.list[..self.count][index]
+    }
+
+    /// Swaps the moves at the two given indexes.
+    ///
+    /// * `a`: The index of the first move.
+    /// * `b`: The index of the second move.
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.list[..self.count].swap(a, b);
+    }
+
+    /// Returns true if the list contains the given move.
+    ///
+    /// * `mv`: The move to look for.
+    pub fn contains(&self, mv: &Move) -> bool {
+        self.iter().any(|m| m == mv)
+    }
+
+    /// Retains only the moves for which the predicate returns true, keeping their order.
+    ///
+    /// * `f`: The predicate to apply to each move.
+    pub fn retain<F: FnMut(&Move) -> bool>(&mut self, mut f: F) {
+        let mut kept = 0;
+        for i in 0..self.count {
+            let mv = self.list[i];
+            if f(&mv) {
+                self.list[kept] = mv;
+                kept += 1;
+            }
+        }
+        self.count = kept;
+    }
+
+    /// An iterator over the moves in the list.
+    pub fn iter(&self) -> std::slice::Iter<'_, Move> {
+        self.list[..self.count].iter()
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Index<usize> for MoveList {
+    type Output = Move;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.list[..self.count][index]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = std::iter::Take<std::array::IntoIter<Move, MAX_LEGAL_MOVES>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter().take(self.count)
+    }
+}
@@ -0,0 +1,542 @@
+use crate::board::{defs::PIECE_VALUES, Board};
+
+use super::{defs::Move, history::HistoryTable, movelist::MoveList, MoveGenerator};
+
+/// The number of killer moves that are kept per ply.
+pub const MAX_KILLER_MOVES: usize = 2;
+
+/// The stages a [`MovePicker`] goes through, in order.
+///
+/// * `TTMove`: The move from the transposition table, if any.
+/// * `GenerateCaptures`: Generate and score captures and promotions.
+/// * `GoodCaptures`: Captures that do not lose material according to SEE.
+/// * `Killers`: Quiet moves that caused a cut-off in a sibling node.
+/// * `GenerateQuiets`: Generate the quiet moves and score them by their history.
+/// * `Quiets`: The remaining quiet moves, best history score first.
+/// * `BadCaptures`: Captures that lose material according to SEE.
+/// * `GenerateQuietChecks`: Generate the quiet moves that give check and score them by their
+///   history.
+/// * `QuietChecks`: The quiet moves that give check, best history score first.
+/// * `GenerateEvasions`: Generate and score the moves that get out of check.
+/// * `Evasions`: The moves that get out of check, captures first.
+/// * `Done`: All moves have been returned.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Stage {
+    TTMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    GenerateQuietChecks,
+    QuietChecks,
+    GenerateEvasions,
+    Evasions,
+    Done,
+}
+
+/// Which moves a [`MovePicker`] returns.
+///
+/// * `Main`: All moves, for a position that is not in check.
+/// * `Evasions`: The moves that get out of check.
+/// * `Captures`: Only captures and promotions that do not lose material, for the quiescence search.
+/// * `CapturesAndChecks`: The same captures and promotions, followed by the quiet moves that give
+///   check, for the first ply of the quiescence search.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PickerKind {
+    Main,
+    Evasions,
+    Captures,
+    CapturesAndChecks,
+}
+
+/// Yields the pseudo-legal moves of a position in stages, so that moves which are likely to cause
+/// a cut-off are tried first, and the remaining moves are not generated at all when they are not
+/// needed.
+///
+/// A stage is only generated when the previous one is exhausted. The transposition table move and
+/// the killer moves are verified to be pseudo-legal before they are returned, and are not returned
+/// again by the later stages.
+///
+/// * `kind`: Which moves are returned.
+/// * `stage`: The current [`Stage`].
+/// * `tt_move`: The move from the transposition table.
+/// * `killers`: The killer moves for the current ply.
+/// * `moves`: The moves of the current generated stage (captures or quiets).
+/// * `bad_captures`: Captures that were deferred because they lose material.
+/// * `index`: Position of the next move to return from the current list.
+pub struct MovePicker {
+    kind: PickerKind,
+    stage: Stage,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; MAX_KILLER_MOVES],
+    moves: MoveList,
+    bad_captures: MoveList,
+    index: usize,
+}
+
+impl MovePicker {
+    /// Create a new [`MovePicker`] for a position.
+    ///
+    /// * `tt_move`: The best move stored in the transposition table for this position, if any.
+    /// * `killers`: The killer moves for the ply of this position.
+    pub fn new(tt_move: Option<Move>, killers: [Option<Move>; MAX_KILLER_MOVES]) -> Self {
+        Self {
+            kind: PickerKind::Main,
+            stage: Stage::TTMove,
+            tt_move,
+            killers,
+            moves: MoveList::new(),
+            bad_captures: MoveList::new(),
+            index: 0,
+        }
+    }
+
+    /// Create a new [`MovePicker`] for a position where the side to move is in check. Only the
+    /// moves that can get out of check are returned.
+    ///
+    /// * `tt_move`: The best move stored in the transposition table for this position, if any.
+    pub fn evasions(tt_move: Option<Move>) -> Self {
+        Self {
+            kind: PickerKind::Evasions,
+            ..MovePicker::new(tt_move, [None; MAX_KILLER_MOVES])
+        }
+    }
+
+    /// Create a new [`MovePicker`] for the quiescence search. Only captures and promotions that
+    /// do not lose material according to SEE are returned.
+    ///
+    /// * `tt_move`: The best move stored in the transposition table for this position, if any. It
+    ///   is only used when it is a capture or a promotion.
+    pub fn captures(tt_move: Option<Move>) -> Self {
+        let tt_move = tt_move.filter(|mv| mv.is_capture() || mv.is_promotion());
+        Self {
+            kind: PickerKind::Captures,
+            ..MovePicker::new(tt_move, [None; MAX_KILLER_MOVES])
+        }
+    }
+
+    /// Create a new [`MovePicker`] for the first ply of the quiescence search: the moves of
+    /// [`MovePicker::captures`], followed by the quiet moves that give check.
+    ///
+    /// * `tt_move`: The best move stored in the transposition table for this position, if any. It
+    ///   is only used when it is a capture or a promotion.
+    pub fn captures_and_checks(tt_move: Option<Move>) -> Self {
+        Self {
+            kind: PickerKind::CapturesAndChecks,
+            ..MovePicker::captures(tt_move)
+        }
+    }
+
+    /// The stage the picker is currently in.
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Returns the next move to search, or None when all moves have been returned.
+    ///
+    /// The board must not be changed between calls, other than a move being made and taken back.
+    ///
+    /// * `board`: The position to pick moves for.
+    /// * `mg`: The move generator.
+    /// * `history`: The history scores used to order the quiet moves.
+    pub fn next(
+        &mut self,
+        board: &Board,
+        mg: &MoveGenerator,
+        history: &HistoryTable,
+    ) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TTMove => {
+                    self.stage = if self.kind == PickerKind::Evasions {
+                        Stage::GenerateEvasions
+                    } else {
+                        Stage::GenerateCaptures
+                    };
+                    if let Some(mv) = self.tt_move {
+                        if mg.is_pseudo_legal(board, mv) {
+                            return Some(mv);
+                        }
+                        self.tt_move = None;
+                    }
+                }
+
+                Stage::GenerateCaptures => {
+                    mg.generate_captures(board, &mut self.moves);
+                    for i in 0..self.moves.len() {
+                        let mv = self.moves.get_mut(i);
+                        mv.set_sort_score(MovePicker::mvv_lva(mv));
+                    }
+                    self.index = 0;
+                    self.stage = Stage::GoodCaptures;
+                }
+
+                Stage::GoodCaptures => {
+                    while let Some(mv) = self.pick_best() {
+                        if self.is_tt_move(mv) {
+                            continue;
+                        }
+                        if mg.see(board, mv) < 0 {
+                            self.bad_captures.push(mv);
+                            continue;
+                        }
+                        return Some(mv);
+                    }
+                    self.index = 0;
+                    self.stage = match self.kind {
+                        PickerKind::Captures => Stage::Done,
+                        PickerKind::CapturesAndChecks => Stage::GenerateQuietChecks,
+                        _ => Stage::Killers,
+                    };
+                }
+
+                Stage::Killers => {
+                    while self.index < MAX_KILLER_MOVES {
+                        let killer = self.killers[self.index];
+                        let seen = self.killers[..self.index].contains(&killer);
+                        self.index += 1;
+                        if let Some(mv) = killer {
+                            if !seen
+                                && !self.is_tt_move(mv)
+                                && !mv.is_capture()
+                                && !mv.is_promotion()
+                                && mg.is_pseudo_legal(board, mv)
+                            {
+                                return Some(mv);
+                            }
+                        }
+                        // Forget killers that are not returned, so they are searched as quiets.
+                        if !seen {
+                            self.killers[self.index - 1] = None;
+                        }
+                    }
+                    self.stage = Stage::GenerateQuiets;
+                }
+
+                Stage::GenerateQuiets => {
+                    self.moves.clear();
+                    mg.generate_quiets(board, &mut self.moves);
+                    let side = board.current_side();
+                    for i in 0..self.moves.len() {
+                        let mv = self.moves.get_mut(i);
+                        mv.set_sort_score(history.get(side, *mv));
+                    }
+                    self.index = 0;
+                    self.stage = Stage::Quiets;
+                }
+
+                Stage::Quiets => {
+                    while let Some(mv) = self.pick_best() {
+                        if !self.is_tt_move(mv) && !self.is_killer(mv) {
+                            return Some(mv);
+                        }
+                    }
+                    self.index = 0;
+                    self.stage = Stage::BadCaptures;
+                }
+
+                Stage::BadCaptures => {
+                    if self.index < self.bad_captures.len() {
+                        let mv = self.bad_captures[self.index];
+                        self.index += 1;
+                        return Some(mv);
+                    }
+                    self.stage = Stage::Done;
+                }
+
+                Stage::GenerateQuietChecks => {
+                    self.moves.clear();
+                    mg.generate_quiet_checks(board, &mut self.moves);
+                    let side = board.current_side();
+                    for i in 0..self.moves.len() {
+                        let mv = self.moves.get_mut(i);
+                        mv.set_sort_score(history.get(side, *mv));
+                    }
+                    self.index = 0;
+                    self.stage = Stage::QuietChecks;
+                }
+
+                Stage::QuietChecks => {
+                    if let Some(mv) = self.pick_best() {
+                        return Some(mv);
+                    }
+                    self.stage = Stage::Done;
+                }
+
+                Stage::GenerateEvasions => {
+                    mg.generate_evasions(board, &mut self.moves);
+                    for i in 0..self.moves.len() {
+                        let mv = self.moves.get_mut(i);
+                        if mv.is_capture() || mv.is_promotion() {
+                            // Keep captures ahead of the quiet evasions, which all score 0.
+                            mv.set_sort_score(MovePicker::mvv_lva(mv) + 1);
+                        }
+                    }
+                    self.index = 0;
+                    self.stage = Stage::Evasions;
+                }
+
+                Stage::Evasions => {
+                    while let Some(mv) = self.pick_best() {
+                        if !self.is_tt_move(mv) {
+                            return Some(mv);
+                        }
+                    }
+                    self.stage = Stage::Done;
+                }
+
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Most Valuable Victim, Least Valuable Attacker: a sort score for captures and promotions
+    /// that prefers taking the most valuable piece with the least valuable attacker.
+    ///
+    /// * `mv`: The capture or promotion to score.
+    fn mvv_lva(mv: &Move) -> u32 {
+        let victim = if mv.is_capture() {
+            PIECE_VALUES[mv.captured()] as u32
+        } else {
+            0
+        };
+        let promotion = if mv.is_promotion() {
+            PIECE_VALUES[mv.promoted()] as u32
+        } else {
+            0
+        };
+
+        // Pieces are numbered from KING (0) to PAWN (5), so a higher number is a cheaper attacker.
+        (victim + promotion) * 16 + mv.piece() as u32
+    }
+
+    /// Selects the move with the highest sort score from the unreturned moves in the current list,
+    /// moves it to the front of the unreturned part and returns it.
+    fn pick_best(&mut self) -> Option<Move> {
+        if self.index >= self.moves.len() {
+            return None;
+        }
+
+        let mut best = self.index;
+        for i in (self.index + 1)..self.moves.len() {
+            if self.moves[i].get_sort_score() > self.moves[best].get_sort_score() {
+                best = i;
+            }
+        }
+
+        self.moves.swap(self.index, best);
+        self.index += 1;
+        Some(self.moves[self.index - 1])
+    }
+
+    fn is_tt_move(&self, mv: Move) -> bool {
+        self.tt_move == Some(mv)
+    }
+
+    fn is_killer(&self, mv: Move) -> bool {
+        self.killers.contains(&Some(mv))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{defs::Squares, Board},
+        movegen::{defs::MoveType, history::HistoryTable, movelist::MoveList, MoveGenerator},
+    };
+
+    use super::{MovePicker, Stage};
+
+    // A position with many captures, promotions and castling moves for both sides.
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn pick_all(picker: &mut MovePicker, board: &Board, mg: &MoveGenerator) -> MoveList {
+        let mut picked = MoveList::new();
+        let history = HistoryTable::new();
+        while let Some(mv) = picker.next(board, mg, &history) {
+            picked.push(mv);
+        }
+        picked
+    }
+
+    #[test]
+    fn test_picker_returns_every_move_once() {
+        let mut board = Board::new();
+        board.fen_read(Some(KIWIPETE)).unwrap();
+        let mg = MoveGenerator::new();
+
+        let mut all = MoveList::new();
+        mg.generate_moves(&board, &mut all);
+
+        let mut picker = MovePicker::new(None, [None, None]);
+        let picked = pick_all(&mut picker, &board, &mg);
+
+        assert_eq!(picked.len(), all.len());
+        for mv in all.iter() {
+            assert_eq!(picked.iter().filter(|m| *m == mv).count(), 1);
+        }
+        assert_eq!(picker.stage(), Stage::Done);
+    }
+
+    #[test]
+    fn test_picker_tt_move_and_killers_first_without_duplicates() {
+        let mut board = Board::new();
+        board.fen_read(Some(KIWIPETE)).unwrap();
+        let mg = MoveGenerator::new();
+
+        let mut quiets = MoveList::new();
+        mg.generate_quiets(&board, &mut quiets);
+        let tt_move = quiets[3];
+        let killer = quiets[7];
+
+        let mut all = MoveList::new();
+        mg.generate_moves(&board, &mut all);
+
+        let mut picker = MovePicker::new(Some(tt_move), [Some(killer), None]);
+        let picked = pick_all(&mut picker, &board, &mg);
+
+        assert_eq!(picked[0], tt_move);
+        assert_eq!(picked.len(), all.len());
+        assert_eq!(picked.iter().filter(|m| **m == killer).count(), 1);
+
+        // The killer comes directly after the last non-losing capture.
+        let killer_index = picked.iter().position(|m| *m == killer).unwrap();
+        assert!(picked[killer_index - 1].is_capture() || picked[killer_index - 1].is_promotion());
+        assert!(!picked[killer_index + 1].is_capture());
+    }
+
+    #[test]
+    fn test_picker_ignores_illegal_tt_move_and_killers() {
+        let mut board = Board::new();
+        board.fen_read(Some(KIWIPETE)).unwrap();
+        let mg = MoveGenerator::new();
+
+        // Moves generated for black are not pseudo-legal with white to move.
+        let mut black = board.clone();
+        black.state.active_side ^= 1;
+        let mut black_moves = MoveList::new();
+        mg.generate_quiets(&black, &mut black_moves);
+
+        let mut all = MoveList::new();
+        mg.generate_moves(&board, &mut all);
+
+        let mut picker = MovePicker::new(
+            Some(black_moves[0]),
+            [Some(black_moves[1]), Some(black_moves[2])],
+        );
+        let picked = pick_all(&mut picker, &board, &mg);
+
+        assert_eq!(picked.len(), all.len());
+        for mv in picked.iter() {
+            assert!(all.contains(mv));
+        }
+    }
+
+    #[test]
+    fn test_picker_orders_good_captures_first_and_bad_captures_last() {
+        let mut board = Board::new();
+        // The queen can take a pawn that is defended by a pawn (bad), the knight can take an
+        // undefended rook (good).
+        board
+            .fen_read(Some("4k3/8/2p5/1p1r4/8/2N5/8/1Q2K3 w - - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+
+        let mut picker = MovePicker::new(None, [None, None]);
+        let picked = pick_all(&mut picker, &board, &mg);
+
+        let first = picked[0];
+        assert_eq!(first.from(), Squares::C3);
+        assert_eq!(first.to(), Squares::D5);
+
+        let last = picked[picked.len() - 1];
+        assert_eq!(last.from(), Squares::B1);
+        assert_eq!(last.to(), Squares::B5);
+        assert!(mg.see(&board, last) < 0);
+    }
+
+    #[test]
+    fn test_picker_evasions_returns_every_legal_move() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("4k3/8/8/8/1b6/8/2N5/4K3 w - - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(&mut board, &mut legal, MoveType::All);
+
+        let mut picker = MovePicker::evasions(None);
+        let mut picked = MoveList::new();
+        while let Some(mv) = picker.next(&board, &mg, &HistoryTable::new()) {
+            if board.make_move(mv, &mg) {
+                board.unmake_move();
+                picked.push(mv);
+            }
+        }
+
+        assert_eq!(picked.len(), legal.len());
+        assert!(picked[0].is_capture());
+        for mv in legal.iter() {
+            assert!(picked.contains(mv));
+        }
+    }
+
+    #[test]
+    fn test_picker_captures_skips_quiets_and_bad_captures() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("4k3/8/2p5/1p1r4/8/2N5/8/1Q2K3 w - - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+
+        let mut picker = MovePicker::captures(None);
+        let picked = pick_all(&mut picker, &board, &mg);
+
+        assert_eq!(picked.len(), 1);
+        assert_eq!(picked[0].from(), Squares::C3);
+        assert_eq!(picked[0].to(), Squares::D5);
+    }
+
+    #[test]
+    fn test_picker_captures_and_checks_adds_quiet_checks() {
+        let mut board = Board::new();
+        // Qh5+ and Bb5+ are quiet checks, Qxd5 is a capture that does not lose material.
+        board
+            .fen_read(Some("4k3/8/8/3p4/8/8/8/3QKB2 w - - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+
+        let mut checks = MoveList::new();
+        mg.generate_quiet_checks(&board, &mut checks);
+        assert!(checks.len() >= 2);
+
+        let mut picker = MovePicker::captures_and_checks(None);
+        let picked = pick_all(&mut picker, &board, &mg);
+        assert_eq!(picker.stage(), Stage::Done);
+        assert_eq!(picked.len(), checks.len() + 1);
+        assert_eq!(picked[0].to(), Squares::D5);
+        for mv in checks.iter() {
+            assert_eq!(picked.iter().filter(|m| *m == mv).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_picker_orders_quiets_by_history() {
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let mg = MoveGenerator::new();
+
+        let mut quiets = MoveList::new();
+        mg.generate_quiets(&board, &mut quiets);
+        let mut history = HistoryTable::new();
+        history.reward(board.current_side(), quiets[12], 3);
+        history.reward(board.current_side(), quiets[9], 5);
+
+        let mut picker = MovePicker::new(None, [None, None]);
+        assert_eq!(picker.next(&board, &mg, &history), Some(quiets[9]));
+        assert_eq!(picker.next(&board, &mg, &history), Some(quiets[12]));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::board::{
+    defs::{BitBoard, NrOf, Pieces, Sides, Square, BB_SQUARES},
+    Board,
+};
+
+use super::{defs::Move, MoveGenerator};
+
+/// Piece values used by the static exchange evaluation, indexed by [`Pieces`].
+///
+/// These match [`crate::board::defs::PIECE_VALUES`] except for the king, which is given a very high
+/// value so that the exchange never ends with the king being "captured".
+const SEE_VALUES: [i32; NrOf::PIECE_TYPES] = [20_000, 900, 500, 300, 300, 100];
+
+/// The order in which pieces are tried when looking for the least valuable attacker.
+const LEAST_VALUABLE_FIRST: [usize; NrOf::PIECE_TYPES] = [
+    Pieces::PAWN,
+    Pieces::KNIGHT,
+    Pieces::BISHOP,
+    Pieces::ROOK,
+    Pieces::QUEEN,
+    Pieces::KING,
+];
+
+/// The maximum length of a capture sequence on a single square (32 pieces on the board).
+const MAX_EXCHANGES: usize = 32;
+
+impl MoveGenerator {
+    /// Returns a [`BitBoard`] with the pieces of both sides that attack the given square.
+    ///
+    /// The occupancy is passed in separately so that pieces can be removed from it one by one
+    /// during an exchange, which reveals sliders (x-rays) that were hidden behind them.
+    ///
+    /// * `board`: The current board.
+    /// * `square`: The square to find the attackers of.
+    /// * `bb_occupied`: The occupancy to use for sliding pieces.
+    pub fn attackers_to(&self, board: &Board, square: Square, bb_occupied: BitBoard) -> BitBoard {
+        let white = &board.bb_pieces[Sides::WHITE];
+        let black = &board.bb_pieces[Sides::BLACK];
+
+        let bb_rook = self.get_slider_attacks(Pieces::ROOK, square, bb_occupied);
+        let bb_bishop = self.get_slider_attacks(Pieces::BISHOP, square, bb_occupied);
+        let bb_queens = white[Pieces::QUEEN] | black[Pieces::QUEEN];
+
+        (self.king[square] & (white[Pieces::KING] | black[Pieces::KING]))
+            | (self.knight[square] & (white[Pieces::KNIGHT] | black[Pieces::KNIGHT]))
+            | (bb_rook & (white[Pieces::ROOK] | black[Pieces::ROOK] | bb_queens))
+            | (bb_bishop & (white[Pieces::BISHOP] | black[Pieces::BISHOP] | bb_queens))
+            | (self.pawns[Sides::BLACK][square] & white[Pieces::PAWN])
+            | (self.pawns[Sides::WHITE][square] & black[Pieces::PAWN])
+    }
+
+    /// Static Exchange Evaluation: the material balance (from the point of view of the side that
+    /// plays the move) after all captures on the destination square of the move have been played
+    /// out, where each side always recaptures with its least valuable piece and may stop capturing
+    /// when continuing would lose material.
+    ///
+    /// A negative value means that the move loses material on that square.
+    ///
+    /// * `board`: The current board.
+    /// * `mv`: The move to evaluate, usually a capture.
+    pub fn see(&self, board: &Board, mv: Move) -> i16 {
+        let to = mv.to();
+        let mut gain = [0i32; MAX_EXCHANGES];
+        let mut depth = 0;
+        let mut side = board.current_side();
+        let mut bb_occupied = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
+
+        // The piece standing on the target square after the move.
+        let mut on_square = mv.piece();
+
+        if mv.is_capture() {
+            gain[0] = SEE_VALUES[mv.captured()];
+        }
+
+        if mv.is_promotion() {
+            gain[0] += SEE_VALUES[mv.promoted()] - SEE_VALUES[Pieces::PAWN];
+            on_square = mv.promoted();
+        }
+
+        bb_occupied ^= BB_SQUARES[mv.from()];
+        if mv.en_passant() {
+            let captured_square = if side == Sides::WHITE { to - 8 } else { to + 8 };
+            bb_occupied ^= BB_SQUARES[captured_square];
+        }
+
+        loop {
+            side ^= 1;
+
+            let bb_attackers = self.attackers_to(board, to, bb_occupied) & bb_occupied;
+            let bb_side_attackers = bb_attackers & board.bb_side[side];
+            if bb_side_attackers == 0 || depth + 1 >= MAX_EXCHANGES {
+                break;
+            }
+
+            depth += 1;
+
+            // The score if the piece on the square is captured, and the opponent stops there.
+            gain[depth] = SEE_VALUES[on_square] - gain[depth - 1];
+
+            // Capture with the least valuable attacker.
+            for piece in LEAST_VALUABLE_FIRST {
+                let bb_piece = bb_side_attackers & board.bb_pieces[side][piece];
+                if bb_piece > 0 {
+                    bb_occupied ^= bb_piece & bb_piece.wrapping_neg();
+                    on_square = piece;
+                    break;
+                }
+            }
+        }
+
+        // Both sides may decline to continue the exchange, so propagate the best outcome for each
+        // side back to the start of the sequence.
+        while depth > 0 {
+            gain[depth - 1] = -((-gain[depth - 1]).max(gain[depth]));
+            depth -= 1;
+        }
+
+        gain[0] as i16
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{
+    board::{
+        defs::{Pieces, Ranks, Sides, Square, BB_RANKS, BB_SQUARES},
+        Board,
+    },
+    utils::bits,
+};
+
+use super::{
+    defs::{Move, Shift},
+    movelist::MoveList,
+    MoveGenerator,
+};
+
+impl MoveGenerator {
+    /// Generates the moves the side that is not to move could have played to reach the current
+    /// position, for the retrograde analysis of endgames. Only moves that do not capture or
+    /// promote are generated, as those would have changed the material. Each move goes from the
+    /// square the piece came from to the square it stands on now.
+    ///
+    /// NOTE: The positions before the moves are not checked: the side to move may be in check
+    /// in them.
+    ///
+    /// * `board`: The current board.
+    /// * `move_list`: A list where the moves will be appended.
+    pub fn generate_unmoves(&self, board: &Board, move_list: &mut MoveList) {
+        let mover = board.opponent();
+        let bb_occupied = board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK];
+        let bb_empty = !bb_occupied;
+
+        for piece in [
+            Pieces::KING,
+            Pieces::QUEEN,
+            Pieces::ROOK,
+            Pieces::BISHOP,
+            Pieces::KNIGHT,
+        ] {
+            let mut bb_pieces = board.bb_pieces[mover][piece];
+            while bb_pieces > 0 {
+                let to = bits::next(&mut bb_pieces);
+                let bb_from = match piece {
+                    Pieces::KING | Pieces::KNIGHT => self.get_non_slider_attacks(piece, to),
+                    _ => self.get_slider_attacks(piece, to, bb_occupied),
+                };
+
+                let mut bb_from = bb_from & bb_empty;
+                while bb_from > 0 {
+                    let from = bits::next(&mut bb_from);
+                    move_list.push(unmove(piece, from, to, false));
+                }
+            }
+        }
+
+        // A pawn came from one square behind, or two from its starting rank.
+        let (behind, bb_double): (fn(Square) -> Square, _) = match mover {
+            Sides::WHITE => (|square| square - 8, BB_RANKS[Ranks::R4]),
+            _ => (|square| square + 8, BB_RANKS[Ranks::R5]),
+        };
+        let bb_first = BB_RANKS[Ranks::R1 + 7 * mover];
+
+        let mut bb_pawns = board.bb_pieces[mover][Pieces::PAWN];
+        while bb_pawns > 0 {
+            let to = bits::next(&mut bb_pawns);
+            let from = behind(to);
+            if BB_SQUARES[from] & bb_empty & !bb_first == 0 {
+                continue;
+            }
+            move_list.push(unmove(Pieces::PAWN, from, to, false));
+
+            if BB_SQUARES[to] & bb_double > 0 {
+                let start = behind(from);
+                if BB_SQUARES[start] & bb_empty > 0 {
+                    move_list.push(unmove(Pieces::PAWN, start, to, true));
+                }
+            }
+        }
+    }
+}
+
+/// A move that neither captures nor promotes.
+fn unmove(piece: usize, from: Square, to: Square, double_step: bool) -> Move {
+    Move::new(
+        piece
+            | from << Shift::FROM_SQ
+            | to << Shift::TO_SQ
+            | Pieces::NONE << Shift::CAPTURE
+            | Pieces::NONE << Shift::PROMOTION
+            | (double_step as usize) << Shift::DOUBLE_STEP,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::Board,
+        movegen::{movelist::MoveList, MoveGenerator},
+    };
+
+    #[test]
+    fn test_unmoves_lead_back_to_the_position() {
+        let mg = MoveGenerator::new();
+        let positions = [
+            ("8/8/8/3k4/8/8/3K4/R7 b - - 0 1", 22),
+            ("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1", 7),
+            ("4k3/8/8/8/4P3/4N3/8/4K3 b - - 0 1", 13),
+            ("4k3/8/8/3p4/8/8/8/4K3 w - - 0 1", 7),
+        ];
+
+        for (fen, count) in positions {
+            let mut board = Board::new();
+            board.fen_read(Some(fen)).unwrap();
+            let mut unmoves = MoveList::new();
+            mg.generate_unmoves(&board, &mut unmoves);
+            assert_eq!(unmoves.len(), count, "{fen}");
+
+            // Taking the move back and playing it again gives the same position.
+            for mv in unmoves.iter() {
+                let mut before = board.clone();
+                let mover = board.opponent();
+                before.move_piece(mover, mv.piece(), mv.to(), mv.from());
+                before.state.active_side ^= 1;
+                assert!(before.make_move(*mv, &mg), "{fen} {mv}");
+                assert_eq!(before.bb_pieces, board.bb_pieces);
+            }
+        }
+    }
+}
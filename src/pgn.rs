@@ -0,0 +1,3 @@
+pub mod defs;
+pub mod reader;
+pub mod writer;
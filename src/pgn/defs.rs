@@ -0,0 +1,239 @@
+use std::{fmt::Display, time::Duration};
+
+use crate::{
+    board::{fen::FenError, notation::MoveParseError},
+    movegen::defs::Move,
+    search::defs::{moves_to_mate, CHECKMATE},
+};
+
+/// The outcome of a game, as written at the end of the movetext and in the `Result` tag.
+///
+/// * `WhiteWins`: `1-0`.
+/// * `BlackWins`: `0-1`.
+/// * `Draw`: `1/2-1/2`.
+/// * `Unknown`: `*`; the game is still going on, was abandoned, or the result is not known.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    #[default]
+    Unknown,
+}
+
+impl GameResult {
+    /// Reads a result as written in PGN. Returns None if the text is not a result.
+    ///
+    /// * `text`: The result to read.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "1-0" => Some(Self::WhiteWins),
+            "0-1" => Some(Self::BlackWins),
+            "1/2-1/2" => Some(Self::Draw),
+            "*" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self {
+            Self::WhiteWins => "1-0",
+            Self::BlackWins => "0-1",
+            Self::Draw => "1/2-1/2",
+            Self::Unknown => "*",
+        };
+        write!(f, "{result}")
+    }
+}
+
+/// An engine's evaluation of a move, written in a comment as `{+0.35/18}`, or `{-M3/20}` for a
+/// mate.
+///
+/// * `score`: The score in centipawns, or a mate score, from the point of view of the side that
+///   played the move.
+/// * `depth`: The depth of the search that found the move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveEval {
+    pub score: i16,
+    pub depth: u8,
+}
+
+impl MoveEval {
+    /// Reads an evaluation as written in a comment. Returns None if the text is not one.
+    ///
+    /// * `text`: The evaluation to read, such as `+0.35/18` or `+M5/22`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (score, depth) = text.split_once('/')?;
+        let depth = depth.parse().ok()?;
+
+        let (negative, unsigned) = match score.as_bytes().first()? {
+            b'-' => (true, &score[1..]),
+            b'+' => (false, &score[1..]),
+            _ => (false, score),
+        };
+
+        let score = match unsigned.strip_prefix('M') {
+            Some(moves) => {
+                let moves: i16 = moves.parse().ok().filter(|&moves| moves > 0)?;
+                match negative {
+                    true => -CHECKMATE + 2 * moves,
+                    false => CHECKMATE - 2 * moves + 1,
+                }
+            }
+            None => {
+                if !unsigned.contains('.') {
+                    return None;
+                }
+                let pawns: f64 = unsigned.parse().ok()?;
+                let centipawns = (pawns * 100.0).round() as i16;
+                if negative {
+                    -centipawns
+                } else {
+                    centipawns
+                }
+            }
+        };
+
+        Some(Self { score, depth })
+    }
+}
+
+impl Display for MoveEval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match moves_to_mate(self.score) {
+            Some(moves) if moves > 0 => write!(f, "+M{moves}/{}", self.depth),
+            Some(moves) => write!(f, "-M{}/{}", -moves, self.depth),
+            None => write!(f, "{:+.2}/{}", self.score as f64 / 100.0, self.depth),
+        }
+    }
+}
+
+/// A move of a game, with the annotations around it.
+///
+/// * `mv`: The move.
+/// * `san`: The move in SAN, as it is written in the game.
+/// * `comment_before`: A comment in front of the move; only found before the first move of the
+///   game or of a variation.
+/// * `nags`: The Numeric Annotation Glyphs of the move, such as 1 for `!` and 2 for `?`.
+/// * `comment`: The comment after the move, without the evaluation and the clock.
+/// * `eval`: The engine's evaluation of the move.
+/// * `clock`: The time left on the clock of the side that played the move, written in the
+///   comment as `[%clk 0:04:57]`.
+/// * `variations`: Lines that could have been played instead of this move, starting from the
+///   same position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnMove {
+    pub mv: Move,
+    pub san: String,
+    pub comment_before: Option<String>,
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    pub eval: Option<MoveEval>,
+    pub clock: Option<Duration>,
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    /// Creates a move without annotations.
+    ///
+    /// * `mv`: The move.
+    /// * `san`: The move in SAN.
+    pub fn new(mv: Move, san: String) -> Self {
+        Self {
+            mv,
+            san,
+            comment_before: None,
+            nags: Vec::new(),
+            comment: None,
+            eval: None,
+            clock: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// A game, as read from or written to a PGN file.
+///
+/// * `tags`: The tag pairs, in the order they appear.
+/// * `moves`: The main line of the game.
+/// * `result`: The outcome of the game.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Game {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+}
+
+impl Game {
+    /// The value of a tag, if the game has it.
+    ///
+    /// * `name`: The name of the tag, which is case sensitive.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The FEN string of the position the game starts from, or None for the starting position.
+    pub fn fen(&self) -> Option<&str> {
+        self.tag("FEN")
+    }
+
+    /// The moves of the main line, without annotations.
+    pub fn main_line(&self) -> Vec<Move> {
+        self.moves.iter().map(|pgn_move| pgn_move.mv).collect()
+    }
+}
+
+/// The errors that can occur when reading a PGN file.
+///
+/// * `Io`: The file could not be read.
+/// * `Tag`: A tag pair is not of the form `[Name "Value"]`.
+/// * `Fen`: The FEN tag could not be read.
+/// * `Move`: A move can not be played in the position.
+/// * `Syntax`: The movetext is malformed, such as an unclosed comment or variation.
+#[derive(Debug, PartialEq)]
+pub enum PgnErrorKind {
+    Io(String),
+    Tag(String),
+    Fen(FenError),
+    Move(MoveParseError),
+    Syntax(String),
+}
+
+impl Display for PgnErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Read error: {error}"),
+            Self::Tag(line) => write!(f, "Invalid tag pair: {line}"),
+            Self::Fen(error) => write!(f, "{error}"),
+            Self::Move(error) => write!(f, "{error}"),
+            Self::Syntax(error) => write!(f, "Invalid movetext: {error}"),
+        }
+    }
+}
+
+/// An error in a PGN file, and where it was found.
+///
+/// * `game`: The number of the game in the file, starting at 1.
+/// * `ply`: The number of the half-move in the game where the error was found, starting at 1;
+///   None when the error is not in the movetext.
+/// * `kind`: What went wrong.
+#[derive(Debug, PartialEq)]
+pub struct PgnError {
+    pub game: usize,
+    pub ply: Option<usize>,
+    pub kind: PgnErrorKind,
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.ply {
+            Some(ply) => write!(f, "Game {}, ply {}: {}", self.game, ply, self.kind),
+            None => write!(f, "Game {}: {}", self.game, self.kind),
+        }
+    }
+}
@@ -0,0 +1,621 @@
+use std::{
+    io::{self, BufRead, Lines},
+    time::Duration,
+};
+
+use crate::{board::Board, movegen::MoveGenerator};
+
+use super::defs::{Game, GameResult, MoveEval, PgnError, PgnErrorKind, PgnMove};
+
+/// The annotations that may be written after a move instead of a NAG, and their NAGs.
+const SUFFIX_NAGS: [(&str, u8); 6] = [
+    ("!", 1),
+    ("?", 2),
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+];
+
+/// The characters that end a symbol in the movetext.
+const SYMBOL_END: &str = "{}();$";
+
+/// A token of the movetext.
+///
+/// * `Move`: A move in SAN, and the NAGs of the annotations written after it (`!`, `?!`, ...).
+/// * `Nag`: A Numeric Annotation Glyph, `$n`.
+/// * `Comment`: A comment, `{...}` or `;...` up to the end of the line.
+/// * `Open`: The start of a variation.
+/// * `Close`: The end of a variation.
+/// * `Result`: The result that ends the game.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Move(String, Vec<u8>),
+    Nag(u8),
+    Comment(String),
+    Open,
+    Close,
+    Result(GameResult),
+}
+
+/// Reads the games of a PGN file one by one, so files with many games never have to be in
+/// memory at once. The moves are replayed on a board, so each one is checked to be legal.
+///
+/// An error in a game does not stop the reader: the next game is read on the next call. Only an
+/// error reading the file ends it.
+///
+/// * `lines`: The lines of the file.
+/// * `mg`: The move generator, used to replay the moves.
+/// * `games`: The number of games read so far.
+/// * `pending`: A tag line that was read ahead, and starts the next game.
+/// * `failed`: The file could not be read, so there are no more games.
+pub struct PgnReader<R: BufRead> {
+    lines: Lines<R>,
+    mg: MoveGenerator,
+    games: usize,
+    pending: Option<String>,
+    failed: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    /// Creates a reader for the games in a PGN file, or any other source of PGN text.
+    ///
+    /// * `reader`: The source to read from.
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            mg: MoveGenerator::new(),
+            games: 0,
+            pending: None,
+            failed: false,
+        }
+    }
+
+    /// Reads the lines of the next game: its tag lines, and its movetext. A game ends where the
+    /// tags of the next game start, or at the end of the file. Returns None when there are no
+    /// more games.
+    fn read_game_text(&mut self) -> io::Result<Option<(Vec<String>, String)>> {
+        let mut tag_lines = Vec::new();
+        let mut movetext = String::new();
+        let mut in_comment = false;
+
+        loop {
+            let line = match self.pending.take() {
+                Some(line) => line,
+                None => match self.lines.next() {
+                    Some(line) => line?,
+                    None => break,
+                },
+            };
+
+            // Lines starting with `%` are escaped, and are not part of the game.
+            if line.starts_with('%') {
+                continue;
+            }
+
+            let line = line.trim().trim_start_matches('\u{feff}');
+            if !in_comment && line.starts_with('[') {
+                if !movetext.trim().is_empty() {
+                    self.pending = Some(line.to_string());
+                    break;
+                }
+                tag_lines.push(line.to_string());
+                continue;
+            }
+
+            in_comment = ends_in_comment(line, in_comment);
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+
+        if tag_lines.is_empty() && movetext.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some((tag_lines, movetext)))
+    }
+
+    /// Reads a game from its tag lines and movetext.
+    ///
+    /// * `tag_lines`: The lines with the tag pairs.
+    /// * `movetext`: The moves, annotations and result.
+    fn parse_game(&self, tag_lines: &[String], movetext: &str) -> Result<Game, PgnError> {
+        let error = |kind| PgnError {
+            game: self.games,
+            ply: None,
+            kind,
+        };
+
+        let mut tags = Vec::new();
+        for line in tag_lines {
+            parse_tag_line(line, &mut tags).map_err(error)?;
+        }
+
+        let mut game = Game {
+            tags,
+            ..Default::default()
+        };
+
+        let mut board = Board::new();
+        board
+            .fen_read(game.fen())
+            .map_err(|fen_error| error(PgnErrorKind::Fen(fen_error)))?;
+
+        let tokens = tokenize(movetext).map_err(|syntax| error(PgnErrorKind::Syntax(syntax)))?;
+        let mut parser = MovetextParser {
+            tokens,
+            index: 0,
+            board,
+            mg: &self.mg,
+            game: self.games,
+        };
+        let (moves, result) = parser.parse_line(false)?;
+
+        game.moves = moves;
+        game.result = result
+            .or_else(|| game.tag("Result").and_then(GameResult::parse))
+            .unwrap_or_default();
+        Ok(game)
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let (tag_lines, movetext) = match self.read_game_text() {
+            Ok(Some(text)) => text,
+            Ok(None) => return None,
+            Err(error) => {
+                self.failed = true;
+                return Some(Err(PgnError {
+                    game: self.games + 1,
+                    ply: None,
+                    kind: PgnErrorKind::Io(error.to_string()),
+                }));
+            }
+        };
+
+        self.games += 1;
+        Some(self.parse_game(&tag_lines, &movetext))
+    }
+}
+
+/// Reads the movetext of a game, replaying the moves on a board.
+///
+/// * `tokens`: The tokens of the movetext.
+/// * `index`: The token to read next.
+/// * `board`: The position after the moves read so far.
+/// * `mg`: The move generator.
+/// * `game`: The number of the game, for errors.
+struct MovetextParser<'a> {
+    tokens: Vec<Token>,
+    index: usize,
+    board: Board,
+    mg: &'a MoveGenerator,
+    game: usize,
+}
+
+impl MovetextParser<'_> {
+    /// Reads a line of moves: the main line, or a variation up to its closing parenthesis.
+    /// Variations are read recursively, from the position before the move they replace. The
+    /// board is back in its position from before the variation when it ends. Returns the moves
+    /// and, for the main line, the result if it is written.
+    ///
+    /// * `variation`: The line is a variation.
+    fn parse_line(
+        &mut self,
+        variation: bool,
+    ) -> Result<(Vec<PgnMove>, Option<GameResult>), PgnError> {
+        let start = self.board.history.len();
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut comment_before = None;
+
+        let result = loop {
+            let Some(token) = self.tokens.get(self.index).cloned() else {
+                if variation {
+                    return Err(self.syntax_error("variation is not closed"));
+                }
+                break None;
+            };
+            self.index += 1;
+
+            match token {
+                Token::Comment(text) => match moves.last_mut() {
+                    Some(last) => annotate(last, text),
+                    None => append_comment(&mut comment_before, text),
+                },
+                Token::Nag(nag) => match moves.last_mut() {
+                    Some(last) => last.nags.push(nag),
+                    None => return Err(self.syntax_error("annotation before the first move")),
+                },
+                Token::Open => {
+                    let Some(last) = moves.last_mut() else {
+                        return Err(self.syntax_error("variation before the first move"));
+                    };
+                    let mv = last.mv;
+
+                    self.board.unmake_move();
+                    let (line, _) = self.parse_line(true)?;
+                    self.board.make_move(mv, self.mg);
+
+                    if let Some(last) = moves.last_mut() {
+                        last.variations.push(line);
+                    }
+                }
+                Token::Close if variation => break None,
+                Token::Close => return Err(self.syntax_error("unexpected `)`")),
+                Token::Result(_) if variation => {
+                    return Err(self.syntax_error("result inside a variation"));
+                }
+                Token::Result(result) => break Some(result),
+                Token::Move(text, nags) => {
+                    let mv = self
+                        .board
+                        .parse_san(&text, self.mg)
+                        .map_err(|error| self.error(PgnErrorKind::Move(error)))?;
+                    let san = self.board.move_to_san(mv, self.mg);
+                    self.board.make_move(mv, self.mg);
+
+                    let mut pgn_move = PgnMove::new(mv, san);
+                    pgn_move.comment_before = comment_before.take();
+                    pgn_move.nags = nags;
+                    moves.push(pgn_move);
+                }
+            }
+        };
+
+        if variation {
+            while self.board.history.len() > start {
+                self.board.unmake_move();
+            }
+        }
+        Ok((moves, result))
+    }
+
+    /// An error at the current ply of the game.
+    fn error(&self, kind: PgnErrorKind) -> PgnError {
+        PgnError {
+            game: self.game,
+            ply: Some(self.board.history.len() + 1),
+            kind,
+        }
+    }
+
+    /// A syntax error at the current ply of the game.
+    fn syntax_error(&self, message: &str) -> PgnError {
+        self.error(PgnErrorKind::Syntax(message.to_string()))
+    }
+}
+
+/// Reads the tag pairs on a line, which usually has one: `[Name "Value"]`. Quotes and
+/// backslashes in the value are escaped with a backslash.
+///
+/// * `line`: The line to read.
+/// * `tags`: Receives the tag pairs.
+fn parse_tag_line(line: &str, tags: &mut Vec<(String, String)>) -> Result<(), PgnErrorKind> {
+    let error = || PgnErrorKind::Tag(line.to_string());
+    let mut rest = line.trim();
+
+    while !rest.is_empty() {
+        let inner = rest.strip_prefix('[').ok_or_else(error)?.trim_start();
+        let (name, after) = inner.split_once(char::is_whitespace).ok_or_else(error)?;
+        let after = after.trim_start().strip_prefix('"').ok_or_else(error)?;
+
+        let mut value = String::new();
+        let mut end = None;
+        let mut chars = after.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => value.extend(chars.next().map(|(_, escaped)| escaped)),
+                '"' => {
+                    end = Some(i);
+                    break;
+                }
+                _ => value.push(c),
+            }
+        }
+
+        let end = end.ok_or_else(error)?;
+        rest = after[end + 1..]
+            .trim_start()
+            .strip_prefix(']')
+            .ok_or_else(error)?
+            .trim_start();
+        tags.push((name.to_string(), value));
+    }
+
+    Ok(())
+}
+
+/// Determines if a line of movetext ends inside a `{...}` comment, which may span lines.
+///
+/// * `line`: The line of movetext.
+/// * `in_comment`: The line starts inside a comment.
+fn ends_in_comment(line: &str, mut in_comment: bool) -> bool {
+    for c in line.chars() {
+        match c {
+            '}' if in_comment => in_comment = false,
+            '{' if !in_comment => in_comment = true,
+            ';' if !in_comment => break,
+            _ => (),
+        }
+    }
+    in_comment
+}
+
+/// Adds a comment to the comment of a move, if there already is one.
+fn append_comment(target: &mut Option<String>, text: String) {
+    match target {
+        Some(comment) => {
+            comment.push(' ');
+            comment.push_str(&text);
+        }
+        None => *target = Some(text),
+    }
+}
+
+/// Adds a comment found after a move to it. An evaluation at the start of the comment and a
+/// `[%clk]` command are taken out of it, into the evaluation and the clock of the move.
+///
+/// * `pgn_move`: The move the comment is about.
+/// * `text`: The text of the comment.
+fn annotate(pgn_move: &mut PgnMove, text: String) {
+    let mut text = text;
+
+    if let Some(start) = text.find("[%clk ") {
+        if let Some(length) = text[start..].find(']') {
+            let clock = parse_clock(text[start + 6..start + length].trim());
+            if clock.is_some() {
+                pgn_move.clock = clock;
+                text.replace_range(start..=start + length, "");
+            }
+        }
+    }
+
+    if pgn_move.eval.is_none() && pgn_move.comment.is_none() {
+        let first = text.split_whitespace().next().unwrap_or_default();
+        if let Some(eval) = MoveEval::parse(first) {
+            pgn_move.eval = Some(eval);
+            text = text.trim_start()[first.len()..].to_string();
+        }
+    }
+
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if !text.is_empty() {
+        append_comment(&mut pgn_move.comment, text);
+    }
+}
+
+/// Reads the time of a `[%clk]` command, as `h:mm:ss` with an optional fraction of a second.
+///
+/// * `text`: The time to read.
+fn parse_clock(text: &str) -> Option<Duration> {
+    let mut parts = text.split(':');
+    let (hours, minutes, seconds) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let whole = hours.parse::<u64>().ok()? * 3600 + minutes.parse::<u64>().ok()? * 60;
+    let seconds = seconds.parse::<f64>().ok()?;
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .map(|seconds| seconds + Duration::from_secs(whole))
+}
+
+/// Splits movetext into tokens. Move numbers (`12.`, `12...`) are skipped.
+///
+/// * `movetext`: The movetext to split.
+fn tokenize(movetext: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = movetext.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => (),
+            '{' => {
+                let mut text = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    text.push(c);
+                }
+                if !closed {
+                    return Err(String::from("comment is not closed"));
+                }
+                tokens.push(Token::Comment(normalize_comment(&text)));
+            }
+            ';' => {
+                let text: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+                tokens.push(Token::Comment(normalize_comment(&text)));
+            }
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '$' => {
+                let mut digits = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(digit);
+                }
+                let nag = digits
+                    .parse()
+                    .map_err(|_| format!("invalid NAG `${digits}`"))?;
+                tokens.push(Token::Nag(nag));
+            }
+            '}' => return Err(String::from("unexpected `}`")),
+            _ => {
+                let mut symbol = String::from(c);
+                while let Some(next) =
+                    chars.next_if(|next| !next.is_whitespace() && !SYMBOL_END.contains(*next))
+                {
+                    symbol.push(next);
+                }
+                tokens.extend(symbol_token(&symbol));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// The token of a symbol in the movetext: a result, or a move, possibly preceded by its move
+/// number. Returns None for a move number on its own.
+///
+/// * `symbol`: The symbol to read.
+fn symbol_token(symbol: &str) -> Option<Token> {
+    if let Some(result) = GameResult::parse(symbol) {
+        return Some(Token::Result(result));
+    }
+
+    let mut text = symbol;
+    if !text.starts_with("0-0") {
+        let without_number = text.trim_start_matches(|c: char| c.is_ascii_digit());
+        if without_number.starts_with('.') || without_number.is_empty() {
+            text = without_number.trim_start_matches('.');
+        }
+    }
+    if text.is_empty() {
+        return None;
+    }
+
+    let san = text.trim_end_matches(['!', '?']);
+    let suffix = &text[san.len()..];
+    let nags = SUFFIX_NAGS
+        .iter()
+        .filter(|(annotation, _)| *annotation == suffix)
+        .map(|(_, nag)| *nag)
+        .collect();
+
+    Some(Token::Move(san.to_string(), nags))
+}
+
+/// Joins the lines of a comment, and removes the whitespace around it.
+fn normalize_comment(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::notation::MoveParseError,
+        pgn::defs::{GameResult, PgnError, PgnErrorKind},
+    };
+
+    use super::PgnReader;
+
+    const GAMES: &str = r#"[Event "Casual \"blitz\""]
+[Site "?"]
+[White "Alice"]
+[Black "Bob"]
+[Result "1-0"]
+
+{Opening comment} 1. e4 e5 2. Nf3 $1 Nc6 {A long
+comment} 3. Bb5 a6 (3... Nf6 4. O-O (4. d3) 4... Nxe4) 4. Ba4!? Nf6 5. 0-0 1-0
+
+[Event "Second"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+[SetUp "1"]
+
+1. e4 Kd7 2. e5 ; rest of the line
+*
+"#;
+
+    fn read(text: &str) -> Vec<Result<crate::pgn::defs::Game, PgnError>> {
+        PgnReader::new(text.as_bytes()).collect()
+    }
+
+    #[test]
+    fn test_read_games() {
+        let games = read(GAMES);
+        assert_eq!(games.len(), 2);
+
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(game.tag("White"), Some("Alice"));
+        assert_eq!(game.result, GameResult::WhiteWins);
+
+        let sans: Vec<&str> = game.moves.iter().map(|m| m.san.as_str()).collect();
+        assert_eq!(
+            sans,
+            ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6", "O-O"]
+        );
+        assert_eq!(
+            game.moves[0].comment_before.as_deref(),
+            Some("Opening comment")
+        );
+        assert_eq!(game.moves[2].nags, [1]);
+        assert_eq!(game.moves[3].comment.as_deref(), Some("A long comment"));
+        assert_eq!(game.moves[6].nags, [5]);
+
+        let variation = &game.moves[5].variations[0];
+        let sans: Vec<&str> = variation.iter().map(|m| m.san.as_str()).collect();
+        assert_eq!(sans, ["Nf6", "O-O", "Nxe4"]);
+        assert_eq!(variation[1].variations[0][0].san, "d3");
+
+        let second = games[1].as_ref().unwrap();
+        assert_eq!(second.fen(), Some("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        assert_eq!(second.main_line().len(), 3);
+        assert_eq!(second.moves[2].comment.as_deref(), Some("rest of the line"));
+        assert_eq!(second.result, GameResult::Unknown);
+    }
+
+    #[test]
+    fn test_errors_point_at_game_and_ply() {
+        let text = "[Event \"1\"]\n\n1. e4 e5 *\n\n\
+                    [Event \"2\"]\n\n1. d4 d5 2. Nf6 *\n\n\
+                    [Event \"3\"]\n\n1. c4 1/2-1/2\n";
+        let games = read(text);
+
+        assert_eq!(games.len(), 3);
+        assert!(games[0].is_ok());
+        assert_eq!(
+            games[1],
+            Err(PgnError {
+                game: 2,
+                ply: Some(3),
+                kind: PgnErrorKind::Move(MoveParseError::Illegal(String::from("Nf6"))),
+            })
+        );
+        assert_eq!(
+            games[1].as_ref().unwrap_err().to_string(),
+            "Game 2, ply 3: Illegal move: Nf6"
+        );
+        assert_eq!(games[2].as_ref().unwrap().result, GameResult::Draw);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let unclosed = read("1. e4 (1. d4 d5");
+        assert_eq!(
+            unclosed[0].as_ref().unwrap_err().kind,
+            PgnErrorKind::Syntax(String::from("variation is not closed"))
+        );
+
+        let result = read("1. e4 (1. d4 d5 *");
+        assert_eq!(
+            result[0].as_ref().unwrap_err().kind,
+            PgnErrorKind::Syntax(String::from("result inside a variation"))
+        );
+
+        let comment = read("1. e4 {never closed *");
+        assert_eq!(
+            comment[0].as_ref().unwrap_err().kind,
+            PgnErrorKind::Syntax(String::from("comment is not closed"))
+        );
+
+        let tag = read("[Event Casual]\n1. e4 *");
+        assert_eq!(
+            tag[0].as_ref().unwrap_err().kind,
+            PgnErrorKind::Tag(String::from("[Event Casual]"))
+        );
+
+        let variation = read("1. e4 e5 2. Nf3 ) *");
+        assert_eq!(variation[0].as_ref().unwrap_err().ply, Some(4));
+    }
+}
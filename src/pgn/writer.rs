@@ -0,0 +1,286 @@
+use std::{io, time::Duration};
+
+use super::defs::{Game, PgnMove};
+
+/// The longest line of movetext that is written, as recommended by the PGN standard.
+const LINE_WIDTH: usize = 80;
+
+/// The tags every game has, in the order they are written. A tag the game does not have is
+/// written with its unknown value.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+impl Game {
+    /// Writes the game in PGN export format: the Seven Tag Roster followed by the other tags,
+    /// then the movetext in SAN, wrapped at 80 columns. Evaluations and clocks are written in
+    /// the comment after each move, as `{+0.35/18 [%clk 0:04:57]}`.
+    ///
+    /// * `writer`: Where to write the game.
+    pub fn write_pgn(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        let result = self.result.to_string();
+
+        for (name, unknown) in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => result.as_str(),
+                _ => self.tag(name).unwrap_or(unknown),
+            };
+            write_tag(writer, name, value)?;
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.iter().any(|(roster, _)| roster == name) {
+                write_tag(writer, name, value)?;
+            }
+        }
+        writeln!(writer)?;
+
+        let mut words = Vec::new();
+        write_line(&self.moves, self.first_ply(), &mut words);
+        words.push(result);
+
+        for line in wrap(&words) {
+            writeln!(writer, "{line}")?;
+        }
+        writeln!(writer)
+    }
+
+    /// The number of half-moves played before the first move of the game, counted from the
+    /// first move of White, as given by the full move number and the side to move of the FEN tag.
+    fn first_ply(&self) -> usize {
+        let Some(fen) = self.fen() else {
+            return 0;
+        };
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let black = fields.get(1) == Some(&"b");
+        let full_move_number = fields
+            .get(5)
+            .and_then(|number| number.parse::<usize>().ok())
+            .unwrap_or(1)
+            .max(1);
+
+        (full_move_number - 1) * 2 + black as usize
+    }
+}
+
+/// Writes a tag pair, escaping quotes and backslashes in the value.
+///
+/// * `writer`: Where to write the tag.
+/// * `name`: The name of the tag.
+/// * `value`: The value of the tag.
+fn write_tag(writer: &mut impl io::Write, name: &str, value: &str) -> io::Result<()> {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(writer, "[{name} \"{value}\"]")
+}
+
+/// Adds the words of a line of moves to the movetext, with its annotations and variations. The
+/// move number is written before every move of White, and before a move of Black that starts
+/// the line or follows a comment or a variation.
+///
+/// * `moves`: The moves of the line.
+/// * `ply`: The number of half-moves played before the first move of the line.
+/// * `words`: The words of the movetext.
+fn write_line(moves: &[PgnMove], ply: usize, words: &mut Vec<String>) {
+    let mut interrupted = true;
+
+    for (index, pgn_move) in moves.iter().enumerate() {
+        let ply = ply + index;
+        let number = ply / 2 + 1;
+
+        if let Some(comment) = &pgn_move.comment_before {
+            push_comment(comment, words);
+            interrupted = true;
+        }
+
+        if ply.is_multiple_of(2) {
+            words.push(format!("{number}."));
+        } else if interrupted {
+            words.push(format!("{number}..."));
+        }
+        words.push(pgn_move.san.clone());
+        words.extend(pgn_move.nags.iter().map(|nag| format!("${nag}")));
+
+        let mut comment = Vec::new();
+        if let Some(eval) = pgn_move.eval {
+            comment.push(eval.to_string());
+        }
+        if let Some(clock) = pgn_move.clock {
+            comment.push(format!("[%clk {}]", format_clock(clock)));
+        }
+        if let Some(text) = &pgn_move.comment {
+            comment.push(text.clone());
+        }
+        interrupted = !comment.is_empty() || !pgn_move.variations.is_empty();
+        if !comment.is_empty() {
+            push_comment(&comment.join(" "), words);
+        }
+
+        for variation in &pgn_move.variations {
+            let mut variation_words = Vec::new();
+            write_line(variation, ply, &mut variation_words);
+            if let Some(first) = variation_words.first_mut() {
+                first.insert(0, '(');
+            }
+            if let Some(last) = variation_words.last_mut() {
+                last.push(')');
+            }
+            words.extend(variation_words);
+        }
+    }
+}
+
+/// Adds a comment to the movetext, split into words so it can be wrapped over several lines. A
+/// closing brace in the comment would end it early, so it is left out.
+///
+/// * `comment`: The text of the comment.
+/// * `words`: The words of the movetext.
+fn push_comment(comment: &str, words: &mut Vec<String>) {
+    let comment = comment.replace('}', "");
+    let mut comment_words: Vec<String> = comment.split_whitespace().map(String::from).collect();
+
+    if comment_words.is_empty() {
+        comment_words.push(String::new());
+    }
+    comment_words[0].insert(0, '{');
+    if let Some(last) = comment_words.last_mut() {
+        last.push('}');
+    }
+    words.extend(comment_words);
+}
+
+/// Formats the time of a `[%clk]` command as `h:mm:ss`, dropping fractions of a second.
+///
+/// * `clock`: The time left on the clock.
+fn format_clock(clock: Duration) -> String {
+    let seconds = clock.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Joins words into lines of at most [`LINE_WIDTH`] characters. A word longer than that is put
+/// on a line of its own.
+///
+/// * `words`: The words to join.
+fn wrap(words: &[String]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in words {
+        if !line.is_empty() && line.len() + 1 + word.len() > LINE_WIDTH {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        pgn::{
+            defs::{Game, MoveEval},
+            reader::PgnReader,
+        },
+        search::defs::CHECKMATE,
+    };
+
+    fn write(game: &Game) -> String {
+        let mut out = Vec::new();
+        game.write_pgn(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn read(text: &str) -> Game {
+        PgnReader::new(text.as_bytes()).next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_write_tags_and_movetext() {
+        let game = read(
+            "[Annotator \"Carol\"]\n[White \"Alice\"]\n[Event \"Club \\\"open\\\"\"]\n\n\
+             {Start} 1. e4 e5 2. Nf3 $1 Nc6 {Knight out} 3. Bb5 a6 (3... Nf6 4. O-O) 4. Ba4 1-0\n",
+        );
+
+        assert_eq!(
+            write(&game),
+            "[Event \"Club \\\"open\\\"\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n\
+             [Round \"?\"]\n[White \"Alice\"]\n[Black \"?\"]\n[Result \"1-0\"]\n\
+             [Annotator \"Carol\"]\n\n\
+             {Start} 1. e4 e5 2. Nf3 $1 Nc6 {Knight out} 3. Bb5 a6 (3... Nf6 4. O-O) 4. Ba4\n\
+             1-0\n\n"
+        );
+    }
+
+    #[test]
+    fn test_eval_and_clock_round_trip() {
+        assert_eq!(
+            MoveEval::parse("+0.35/18"),
+            Some(MoveEval {
+                score: 35,
+                depth: 18
+            })
+        );
+        assert_eq!(MoveEval::parse("-1.5/7").unwrap().to_string(), "-1.50/7");
+        assert_eq!(MoveEval::parse("+M5/22").unwrap().score, CHECKMATE - 9);
+        assert_eq!(MoveEval::parse("-M3/20").unwrap().to_string(), "-M3/20");
+        assert_eq!(MoveEval::parse("Good/move"), None);
+
+        let game = read(
+            "1. d4 {+0.20/15 [%clk 1:02:03]} d5 {[%clk 0:00:59.5] -0.18/14 solid} \
+             2. c4 {Queen's gambit} *\n",
+        );
+        let d4 = &game.moves[0];
+        assert_eq!(
+            d4.eval,
+            Some(MoveEval {
+                score: 20,
+                depth: 15
+            })
+        );
+        assert_eq!(d4.clock, Some(Duration::from_secs(3723)));
+        assert_eq!(d4.comment, None);
+        assert_eq!(game.moves[1].clock, Some(Duration::from_millis(59_500)));
+        assert_eq!(game.moves[1].eval.unwrap().score, -18);
+        assert_eq!(game.moves[1].comment.as_deref(), Some("solid"));
+
+        let text = write(&game);
+        assert!(text.contains(
+            "1. d4 {+0.20/15 [%clk 1:02:03]} 1... d5 {-0.18/14 [%clk 0:00:59] solid} 2. c4"
+        ));
+        assert_eq!(read(&text).moves[0], game.moves[0]);
+    }
+
+    #[test]
+    fn test_wrapping_and_move_numbers() {
+        let game = read(
+            "[FEN \"rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 7\"]\n\
+             [SetUp \"1\"]\n\n\
+             7... Nc6 8. Nf3 Nf6 9. Nc3 Bb4 10. Bb5 O-O 11. O-O d6 12. d3 Bxc3 13. bxc3 Ne7 \
+             14. Re1 Ng6 15. d4 c6 16. Bd3 Re8 17. h3 h6 18. a4 Be6 19. Ba3 Qc7 1/2-1/2\n",
+        );
+        let text = write(&game);
+        let movetext: Vec<&str> = text.lines().skip(10).filter(|l| !l.is_empty()).collect();
+
+        assert!(movetext[0].starts_with("7... Nc6 8. Nf3"));
+        assert!(movetext.len() > 1);
+        assert!(movetext.iter().all(|line| line.len() <= 80));
+        assert_eq!(read(&text).moves, game.moves);
+    }
+}
@@ -0,0 +1,757 @@
+mod alpha_beta;
+pub mod defs;
+mod extensions;
+pub mod mate;
+mod qsearch;
+mod tablebase;
+pub mod threads;
+pub mod time;
+pub mod transposition;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    board::{defs::MAX_MOVE_RULE, Board},
+    dtm::DtmTables,
+    evaluation::wdl,
+    movegen::{
+        defs::{Move, MoveType},
+        history::HistoryTable,
+        movelist::MoveList,
+        picker::MAX_KILLER_MOVES,
+        MoveGenerator,
+    },
+    syzygy::Tablebases,
+};
+
+use defs::{
+    SearchInfo, SearchLimits, SearchOptions, SearchResult, SearchSummary, CHECKMATE, CHECK_NODES,
+    DRAW, INF, MAX_DEPTH, MAX_PLY,
+};
+use time::TimeManager;
+use transposition::{Bound, TranspositionTable};
+
+/// Helper threads skip some depths of iterative deepening, so that not all threads search the
+/// same depth at the same time. Helper `i` skips a depth when
+/// `(depth + SKIP_PHASE[i]) / SKIP_SIZE[i]` is odd.
+const SKIP_SIZE: [u8; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [u8; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+/// Everything that is shared by the threads searching the same position.
+///
+/// The move generator is read-only after it is created, and the transposition table can be
+/// written to without locks, so both can be shared between threads directly.
+///
+/// * `mg`: The move generator.
+/// * `tt`: The transposition table.
+/// * `stop`: When set, all threads stop as soon as possible.
+/// * `ponder`: Set while the search runs on the opponent's time; cleared on `ponderhit`.
+/// * `nodes`: The number of nodes searched by all threads together.
+/// * `tablebases`: The endgame tablebases; none unless set with [`SearchShared::with_tablebases`].
+/// * `dtm`: The DTM tables; none unless set with [`SearchShared::with_dtm_tables`].
+/// * `tbhits`: The number of successful tablebase probes of all threads together.
+pub struct SearchShared {
+    pub mg: Arc<MoveGenerator>,
+    pub tt: Arc<TranspositionTable>,
+    pub stop: Arc<AtomicBool>,
+    pub ponder: Arc<AtomicBool>,
+    pub nodes: AtomicU64,
+    pub tablebases: Arc<Tablebases>,
+    pub dtm: Arc<DtmTables>,
+    pub tbhits: AtomicU64,
+}
+
+impl SearchShared {
+    pub fn new(mg: Arc<MoveGenerator>, tt: Arc<TranspositionTable>, stop: Arc<AtomicBool>) -> Self {
+        Self {
+            mg,
+            tt,
+            stop,
+            ponder: Arc::new(AtomicBool::new(false)),
+            nodes: AtomicU64::new(0),
+            tablebases: Arc::new(Tablebases::default()),
+            dtm: Arc::new(DtmTables::default()),
+            tbhits: AtomicU64::new(0),
+        }
+    }
+
+    /// Lets the search probe the given endgame tablebases.
+    ///
+    /// * `tablebases`: The tablebases.
+    pub fn with_tablebases(mut self, tablebases: Arc<Tablebases>) -> Self {
+        self.tablebases = tablebases;
+        self
+    }
+
+    /// Lets the search probe the given DTM tables.
+    ///
+    /// * `dtm`: The tables.
+    pub fn with_dtm_tables(mut self, dtm: Arc<DtmTables>) -> Self {
+        self.dtm = dtm;
+        self
+    }
+}
+
+/// A move at the root of the search, with what the search learned about it so far.
+///
+/// * `mv`: The move.
+/// * `score`: The score of the move in the last iteration in which it was the best move.
+/// * `nodes`: The number of nodes spent searching this move, over all iterations.
+/// * `pv`: The principal variation starting with this move.
+#[derive(Clone, Debug)]
+struct RootMove {
+    mv: Move,
+    score: i16,
+    nodes: u64,
+    pv: Vec<Move>,
+}
+
+impl RootMove {
+    fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            score: -INF,
+            nodes: 0,
+            pv: vec![mv],
+        }
+    }
+}
+
+/// Finds the best move in a position, using an iterative deepening alpha-beta search.
+///
+/// The search works on its own copy of the board, so it can run in a separate thread while the
+/// engine keeps its position. It stops when one of its limits is reached, or when the stop flag
+/// is set. Several searches of the same position can run at the same time, sharing a
+/// [`SearchShared`]; see [`threads::run_threads`].
+///
+/// * `board`: The position to search.
+/// * `shared`: The data shared with the other threads.
+/// * `mg`: The move generator.
+/// * `tt`: The transposition table.
+/// * `thread_id`: 0 for the main thread, which reports and manages the time; helpers count up.
+/// * `limits`: The limits of this search.
+/// * `options`: The settings that apply to all searches.
+/// * `time`: Decides how long the search may take.
+/// * `nodes`: The number of nodes searched by this thread.
+/// * `flushed_nodes`: The part of `nodes` that was already added to the shared node count.
+/// * `seldepth`: The highest ply reached in the current iteration.
+/// * `aborted`: The current iteration was aborted, so its result can not be used.
+/// * `root_depth`: The depth of the current iteration.
+/// * `excluded`: The move that is skipped at each ply, during the verification search of a
+///   singular extension.
+/// * `killers`: Quiet moves that caused a beta cut-off, per ply.
+/// * `history`: How often quiet moves caused a beta cut-off in this thread.
+/// * `root_moves`: The legal moves at the root, best move first.
+/// * `tb_pieces`: Positions with at most this many pieces are probed in the tablebases during the
+///   search; 0 when the search does not probe them.
+pub struct Search<'a> {
+    board: Board,
+    shared: &'a SearchShared,
+    mg: &'a MoveGenerator,
+    tt: &'a TranspositionTable,
+    thread_id: usize,
+    limits: SearchLimits,
+    options: SearchOptions,
+    time: TimeManager,
+    nodes: u64,
+    flushed_nodes: u64,
+    seldepth: usize,
+    aborted: bool,
+    root_depth: u8,
+    excluded: [Option<Move>; MAX_PLY],
+    killers: [[Option<Move>; MAX_KILLER_MOVES]; MAX_PLY],
+    history: HistoryTable,
+    root_moves: Vec<RootMove>,
+    tb_pieces: usize,
+}
+
+impl<'a> Search<'a> {
+    /// Create a new [`Search`] for the main thread. The clock starts when [`Search::run`] is
+    /// called.
+    ///
+    /// * `board`: The position to search, including the history of the game.
+    /// * `shared`: The move generator, transposition table and stop flag.
+    /// * `limits`: The limits of this search.
+    /// * `options`: The settings that apply to all searches.
+    pub fn new(
+        board: Board,
+        shared: &'a SearchShared,
+        limits: SearchLimits,
+        options: SearchOptions,
+    ) -> Self {
+        let time = TimeManager::new(&limits, board.current_side(), options.move_overhead);
+
+        Self {
+            board,
+            shared,
+            mg: &shared.mg,
+            tt: &shared.tt,
+            thread_id: 0,
+            limits,
+            options,
+            time,
+            nodes: 0,
+            flushed_nodes: 0,
+            seldepth: 0,
+            aborted: false,
+            root_depth: 0,
+            excluded: [None; MAX_PLY],
+            killers: [[None; MAX_KILLER_MOVES]; MAX_PLY],
+            history: HistoryTable::new(),
+            root_moves: Vec::new(),
+            tb_pieces: 0,
+        }
+    }
+
+    /// Turns the search into a helper thread. Helpers do not report, do not manage the time and
+    /// skip some depths; they keep searching until the main thread sets the stop flag. They only
+    /// search for the best line, even in MultiPV mode.
+    ///
+    /// * `thread_id`: The number of the helper, starting at 1.
+    pub fn helper(mut self, thread_id: usize) -> Self {
+        self.thread_id = thread_id;
+        self.options.multipv = 1;
+        self
+    }
+
+    /// True for the main thread.
+    fn is_main(&self) -> bool {
+        self.thread_id == 0
+    }
+
+    /// Runs the search until one of its limits is reached or it is stopped, and returns the best
+    /// move that was found.
+    ///
+    /// * `report`: Called with the progress of the search.
+    pub fn run(&mut self, report: &mut dyn FnMut(&SearchInfo)) -> SearchResult {
+        self.time = TimeManager::new(
+            &self.limits,
+            self.board.current_side(),
+            self.options.move_overhead,
+        );
+
+        // Only the moves of `searchmoves` are searched, unless none of them is legal.
+        let mut legal = MoveList::new();
+        self.mg
+            .generate_legal_moves(&mut self.board, &mut legal, MoveType::All);
+        let search_moves = &self.limits.search_moves;
+        let restricted = legal.iter().any(|mv| search_moves.contains(mv));
+        self.root_moves = legal
+            .iter()
+            .filter(|mv| !restricted || search_moves.contains(mv))
+            .map(|mv| RootMove::new(*mv))
+            .collect();
+
+        if self.root_moves.is_empty() {
+            let score = if self.mg.in_check(&self.board) {
+                -CHECKMATE
+            } else {
+                DRAW
+            };
+            self.wait_for_stop();
+            return self.result(vec![self.summary(1, 0, score, Vec::new())]);
+        }
+
+        self.filter_root_moves();
+        if self.root_moves.len() == 1 {
+            self.time.set_single_reply();
+        }
+
+        let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let multipv = self.options.multipv.clamp(1, self.root_moves.len());
+        let mut last = Vec::new();
+
+        'deepening: for depth in 1..=max_depth {
+            if self.skips_depth(depth) {
+                continue;
+            }
+
+            self.seldepth = 0;
+            let mut lines = Vec::with_capacity(multipv);
+            for pv_index in 0..multipv {
+                if !self.aspiration(depth, pv_index, report) {
+                    break 'deepening;
+                }
+
+                let root = &self.root_moves[pv_index];
+                lines.push(self.summary(pv_index + 1, depth, root.score, root.pv.clone()));
+            }
+            last = lines;
+
+            if !self.is_main() {
+                continue;
+            }
+            for line in &last {
+                report(&SearchInfo::Summary(line.clone()));
+            }
+
+            self.check_ponderhit();
+            let root_nodes = self.root_moves.iter().map(|root| root.nodes).sum();
+            let best = &self.root_moves[0];
+            self.time
+                .update(depth, best.mv, best.score, best.nodes, root_nodes);
+
+            if self.time.should_stop() {
+                break;
+            }
+        }
+
+        self.flush_nodes();
+        if self.is_main() {
+            self.wait_for_stop();
+        }
+
+        // Even if the first iteration did not complete, a legal move has to be played.
+        if last.is_empty() {
+            let pv = vec![self.root_moves[0].mv];
+            last.push(self.summary(1, 0, DRAW, pv));
+        }
+        self.result(last)
+    }
+
+    /// The number of nodes searched so far by this thread.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Determines if a helper thread skips the given depth.
+    fn skips_depth(&self, depth: u8) -> bool {
+        if self.is_main() || depth == 1 {
+            return false;
+        }
+
+        let i = (self.thread_id - 1) % SKIP_SIZE.len();
+        !((depth + SKIP_PHASE[i]) / SKIP_SIZE[i]).is_multiple_of(2)
+    }
+
+    /// Creates a [`SearchSummary`] for the current state of the search. The node count includes
+    /// the nodes of the other threads.
+    fn summary(&self, multipv: usize, depth: u8, score: i16, pv: Vec<Move>) -> SearchSummary {
+        let material = wdl::material_count(&self.board);
+        let wdl = self
+            .options
+            .show_wdl
+            .then(|| wdl::score_to_wdl(score, material));
+        let score = if self.options.normalize_score {
+            wdl::normalize_score(score, material)
+        } else {
+            score
+        };
+
+        SearchSummary {
+            multipv,
+            depth,
+            seldepth: self.seldepth,
+            score,
+            bound: Bound::Exact,
+            wdl,
+            nodes: self.total_nodes(),
+            hashfull: self.tt.hashfull(),
+            tbhits: self.shared.tbhits.load(Ordering::Relaxed),
+            time: self.time.elapsed(),
+            pv,
+        }
+    }
+
+    /// The number of nodes searched by all threads, including the nodes of this thread that were
+    /// not added to the shared count yet.
+    fn total_nodes(&self) -> u64 {
+        self.shared.nodes.load(Ordering::Relaxed) + self.nodes - self.flushed_nodes
+    }
+
+    /// Adds the nodes searched since the last call to the shared node count.
+    fn flush_nodes(&mut self) {
+        let unflushed = self.nodes - self.flushed_nodes;
+        self.shared.nodes.fetch_add(unflushed, Ordering::Relaxed);
+        self.flushed_nodes = self.nodes;
+    }
+
+    /// Creates the [`SearchResult`] from the lines of the last completed iteration.
+    fn result(&self, lines: Vec<SearchSummary>) -> SearchResult {
+        let summary = lines[0].clone();
+        SearchResult {
+            best_move: summary.pv.first().copied(),
+            ponder_move: summary.pv.get(1).copied(),
+            summary,
+            lines,
+        }
+    }
+
+    /// An infinite search may not return before it is told to stop, even if it has nothing left
+    /// to search. The same holds for pondering, until the `ponderhit`.
+    fn wait_for_stop(&mut self) {
+        while !self.shared.stop.load(Ordering::Relaxed) {
+            self.check_ponderhit();
+            if !self.limits.infinite && !self.time.is_pondering() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Switches the time manager to the time limits once the opponent played the expected move.
+    fn check_ponderhit(&mut self) {
+        if self.time.is_pondering() && !self.shared.ponder.load(Ordering::Relaxed) {
+            self.time.ponderhit();
+        }
+    }
+
+    /// Counts a node and, every [`CHECK_NODES`] nodes, checks if the search has to stop. A node
+    /// limit is checked at every node, so it is never exceeded.
+    fn count_node(&mut self, ply: usize) {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if self
+            .limits
+            .nodes
+            .is_some_and(|max| self.total_nodes() >= max)
+        {
+            self.aborted = true;
+        }
+
+        if self.nodes.is_multiple_of(CHECK_NODES) {
+            self.flush_nodes();
+            if self.is_main() {
+                self.check_ponderhit();
+            }
+            let out_of_time = self.is_main() && self.time.out_of_time();
+            if out_of_time || self.shared.stop.load(Ordering::Relaxed) {
+                self.aborted = true;
+            }
+        }
+    }
+
+    /// Determines if the position on the board is a draw by the fifty-move rule, repetition or
+    /// insufficient material.
+    fn is_draw(&self) -> bool {
+        self.board.state.half_move_clock >= MAX_MOVE_RULE
+            || self.board.is_repetition()
+            || self.board.is_insufficient_material()
+    }
+
+    /// Remembers a quiet move that caused a beta cut-off, so it is tried early in sibling nodes
+    /// and in other positions.
+    fn store_quiet_cutoff(&mut self, mv: Move, depth: i8, ply: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        self.history.reward(self.board.current_side(), mv, depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        board::{defs::Squares, Board},
+        dtm::{self, Dtm},
+        movegen::{defs::MoveType, movelist::MoveList, MoveGenerator},
+        search::{
+            defs::{moves_to_mate, SearchInfo, SearchLimits, SearchOptions, SearchResult, DRAW},
+            transposition::TranspositionTable,
+        },
+        syzygy::{self, Wdl},
+    };
+
+    use super::{Search, SearchShared};
+
+    fn shared(stop: bool) -> SearchShared {
+        SearchShared::new(
+            Arc::new(MoveGenerator::new()),
+            Arc::new(TranspositionTable::new(1)),
+            Arc::new(AtomicBool::new(stop)),
+        )
+    }
+
+    fn search(fen: &str, depth: u8) -> SearchResult {
+        let mut board = Board::new();
+        board.fen_read(Some(fen)).unwrap();
+        let limits = SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        };
+        let shared = shared(false);
+        let mut search = Search::new(board, &shared, limits, SearchOptions::default());
+        search.run(&mut |_| {})
+    }
+
+    #[test]
+    fn test_search_finds_mate_in_one() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        let best = result.best_move.unwrap();
+        assert_eq!(best.to(), Squares::A8);
+        assert_eq!(moves_to_mate(result.summary.score), Some(1));
+    }
+
+    #[test]
+    fn test_search_finds_mate_in_two() {
+        // 1. Kf7 Kh7 2. Rh1#
+        let result = search("7k/8/5K2/8/8/8/8/R7 w - - 0 1", 4);
+        assert_eq!(moves_to_mate(result.summary.score), Some(2));
+        assert_eq!(result.summary.pv.len(), 3);
+    }
+
+    #[test]
+    fn test_search_wins_hanging_queen() {
+        let result = search("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2);
+        let best = result.best_move.unwrap();
+        assert_eq!(best.to(), Squares::D5);
+        assert!(result.summary.score > 0);
+    }
+
+    #[test]
+    fn test_multipv_finds_distinct_lines_in_order() {
+        let mut board = Board::new();
+        // Taking the queen is best; the pawns can also take a rook or a knight.
+        board
+            .fen_read(Some("4k3/8/8/8/1q1r1n2/2P1P3/8/4K3 w - - 0 1"))
+            .unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..Default::default()
+        };
+        let options = SearchOptions {
+            multipv: 3,
+            ..Default::default()
+        };
+        let shared = shared(false);
+
+        let mut reported = Vec::new();
+        let mut search = Search::new(board, &shared, limits, options);
+        let result = search.run(&mut |info| {
+            if let SearchInfo::Summary(line) = info {
+                reported.push(line.multipv);
+            }
+        });
+
+        assert_eq!(reported, [1, 2, 3, 1, 2, 3, 1, 2, 3]);
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.summary, result.lines[0]);
+
+        let first: Vec<_> = result.lines.iter().map(|line| line.pv[0]).collect();
+        assert_eq!(first[0].to(), Squares::B4);
+        assert!(first[0] != first[1] && first[0] != first[2] && first[1] != first[2]);
+        assert!(result.lines[0].score > result.lines[1].score);
+        assert!(result.lines[1].score >= result.lines[2].score);
+    }
+
+    #[test]
+    fn test_multipv_is_limited_to_legal_moves() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("7k/8/8/8/8/8/6q1/7K w - - 0 1"))
+            .unwrap();
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..Default::default()
+        };
+        let options = SearchOptions {
+            multipv: 4,
+            ..Default::default()
+        };
+        let shared = shared(false);
+
+        let result = Search::new(board, &shared, limits, options).run(&mut |_| {});
+        assert_eq!(result.lines.len(), 1);
+    }
+
+    #[test]
+    fn test_search_without_legal_moves() {
+        let stalemate = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+        assert_eq!(stalemate.best_move, None);
+        assert_eq!(stalemate.summary.score, DRAW);
+
+        let mated = search("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 3);
+        assert_eq!(mated.best_move, None);
+        assert!(mated.summary.score < 0);
+    }
+
+    #[test]
+    fn test_search_is_stopped_by_flag() {
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let shared = shared(true);
+        let mut search = Search::new(
+            board,
+            &shared,
+            SearchLimits::default(),
+            SearchOptions::default(),
+        );
+
+        let result = search.run(&mut |_| {});
+        assert!(result.best_move.is_some());
+        assert!(search.nodes() < 10_000);
+    }
+
+    #[test]
+    fn test_ponder_search_waits_for_ponderhit() {
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let limits = SearchLimits {
+            depth: Some(2),
+            ponder: true,
+            ..Default::default()
+        };
+        let shared = shared(false);
+        shared.ponder.store(true, Ordering::Relaxed);
+
+        thread::scope(|scope| {
+            let handle = scope.spawn(|| {
+                Search::new(board, &shared, limits, SearchOptions::default()).run(&mut |_| {})
+            });
+
+            thread::sleep(Duration::from_millis(50));
+            assert!(!handle.is_finished());
+
+            shared.ponder.store(false, Ordering::Relaxed);
+            let result = handle.join().unwrap();
+            assert!(result.best_move.is_some());
+            assert!(!shared.stop.load(Ordering::Relaxed));
+        });
+    }
+
+    #[test]
+    fn test_search_moves_restrict_root() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(&mut board, &mut legal, MoveType::All);
+        let king_move = *legal.iter().find(|mv| mv.from() == Squares::G1).unwrap();
+
+        let limits = SearchLimits {
+            depth: Some(3),
+            search_moves: vec![king_move],
+            ..Default::default()
+        };
+        let shared = shared(false);
+        let result = Search::new(board, &shared, limits, SearchOptions::default()).run(&mut |_| {});
+
+        // The mate with the rook is not allowed.
+        assert_eq!(result.best_move, Some(king_move));
+        assert_eq!(moves_to_mate(result.summary.score), None);
+    }
+
+    #[test]
+    fn test_node_limit_is_exact_and_reproducible() {
+        let run = || {
+            let mut board = Board::new();
+            board.fen_read(None).unwrap();
+            let limits = SearchLimits {
+                nodes: Some(20_000),
+                ..Default::default()
+            };
+            let shared = shared(false);
+            let mut search = Search::new(board, &shared, limits, SearchOptions::default());
+            let result = search.run(&mut |_| {});
+            (result, search.nodes())
+        };
+
+        let (first, nodes) = run();
+        let (second, _) = run();
+
+        assert_eq!(nodes, 20_000);
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.summary.pv, second.summary.pv);
+        assert_eq!(first.summary.score, second.summary.score);
+        assert_eq!(first.summary.depth, second.summary.depth);
+    }
+
+    #[test]
+    fn test_tablebases_filter_root_moves() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("8/8/8/8/8/2k5/8/KQ6 w - - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(&mut board, &mut legal, MoveType::All);
+
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..Default::default()
+        };
+        let options = SearchOptions {
+            multipv: legal.len(),
+            ..Default::default()
+        };
+        let shared = shared(false).with_tablebases(syzygy::tests::tablebases());
+        let result = Search::new(board.clone(), &shared, limits, options).run(&mut |_| {});
+
+        // The moves that give away the queen are not searched.
+        assert!(result.lines.len() < legal.len());
+        for line in &result.lines {
+            board.make_move(line.pv[0], &mg);
+            let wdl = shared.tablebases.probe_wdl(&mut board, &mg);
+            board.unmake_move();
+            assert_eq!(wdl, Some(Wdl::Loss));
+        }
+        assert!(result.summary.tbhits >= legal.len() as u64);
+    }
+
+    #[test]
+    fn test_tablebases_are_probed_in_search() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("4k3/8/8/3n4/8/8/8/3QK3 w - - 0 1"))
+            .unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..Default::default()
+        };
+        let shared = shared(false).with_tablebases(syzygy::tests::tablebases());
+        let result = Search::new(board, &shared, limits, SearchOptions::default()).run(&mut |_| {});
+
+        assert_eq!(result.best_move.unwrap().to(), Squares::D5);
+        assert!(result.summary.tbhits > 0);
+    }
+
+    #[test]
+    fn test_dtm_tables_give_the_distance_to_mate() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("8/8/8/8/8/2k5/8/KQ6 w - - 0 1"))
+            .unwrap();
+        let shared = shared(false).with_dtm_tables(dtm::tests::dtm_tables());
+        let Some(Dtm::Win(plies)) = shared.dtm.probe(&board) else {
+            panic!("KQvK is not won");
+        };
+
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..Default::default()
+        };
+        let result =
+            Search::new(board.clone(), &shared, limits, SearchOptions::default()).run(&mut |_| {});
+
+        // The search finds the mate as fast as the table, and keeps to the moves that do.
+        assert_eq!(
+            moves_to_mate(result.summary.score),
+            Some((plies as i16 + 1) / 2)
+        );
+        board.make_move(result.best_move.unwrap(), &MoveGenerator::new());
+        assert_eq!(shared.dtm.probe(&board), Some(Dtm::Loss(plies - 1)));
+        assert!(result.summary.tbhits > 0);
+    }
+}
@@ -0,0 +1,367 @@
+use crate::{
+    evaluation,
+    movegen::{defs::Move, picker::MovePicker},
+};
+
+use super::{
+    defs::{
+        CurrentMove, SearchInfo, ASPIRATION_MAX_WINDOW, ASPIRATION_MIN_DEPTH, ASPIRATION_WINDOW,
+        CHECKMATE, CHECKMATE_THRESHOLD, CURRMOVE_MIN_TIME, DRAW, INF, MAX_PLY, TB_DEPTH_BONUS,
+    },
+    transposition::{Bound, TTData},
+    Search,
+};
+
+impl Search<'_> {
+    /// Searches one line of an iteration with an aspiration window: a narrow window around the
+    /// score of the previous iteration, which makes the search faster when the score stays
+    /// inside it. When the search fails high or low, the window is widened on that side and the
+    /// line is searched again. The main thread reports each failed search as a bound. Returns
+    /// false when the iteration was aborted.
+    ///
+    /// * `depth`: The depth to search to.
+    /// * `pv_index`: The index of the line; see [`Search::search_root`].
+    /// * `report`: Called with the outcome of each failed search, and with the move that is
+    ///   being searched.
+    pub(super) fn aspiration(
+        &mut self,
+        depth: u8,
+        pv_index: usize,
+        report: &mut dyn FnMut(&SearchInfo),
+    ) -> bool {
+        let previous = self.root_moves[pv_index].score;
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = (-INF, INF);
+
+        if depth >= ASPIRATION_MIN_DEPTH && previous.abs() < CHECKMATE_THRESHOLD {
+            alpha = previous - delta;
+            beta = previous + delta;
+        }
+
+        loop {
+            let Some(score) = self.search_root(depth, pv_index, alpha, beta, report) else {
+                return false;
+            };
+
+            let bound = if score <= alpha {
+                // Lower beta as well, so the search does not fail high right after failing low.
+                beta = ((alpha as i32 + beta as i32) / 2) as i16;
+                alpha = score.saturating_sub(delta).max(-INF);
+                Bound::Upper
+            } else if score >= beta {
+                beta = score.saturating_add(delta).min(INF);
+                Bound::Lower
+            } else {
+                return true;
+            };
+
+            if self.is_main() {
+                let root = &self.root_moves[pv_index];
+                let mut line = self.summary(pv_index + 1, depth, score, root.pv.clone());
+                line.bound = bound;
+                report(&SearchInfo::Summary(line));
+            }
+
+            delta = delta.saturating_add(delta / 2);
+            if delta > ASPIRATION_MAX_WINDOW {
+                (alpha, beta) = (-INF, INF);
+            }
+        }
+    }
+
+    /// Searches the root moves to the given depth within a window, and moves the best one to
+    /// position `pv_index` of the root move list. The moves before that position are the best
+    /// moves of the earlier lines of a MultiPV search, and are excluded.
+    ///
+    /// Returns the score of the line, which is `alpha` when no move reached it (a fail low) or
+    /// `beta` when a move reached it (a fail high). Returns None when the iteration was aborted.
+    ///
+    /// * `depth`: The depth to search to.
+    /// * `pv_index`: The number of moves at the front of the root move list to exclude.
+    /// * `alpha`: The lower bound of the window.
+    /// * `beta`: The upper bound of the window.
+    /// * `report`: Called with the move that is being searched, once the search has run for
+    ///   [`CURRMOVE_MIN_TIME`].
+    pub(super) fn search_root(
+        &mut self,
+        depth: u8,
+        pv_index: usize,
+        mut alpha: i16,
+        beta: i16,
+        report: &mut dyn FnMut(&SearchInfo),
+    ) -> Option<i16> {
+        let mut best = pv_index;
+        let mut line = Vec::new();
+        self.root_depth = depth;
+
+        for i in pv_index..self.root_moves.len() {
+            let mv = self.root_moves[i].mv;
+            let nodes_before = self.nodes;
+
+            if self.is_main() && self.time.elapsed() >= CURRMOVE_MIN_TIME {
+                let number = i + 1;
+                report(&SearchInfo::CurrentMove(CurrentMove { depth, mv, number }));
+            }
+
+            // Root moves are legal, so they can always be played.
+            self.board.make_move(mv, self.mg);
+            line.clear();
+            let score = self.search_move(i == pv_index, depth as i8 - 1, alpha, beta, 1, &mut line);
+            self.board.unmake_move();
+
+            self.root_moves[i].nodes += self.nodes - nodes_before;
+            if self.aborted {
+                return None;
+            }
+
+            if score > alpha {
+                alpha = score.min(beta);
+                best = i;
+
+                let root = &mut self.root_moves[i];
+                root.score = alpha;
+                root.pv.truncate(1);
+                root.pv.extend_from_slice(&line);
+
+                if score >= beta {
+                    break;
+                }
+            }
+        }
+
+        // The other root moves keep their order, so the previous best move is searched second.
+        self.root_moves[pv_index..=best].rotate_right(1);
+        Some(alpha)
+    }
+
+    /// Searches the position after a move with principal variation search (PVS). The first move
+    /// of a node is expected to be the best, and is searched with the full window. The other moves
+    /// only have to be proven worse, which a search with a null window does quickly; only when
+    /// that fails is the move searched again with the full window.
+    ///
+    /// * `first`: This is the first move searched in the node.
+    /// * `depth`: The remaining depth after the move.
+    /// * `alpha`: The lower bound of the window of the node.
+    /// * `beta`: The upper bound of the window of the node.
+    /// * `ply`: The distance of the position after the move to the root.
+    /// * `line`: Receives the principal variation after the move.
+    fn search_move(
+        &mut self,
+        first: bool,
+        depth: i8,
+        alpha: i16,
+        beta: i16,
+        ply: usize,
+        line: &mut Vec<Move>,
+    ) -> i16 {
+        if !first {
+            let score = -self.alpha_beta(depth, -alpha - 1, -alpha, ply, line);
+            if self.aborted || score <= alpha || score >= beta {
+                return score;
+            }
+            line.clear();
+        }
+
+        -self.alpha_beta(depth, -beta, -alpha, ply, line)
+    }
+
+    /// A fail-hard negamax alpha-beta search.
+    ///
+    /// A node where the side to move is in check is searched one ply deeper, as are recaptures,
+    /// pawn pushes to the seventh rank and singular moves. During the verification search of a
+    /// singular extension the node is searched without the move in `excluded`; its result is then
+    /// not stored in the transposition table. Positions in the endgame tablebases are cut off by
+    /// their tablebase score; see [`Search::probe_tablebases`].
+    ///
+    /// * `depth`: The remaining depth; the quiescence search is started when it reaches 0.
+    /// * `alpha`: The score the side to move is already guaranteed.
+    /// * `beta`: The score the opponent is already guaranteed.
+    /// * `ply`: The distance to the root.
+    /// * `pv`: Receives the principal variation of this node.
+    pub(super) fn alpha_beta(
+        &mut self,
+        mut depth: i8,
+        mut alpha: i16,
+        beta: i16,
+        ply: usize,
+        pv: &mut Vec<Move>,
+    ) -> i16 {
+        if depth <= 0 {
+            return self.quiescence(alpha, beta, 0, ply);
+        }
+
+        self.count_node(ply);
+        if self.aborted {
+            return 0;
+        }
+
+        if self.is_draw() {
+            return DRAW;
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return evaluation::evaluate_position(&self.board);
+        }
+
+        let in_check = self.mg.in_check(&self.board);
+        let can_extend = self.can_extend(ply);
+        if in_check && can_extend {
+            depth += 1;
+        }
+
+        let pv_node = beta as i32 - alpha as i32 > 1;
+        let key = self.board.state.zobrist_key;
+        let excluded = self.excluded[ply];
+        let tt_data = self.tt.probe(key, ply);
+        if let Some(cutoff) = tt_data
+            .filter(|_| excluded.is_none())
+            .and_then(|data| Search::tt_cutoff(data, depth, alpha, beta))
+        {
+            return cutoff;
+        }
+        if excluded.is_none() {
+            if let Some((score, bound)) = self.probe_tablebases(ply) {
+                let cutoff = match bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if cutoff {
+                    let depth = depth.saturating_add(TB_DEPTH_BONUS);
+                    self.tt_store(key, ply, None, score, depth, bound);
+                    return score.clamp(alpha, beta);
+                }
+            }
+        }
+
+        let tt_move = tt_data.and_then(|data| data.best_move);
+        let singular = can_extend && tt_data.is_some_and(|data| self.is_singular(data, depth, ply));
+        if self.aborted {
+            return 0;
+        }
+
+        let mut picker = if in_check {
+            MovePicker::evasions(tt_move)
+        } else {
+            MovePicker::new(tt_move, self.killers[ply])
+        };
+
+        let mut legal_moves = 0;
+        let mut best_move = None;
+        let mut line = Vec::new();
+
+        while let Some(mv) = picker.next(&self.board, self.mg, &self.history) {
+            if Some(mv) == excluded {
+                continue;
+            }
+
+            let extension = if !can_extend {
+                0
+            } else if singular && Some(mv) == tt_move {
+                1
+            } else {
+                self.move_extension(mv, pv_node)
+            };
+
+            if !self.board.make_move(mv, self.mg) {
+                continue;
+            }
+            legal_moves += 1;
+
+            line.clear();
+            let first = legal_moves == 1;
+            let new_depth = depth - 1 + extension;
+            let score = self.search_move(first, new_depth, alpha, beta, ply + 1, &mut line);
+            self.board.unmake_move();
+
+            if self.aborted {
+                return 0;
+            }
+
+            if score >= beta {
+                if !mv.is_capture() && !mv.is_promotion() {
+                    self.store_quiet_cutoff(mv, depth, ply);
+                }
+                if excluded.is_none() {
+                    self.tt_store(key, ply, Some(mv), beta, depth, Bound::Lower);
+                }
+                return beta;
+            }
+
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&line);
+            }
+        }
+
+        // Without the excluded move there may be no moves left, but the position is not lost.
+        if legal_moves == 0 && excluded.is_some() {
+            return alpha;
+        }
+
+        if legal_moves == 0 {
+            return if in_check {
+                -CHECKMATE + ply as i16
+            } else {
+                DRAW
+            };
+        }
+
+        if excluded.is_none() {
+            let bound = if best_move.is_some() {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+            self.tt_store(key, ply, best_move, alpha, depth, bound);
+        }
+
+        alpha
+    }
+
+    /// Returns the score to return from a node when the transposition table entry makes
+    /// searching it unnecessary. An exact score is not used when it lies inside the window, as
+    /// the principal variation would then be cut short.
+    ///
+    /// * `data`: The transposition table entry of the node.
+    /// * `depth`: The remaining depth of the node.
+    /// * `alpha`: The lower bound of the window.
+    /// * `beta`: The upper bound of the window.
+    fn tt_cutoff(data: TTData, depth: i8, alpha: i16, beta: i16) -> Option<i16> {
+        if (data.depth as i8) < depth {
+            return None;
+        }
+
+        let fails_high = data.score >= beta && data.bound != Bound::Upper;
+        let fails_low = data.score <= alpha && data.bound != Bound::Lower;
+
+        if fails_high {
+            Some(beta)
+        } else if fails_low {
+            Some(alpha)
+        } else {
+            None
+        }
+    }
+
+    fn tt_store(
+        &self,
+        key: u64,
+        ply: usize,
+        best_move: Option<Move>,
+        score: i16,
+        depth: i8,
+        bound: Bound,
+    ) {
+        let data = TTData {
+            best_move,
+            score,
+            depth: depth.max(0) as u8,
+            bound,
+        };
+        self.tt.store(key, ply, data);
+    }
+}
@@ -0,0 +1,232 @@
+use std::time::Duration;
+
+use crate::movegen::{defs::Move, movelist::MAX_LEGAL_MOVES};
+
+use super::{threads::DEFAULT_THREADS, transposition::Bound};
+
+/// The maximum number of plies the search can look ahead.
+pub const MAX_PLY: usize = 128;
+
+/// The maximum depth that iterative deepening will search to when no depth limit is given.
+pub const MAX_DEPTH: u8 = 100;
+
+/// A score that is higher than any score the search can return.
+pub const INF: i16 = 32_000;
+
+/// The score of being checkmated on the current ply. The score of a mate found deeper in the
+/// tree is reduced by the number of plies it takes to get there, so shorter mates score higher.
+pub const CHECKMATE: i16 = 31_000;
+
+/// Any score beyond this (in absolute value) is a forced mate.
+pub const CHECKMATE_THRESHOLD: i16 = CHECKMATE - MAX_PLY as i16;
+
+/// The score of a position the endgame tablebases show to be won, reduced by the distance to the
+/// root like a mate score. It is lower than any mate, as the tables do not say how far away the
+/// mate is.
+pub const TB_WIN: i16 = CHECKMATE_THRESHOLD - 1;
+
+/// A tablebase score is stored in the transposition table as if it came from a search this much
+/// deeper than the node, as a search would not improve on it.
+pub const TB_DEPTH_BONUS: i8 = 6;
+
+/// The score of a drawn position.
+pub const DRAW: i16 = 0;
+
+/// The number of nodes between two checks of the stop flag and the clock.
+pub const CHECK_NODES: u64 = 2048;
+
+/// After searching this long, the move that is being searched at the root is reported.
+pub const CURRMOVE_MIN_TIME: Duration = Duration::from_secs(3);
+
+/// Aspiration windows are used from this depth on; shallower iterations search with a full
+/// window, as their scores are not stable yet.
+pub const ASPIRATION_MIN_DEPTH: u8 = 5;
+
+/// The initial distance of the bounds of an aspiration window from the previous score. The
+/// distance grows by half each time the search fails.
+pub const ASPIRATION_WINDOW: i16 = 100;
+
+/// Beyond this distance the window is opened completely.
+pub const ASPIRATION_MAX_WINDOW: i16 = 500;
+
+/// The time left on the clocks, as sent by `go wtime .. btime .. winc .. binc .. movestogo ..`.
+///
+/// * `wtime`: The time White has left.
+/// * `btime`: The time Black has left.
+/// * `winc`: The increment White receives after each move.
+/// * `binc`: The increment Black receives after each move.
+/// * `moves_to_go`: The number of moves until the next time control, if any.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GameTime {
+    pub wtime: Duration,
+    pub btime: Duration,
+    pub winc: Duration,
+    pub binc: Duration,
+    pub moves_to_go: Option<u32>,
+}
+
+/// The limits a search has to stay within. When no limit is set, the search runs until it is
+/// stopped.
+///
+/// * `depth`: The maximum depth to search to.
+/// * `nodes`: The maximum number of nodes to search. The search stops exactly at this number, so
+///   a single-threaded search with a node limit always gives the same result.
+/// * `move_time`: The exact time to spend on this move.
+/// * `game_time`: The clocks; the time to spend is decided by the
+///   [`TimeManager`](super::time::TimeManager).
+/// * `mate`: Only search for a mate in this many moves, using the
+///   [`MateSearch`](super::mate::MateSearch).
+/// * `infinite`: Search until stopped, ignoring all other limits.
+/// * `ponder`: Search on the opponent's time, in the position after the expected reply. The
+///   search runs until `ponderhit` or `stop`; the time limits apply from `ponderhit` on.
+/// * `search_moves`: Only these moves are searched at the root; all moves when empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<Duration>,
+    pub game_time: Option<GameTime>,
+    pub mate: Option<u8>,
+    pub infinite: bool,
+    pub ponder: bool,
+    pub search_moves: Vec<Move>,
+}
+
+/// The outcome of one completed iteration of iterative deepening, for one of the lines of a
+/// MultiPV search.
+///
+/// * `multipv`: The rank of the line, starting at 1 for the best line.
+/// * `depth`: The depth that was searched.
+/// * `seldepth`: The highest ply that was reached, including the quiescence search.
+/// * `score`: The score of the best move, from the side to move's point of view; normalised if
+///   [`SearchOptions::normalize_score`] is set.
+/// * `bound`: Whether the score is exact, or only a bound because the search of an aspiration
+///   window failed high (a lower bound) or low (an upper bound).
+/// * `wdl`: The expected outcome, in permille, if [`SearchOptions::show_wdl`] is set.
+/// * `nodes`: The total number of nodes searched so far.
+/// * `hashfull`: How full the transposition table is, in permille.
+/// * `tbhits`: The number of successful endgame tablebase probes.
+/// * `time`: The time spent searching so far.
+/// * `pv`: The principal variation, starting with the best move.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchSummary {
+    pub multipv: usize,
+    pub depth: u8,
+    pub seldepth: usize,
+    pub score: i16,
+    pub bound: Bound,
+    pub wdl: Option<Wdl>,
+    pub nodes: u64,
+    pub hashfull: u16,
+    pub tbhits: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+}
+
+/// The chances of winning, drawing and losing, from the side to move's point of view, in
+/// permille. The three add up to 1000.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Wdl {
+    pub win: u16,
+    pub draw: u16,
+    pub loss: u16,
+}
+
+/// The move the root search is working on.
+///
+/// * `depth`: The depth of the iteration.
+/// * `mv`: The move that is being searched.
+/// * `number`: The position of the move in the list of root moves, starting at 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CurrentMove {
+    pub depth: u8,
+    pub mv: Move,
+    pub number: usize,
+}
+
+/// The progress of a running search.
+///
+/// * `Summary`: An iteration completed for one of the lines, or the search of an aspiration
+///   window failed.
+/// * `CurrentMove`: The root search started on another move. This is only reported once the
+///   search has run for [`CURRMOVE_MIN_TIME`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchInfo {
+    Summary(SearchSummary),
+    CurrentMove(CurrentMove),
+}
+
+impl SearchSummary {
+    /// The number of nodes searched per second.
+    pub fn nps(&self) -> u64 {
+        let millis = self.time.as_millis().max(1) as u64;
+        self.nodes * 1000 / millis
+    }
+}
+
+/// The final outcome of a search.
+///
+/// * `best_move`: The move to play; None if the position has no legal moves.
+/// * `ponder_move`: The expected reply to the best move, if known.
+/// * `summary`: The best line of the last completed iteration.
+/// * `lines`: All lines of the last completed iteration, best first; more than one in MultiPV
+///   mode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub ponder_move: Option<Move>,
+    pub summary: SearchSummary,
+    pub lines: Vec<SearchSummary>,
+}
+
+/// Converts a mate score into the number of moves to mate: positive when the side to move mates,
+/// negative when it is mated. Returns None when the score is not a mate score.
+///
+/// * `score`: The score to convert.
+pub fn moves_to_mate(score: i16) -> Option<i16> {
+    if score >= CHECKMATE_THRESHOLD {
+        Some((CHECKMATE - score + 1) / 2)
+    } else if score <= -CHECKMATE_THRESHOLD {
+        Some(-(CHECKMATE + score) / 2)
+    } else {
+        None
+    }
+}
+
+/// The default value of the move overhead, in milliseconds.
+pub const DEFAULT_MOVE_OVERHEAD: u64 = 30;
+
+/// The default value and limits of the `MultiPV` option.
+pub const DEFAULT_MULTIPV: usize = 1;
+pub const MULTIPV_MIN: usize = 1;
+pub const MULTIPV_MAX: usize = MAX_LEGAL_MOVES;
+
+/// Settings that apply to every search, as opposed to the [`SearchLimits`] of a single search.
+///
+/// * `move_overhead`: Time reserved per move for communication delays with the GUI.
+/// * `threads`: The number of threads that search at the same time.
+/// * `multipv`: The number of best lines to find, instead of only the best move.
+/// * `show_wdl`: Report the expected outcome of each line, from
+///   [`score_to_wdl`](crate::evaluation::wdl::score_to_wdl).
+/// * `normalize_score`: Report scores scaled so that 100 centipawns is a 50% chance of winning;
+///   see [`normalize_score`](crate::evaluation::wdl::normalize_score).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub move_overhead: Duration,
+    pub threads: usize,
+    pub multipv: usize,
+    pub show_wdl: bool,
+    pub normalize_score: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD),
+            threads: DEFAULT_THREADS,
+            multipv: DEFAULT_MULTIPV,
+            show_wdl: false,
+            normalize_score: false,
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{
+    board::defs::{Pieces, Ranks, Sides},
+    movegen::defs::Move,
+};
+
+use super::{
+    defs::CHECKMATE_THRESHOLD,
+    transposition::{Bound, TTData},
+    Search,
+};
+
+/// Singular extensions are only tried from this depth on, as the verification search is costly.
+const SINGULAR_MIN_DEPTH: i8 = 6;
+
+/// The transposition table entry has to come from a search at most this much shallower.
+const SINGULAR_TT_DEPTH_MARGIN: i8 = 3;
+
+/// How much worse, per ply of depth, all other moves have to be for the transposition table move
+/// to be singular.
+const SINGULAR_MARGIN: i16 = 2;
+
+impl Search<'_> {
+    /// Determines if a node may be extended. Extensions stop at twice the depth of the iteration,
+    /// so a long series of checks can not make the search explode.
+    ///
+    /// * `ply`: The distance to the root.
+    pub(super) fn can_extend(&self, ply: usize) -> bool {
+        ply < 2 * self.root_depth as usize
+    }
+
+    /// The extension of a move, which is 1 for a pawn push to the seventh rank, and for a
+    /// recapture on the square the opponent just captured on; 0 otherwise. Recaptures are only
+    /// extended in PV nodes, as extending every exchange in the tree costs far too much. Must be
+    /// called before the move is played.
+    ///
+    /// * `mv`: The move to extend.
+    /// * `pv_node`: The node is searched with an open window.
+    pub(super) fn move_extension(&self, mv: Move, pv_node: bool) -> i8 {
+        let recapture = pv_node
+            && mv.is_capture()
+            && self.board.history.last().is_some_and(|previous| {
+                previous.next_move.is_capture() && previous.next_move.to() == mv.to()
+            });
+
+        let seventh_rank = if self.board.current_side() == Sides::WHITE {
+            Ranks::R7
+        } else {
+            Ranks::R2
+        };
+        let pawn_push = mv.piece() == Pieces::PAWN && mv.to() / 8 == seventh_rank;
+
+        i8::from(recapture || pawn_push)
+    }
+
+    /// Determines if the move of the transposition table is singular: much better than all other
+    /// moves, so it deserves to be searched deeper. This is verified with a reduced search of the
+    /// node without that move, against a bound just below the stored score. If no other move
+    /// reaches it, the move is singular.
+    ///
+    /// * `tt_data`: The transposition table entry of the node.
+    /// * `depth`: The remaining depth of the node.
+    /// * `ply`: The distance to the root.
+    pub(super) fn is_singular(&mut self, tt_data: TTData, depth: i8, ply: usize) -> bool {
+        let Some(tt_move) = tt_data.best_move else {
+            return false;
+        };
+
+        if depth < SINGULAR_MIN_DEPTH
+            || self.excluded[ply].is_some()
+            || tt_data.bound == Bound::Upper
+            || (tt_data.depth as i8) < depth - SINGULAR_TT_DEPTH_MARGIN
+            || tt_data.score.abs() >= CHECKMATE_THRESHOLD
+        {
+            return false;
+        }
+
+        let singular_beta = tt_data.score - SINGULAR_MARGIN * depth as i16;
+        let mut line = Vec::new();
+
+        self.excluded[ply] = Some(tt_move);
+        let score = self.alpha_beta(
+            (depth - 1) / 2,
+            singular_beta - 1,
+            singular_beta,
+            ply,
+            &mut line,
+        );
+        self.excluded[ply] = None;
+
+        !self.aborted && score < singular_beta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    use crate::{
+        board::{defs::Squares, Board},
+        movegen::{
+            defs::{Move, MoveType},
+            movelist::MoveList,
+            MoveGenerator,
+        },
+        search::{
+            defs::{SearchLimits, SearchOptions, INF},
+            transposition::{Bound, TTData, TranspositionTable},
+            Search, SearchShared,
+        },
+    };
+
+    fn shared() -> SearchShared {
+        SearchShared::new(
+            Arc::new(MoveGenerator::new()),
+            Arc::new(TranspositionTable::new(1)),
+            Arc::new(AtomicBool::new(false)),
+        )
+    }
+
+    fn new_search<'a>(shared: &'a SearchShared, fen: &str) -> Search<'a> {
+        let mut board = Board::new();
+        board.fen_read(Some(fen)).unwrap();
+        Search::new(
+            board,
+            shared,
+            SearchLimits::default(),
+            SearchOptions::default(),
+        )
+    }
+
+    /// The score of a move, searched as deep as the verification search of a singular extension
+    /// at the given depth.
+    fn move_score(search: &mut Search, mv: Move, depth: i8) -> i16 {
+        assert!(search.board.make_move(mv, search.mg));
+        let score = -search.alpha_beta((depth - 1) / 2, -INF, INF, 1, &mut Vec::new());
+        search.board.unmake_move();
+        score
+    }
+
+    fn find(board: &mut Board, mg: &MoveGenerator, from: usize, to: usize) -> Move {
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(board, &mut legal, MoveType::All);
+        *legal
+            .iter()
+            .find(|mv| mv.from() == from && mv.to() == to)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_recapture_is_extended() {
+        let shared = shared();
+        let mut board = Board::new();
+        board
+            .fen_read(Some("4k3/8/2p5/3p4/5N2/8/8/4K3 w - - 0 1"))
+            .unwrap();
+        let capture = find(&mut board, &shared.mg, Squares::F4, Squares::D5);
+        board.make_move(capture, &shared.mg);
+
+        let recapture = find(&mut board, &shared.mg, Squares::C6, Squares::D5);
+        let quiet = find(&mut board, &shared.mg, Squares::E8, Squares::D7);
+        let search = Search::new(
+            board,
+            &shared,
+            SearchLimits::default(),
+            SearchOptions::default(),
+        );
+
+        assert_eq!(search.move_extension(recapture, true), 1);
+        assert_eq!(search.move_extension(recapture, false), 0);
+        assert_eq!(search.move_extension(quiet, true), 0);
+    }
+
+    #[test]
+    fn test_pawn_push_to_seventh_rank_is_extended() {
+        let shared = shared();
+        let mut board = Board::new();
+        board
+            .fen_read(Some("4k3/8/3P4/8/8/8/4p3/1K6 w - - 0 1"))
+            .unwrap();
+        let push = find(&mut board, &shared.mg, Squares::D6, Squares::D7);
+        let king = find(&mut board, &shared.mg, Squares::B1, Squares::C2);
+        let search = Search::new(
+            board,
+            &shared,
+            SearchLimits::default(),
+            SearchOptions::default(),
+        );
+
+        assert_eq!(search.move_extension(push, false), 1);
+        assert_eq!(search.move_extension(king, true), 0);
+    }
+
+    #[test]
+    fn test_only_good_move_is_singular() {
+        let depth = 8;
+        let tt_data = |mv, score| TTData {
+            best_move: Some(mv),
+            score,
+            depth: depth as u8,
+            bound: Bound::Exact,
+        };
+
+        // Only the rook can take the queen; every other move loses the rook instead.
+        let shared = shared();
+        let mut search = new_search(&shared, "6k1/5ppp/8/8/3q4/8/5PPP/3R2K1 w - - 0 1");
+        let rook = find(&mut search.board, search.mg, Squares::D1, Squares::D4);
+        let score = move_score(&mut search, rook, depth);
+        assert!(search.is_singular(tt_data(rook, score), depth, 0));
+        assert!(search.excluded[0].is_none());
+
+        // A node that is too shallow is not verified.
+        assert!(!search.is_singular(tt_data(rook, score), 5, 0));
+
+        // The rook and the knight can both take the queen, so neither move is singular.
+        let mut search = new_search(&shared, "6k1/5ppp/8/8/3q4/5N2/5PPP/3R2K1 w - - 0 1");
+        let rook = find(&mut search.board, search.mg, Squares::D1, Squares::D4);
+        let knight = find(&mut search.board, search.mg, Squares::F3, Squares::D4);
+        let rook_score = move_score(&mut search, rook, depth);
+        let knight_score = move_score(&mut search, knight, depth);
+        let (worst, score) = if rook_score < knight_score {
+            (rook, rook_score)
+        } else {
+            (knight, knight_score)
+        };
+        assert!(!search.is_singular(tt_data(worst, score), depth, 0));
+    }
+
+    #[test]
+    fn test_check_is_extended_up_to_twice_the_root_depth() {
+        // Black is in check and has a single evasion, Kg7.
+        let fen = "3R3k/7p/8/8/8/8/5PPP/6K1 b - - 0 1";
+
+        let pv_length = |root_depth: u8, ply: usize| {
+            let shared = shared();
+            let mut search = new_search(&shared, fen);
+            search.root_depth = root_depth;
+            assert_eq!(search.can_extend(ply), ply < 2 * root_depth as usize);
+
+            let mut pv = Vec::new();
+            search.alpha_beta(1, -INF, INF, ply, &mut pv);
+            assert_eq!(pv[0].to(), Squares::G7);
+            pv.len()
+        };
+
+        // Extended, the reply of White is searched too; at twice the root depth it is not.
+        assert_eq!(pv_length(1, 0), 2);
+        assert_eq!(pv_length(3, 5), 2);
+        assert_eq!(pv_length(3, 6), 1);
+        assert_eq!(pv_length(0, 0), 1);
+    }
+}
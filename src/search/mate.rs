@@ -0,0 +1,558 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::Board,
+    evaluation::wdl,
+    movegen::{
+        defs::{Move, MoveType},
+        movelist::MoveList,
+        MoveGenerator,
+    },
+};
+
+use super::{
+    defs::{
+        SearchInfo, SearchLimits, SearchOptions, SearchResult, SearchSummary, CHECKMATE,
+        CHECK_NODES, DRAW, MAX_PLY,
+    },
+    time::TimeManager,
+    transposition::Bound,
+    SearchShared,
+};
+
+/// The longest mate the mate search looks for, in moves.
+pub const MAX_MATE_MOVES: u8 = (MAX_PLY / 2 - 1) as u8;
+
+/// The outcome of a mate search.
+///
+/// * `Found`: A forced mate, with the mating line: the moves of the attacker and the longest
+///   defence.
+/// * `Refuted`: There is no forced mate within the given number of moves.
+/// * `Stopped`: The search was stopped before it could decide.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MateOutcome {
+    Found(Vec<Move>),
+    Refuted,
+    Stopped,
+}
+
+/// Proves or refutes a forced mate in a given number of moves, for solving composed problems.
+///
+/// Unlike the normal search this search is exact: it has no evaluation, no null-move and no
+/// pruning, so a mate it does not find does not exist. Every move of the attacker is tried, checks
+/// first and then captures, as those are the most likely to mate; every reply of the defender
+/// has to lose. On the last move only checks are tried. Draw rules are ignored, as usual for
+/// problems.
+///
+/// * `board`: The position to solve, with the attacker to move.
+/// * `mg`: The move generator.
+/// * `stop`: When set, the search stops as soon as possible.
+/// * `ponder`: Set while the search runs on the opponent's time; cleared on `ponderhit`.
+/// * `time`: Decides when the search has to stop; None for a search without time limits.
+/// * `max_nodes`: The search stops after visiting this many positions.
+/// * `start`: The moment the search started.
+/// * `nodes`: The number of positions visited.
+/// * `aborted`: The search was stopped; its results can not be used.
+/// * `proven`: Positions with the attacker to move that are mate in at most this many moves.
+/// * `refuted`: Positions with the attacker to move that are not mate in this many moves.
+pub struct MateSearch<'a> {
+    board: Board,
+    mg: &'a MoveGenerator,
+    stop: Option<&'a AtomicBool>,
+    ponder: Option<&'a AtomicBool>,
+    time: Option<TimeManager>,
+    max_nodes: Option<u64>,
+    start: Instant,
+    nodes: u64,
+    aborted: bool,
+    proven: HashMap<u64, u8>,
+    refuted: HashMap<u64, u8>,
+}
+
+impl<'a> MateSearch<'a> {
+    /// Create a new [`MateSearch`] of the given position.
+    ///
+    /// * `board`: The position to solve, with the attacker to move.
+    /// * `mg`: The move generator.
+    pub fn new(board: Board, mg: &'a MoveGenerator) -> Self {
+        Self {
+            board,
+            mg,
+            stop: None,
+            ponder: None,
+            time: None,
+            max_nodes: None,
+            start: Instant::now(),
+            nodes: 0,
+            aborted: false,
+            proven: HashMap::new(),
+            refuted: HashMap::new(),
+        }
+    }
+
+    /// Lets the search be stopped through a flag.
+    ///
+    /// * `stop`: When set, the search stops as soon as possible.
+    pub fn with_stop(mut self, stop: &'a AtomicBool) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Lets the search be stopped by the time and node limits of `go`, like the normal search.
+    ///
+    /// * `time`: Decides when the search has to stop; its clock starts when it is created.
+    /// * `max_nodes`: The maximum number of positions to visit.
+    /// * `ponder`: Cleared on `ponderhit`, after which the time limits apply.
+    pub fn with_limits(
+        mut self,
+        time: TimeManager,
+        max_nodes: Option<u64>,
+        ponder: &'a AtomicBool,
+    ) -> Self {
+        self.time = Some(time);
+        self.max_nodes = max_nodes;
+        self.ponder = Some(ponder);
+        self
+    }
+
+    /// The number of positions visited so far.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// The time that passed since the search started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Searches for a forced mate in at most the given number of moves. The shortest mate is
+    /// found first; its line follows the defence that delays the mate the longest.
+    ///
+    /// * `moves`: The number of moves of the attacker, at most [`MAX_MATE_MOVES`].
+    pub fn solve(&mut self, moves: u8) -> MateOutcome {
+        self.start = Instant::now();
+
+        for n in 1..=moves.min(MAX_MATE_MOVES) {
+            let mate = self.attack(n);
+            if self.aborted {
+                return MateOutcome::Stopped;
+            }
+
+            if mate {
+                let line = self.line(n);
+                return if self.aborted {
+                    MateOutcome::Stopped
+                } else {
+                    MateOutcome::Found(line)
+                };
+            }
+        }
+
+        MateOutcome::Refuted
+    }
+
+    /// Determines if the attacker, who is to move, mates in at most `n` moves.
+    fn attack(&mut self, n: u8) -> bool {
+        self.count_node();
+        if self.aborted {
+            return false;
+        }
+
+        let key = self.board.state.zobrist_key;
+        if self.proven.get(&key).is_some_and(|&proven| proven <= n) {
+            return true;
+        }
+        if self.refuted.get(&key).is_some_and(|&refuted| refuted >= n) {
+            return false;
+        }
+
+        for mv in self.attacking_moves(n) {
+            if !self.board.make_move(mv, self.mg) {
+                continue;
+            }
+            let mate = self.defend(n);
+            self.board.unmake_move();
+
+            if self.aborted {
+                return false;
+            }
+            if mate {
+                self.proven.insert(key, n);
+                return true;
+            }
+        }
+
+        self.refuted.insert(key, n);
+        false
+    }
+
+    /// Determines if the defender, who is to move, is mated now or within the remaining `n - 1`
+    /// moves of the attacker, whatever it plays.
+    fn defend(&mut self, n: u8) -> bool {
+        self.count_node();
+        if self.aborted {
+            return false;
+        }
+
+        let mut moves = MoveList::new();
+        self.mg.generate_moves(&self.board, &mut moves);
+
+        let mut legal_moves = 0;
+        for mv in moves.iter() {
+            if !self.board.make_move(*mv, self.mg) {
+                continue;
+            }
+            legal_moves += 1;
+            let mated = n > 1 && self.attack(n - 1);
+            self.board.unmake_move();
+
+            if !mated {
+                return false;
+            }
+        }
+
+        // Without legal moves the defender is either mated or stalemated.
+        legal_moves > 0 || self.mg.in_check(&self.board)
+    }
+
+    /// The pseudo-legal moves of the attacker, checks first and then captures. With one move
+    /// left, only checks can mate.
+    fn attacking_moves(&self, n: u8) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.mg.generate_moves(&self.board, &mut moves);
+
+        let mut checks = Vec::new();
+        let mut captures = Vec::new();
+        let mut others = Vec::new();
+        for mv in moves.iter() {
+            if self.mg.gives_check(&self.board, *mv) {
+                checks.push(*mv);
+            } else if n > 1 && mv.is_capture() {
+                captures.push(*mv);
+            } else if n > 1 {
+                others.push(*mv);
+            }
+        }
+
+        checks.extend(captures);
+        checks.extend(others);
+        checks
+    }
+
+    /// Builds the mating line of a position that is known to be mate in `n`: the first mating
+    /// move of the attacker, followed by the reply that delays the mate the longest.
+    fn line(&mut self, n: u8) -> Vec<Move> {
+        let mut line = Vec::new();
+
+        for mv in self.attacking_moves(n) {
+            if !self.board.make_move(mv, self.mg) {
+                continue;
+            }
+            if !self.defend(n) {
+                self.board.unmake_move();
+                continue;
+            }
+
+            line.push(mv);
+            if let Some((reply, remaining)) = self.longest_defence(n) {
+                line.push(reply);
+                self.board.make_move(reply, self.mg);
+                line.extend(self.line(remaining));
+                self.board.unmake_move();
+            }
+            self.board.unmake_move();
+            break;
+        }
+
+        line
+    }
+
+    /// Finds the reply of the defender after which the mate takes the most moves, in a position
+    /// that is known to be lost within the remaining `n - 1` moves. Returns None when the
+    /// defender is mated.
+    fn longest_defence(&mut self, n: u8) -> Option<(Move, u8)> {
+        let mut moves = MoveList::new();
+        self.mg.generate_moves(&self.board, &mut moves);
+
+        let mut longest: Option<(Move, u8)> = None;
+        for mv in moves.iter() {
+            if !self.board.make_move(*mv, self.mg) {
+                continue;
+            }
+            let remaining = (1..n).find(|&k| self.attack(k)).unwrap_or(n - 1);
+            self.board.unmake_move();
+
+            if longest.is_none_or(|(_, longest)| remaining > longest) {
+                longest = Some((*mv, remaining));
+            }
+        }
+
+        longest
+    }
+
+    /// Counts a node and, every [`CHECK_NODES`] nodes, checks if the search has to stop. A node
+    /// limit is checked at every node, so it is never exceeded.
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        if self.max_nodes.is_some_and(|max| self.nodes >= max) {
+            self.aborted = true;
+        }
+
+        if self.nodes.is_multiple_of(CHECK_NODES) {
+            if let (Some(time), Some(ponder)) = (&mut self.time, self.ponder) {
+                if time.is_pondering() && !ponder.load(Ordering::Relaxed) {
+                    time.ponderhit();
+                }
+            }
+            let out_of_time = self.time.as_ref().is_some_and(TimeManager::out_of_time);
+            if out_of_time || self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed)) {
+                self.aborted = true;
+            }
+        }
+    }
+}
+
+/// Runs a mate search for `go mate`, and converts its outcome into a [`SearchResult`]. When a
+/// mate is found, its line is reported once. When there is none, or the search was stopped, the
+/// first legal move is played with a draw score, as a best move has to be sent; only a position
+/// without legal moves has no best move.
+///
+/// * `board`: The position to solve.
+/// * `shared`: The data shared with the engine; only the move generator and the stop and ponder
+///   flags are used.
+/// * `moves`: The number of moves to find a mate in.
+/// * `limits`: The other limits of `go`: the time and the number of nodes.
+/// * `options`: The settings of the search.
+/// * `report`: Called with the mating line, if one is found.
+pub fn run_mate_search(
+    board: &Board,
+    shared: &SearchShared,
+    moves: u8,
+    limits: &SearchLimits,
+    options: SearchOptions,
+    report: &mut dyn FnMut(&SearchInfo),
+) -> SearchResult {
+    let time = TimeManager::new(limits, board.current_side(), options.move_overhead);
+    let mut search = MateSearch::new(board.clone(), &shared.mg)
+        .with_stop(&shared.stop)
+        .with_limits(time, limits.nodes, &shared.ponder);
+    let outcome = search.solve(moves);
+
+    let (pv, score, depth) = match outcome {
+        MateOutcome::Found(line) => {
+            let score = CHECKMATE - line.len() as i16;
+            let depth = line.len();
+            (line, score, depth)
+        }
+        MateOutcome::Refuted | MateOutcome::Stopped => {
+            let mut board = board.clone();
+            let mut legal = MoveList::new();
+            shared
+                .mg
+                .generate_legal_moves(&mut board, &mut legal, MoveType::All);
+            (legal.iter().take(1).copied().collect(), DRAW, 0)
+        }
+    };
+
+    let wdl = options
+        .show_wdl
+        .then(|| wdl::score_to_wdl(score, wdl::material_count(board)));
+    let summary = SearchSummary {
+        multipv: 1,
+        depth: depth as u8,
+        seldepth: depth,
+        score,
+        bound: Bound::Exact,
+        wdl,
+        nodes: search.nodes(),
+        hashfull: 0,
+        tbhits: 0,
+        time: search.elapsed(),
+        pv,
+    };
+    if depth > 0 {
+        report(&SearchInfo::Summary(summary.clone()));
+    }
+
+    SearchResult {
+        best_move: summary.pv.first().copied(),
+        ponder_move: summary.pv.get(1).copied(),
+        summary: summary.clone(),
+        lines: vec![summary],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::AtomicBool, Arc},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        board::{defs::Squares, Board},
+        movegen::{defs::MoveType, movelist::MoveList, MoveGenerator},
+        search::{
+            defs::{SearchLimits, SearchOptions, SearchResult, CHECK_NODES, DRAW},
+            transposition::TranspositionTable,
+            SearchShared,
+        },
+    };
+
+    use super::{run_mate_search, MateOutcome, MateSearch};
+
+    fn solve(fen: &str, moves: u8) -> MateOutcome {
+        let mut board = Board::new();
+        board.fen_read(Some(fen)).unwrap();
+        let mg = MoveGenerator::new();
+        MateSearch::new(board, &mg).solve(moves)
+    }
+
+    #[test]
+    fn test_mate_in_one() {
+        let MateOutcome::Found(line) = solve("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1) else {
+            panic!("mate not found");
+        };
+        assert_eq!(line.len(), 1);
+        assert_eq!(line[0].to(), Squares::A8);
+    }
+
+    #[test]
+    fn test_mate_in_two_has_full_line() {
+        let MateOutcome::Found(line) = solve("7k/8/5K2/8/8/8/8/R7 w - - 0 1", 3) else {
+            panic!("mate not found");
+        };
+        assert_eq!(line.len(), 3);
+    }
+
+    #[test]
+    fn test_mate_is_refuted() {
+        assert_eq!(
+            solve("7k/8/5K2/8/8/8/8/R7 w - - 0 1", 1),
+            MateOutcome::Refuted
+        );
+        assert_eq!(
+            solve("8/8/8/8/8/2k5/8/K2R4 w - - 0 1", 3),
+            MateOutcome::Refuted
+        );
+    }
+
+    #[test]
+    fn test_line_ends_in_mate_and_is_shortest() {
+        let fen = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        let mut board = Board::new();
+        board.fen_read(Some(fen)).unwrap();
+        let mg = MoveGenerator::new();
+
+        let MateOutcome::Found(line) = MateSearch::new(board.clone(), &mg).solve(4) else {
+            panic!("mate not found");
+        };
+        assert_eq!(line.len(), 3);
+
+        for mv in &line {
+            assert!(board.make_move(*mv, &mg));
+        }
+        let mut replies = MoveList::new();
+        mg.generate_legal_moves(&mut board, &mut replies, MoveType::All);
+        assert!(mg.in_check(&board));
+        assert_eq!(replies.len(), 0);
+
+        assert_eq!(solve(fen, 1), MateOutcome::Refuted);
+    }
+
+    #[test]
+    fn test_mate_search_is_stopped_by_flag() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("8/8/8/8/8/2k5/8/K2R4 w - - 0 1"))
+            .unwrap();
+        let mg = MoveGenerator::new();
+        let stop = AtomicBool::new(true);
+
+        let mut search = MateSearch::new(board, &mg).with_stop(&stop);
+        assert_eq!(search.solve(10), MateOutcome::Stopped);
+        assert!(search.nodes() <= CHECK_NODES);
+    }
+
+    fn go_mate(fen: &str, moves: u8, stop: bool, limits: SearchLimits) -> (Board, SearchResult) {
+        let options = SearchOptions {
+            show_wdl: true,
+            ..Default::default()
+        };
+        let mut board = Board::new();
+        board.fen_read(Some(fen)).unwrap();
+        let shared = SearchShared::new(
+            Arc::new(MoveGenerator::new()),
+            Arc::new(TranspositionTable::new(1)),
+            Arc::new(AtomicBool::new(stop)),
+        );
+        let mut reports = 0;
+        let result = run_mate_search(&board, &shared, moves, &limits, options, &mut |_| {
+            reports += 1
+        });
+        assert_eq!(reports, result.summary.depth.min(1));
+        (board, result)
+    }
+
+    #[test]
+    fn test_go_mate_always_plays_a_legal_move() {
+        let startpos = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mg = MoveGenerator::new();
+
+        // Refuted, and stopped before the search could decide.
+        for (fen, moves, stop) in [
+            (startpos, 2, false),
+            ("8/8/8/8/8/2k5/8/K2R4 w - - 0 1", 10, true),
+        ] {
+            let (mut board, result) = go_mate(fen, moves, stop, SearchLimits::default());
+            let best = result.best_move.expect("no best move");
+            assert_eq!(result.summary.score, DRAW);
+            assert_eq!(result.summary.pv, vec![best]);
+            assert!(board.make_move(best, &mg));
+        }
+
+        let (_, result) = go_mate(
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            2,
+            false,
+            SearchLimits::default(),
+        );
+        assert_eq!(result.best_move.map(|mv| mv.to()), Some(Squares::A8));
+        assert_eq!(result.summary.wdl.map(|wdl| wdl.win), Some(1000));
+
+        // Without legal moves there is nothing to play.
+        let (_, result) = go_mate(
+            "R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1",
+            2,
+            false,
+            SearchLimits::default(),
+        );
+        assert_eq!(result.best_move, None);
+    }
+
+    #[test]
+    fn test_go_mate_keeps_to_the_limits() {
+        let startpos = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+        let limits = SearchLimits {
+            nodes: Some(1000),
+            ..Default::default()
+        };
+        let (_, result) = go_mate(startpos, 5, false, limits);
+        assert!(result.best_move.is_some());
+        assert_eq!(result.summary.nodes, 1000);
+
+        let start = Instant::now();
+        let limits = SearchLimits {
+            move_time: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let (_, result) = go_mate(startpos, 30, false, limits);
+        assert!(result.best_move.is_some());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
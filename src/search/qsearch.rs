@@ -0,0 +1,71 @@
+use crate::{evaluation, movegen::picker::MovePicker};
+
+use super::{
+    defs::{CHECKMATE, MAX_PLY},
+    Search,
+};
+
+impl Search<'_> {
+    /// Searches captures until the position is quiet, so the evaluation is not taken in the
+    /// middle of an exchange. The side to move may "stand pat" and accept the static evaluation,
+    /// unless it is in check, in which case all evasions are searched. On the first ply the quiet
+    /// moves that give check are searched as well, so that short mating attacks are seen.
+    ///
+    /// * `alpha`: The score the side to move is already guaranteed.
+    /// * `beta`: The score the opponent is already guaranteed.
+    /// * `depth`: 0 on the first ply of the quiescence search, and negative on the plies after it.
+    /// * `ply`: The distance to the root.
+    pub(super) fn quiescence(&mut self, mut alpha: i16, beta: i16, depth: i8, ply: usize) -> i16 {
+        self.count_node(ply);
+        if self.aborted {
+            return 0;
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return evaluation::evaluate_position(&self.board);
+        }
+
+        let in_check = self.mg.in_check(&self.board);
+        let mut picker = if in_check {
+            MovePicker::evasions(None)
+        } else {
+            let stand_pat = evaluation::evaluate_position(&self.board);
+            if stand_pat >= beta {
+                return beta;
+            }
+            alpha = alpha.max(stand_pat);
+            if depth == 0 {
+                MovePicker::captures_and_checks(None)
+            } else {
+                MovePicker::captures(None)
+            }
+        };
+
+        let mut legal_moves = 0;
+
+        while let Some(mv) = picker.next(&self.board, self.mg, &self.history) {
+            if !self.board.make_move(mv, self.mg) {
+                continue;
+            }
+            legal_moves += 1;
+
+            let score = -self.quiescence(-beta, -alpha, depth - 1, ply + 1);
+            self.board.unmake_move();
+
+            if self.aborted {
+                return 0;
+            }
+
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+
+        if in_check && legal_moves == 0 {
+            return -CHECKMATE + ply as i16;
+        }
+
+        alpha
+    }
+}
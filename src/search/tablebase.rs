@@ -0,0 +1,118 @@
+use std::sync::atomic::Ordering;
+
+use crate::{board::defs::Sides, dtm::Dtm, syzygy::Wdl};
+
+use super::{
+    defs::{CHECKMATE, DRAW, MAX_PLY, TB_WIN},
+    transposition::Bound,
+    Search,
+};
+
+impl Search<'_> {
+    /// Keeps only the best root moves when the root position is in the endgame tablebases. The
+    /// DTM tables are tried first, and keep the moves that mate the fastest or put off being
+    /// mated the longest. With the DTZ tables the moves that win within the fifty-move rule are
+    /// kept, or the ones that put off a loss the longest; the search then does not probe the
+    /// tables itself, as it would only find that every line wins. With only the WDL tables, the
+    /// moves that keep the best outcome are kept, and the search probes the tables to find the
+    /// way to win.
+    pub(super) fn filter_root_moves(&mut self) {
+        let tablebases = &self.shared.tablebases;
+        self.tb_pieces = tablebases.max_pieces();
+        let moves: Vec<_> = self.root_moves.iter().map(|root| root.mv).collect();
+
+        if let Some(ranks) = self
+            .shared
+            .dtm
+            .rank_root_moves(&mut self.board, self.mg, &moves)
+        {
+            self.keep_best_root_moves(ranks);
+            return;
+        }
+        if !tablebases.can_probe(&self.board) {
+            return;
+        }
+
+        let (ranks, dtz) = match tablebases.rank_root_moves(&mut self.board, self.mg, &moves) {
+            Some(ranks) => (ranks, true),
+            None => match tablebases.rank_root_moves_wdl(&mut self.board, self.mg, &moves) {
+                Some(ranks) => (ranks, false),
+                None => return,
+            },
+        };
+
+        let best = self.keep_best_root_moves(ranks);
+        if dtz || best <= 0 {
+            self.tb_pieces = 0;
+        }
+    }
+
+    /// Keeps the root moves with the best rank, and returns that rank. Each rank took a probe.
+    ///
+    /// * `ranks`: The rank of each root move.
+    fn keep_best_root_moves(&mut self, ranks: Vec<i32>) -> i32 {
+        if self.is_main() {
+            self.shared
+                .tbhits
+                .fetch_add(ranks.len() as u64, Ordering::Relaxed);
+        }
+
+        let best = ranks.iter().copied().max().unwrap_or(0);
+        let mut ranks = ranks.into_iter();
+        self.root_moves.retain(|_| ranks.next() == Some(best));
+        best
+    }
+
+    /// Probes the WDL tables for the position on the board. Returns its score and what the score
+    /// is: a win scores at least [`TB_WIN`] and a loss at most `-TB_WIN`, as the tables do not
+    /// say how far away the mate is; draws are exact. Cursed wins and blessed losses, which are
+    /// draws by the fifty-move rule, score just above and below a draw. Only positions right
+    /// after a capture or pawn move are probed, as the tables ignore the moves already played
+    /// towards the fifty-move rule.
+    ///
+    /// Positions in the DTM tables are probed first, whatever moves were played before: they
+    /// score as a mate at the distance the table gives, or as a tablebase win if that is beyond
+    /// the reach of the search.
+    ///
+    /// * `ply`: The distance to the root.
+    pub(super) fn probe_tablebases(&mut self, ply: usize) -> Option<(i16, Bound)> {
+        if let Some(dtm) = self.shared.dtm.probe(&self.board) {
+            self.shared.tbhits.fetch_add(1, Ordering::Relaxed);
+            return Some(dtm_score(dtm, ply));
+        }
+
+        let state = &self.board.state;
+        let pieces = (self.board.bb_side[Sides::WHITE] | self.board.bb_side[Sides::BLACK])
+            .count_ones() as usize;
+        if pieces > self.tb_pieces || state.half_move_clock != 0 || state.castling != 0 {
+            return None;
+        }
+
+        let wdl = self.shared.tablebases.probe_wdl(&mut self.board, self.mg)?;
+        self.shared.tbhits.fetch_add(1, Ordering::Relaxed);
+
+        Some(match wdl {
+            Wdl::Win => (TB_WIN - ply as i16, Bound::Lower),
+            Wdl::Loss => (-TB_WIN + ply as i16, Bound::Upper),
+            wdl => (DRAW + wdl as i16, Bound::Exact),
+        })
+    }
+}
+
+/// The score of a position in the DTM tables.
+///
+/// * `dtm`: The distance to mate of the position.
+/// * `ply`: The distance to the root.
+fn dtm_score(dtm: Dtm, ply: usize) -> (i16, Bound) {
+    match dtm {
+        Dtm::Win(plies) if ply + (plies as usize) < MAX_PLY => {
+            (CHECKMATE - (ply + plies as usize) as i16, Bound::Exact)
+        }
+        Dtm::Loss(plies) if ply + (plies as usize) < MAX_PLY => {
+            (-CHECKMATE + (ply + plies as usize) as i16, Bound::Exact)
+        }
+        Dtm::Win(_) => (TB_WIN - ply as i16, Bound::Lower),
+        Dtm::Loss(_) => (-TB_WIN + ply as i16, Bound::Upper),
+        Dtm::Draw => (DRAW, Bound::Exact),
+    }
+}
@@ -0,0 +1,225 @@
+use std::{collections::HashMap, sync::atomic::Ordering, thread};
+
+use crate::board::Board;
+
+use super::{
+    defs::{SearchInfo, SearchLimits, SearchOptions, SearchResult},
+    Search, SearchShared,
+};
+
+/// The default value of the `Threads` option.
+pub const DEFAULT_THREADS: usize = 1;
+
+/// The limits of the `Threads` option.
+pub const THREADS_MIN: usize = 1;
+pub const THREADS_MAX: usize = 256;
+
+/// Every vote for a move is worth at least this much, so the thread with the lowest score still
+/// counts.
+const VOTE_BASE: i32 = 20;
+
+/// Searches a position with [`SearchOptions::threads`] threads (Lazy SMP).
+///
+/// All threads search the same position, sharing the transposition table, so the results of one
+/// thread speed up the others. The main thread runs on the calling thread; it reports its
+/// progress and decides when to stop, after which the helpers are stopped too. The move that is
+/// played is chosen by a vote of all threads, weighted by depth and score. In MultiPV mode only
+/// the main thread searches all lines, so its result is used.
+///
+/// * `board`: The position to search.
+/// * `shared`: The data shared by all threads.
+/// * `limits`: The limits of the search.
+/// * `options`: The settings that apply to all searches.
+/// * `report`: Called by the main thread with the progress of the search.
+pub fn run_threads(
+    board: &Board,
+    shared: &SearchShared,
+    limits: SearchLimits,
+    options: SearchOptions,
+    report: &mut dyn FnMut(&SearchInfo),
+) -> SearchResult {
+    shared.tt.new_search();
+    shared.nodes.store(0, Ordering::Relaxed);
+    shared.tbhits.store(0, Ordering::Relaxed);
+
+    let results = thread::scope(|scope| {
+        let helpers: Vec<_> = (1..options.threads.max(1))
+            .map(|thread_id| {
+                let board = board.clone();
+                let limits = limits.clone();
+                scope.spawn(move || {
+                    Search::new(board, shared, limits, options)
+                        .helper(thread_id)
+                        .run(&mut |_| {})
+                })
+            })
+            .collect();
+
+        let main = Search::new(board.clone(), shared, limits, options).run(report);
+
+        // The main thread decides when the search is over.
+        shared.stop.store(true, Ordering::Relaxed);
+
+        let mut results = vec![main];
+        for helper in helpers {
+            results.push(helper.join().expect("Search thread panicked."));
+        }
+        results
+    });
+
+    let voted = if options.multipv > 1 {
+        &results[0]
+    } else {
+        vote(&results)
+    };
+    let from_helper = !std::ptr::eq(voted, &results[0]);
+    let mut result = voted.clone();
+    result.summary.nodes = shared.nodes.load(Ordering::Relaxed);
+    result.summary.tbhits = shared.tbhits.load(Ordering::Relaxed);
+
+    // The GUI has only seen the lines of the main thread.
+    if from_helper {
+        report(&SearchInfo::Summary(result.summary.clone()));
+    }
+
+    result
+}
+
+/// Chooses the result to use from the results of all threads. Every thread votes for its best
+/// move; a vote counts more when the thread searched deeper or found a higher score. Of the
+/// threads that voted for the winning move, the one that searched deepest is returned.
+///
+/// * `results`: The results of all threads, the main thread first.
+fn vote(results: &[SearchResult]) -> &SearchResult {
+    let min_score = results
+        .iter()
+        .map(|result| result.summary.score as i32)
+        .min()
+        .unwrap_or(0);
+
+    let mut votes: HashMap<u32, i64> = HashMap::new();
+    for result in results {
+        if let Some(mv) = result.best_move {
+            let weight = (result.summary.score as i32 - min_score + VOTE_BASE) as i64
+                * result.summary.depth as i64;
+            *votes.entry(mv.get_move()).or_default() += weight;
+        }
+    }
+
+    let mut best = &results[0];
+    for result in &results[1..] {
+        let (Some(mv), Some(best_mv)) = (result.best_move, best.best_move) else {
+            continue;
+        };
+
+        let vote = votes[&mv.get_move()];
+        let best_vote = votes[&best_mv.get_move()];
+        if vote > best_vote || (mv == best_mv && result.summary.depth > best.summary.depth) {
+            best = result;
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::AtomicBool, Arc},
+        time::Duration,
+    };
+
+    use crate::{
+        board::Board,
+        movegen::{defs::Move, movelist::MoveList, MoveGenerator},
+        search::{
+            defs::{moves_to_mate, SearchLimits, SearchOptions, SearchResult, SearchSummary},
+            transposition::{Bound, TranspositionTable},
+            SearchShared,
+        },
+    };
+
+    use super::{run_threads, vote};
+
+    fn result(best_move: Option<Move>, score: i16, depth: u8) -> SearchResult {
+        let summary = SearchSummary {
+            multipv: 1,
+            depth,
+            seldepth: depth as usize,
+            score,
+            bound: Bound::Exact,
+            wdl: None,
+            nodes: 0,
+            hashfull: 0,
+            tbhits: 0,
+            time: Duration::ZERO,
+            pv: best_move.into_iter().collect(),
+        };
+        SearchResult {
+            best_move,
+            ponder_move: None,
+            summary: summary.clone(),
+            lines: vec![summary],
+        }
+    }
+
+    #[test]
+    fn test_vote_prefers_deeper_and_better_results() {
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let mg = MoveGenerator::new();
+        let mut moves = MoveList::new();
+        mg.generate_moves(&board, &mut moves);
+        let (a, b) = (moves[0], moves[1]);
+
+        // Two helpers that agree outvote the main thread.
+        let results = [
+            result(Some(a), 10, 8),
+            result(Some(b), 10, 8),
+            result(Some(b), 5, 9),
+        ];
+        assert_eq!(vote(&results).best_move, Some(b));
+        assert_eq!(vote(&results).summary.depth, 9);
+
+        // A much higher score wins on its own.
+        let results = [
+            result(Some(a), 200, 8),
+            result(Some(b), 0, 8),
+            result(Some(b), 0, 8),
+        ];
+        assert_eq!(vote(&results).best_move, Some(a));
+
+        // Threads without a move do not vote.
+        let results = [result(Some(a), 0, 3), result(None, 0, 0)];
+        assert_eq!(vote(&results).best_move, Some(a));
+    }
+
+    #[test]
+    fn test_run_threads_finds_mate() {
+        let mut board = Board::new();
+        board
+            .fen_read(Some("7k/8/5K2/8/8/8/8/R7 w - - 0 1"))
+            .unwrap();
+
+        let shared = SearchShared::new(
+            Arc::new(MoveGenerator::new()),
+            Arc::new(TranspositionTable::new(1)),
+            Arc::new(AtomicBool::new(false)),
+        );
+        let limits = SearchLimits {
+            depth: Some(5),
+            ..Default::default()
+        };
+        let options = SearchOptions {
+            threads: 4,
+            ..Default::default()
+        };
+
+        let mut reports = 0;
+        let result = run_threads(&board, &shared, limits, options, &mut |_| reports += 1);
+
+        assert!(reports >= 5);
+        assert_eq!(moves_to_mate(result.summary.score), Some(2));
+        assert!(result.summary.nodes > 0);
+    }
+}
@@ -0,0 +1,419 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    board::defs::{Side, Sides},
+    movegen::defs::Move,
+};
+
+use super::defs::SearchLimits;
+
+/// The number of moves the remaining time is divided over when the GUI does not send
+/// `movestogo`.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// `movestogo` is capped, so a long time control does not lead to moves that are played almost
+/// instantly.
+const MAX_MOVES_TO_GO: u32 = 50;
+
+/// The hard limit is at most this many times the soft limit.
+const HARD_LIMIT_FACTOR: u32 = 4;
+
+/// The share of the remaining time (in percent) that can be used for a single move.
+const MAX_TIME_USAGE: u32 = 80;
+
+/// A score that drops by this many centipawns between iterations gets the maximum extension.
+const SCORE_DROP_RANGE: i16 = 100;
+
+/// When the best move used at least this share (in percent) of the root nodes, it is considered
+/// to be clearly best.
+const CLEAR_BEST_MOVE_NODES: u64 = 90;
+
+/// A best move is only considered clearly best from this depth on.
+const CLEAR_BEST_MOVE_DEPTH: u8 = 8;
+
+/// Decides how long the search may take for a move.
+///
+/// Two limits are used. The soft limit is checked after each iteration of iterative deepening; it
+/// is extended when the best move keeps changing or the score drops, and shortened when one move
+/// is clearly best. The hard limit is checked during the search and is never exceeded.
+///
+/// While pondering, neither limit applies. After `ponderhit` the time spent pondering counts
+/// towards the soft limit, but the hard limit is measured from the `ponderhit`, as that is when
+/// the clock of the engine started running.
+///
+/// * `start`: The moment the search started.
+/// * `clock_start`: The moment the clock of the engine started: the start of the search, or the
+///   `ponderhit` when pondering.
+/// * `pondering`: The engine is searching on the opponent's time.
+/// * `soft`: The time after which no new iteration is started; None for an untimed search.
+/// * `hard`: The time after which the search is aborted; None for an untimed search.
+/// * `timed`: The time is allocated from the clocks, so the soft limit can be adjusted.
+/// * `instability`: Grows each time the best move changes, and decays each iteration.
+/// * `score_drop`: How much the score dropped in the last iteration.
+/// * `clear_best`: One move used almost all of the nodes at the root.
+/// * `single_reply`: There is only one legal move.
+/// * `previous`: The best move and score of the previous iteration.
+pub struct TimeManager {
+    start: Instant,
+    clock_start: Instant,
+    pondering: bool,
+    soft: Option<Duration>,
+    hard: Option<Duration>,
+    timed: bool,
+    instability: f64,
+    score_drop: i16,
+    clear_best: bool,
+    single_reply: bool,
+    previous: Option<(Move, i16)>,
+}
+
+impl TimeManager {
+    /// Create a new [`TimeManager`] and start its clock.
+    ///
+    /// * `limits`: The limits of the search.
+    /// * `side`: The side to move; the time of this side is allocated.
+    /// * `move_overhead`: Time reserved per move for communication delays with the GUI.
+    pub fn new(limits: &SearchLimits, side: Side, move_overhead: Duration) -> Self {
+        let (soft, hard, timed) = if limits.infinite {
+            (None, None, false)
+        } else if let Some(move_time) = limits.move_time {
+            let time = move_time.saturating_sub(move_overhead);
+            (Some(time), Some(time), false)
+        } else if let Some(game_time) = limits.game_time {
+            let (time, increment) = if side == Sides::WHITE {
+                (game_time.wtime, game_time.winc)
+            } else {
+                (game_time.btime, game_time.binc)
+            };
+            let moves_to_go = game_time
+                .moves_to_go
+                .unwrap_or(DEFAULT_MOVES_TO_GO)
+                .clamp(1, MAX_MOVES_TO_GO);
+            let (soft, hard) = TimeManager::allocate(time, increment, moves_to_go, move_overhead);
+            (Some(soft), Some(hard), true)
+        } else {
+            (None, None, false)
+        };
+
+        let start = Instant::now();
+
+        Self {
+            start,
+            clock_start: start,
+            pondering: limits.ponder,
+            soft,
+            hard,
+            timed,
+            instability: 0.0,
+            score_drop: 0,
+            clear_best: false,
+            single_reply: false,
+            previous: None,
+        }
+    }
+
+    /// Calculates the soft and hard limits for one move from the clock.
+    ///
+    /// * `time`: The time left on the clock.
+    /// * `increment`: The increment per move.
+    /// * `moves_to_go`: The number of moves the time has to last for.
+    /// * `move_overhead`: Time reserved per move for communication delays.
+    fn allocate(
+        time: Duration,
+        increment: Duration,
+        moves_to_go: u32,
+        move_overhead: Duration,
+    ) -> (Duration, Duration) {
+        let available = time.saturating_sub(move_overhead);
+        let max_usage = available * MAX_TIME_USAGE / 100;
+
+        let soft = (available / moves_to_go + increment * 3 / 4).min(max_usage);
+        let hard = (soft * HARD_LIMIT_FACTOR).min(max_usage);
+
+        (soft, hard)
+    }
+
+    /// The time that passed since the search started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// The soft limit, before it is adjusted by the progress of the search.
+    pub fn soft_limit(&self) -> Option<Duration> {
+        self.soft
+    }
+
+    /// The hard limit.
+    pub fn hard_limit(&self) -> Option<Duration> {
+        self.hard
+    }
+
+    /// Determines if the hard limit has passed, in which case the search must stop immediately.
+    pub fn out_of_time(&self) -> bool {
+        !self.pondering
+            && self
+                .hard
+                .is_some_and(|hard| self.clock_start.elapsed() >= hard)
+    }
+
+    /// Determines if the engine is still searching on the opponent's time.
+    pub fn is_pondering(&self) -> bool {
+        self.pondering
+    }
+
+    /// The opponent played the expected move: the search continues, now within the time limits.
+    pub fn ponderhit(&mut self) {
+        if self.pondering {
+            self.pondering = false;
+            self.clock_start = Instant::now();
+        }
+    }
+
+    /// Tells the time manager that there is only one legal move, so there is no need to think
+    /// about it once a score is known.
+    pub fn set_single_reply(&mut self) {
+        self.single_reply = true;
+    }
+
+    /// Updates the time manager with the outcome of a completed iteration.
+    ///
+    /// * `depth`: The depth of the iteration.
+    /// * `best_move`: The best move after the iteration.
+    /// * `score`: The score of the best move.
+    /// * `best_move_nodes`: The number of nodes spent on the best move at the root.
+    /// * `root_nodes`: The number of nodes spent on all moves at the root.
+    pub fn update(
+        &mut self,
+        depth: u8,
+        best_move: Move,
+        score: i16,
+        best_move_nodes: u64,
+        root_nodes: u64,
+    ) {
+        self.instability *= 0.5;
+        self.score_drop = 0;
+
+        if let Some((previous_move, previous_score)) = self.previous {
+            if previous_move != best_move {
+                self.instability += 1.0;
+            }
+            self.score_drop = (previous_score - score).clamp(0, SCORE_DROP_RANGE);
+        }
+
+        self.clear_best = depth >= CLEAR_BEST_MOVE_DEPTH
+            && root_nodes > 0
+            && best_move_nodes * 100 >= root_nodes * CLEAR_BEST_MOVE_NODES;
+
+        self.previous = Some((best_move, score));
+    }
+
+    /// The soft limit, adjusted for the progress of the search. A best move that keeps changing
+    /// can almost double the time; a dropping score can extend it by half; a clearly best move
+    /// halves it.
+    pub fn adjusted_soft_limit(&self) -> Option<Duration> {
+        let soft = self.soft?;
+        if !self.timed {
+            return Some(soft);
+        }
+
+        let instability = 1.0 + self.instability.min(2.0) * 0.45;
+        let score_drop = 1.0 + f64::from(self.score_drop) / f64::from(SCORE_DROP_RANGE) * 0.5;
+        let clear_best = if self.clear_best { 0.5 } else { 1.0 };
+
+        let adjusted = soft.mul_f64(instability * score_drop * clear_best);
+        Some(self.hard.map_or(adjusted, |hard| adjusted.min(hard)))
+    }
+
+    /// Determines if the search should stop after the iteration that just completed.
+    pub fn should_stop(&self) -> bool {
+        if self.pondering {
+            return false;
+        }
+
+        if self.timed && self.single_reply {
+            return true;
+        }
+
+        self.adjusted_soft_limit()
+            .is_some_and(|soft| self.elapsed() >= soft)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        board::defs::Sides,
+        movegen::defs::Move,
+        search::defs::{GameTime, SearchLimits},
+    };
+
+    use super::TimeManager;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn game_time(wtime: u64, btime: u64, inc: u64, moves_to_go: Option<u32>) -> SearchLimits {
+        SearchLimits {
+            game_time: Some(GameTime {
+                wtime: ms(wtime),
+                btime: ms(btime),
+                winc: ms(inc),
+                binc: ms(inc),
+                moves_to_go,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_infinite_and_depth_searches_have_no_limits() {
+        let infinite = SearchLimits {
+            infinite: true,
+            move_time: Some(ms(100)),
+            ..Default::default()
+        };
+        let depth = SearchLimits {
+            depth: Some(5),
+            ..Default::default()
+        };
+
+        for limits in [infinite, depth] {
+            let tm = TimeManager::new(&limits, Sides::WHITE, ms(30));
+            assert_eq!(tm.soft_limit(), None);
+            assert_eq!(tm.hard_limit(), None);
+            assert!(!tm.out_of_time());
+            assert!(!tm.should_stop());
+        }
+    }
+
+    #[test]
+    fn test_move_time_subtracts_move_overhead() {
+        let limits = SearchLimits {
+            move_time: Some(ms(1000)),
+            ..Default::default()
+        };
+        let tm = TimeManager::new(&limits, Sides::WHITE, ms(50));
+        assert_eq!(tm.soft_limit(), Some(ms(950)));
+        assert_eq!(tm.hard_limit(), Some(ms(950)));
+    }
+
+    #[test]
+    fn test_game_time_uses_time_of_side_to_move() {
+        let limits = game_time(60_000, 30_000, 0, Some(10));
+
+        let white = TimeManager::new(&limits, Sides::WHITE, ms(0));
+        let black = TimeManager::new(&limits, Sides::BLACK, ms(0));
+
+        assert_eq!(white.soft_limit(), Some(ms(6_000)));
+        assert_eq!(black.soft_limit(), Some(ms(3_000)));
+        assert_eq!(white.hard_limit(), Some(ms(24_000)));
+        assert_eq!(black.hard_limit(), Some(ms(12_000)));
+    }
+
+    #[test]
+    fn test_game_time_adds_increment_and_respects_overhead() {
+        let limits = game_time(30_100, 30_100, 1_000, None);
+        let tm = TimeManager::new(&limits, Sides::WHITE, ms(100));
+
+        // 30 000 ms over 30 moves, plus three quarters of the increment.
+        assert_eq!(tm.soft_limit(), Some(ms(1_750)));
+        assert_eq!(tm.hard_limit(), Some(ms(7_000)));
+    }
+
+    #[test]
+    fn test_game_time_never_uses_whole_clock() {
+        let limits = game_time(1_000, 1_000, 5_000, Some(1));
+        let tm = TimeManager::new(&limits, Sides::WHITE, ms(100));
+
+        assert_eq!(tm.soft_limit(), Some(ms(720)));
+        assert_eq!(tm.hard_limit(), Some(ms(720)));
+
+        let flagging = game_time(50, 50, 0, None);
+        let tm = TimeManager::new(&flagging, Sides::WHITE, ms(100));
+        assert_eq!(tm.hard_limit(), Some(ms(0)));
+        assert!(tm.out_of_time());
+    }
+
+    #[test]
+    fn test_changing_best_move_and_dropping_score_extend_time() {
+        let limits = game_time(60_000, 60_000, 0, Some(20));
+        let mut tm = TimeManager::new(&limits, Sides::WHITE, ms(0));
+        let soft = tm.soft_limit().unwrap();
+
+        tm.update(1, Move::new(1), 50, 10, 100);
+        assert_eq!(tm.adjusted_soft_limit(), Some(soft));
+
+        tm.update(2, Move::new(2), 50, 10, 100);
+        let unstable = tm.adjusted_soft_limit().unwrap();
+        assert!(unstable > soft);
+
+        tm.update(3, Move::new(2), -50, 10, 100);
+        let dropped = tm.adjusted_soft_limit().unwrap();
+        assert!(dropped > unstable * 10 / 9);
+        assert!(dropped <= tm.hard_limit().unwrap());
+    }
+
+    #[test]
+    fn test_clearly_best_move_shortens_time() {
+        let limits = game_time(60_000, 60_000, 0, Some(20));
+        let mut tm = TimeManager::new(&limits, Sides::WHITE, ms(0));
+        let soft = tm.soft_limit().unwrap();
+
+        tm.update(7, Move::new(1), 50, 95, 100);
+        assert_eq!(tm.adjusted_soft_limit(), Some(soft));
+
+        tm.update(8, Move::new(1), 50, 95, 100);
+        assert_eq!(tm.adjusted_soft_limit(), Some(soft / 2));
+    }
+
+    #[test]
+    fn test_single_reply_stops_timed_search() {
+        let limits = game_time(60_000, 60_000, 0, None);
+        let mut tm = TimeManager::new(&limits, Sides::WHITE, ms(0));
+        assert!(!tm.should_stop());
+        tm.set_single_reply();
+        assert!(tm.should_stop());
+
+        let mut fixed = TimeManager::new(&SearchLimits::default(), Sides::WHITE, ms(0));
+        fixed.set_single_reply();
+        assert!(!fixed.should_stop());
+    }
+
+    #[test]
+    fn test_pondering_ignores_limits_until_ponderhit() {
+        let limits = SearchLimits {
+            ponder: true,
+            ..game_time(50, 50, 0, None)
+        };
+        let mut tm = TimeManager::new(&limits, Sides::WHITE, ms(100));
+        tm.set_single_reply();
+
+        assert!(tm.is_pondering());
+        assert_eq!(tm.hard_limit(), Some(ms(0)));
+        assert!(!tm.out_of_time());
+        assert!(!tm.should_stop());
+
+        tm.ponderhit();
+        assert!(!tm.is_pondering());
+        assert!(tm.out_of_time());
+        assert!(tm.should_stop());
+    }
+
+    #[test]
+    fn test_hard_limit_starts_at_ponderhit() {
+        let limits = SearchLimits {
+            ponder: true,
+            move_time: Some(ms(1_000)),
+            ..Default::default()
+        };
+        let mut tm = TimeManager::new(&limits, Sides::WHITE, ms(0));
+        std::thread::sleep(ms(20));
+
+        tm.ponderhit();
+        assert!(tm.elapsed() >= ms(20));
+        assert!(!tm.out_of_time());
+    }
+}
@@ -0,0 +1,332 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::movegen::defs::Move;
+
+use super::defs::CHECKMATE_THRESHOLD;
+
+/// The default size of the transposition table, in megabytes.
+pub const DEFAULT_HASH_SIZE: usize = 16;
+
+/// The limits of the `Hash` option, in megabytes.
+pub const HASH_SIZE_MIN: usize = 1;
+pub const HASH_SIZE_MAX: usize = 65536;
+
+/// The size of a single entry, in bytes.
+const ENTRY_SIZE: usize = std::mem::size_of::<Entry>();
+
+/// Layout of the data word of an entry.
+const MOVE_BITS: u64 = 24;
+const SCORE_SHIFT: u64 = 24;
+const DEPTH_SHIFT: u64 = 40;
+const BOUND_SHIFT: u64 = 48;
+const GENERATION_SHIFT: u64 = 50;
+
+/// What the stored score says about the real score of the position.
+///
+/// * `Exact`: The score is exact.
+/// * `Lower`: The search failed high; the real score is at least this score.
+/// * `Upper`: The search failed low; the real score is at most this score.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// The information stored for a position.
+///
+/// * `best_move`: The best move found, if any.
+/// * `score`: The score, with mate scores relative to the stored position.
+/// * `depth`: The depth that was searched.
+/// * `bound`: What kind of score was stored.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TTData {
+    pub best_move: Option<Move>,
+    pub score: i16,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+impl TTData {
+    /// Packs the data into a single word, together with the generation of the search.
+    fn pack(&self, generation: u8) -> u64 {
+        let mv = self.best_move.map_or(0, |mv| mv.get_move()) as u64;
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+
+        mv | ((self.score as u16 as u64) << SCORE_SHIFT)
+            | ((self.depth as u64) << DEPTH_SHIFT)
+            | (bound << BOUND_SHIFT)
+            | ((generation as u64) << GENERATION_SHIFT)
+    }
+
+    /// Unpacks a data word created by [`TTData::pack`].
+    fn unpack(data: u64) -> Self {
+        let mv = data & ((1 << MOVE_BITS) - 1);
+        let bound = match (data >> BOUND_SHIFT) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+
+        Self {
+            best_move: (mv != 0).then(|| Move::new(mv as usize)),
+            score: (data >> SCORE_SHIFT) as u16 as i16,
+            depth: (data >> DEPTH_SHIFT) as u8,
+            bound,
+        }
+    }
+
+    fn generation(data: u64) -> u8 {
+        (data >> GENERATION_SHIFT) as u8
+    }
+}
+
+/// A slot in the table. Instead of the key, the key XOR the data is stored. When another thread
+/// writes the same slot at the same time, the two words may come from different writes; the
+/// check then fails and the entry is ignored. This makes the table safe to share between threads
+/// without locks.
+///
+/// * `check`: The Zobrist key XOR the data.
+/// * `data`: The packed [`TTData`].
+#[derive(Default)]
+struct Entry {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+/// A hash table that stores the results of earlier searches, indexed by the Zobrist key of the
+/// position. It is shared by all search threads.
+///
+/// * `entries`: The slots of the table.
+/// * `generation`: Increased for every new search, so entries from old searches are replaced
+///   first.
+pub struct TranspositionTable {
+    entries: Vec<Entry>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Create a new [`TranspositionTable`] that uses at most the given number of megabytes.
+    ///
+    /// * `megabytes`: The size of the table.
+    pub fn new(megabytes: usize) -> Self {
+        let count = (megabytes.max(1) * 1024 * 1024 / ENTRY_SIZE).max(1);
+        let mut entries = Vec::with_capacity(count);
+        entries.resize_with(count, Entry::default);
+
+        Self {
+            entries,
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) {
+        for entry in &self.entries {
+            entry.check.store(0, Ordering::Relaxed);
+            entry.data.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// Tells the table a new search has started, so the entries of the previous searches are
+    /// replaced before the entries of the current one.
+    pub fn new_search(&self) {
+        let generation = self.generation.load(Ordering::Relaxed).wrapping_add(1);
+        self.generation.store(generation, Ordering::Relaxed);
+    }
+
+    /// Looks up a position.
+    ///
+    /// * `key`: The Zobrist key of the position.
+    /// * `ply`: The distance to the root, used to convert mate scores.
+    pub fn probe(&self, key: u64, ply: usize) -> Option<TTData> {
+        let entry = &self.entries[self.index(key)];
+        let data = entry.data.load(Ordering::Relaxed);
+        let check = entry.check.load(Ordering::Relaxed);
+
+        if data == 0 || check ^ data != key {
+            return None;
+        }
+
+        let mut tt_data = TTData::unpack(data);
+        tt_data.score = score_from_tt(tt_data.score, ply);
+        Some(tt_data)
+    }
+
+    /// Stores the result of a search of a position. An entry of the current search is only
+    /// replaced by a search of another position if that search was at least as deep.
+    ///
+    /// * `key`: The Zobrist key of the position.
+    /// * `ply`: The distance to the root, used to convert mate scores.
+    /// * `tt_data`: The data to store.
+    pub fn store(&self, key: u64, ply: usize, mut tt_data: TTData) {
+        let entry = &self.entries[self.index(key)];
+        let generation = self.generation.load(Ordering::Relaxed);
+
+        let old_data = entry.data.load(Ordering::Relaxed);
+        let old_key = entry.check.load(Ordering::Relaxed) ^ old_data;
+        if old_data != 0 {
+            let old = TTData::unpack(old_data);
+            let same_search = TTData::generation(old_data) == generation;
+
+            if old_key != key && same_search && old.depth > tt_data.depth {
+                return;
+            }
+
+            // Keep the best move of an earlier search of this position when there is no new one.
+            if old_key == key && tt_data.best_move.is_none() {
+                tt_data.best_move = old.best_move;
+            }
+        }
+
+        tt_data.score = score_to_tt(tt_data.score, ply);
+        let data = tt_data.pack(generation);
+        entry.check.store(key ^ data, Ordering::Relaxed);
+        entry.data.store(data, Ordering::Relaxed);
+    }
+
+    /// How full the table is, in permille, based on a sample of the entries of the current
+    /// search.
+    pub fn hashfull(&self) -> u16 {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let sample = self.entries.len().min(1000);
+        let used = self.entries[..sample]
+            .iter()
+            .filter(|entry| {
+                let data = entry.data.load(Ordering::Relaxed);
+                data != 0 && TTData::generation(data) == generation
+            })
+            .count();
+
+        (used * 1000 / sample) as u16
+    }
+
+    fn index(&self, key: u64) -> usize {
+        // Maps the key onto the table without a division.
+        ((key as u128 * self.entries.len() as u128) >> 64) as usize
+    }
+}
+
+/// Mate scores are stored relative to the stored position instead of the root, so they are
+/// still correct when the position is found at another ply.
+fn score_to_tt(score: i16, ply: usize) -> i16 {
+    if score >= CHECKMATE_THRESHOLD {
+        score + ply as i16
+    } else if score <= -CHECKMATE_THRESHOLD {
+        score - ply as i16
+    } else {
+        score
+    }
+}
+
+/// Converts a mate score stored by [`score_to_tt`] back to a score relative to the root.
+fn score_from_tt(score: i16, ply: usize) -> i16 {
+    if score >= CHECKMATE_THRESHOLD {
+        score - ply as i16
+    } else if score <= -CHECKMATE_THRESHOLD {
+        score + ply as i16
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::Board,
+        movegen::{movelist::MoveList, MoveGenerator},
+        search::defs::CHECKMATE,
+    };
+
+    use super::{Bound, TTData, TranspositionTable};
+
+    #[test]
+    fn test_store_and_probe() {
+        let mut board = Board::new();
+        board.fen_read(None).unwrap();
+        let mg = MoveGenerator::new();
+        let mut moves = MoveList::new();
+        mg.generate_moves(&board, &mut moves);
+
+        let tt = TranspositionTable::new(1);
+        let key = board.state.zobrist_key;
+        let data = TTData {
+            best_move: Some(moves[5]),
+            score: -123,
+            depth: 7,
+            bound: Bound::Lower,
+        };
+
+        assert_eq!(tt.probe(key, 0), None);
+        tt.store(key, 0, data);
+        assert_eq!(tt.probe(key, 0), Some(data));
+        assert_eq!(tt.probe(key ^ 1, 0), None);
+
+        tt.clear();
+        assert_eq!(tt.probe(key, 0), None);
+    }
+
+    #[test]
+    fn test_mate_scores_are_relative_to_the_position() {
+        let tt = TranspositionTable::new(1);
+        let data = TTData {
+            best_move: None,
+            score: CHECKMATE - 5,
+            depth: 3,
+            bound: Bound::Exact,
+        };
+
+        tt.store(42, 2, data);
+        assert_eq!(tt.probe(42, 2).unwrap().score, CHECKMATE - 5);
+        assert_eq!(tt.probe(42, 4).unwrap().score, CHECKMATE - 7);
+    }
+
+    #[test]
+    fn test_corrupted_entry_is_rejected() {
+        let tt = TranspositionTable::new(1);
+        let data = TTData {
+            best_move: None,
+            score: 10,
+            depth: 3,
+            bound: Bound::Upper,
+        };
+        tt.store(42, 0, data);
+
+        // Simulate a concurrent write that only replaced the data word.
+        let entry = &tt.entries[tt.index(42)];
+        entry
+            .data
+            .fetch_xor(1 << 30, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(tt.probe(42, 0), None);
+    }
+
+    #[test]
+    fn test_deeper_entries_of_current_search_are_kept() {
+        let tt = TranspositionTable::new(1);
+        let deep = TTData {
+            best_move: None,
+            score: 10,
+            depth: 9,
+            bound: Bound::Exact,
+        };
+        let shallow = TTData { depth: 2, ..deep };
+
+        // Both keys map to the same slot.
+        let (a, b) = (1, 2);
+        assert_eq!(tt.index(a), tt.index(b));
+
+        tt.store(a, 0, deep);
+        tt.store(b, 0, shallow);
+        assert_eq!(tt.probe(a, 0), Some(deep));
+
+        tt.new_search();
+        tt.store(b, 0, shallow);
+        assert_eq!(tt.probe(b, 0), Some(shallow));
+        assert_eq!(tt.hashfull(), 1);
+    }
+}
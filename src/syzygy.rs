@@ -0,0 +1,1043 @@
+mod encoding;
+mod table;
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    ops::Neg,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{
+    board::{
+        defs::{Pieces, Sides, MAX_MOVE_RULE, PIECE_CHAR_CAPS},
+        Board,
+    },
+    movegen::{
+        defs::{Move, MoveType},
+        movelist::MoveList,
+        MoveGenerator,
+    },
+};
+
+use table::{Material, Table, TableKind, TableProbe};
+
+/// The extensions of the two kinds of table files.
+const WDL_EXTENSION: &str = "rtbw";
+const DTZ_EXTENSION: &str = "rtbz";
+
+/// The rank of a root move that certainly wins; see [`Tablebases::rank_root_moves`]. Moves that
+/// win, but where the fifty-move rule may get in the way, rank lower the longer they take.
+pub const MAX_DTZ: i32 = 1 << 18;
+
+/// The outcome of a position with perfect play, from the side to move's point of view. A cursed
+/// win would be a win without the fifty-move rule, a blessed loss a loss; with the rule they
+/// are draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(i8)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    /// Converts a value from -2 to 2, as stored in a WDL table.
+    ///
+    /// * `value`: The value.
+    fn from_value(value: i32) -> Option<Self> {
+        match value {
+            -2 => Some(Self::Loss),
+            -1 => Some(Self::BlessedLoss),
+            0 => Some(Self::Draw),
+            1 => Some(Self::CursedWin),
+            2 => Some(Self::Win),
+            _ => None,
+        }
+    }
+
+    /// The sign of the value: 1 for (cursed) wins, -1 for (blessed) losses, 0 for draws.
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self {
+            Self::Loss => Self::Win,
+            Self::BlessedLoss => Self::CursedWin,
+            Self::Draw => Self::Draw,
+            Self::CursedWin => Self::BlessedLoss,
+            Self::Win => Self::Loss,
+        }
+    }
+}
+
+/// Whether the best move of a position is a capture or pawn move. Tables do not store a useful
+/// value for such positions, as it follows from the positions after the move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ProbeState {
+    Ok,
+    ZeroingBestMove,
+}
+
+/// A table file that was found, and is read when it is first probed.
+///
+/// * `path`: The path of the file.
+/// * `material`: The pieces of the table.
+/// * `table`: The table, once read; None if it could not be read.
+struct TableEntry {
+    path: PathBuf,
+    material: Material,
+    table: OnceLock<Option<Table>>,
+}
+
+impl TableEntry {
+    /// The table, which is read from its file on first use.
+    ///
+    /// * `kind`: The kind of table in the file.
+    fn table(&self, kind: TableKind) -> Option<&Table> {
+        self.table
+            .get_or_init(|| Table::open(&self.path, kind, self.material).ok())
+            .as_ref()
+    }
+}
+
+/// The Syzygy endgame tablebases found in a set of directories. They give the outcome (WDL) of
+/// positions with few pieces, and the distance to the next capture or pawn move (DTZ), so that
+/// a won position can be won within the fifty-move rule.
+///
+/// Positions with castling rights can not be probed. The value of positions with an en-passant
+/// capture is found by searching the captures, as tables do not store them.
+///
+/// * `wdl`: The WDL tables, by name (such as `KRvK`).
+/// * `dtz`: The DTZ tables, by name.
+/// * `max_pieces`: The number of pieces of the largest WDL table.
+#[derive(Default)]
+pub struct Tablebases {
+    wdl: HashMap<String, TableEntry>,
+    dtz: HashMap<String, TableEntry>,
+    max_pieces: usize,
+}
+
+impl Tablebases {
+    /// Finds the table files in a list of directories, such as the value of the `SyzygyPath`
+    /// option. The files are only read when they are first probed.
+    ///
+    /// * `paths`: The directories, separated as in the `PATH` environment variable (`:` on Unix,
+    ///   `;` on Windows).
+    pub fn open(paths: &str) -> Self {
+        let mut tablebases = Self::default();
+
+        for dir in env::split_paths(paths).filter(|dir| !dir.as_os_str().is_empty()) {
+            let Ok(files) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for path in files.flatten().map(|file| file.path()) {
+                tablebases.add(&path);
+            }
+        }
+
+        tablebases.max_pieces = tablebases
+            .wdl
+            .values()
+            .map(|entry| entry.material.piece_count)
+            .max()
+            .unwrap_or(0);
+        tablebases
+    }
+
+    /// Adds a file if it is a table. When a table is found in several directories, the first one
+    /// is used.
+    ///
+    /// * `path`: The path of the file.
+    fn add(&mut self, path: &Path) {
+        let (Some(name), Some(extension)) = (path.file_stem(), path.extension()) else {
+            return;
+        };
+        let Some(material) = name.to_str().and_then(Material::parse) else {
+            return;
+        };
+        let tables = match extension.to_str() {
+            Some(WDL_EXTENSION) => &mut self.wdl,
+            Some(DTZ_EXTENSION) => &mut self.dtz,
+            _ => return,
+        };
+
+        let name = name.to_string_lossy().into_owned();
+        tables.entry(name).or_insert_with(|| TableEntry {
+            path: path.to_path_buf(),
+            material,
+            table: OnceLock::new(),
+        });
+    }
+
+    /// The number of WDL and DTZ tables that were found.
+    pub fn counts(&self) -> (usize, usize) {
+        (self.wdl.len(), self.dtz.len())
+    }
+
+    /// The number of pieces, including the kings, of the largest table; 0 without tables.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// True when the position may be in the tables: it has few enough pieces and no castling
+    /// rights. The table for its pieces may still be missing.
+    ///
+    /// * `board`: The position.
+    pub fn can_probe(&self, board: &Board) -> bool {
+        let pieces = (board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK]).count_ones();
+        board.state.castling == 0 && pieces as usize <= self.max_pieces
+    }
+
+    /// The outcome of a position with perfect play, if the tables have it.
+    ///
+    /// * `board`: The position.
+    /// * `mg`: The move generator.
+    pub fn probe_wdl(&self, board: &mut Board, mg: &MoveGenerator) -> Option<Wdl> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        self.search(board, mg, false).map(|(wdl, _)| wdl)
+    }
+
+    /// The number of plies to the next capture or pawn move with perfect play, if the tables
+    /// have it: positive when the side to move wins, negative when it loses and 0 for a draw.
+    /// Cursed wins and blessed losses count 100 plies more, as the fifty-move rule makes them
+    /// draws. The distance may be one ply too high, but never too low.
+    ///
+    /// * `board`: The position.
+    /// * `mg`: The move generator.
+    pub fn probe_dtz(&self, board: &mut Board, mg: &MoveGenerator) -> Option<i32> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        let (wdl, state) = self.search(board, mg, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if state == ProbeState::ZeroingBestMove {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        match self.probe_table(&self.dtz, TableKind::Dtz, board, wdl)? {
+            TableProbe::Value(dtz) => {
+                let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+                Some((dtz + if cursed { 100 } else { 0 }) * wdl.signum())
+            }
+            TableProbe::ChangeStm => self.dtz_by_search(board, mg, wdl),
+        }
+    }
+
+    /// Ranks the moves of the root position by their DTZ value, so the engine can keep to the
+    /// best ones. Wins within the fifty-move rule rank [`MAX_DTZ`], losses `-MAX_DTZ` and draws
+    /// 0; other moves rank in between, so that a win is made as fast as the rule requires and a
+    /// loss is put off as long as possible. Returns None if a position is not in the tables.
+    ///
+    /// * `board`: The root position.
+    /// * `mg`: The move generator.
+    /// * `moves`: The legal moves of the root position.
+    pub fn rank_root_moves(
+        &self,
+        board: &mut Board,
+        mg: &MoveGenerator,
+        moves: &[Move],
+    ) -> Option<Vec<i32>> {
+        if !self.can_probe(board) || self.dtz.is_empty() {
+            return None;
+        }
+
+        let clock = i32::from(board.state.half_move_clock);
+        let repeated = board.is_repetition();
+
+        moves
+            .iter()
+            .map(|&mv| {
+                board.make_move(mv, mg);
+                let dtz = self.root_move_dtz(board, mg);
+                board.unmake_move();
+
+                Some(match dtz? {
+                    dtz if dtz > 0 && dtz + clock <= 99 && !repeated => MAX_DTZ,
+                    dtz if dtz > 0 => MAX_DTZ - (dtz + clock),
+                    dtz if dtz < 0 && -dtz * 2 + clock < 100 => -MAX_DTZ,
+                    dtz if dtz < 0 => -MAX_DTZ + (-dtz + clock),
+                    _ => 0,
+                })
+            })
+            .collect()
+    }
+
+    /// Ranks the moves of the root position by their WDL value only, for when the DTZ tables
+    /// are missing: certain wins rank [`MAX_DTZ`] and certain losses `-MAX_DTZ`. Returns None if
+    /// a position is not in the tables.
+    ///
+    /// * `board`: The root position.
+    /// * `mg`: The move generator.
+    /// * `moves`: The legal moves of the root position.
+    pub fn rank_root_moves_wdl(
+        &self,
+        board: &mut Board,
+        mg: &MoveGenerator,
+        moves: &[Move],
+    ) -> Option<Vec<i32>> {
+        moves
+            .iter()
+            .map(|&mv| {
+                board.make_move(mv, mg);
+                let wdl = self.probe_wdl(board, mg);
+                board.unmake_move();
+
+                Some(match -wdl? {
+                    Wdl::Win => MAX_DTZ,
+                    Wdl::CursedWin => MAX_DTZ - 101,
+                    Wdl::Draw => 0,
+                    Wdl::BlessedLoss => -MAX_DTZ + 101,
+                    Wdl::Loss => -MAX_DTZ,
+                })
+            })
+            .collect()
+    }
+
+    /// The DTZ value of the root position after a move, counted from the root.
+    ///
+    /// * `board`: The position after the move.
+    /// * `mg`: The move generator.
+    fn root_move_dtz(&self, board: &mut Board, mg: &MoveGenerator) -> Option<i32> {
+        let dtz = if board.state.half_move_clock == 0 {
+            dtz_before_zeroing(-self.probe_wdl(board, mg)?)
+        } else if board.is_repetition() || board.state.half_move_clock >= MAX_MOVE_RULE {
+            0
+        } else {
+            let dtz = -self.probe_dtz(board, mg)?;
+            dtz + dtz.signum()
+        };
+
+        // A mate ends the game at once.
+        if dtz == 2 && is_checkmate(board, mg) {
+            return Some(1);
+        }
+        Some(dtz)
+    }
+
+    /// Finds the WDL value of a position by trying its captures (and with `zeroing` its pawn
+    /// moves) and probing the table. Tables may store any value for a position where a capture
+    /// wins, and a loss where a capture draws, so the best of both is the real value.
+    ///
+    /// * `board`: The position.
+    /// * `mg`: The move generator.
+    /// * `zeroing`: Also try pawn moves, as the DTZ table does not store positions where a pawn
+    ///   move wins.
+    fn search(
+        &self,
+        board: &mut Board,
+        mg: &MoveGenerator,
+        zeroing: bool,
+    ) -> Option<(Wdl, ProbeState)> {
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(board, &mut legal, MoveType::All);
+
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for &mv in legal.iter() {
+            if !mv.is_capture() && (!zeroing || mv.piece() != Pieces::PAWN) {
+                continue;
+            }
+            searched += 1;
+
+            board.make_move(mv, mg);
+            let value = self.search(board, mg, false);
+            board.unmake_move();
+
+            let value = -value?.0;
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, ProbeState::ZeroingBestMove));
+                }
+            }
+        }
+
+        // When all moves were searched, the table value is not needed (and may be wrong, when
+        // an en-passant capture is possible).
+        let all_searched = searched > 0 && searched == legal.len();
+        let value = if all_searched {
+            best
+        } else {
+            self.probe_wdl_table(board)?
+        };
+
+        if best >= value {
+            let state = if best > Wdl::Draw || all_searched {
+                ProbeState::ZeroingBestMove
+            } else {
+                ProbeState::Ok
+            };
+            return Some((best, state));
+        }
+        Some((value, ProbeState::Ok))
+    }
+
+    /// Finds the DTZ value of a position that the DTZ table only has with the other side to
+    /// move, by probing the positions after each move.
+    ///
+    /// * `board`: The position.
+    /// * `mg`: The move generator.
+    /// * `wdl`: The WDL value of the position.
+    fn dtz_by_search(&self, board: &mut Board, mg: &MoveGenerator, wdl: Wdl) -> Option<i32> {
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(board, &mut legal, MoveType::All);
+
+        let mut min_dtz = i32::MAX;
+        for &mv in legal.iter() {
+            let zeroing = mv.is_capture() || mv.piece() == Pieces::PAWN;
+
+            board.make_move(mv, mg);
+            // For a zeroing move, the distance is that of the move itself; only the outcome
+            // after it matters.
+            let dtz = if zeroing {
+                self.search(board, mg, false)
+                    .map(|(wdl, _)| -dtz_before_zeroing(wdl))
+            } else {
+                self.probe_dtz(board, mg).map(|dtz| -dtz)
+            };
+            let mate = dtz == Some(1) && is_checkmate(board, mg);
+            board.unmake_move();
+
+            let mut dtz = dtz?;
+            if mate {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+        }
+
+        // Without legal moves the position is mate.
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    /// The value of a position in its WDL table, without looking at captures.
+    ///
+    /// * `board`: The position.
+    fn probe_wdl_table(&self, board: &Board) -> Option<Wdl> {
+        match self.probe_table(&self.wdl, TableKind::Wdl, board, Wdl::Draw)? {
+            TableProbe::Value(value) => Wdl::from_value(value),
+            TableProbe::ChangeStm => None,
+        }
+    }
+
+    /// Probes the table for the pieces of a position. Two bare kings are a draw without a table.
+    ///
+    /// * `tables`: The WDL or DTZ tables.
+    /// * `kind`: The kind of the tables.
+    /// * `board`: The position.
+    /// * `wdl`: For a DTZ table, the WDL value of the position.
+    fn probe_table(
+        &self,
+        tables: &HashMap<String, TableEntry>,
+        kind: TableKind,
+        board: &Board,
+        wdl: Wdl,
+    ) -> Option<TableProbe> {
+        let pieces = (board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK]).count_ones();
+        if pieces == 2 {
+            return Some(TableProbe::Value(0));
+        }
+
+        let white = material_name(board, Sides::WHITE);
+        let black = material_name(board, Sides::BLACK);
+        let (entry, black_stronger) = match tables.get(&format!("{white}v{black}")) {
+            Some(entry) => (entry, false),
+            None => (tables.get(&format!("{black}v{white}"))?, true),
+        };
+
+        entry.table(kind)?.probe(board, black_stronger, wdl)
+    }
+}
+
+/// The pieces of one side, as in the names of the tables: `K` first, then `QRBNP`.
+///
+/// * `board`: The position.
+/// * `side`: The side.
+fn material_name(board: &Board, side: usize) -> String {
+    board.bb_pieces[side]
+        .iter()
+        .enumerate()
+        .map(|(piece, bb)| PIECE_CHAR_CAPS[piece].repeat(bb.count_ones() as usize))
+        .collect()
+}
+
+/// The DTZ value of a position where the best move is a capture or pawn move, which resets the
+/// fifty-move counter.
+///
+/// * `wdl`: The WDL value of the position.
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+/// True when the side to move is checkmated.
+fn is_checkmate(board: &mut Board, mg: &MoveGenerator) -> bool {
+    let mut legal = MoveList::new();
+    mg.generate_legal_moves(board, &mut legal, MoveType::All);
+    legal.is_empty() && mg.in_check(board)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        fs,
+        path::Path,
+        sync::{Arc, OnceLock},
+    };
+
+    use super::{
+        table::{
+            tests::{
+                file_index, file_size, index, position, read_table, size, write_table, Subtable,
+            },
+            Material, Table, TableKind,
+        },
+        Tablebases, Wdl, MAX_DTZ,
+    };
+    use crate::{
+        board::{
+            defs::{Pieces, Sides},
+            Board,
+        },
+        dtm::{self, Dtm},
+        evaluation::kpk,
+        movegen::{defs::MoveType, movelist::MoveList, MoveGenerator},
+    };
+
+    /// The squares of the a1-d1-d4 triangle: every position can be mirrored so that the white
+    /// king is on one of them.
+    const TRIANGLE: [usize; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+    /// Tables written for the tests: KQvK (WDL and DTZ, computed by retrograde analysis), KNvK
+    /// (WDL) and KPvK (WDL, from the KPK bitbase).
+    ///
+    /// * `layout`: A table with the index of the KQvK positions.
+    /// * `dtm`: The number of plies to mate of the KQvK positions, by side to move and index;
+    ///   None for draws and illegal positions.
+    /// * `tablebases`: The tables.
+    struct TestTables {
+        layout: Table,
+        dtm: [Vec<Option<u16>>; Sides::BOTH],
+        tablebases: Arc<Tablebases>,
+    }
+
+    /// The tables, written to a temporary directory the first time they are needed.
+    fn test_tables() -> &'static TestTables {
+        static TABLES: OnceLock<TestTables> = OnceLock::new();
+        TABLES.get_or_init(|| {
+            let dir = std::env::temp_dir().join(format!("lark-syzygy-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let (layout, dtm) = write_tables(&dir);
+            let tablebases = Tablebases::open(dir.to_str().unwrap());
+
+            // Tables are read on first use, so read them before the directory is removed.
+            let mg = MoveGenerator::new();
+            let mut board = Board::new();
+            for fen in [
+                "8/8/8/8/8/2k5/8/KQ6 w - - 0 1",
+                "8/8/8/3k4/8/8/8/KN6 w - - 0 1",
+                "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1",
+            ] {
+                board.fen_read(Some(fen)).unwrap();
+                tablebases.probe_dtz(&mut board, &mg);
+            }
+            fs::remove_dir_all(&dir).ok();
+
+            TestTables {
+                layout,
+                dtm,
+                tablebases: Arc::new(tablebases),
+            }
+        })
+    }
+
+    /// The test tables, for the tests of the search.
+    pub fn tablebases() -> Arc<Tablebases> {
+        Arc::clone(&test_tables().tablebases)
+    }
+
+    fn write_tables(dir: &Path) -> (Table, [Vec<Option<u16>>; Sides::BOTH]) {
+        let kqvk = Material::parse("KQvK").unwrap();
+        let pieces = [6, 5, 14];
+        let placeholder = || Subtable {
+            flags: 0,
+            values: vec![0],
+        };
+        let layout = read_table(
+            TableKind::Wdl,
+            &kqvk,
+            &pieces,
+            &[placeholder(), placeholder()],
+            &[],
+        );
+        let dtm = solve_kqvk(&layout, &MoveGenerator::new());
+
+        // With White to move, every legal position is won.
+        let wdl = [
+            Subtable {
+                flags: 0,
+                values: vec![4],
+            },
+            Subtable {
+                flags: 0,
+                values: dtm[1]
+                    .iter()
+                    .map(|d| if d.is_some() { 0 } else { 2 })
+                    .collect(),
+            },
+        ];
+        let bytes = write_table(TableKind::Wdl, &kqvk, &pieces, &wdl, &[]);
+        fs::write(dir.join("KQvK.rtbw"), bytes).unwrap();
+
+        // Wins are stored in moves; the table only has White to move.
+        let dtz = Subtable {
+            flags: 0,
+            values: dtm[0]
+                .iter()
+                .map(|d| d.map_or(0, |d| (d - 1) / 2))
+                .collect(),
+        };
+        let bytes = write_table(TableKind::Dtz, &kqvk, &pieces, &[dtz], &[]);
+        fs::write(dir.join("KQvK.rtbz"), bytes).unwrap();
+
+        let knvk = Material::parse("KNvK").unwrap();
+        let draw = || Subtable {
+            flags: 0,
+            values: vec![2],
+        };
+        let bytes = write_table(TableKind::Wdl, &knvk, &[6, 2, 14], &[draw(), draw()], &[]);
+        fs::write(dir.join("KNvK.rtbw"), bytes).unwrap();
+
+        // Only the files of the leading pawn from a to d have subtables; the others mirror them.
+        let kpvk = Material::parse("KPvK").unwrap();
+        let pieces = [1, 6, 14];
+        let placeholders: Vec<_> = (0..8).map(|_| placeholder()).collect();
+        let layout_kpvk = read_table(TableKind::Wdl, &kpvk, &pieces, &placeholders, &[]);
+        let mut wdl: Vec<_> = (0..8)
+            .map(|i| Subtable {
+                flags: 0,
+                values: vec![2; file_size(&layout_kpvk, i % 2, i / 2)],
+            })
+            .collect();
+        let mg = MoveGenerator::new();
+        for side in [Sides::WHITE, Sides::BLACK] {
+            for king in 0..64 {
+                for pawn in (8..56).filter(|pawn| pawn % 8 < 4) {
+                    for other in 0..64 {
+                        let Some(board) = kpvk_position([king, pawn, other], side, &mg) else {
+                            continue;
+                        };
+                        let (stm, file, idx) = file_index(&layout_kpvk, &board, false).unwrap();
+                        wdl[file * 2 + stm].values[idx as usize] =
+                            match (kpk::probe(Sides::WHITE, king, pawn, other, side), side) {
+                                (false, _) => 2,
+                                (true, Sides::WHITE) => 4,
+                                (true, _) => 0,
+                            };
+                    }
+                }
+            }
+        }
+        let bytes = write_table(TableKind::Wdl, &kpvk, &pieces, &wdl, &[]);
+        fs::write(dir.join("KPvK.rtbw"), bytes).unwrap();
+
+        (layout, dtm)
+    }
+
+    /// A position with the given pieces, as (side, piece, square), or None if two of them are
+    /// on the same square or the side that is not to move is in check.
+    fn legal_position(
+        pieces: &[(usize, usize, usize)],
+        side_to_move: usize,
+        mg: &MoveGenerator,
+    ) -> Option<Board> {
+        for (i, &(.., square)) in pieces.iter().enumerate() {
+            if pieces[..i].iter().any(|&(.., other)| other == square) {
+                return None;
+            }
+        }
+        let opponent_in_check = mg.in_check(&position(pieces, side_to_move ^ 1));
+        (!opponent_in_check).then(|| position(pieces, side_to_move))
+    }
+
+    /// A KQvK position, or None if the side that is not to move is in check.
+    fn kqvk_position(
+        squares: [usize; 3],
+        side_to_move: usize,
+        mg: &MoveGenerator,
+    ) -> Option<Board> {
+        let [king, queen, other] = squares;
+        let pieces = [
+            (Sides::WHITE, Pieces::KING, king),
+            (Sides::WHITE, Pieces::QUEEN, queen),
+            (Sides::BLACK, Pieces::KING, other),
+        ];
+        legal_position(&pieces, side_to_move, mg)
+    }
+
+    /// A KPvK position, or None if the side that is not to move is in check.
+    fn kpvk_position(
+        squares: [usize; 3],
+        side_to_move: usize,
+        mg: &MoveGenerator,
+    ) -> Option<Board> {
+        let [king, pawn, other] = squares;
+        let pieces = [
+            (Sides::WHITE, Pieces::KING, king),
+            (Sides::WHITE, Pieces::PAWN, pawn),
+            (Sides::BLACK, Pieces::KING, other),
+        ];
+        legal_position(&pieces, side_to_move, mg)
+    }
+
+    /// Finds the number of plies to mate of every KQvK position by retrograde analysis. Black
+    /// draws when it can take the queen or is stalemated.
+    fn solve_kqvk(layout: &Table, mg: &MoveGenerator) -> [Vec<Option<u16>>; Sides::BOTH] {
+        let mut successors: [Vec<Option<Vec<usize>>>; Sides::BOTH] =
+            [vec![None; size(layout, 0)], vec![None; size(layout, 1)]];
+        let mut dtm = [vec![None; size(layout, 0)], vec![None; size(layout, 1)]];
+
+        for side in [Sides::WHITE, Sides::BLACK] {
+            for king in TRIANGLE {
+                for queen in 0..64 {
+                    for other in 0..64 {
+                        let Some(mut board) = kqvk_position([king, queen, other], side, mg) else {
+                            continue;
+                        };
+                        let (stm, idx) = index(layout, &board, false).unwrap();
+                        assert_eq!(stm, side);
+                        if successors[stm][idx as usize].is_some() {
+                            continue;
+                        }
+
+                        let mut legal = MoveList::new();
+                        mg.generate_legal_moves(&mut board, &mut legal, MoveType::All);
+                        if legal.iter().any(|mv| mv.is_capture()) {
+                            successors[stm][idx as usize] = Some(Vec::new());
+                            continue;
+                        }
+                        if legal.is_empty() && mg.in_check(&board) {
+                            dtm[stm][idx as usize] = Some(0);
+                        }
+
+                        let mut next = Vec::new();
+                        for &mv in legal.iter() {
+                            board.make_move(mv, mg);
+                            next.push(index(layout, &board, false).unwrap().1 as usize);
+                            board.unmake_move();
+                        }
+                        successors[stm][idx as usize] = Some(next);
+                    }
+                }
+            }
+        }
+
+        let mut ply: u16 = 1;
+        let mut last_change = 0;
+        while ply <= last_change + 2 {
+            let stm = usize::from((ply + 1) % 2);
+            for idx in 0..dtm[stm].len() {
+                let Some(next) = &successors[stm][idx] else {
+                    continue;
+                };
+                if dtm[stm][idx].is_some() || next.is_empty() {
+                    continue;
+                }
+                let mut next = next.iter().map(|&n| dtm[stm ^ 1][n]);
+                let solved = if stm == Sides::WHITE {
+                    next.any(|d| d == Some(ply - 1))
+                } else {
+                    next.all(|d| d.is_some())
+                };
+                if solved {
+                    dtm[stm][idx] = Some(ply);
+                    last_change = ply;
+                }
+            }
+            ply += 1;
+        }
+
+        dtm
+    }
+
+    #[test]
+    fn test_probe_wdl() {
+        let tables = test_tables();
+        let tablebases = &tables.tablebases;
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+
+        assert_eq!(tablebases.counts(), (3, 1));
+        assert_eq!(tablebases.max_pieces(), 3);
+
+        let positions = [
+            ("8/8/8/8/8/2k5/1Q6/K7 b - - 0 1", Some(Wdl::Loss)),
+            ("k7/1q6/2K5/8/8/8/8/8 w - - 0 1", Some(Wdl::Loss)),
+            ("8/8/8/8/8/8/1Qk5/7K b - - 0 1", Some(Wdl::Draw)),
+            ("k7/8/1QK5/8/8/8/8/8 b - - 0 1", Some(Wdl::Draw)),
+            ("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1", Some(Wdl::Loss)),
+            ("8/8/8/8/8/2k5/8/KQ6 w - - 0 1", Some(Wdl::Win)),
+            ("8/8/8/3k4/8/8/8/KN6 w - - 0 1", Some(Wdl::Draw)),
+            ("8/8/8/3kn3/8/8/8/K7 b - - 0 1", Some(Wdl::Draw)),
+            ("8/8/8/3k4/8/8/8/KR6 w - - 0 1", None),
+            ("8/8/8/3k4/8/8/8/K7 w - - 0 1", Some(Wdl::Draw)),
+            ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", Some(Wdl::Win)),
+            ("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1", Some(Wdl::Draw)),
+            ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", Some(Wdl::Loss)),
+            ("8/8/8/8/3p4/3k4/8/3K4 w - - 0 1", Some(Wdl::Loss)),
+        ];
+        for (fen, wdl) in positions {
+            board.fen_read(Some(fen)).unwrap();
+            assert_eq!(tablebases.probe_wdl(&mut board, &mg), wdl, "{fen}");
+        }
+
+        // Swapping the colors gives the same value.
+        for king in TRIANGLE {
+            for (queen, other) in [(5, 40), (17, 26), (44, 29), (63, 7)] {
+                let Some(mut board) = kqvk_position([king, queen, other], Sides::BLACK, &mg) else {
+                    continue;
+                };
+                let (_, idx) = index(&tables.layout, &board, false).unwrap();
+                let wdl = tablebases.probe_wdl(&mut board, &mg);
+                let expected =
+                    tables.dtm[Sides::BLACK][idx as usize].map_or(Wdl::Draw, |_| Wdl::Loss);
+                assert_eq!(wdl, Some(expected));
+
+                let pieces = [
+                    (Sides::BLACK, Pieces::KING, king ^ 56),
+                    (Sides::BLACK, Pieces::QUEEN, queen ^ 56),
+                    (Sides::WHITE, Pieces::KING, other ^ 56),
+                ];
+                let mut flipped = position(&pieces, Sides::WHITE);
+                assert_eq!(tablebases.probe_wdl(&mut flipped, &mg), wdl);
+            }
+        }
+    }
+
+    #[test]
+    fn test_probe_wdl_with_pawns() {
+        let tablebases = &test_tables().tablebases;
+        let mg = MoveGenerator::new();
+
+        let mut probed = 0;
+        for side in [Sides::WHITE, Sides::BLACK] {
+            for king in (0..64).step_by(3) {
+                for pawn in 8..56 {
+                    for other in (0..64).step_by(5) {
+                        let Some(mut board) = kpvk_position([king, pawn, other], side, &mg) else {
+                            continue;
+                        };
+                        let expected =
+                            match (kpk::probe(Sides::WHITE, king, pawn, other, side), side) {
+                                (false, _) => Wdl::Draw,
+                                (true, Sides::WHITE) => Wdl::Win,
+                                (true, _) => Wdl::Loss,
+                            };
+                        let wdl = tablebases.probe_wdl(&mut board, &mg);
+                        assert_eq!(wdl, Some(expected), "{king} {pawn} {other} {side}");
+
+                        // The same position with the colors swapped, so Black has the pawn.
+                        let pieces = [
+                            (Sides::BLACK, Pieces::KING, king ^ 56),
+                            (Sides::BLACK, Pieces::PAWN, pawn ^ 56),
+                            (Sides::WHITE, Pieces::KING, other ^ 56),
+                        ];
+                        let mut flipped = position(&pieces, side ^ 1);
+                        assert_eq!(tablebases.probe_wdl(&mut flipped, &mg), wdl);
+                        probed += 1;
+                    }
+                }
+            }
+        }
+        assert!(probed > 1000);
+    }
+
+    /// The positions with the given pieces, as (side, piece), and either side to move, taking
+    /// every `step`th placement of the pieces. Each comes with a description for failures.
+    fn sample_positions(
+        pieces: &[(usize, usize)],
+        step: usize,
+        mg: &MoveGenerator,
+    ) -> Vec<(Board, String)> {
+        let placements = 64usize.pow(pieces.len() as u32);
+        let mut positions = Vec::new();
+        for n in (0..placements).step_by(step) {
+            let squares = (0..pieces.len()).map(|i| n / 64usize.pow(i as u32) % 64);
+            let pieces: Vec<_> = pieces
+                .iter()
+                .zip(squares)
+                .map(|(&(side, piece), square)| (side, piece, square))
+                .collect();
+            let on_last_rank = |&(_, piece, square): &(usize, usize, usize)| {
+                piece == Pieces::PAWN && !(8..56).contains(&square)
+            };
+            if pieces.iter().any(on_last_rank) {
+                continue;
+            }
+            for side in [Sides::WHITE, Sides::BLACK] {
+                let position = legal_position(&pieces, side, mg);
+                positions.extend(position.map(|board| (board, format!("{pieces:?} {side}"))));
+            }
+        }
+        positions
+    }
+
+    /// Checks real tables, which are not kept with the sources. It is ignored unless asked for
+    /// with `cargo test -- --ignored`, and then `LARK_SYZYGY_PATH` must name a directory with
+    /// at least the KQvK, KRvK, KPvK and KRvKP tables and the ones KRvKP turns into. The
+    /// three-piece tables must agree with the KPK bitbase and with distances to mate found by
+    /// the DTM generator, and the value of a KRvKP position must be the best value of the
+    /// positions after its moves.
+    #[test]
+    #[ignore = "needs LARK_SYZYGY_PATH"]
+    fn test_real_tables() {
+        let path = std::env::var("LARK_SYZYGY_PATH").expect("LARK_SYZYGY_PATH is set");
+        let tablebases = Tablebases::open(&path);
+        let dtm_tables = dtm::tests::dtm_tables();
+        let mg = MoveGenerator::new();
+        let (white, black) = (Sides::WHITE, Sides::BLACK);
+
+        for material in [Pieces::QUEEN, Pieces::ROOK, Pieces::PAWN] {
+            let pieces = [
+                (white, Pieces::KING),
+                (white, material),
+                (black, Pieces::KING),
+            ];
+            for (mut board, name) in sample_positions(&pieces, 7, &mg) {
+                let wdl = tablebases.probe_wdl(&mut board, &mg);
+                let dtm = dtm_tables.probe(&board).unwrap();
+                let expected = match dtm {
+                    Dtm::Win(_) => Wdl::Win,
+                    Dtm::Loss(_) => Wdl::Loss,
+                    Dtm::Draw => Wdl::Draw,
+                };
+                assert_eq!(wdl, Some(expected), "{name}");
+
+                // Without pawns the only zeroing move that matters is the mate.
+                if material != Pieces::PAWN {
+                    let dtz = tablebases.probe_dtz(&mut board, &mg).unwrap();
+                    let plies = match dtm {
+                        Dtm::Win(plies) => i32::from(plies),
+                        Dtm::Loss(plies) => -i32::from(plies),
+                        Dtm::Draw => 0,
+                    };
+                    assert!(
+                        dtz.signum() == plies.signum() || plies == 0 && dtz == -1,
+                        "{name}"
+                    );
+                    assert!(
+                        (plies.abs()..=plies.abs() + 1).contains(&dtz.abs()),
+                        "{name}"
+                    );
+                }
+            }
+        }
+
+        let pieces = [
+            (white, Pieces::KING),
+            (white, Pieces::ROOK),
+            (black, Pieces::KING),
+            (black, Pieces::PAWN),
+        ];
+        let mut probed = 0;
+        for (mut board, name) in sample_positions(&pieces, 997, &mg) {
+            let wdl = tablebases.probe_wdl(&mut board, &mg).unwrap();
+            let mut legal = MoveList::new();
+            mg.generate_legal_moves(&mut board, &mut legal, MoveType::All);
+            let stalemate = legal.is_empty() && !mg.in_check(&board);
+            let mut best = if stalemate { 0 } else { -1 };
+            for &mv in legal.iter() {
+                board.make_move(mv, &mg);
+                let next = tablebases.probe_wdl(&mut board, &mg).unwrap();
+                board.unmake_move();
+                best = best.max(-(next as i8).signum());
+            }
+            assert_eq!((wdl as i8).signum(), best, "{name}");
+            probed += 1;
+        }
+        assert!(probed > 1000);
+    }
+
+    #[test]
+    fn test_probe_dtz() {
+        let tables = test_tables();
+        let mg = MoveGenerator::new();
+
+        let mut probed = 0;
+        for side in [Sides::WHITE, Sides::BLACK] {
+            for king in TRIANGLE {
+                for queen in (0..64).step_by(7) {
+                    for other in (0..64).step_by(5) {
+                        let Some(mut board) = kqvk_position([king, queen, other], side, &mg) else {
+                            continue;
+                        };
+                        let (_, idx) = index(&tables.layout, &board, false).unwrap();
+                        let dtm = tables.dtm[side][idx as usize].map(i32::from);
+                        let expected = match (side, dtm) {
+                            (Sides::WHITE, Some(dtm)) => dtm,
+                            (_, Some(dtm)) => -dtm.max(1),
+                            (_, None) => 0,
+                        };
+                        let dtz = tables.tablebases.probe_dtz(&mut board, &mg);
+                        assert_eq!(dtz, Some(expected), "{king} {queen} {other} {side}");
+                        probed += 1;
+                    }
+                }
+            }
+        }
+        assert!(probed > 1000);
+    }
+
+    #[test]
+    fn test_rank_root_moves() {
+        let tablebases = &test_tables().tablebases;
+        let mg = MoveGenerator::new();
+        let mut board = Board::new();
+
+        // Qc8 mates, Qc7 stalemates; the other wins take too long for the fifty-move rule.
+        board
+            .fen_read(Some("k7/8/1K6/8/8/8/8/2Q5 w - - 98 80"))
+            .unwrap();
+        let mut legal = MoveList::new();
+        mg.generate_legal_moves(&mut board, &mut legal, MoveType::All);
+        let moves: Vec<_> = legal.iter().copied().collect();
+        let mate = board.parse_uci_move("c1c8", &mg).unwrap();
+        let stalemate = board.parse_uci_move("c1c7", &mg).unwrap();
+        let rank = |ranks: &[i32], mv| ranks[moves.iter().position(|&m| m == mv).unwrap()];
+
+        let ranks = tablebases.rank_root_moves(&mut board, &mg, &moves).unwrap();
+        assert_eq!(rank(&ranks, mate), MAX_DTZ);
+        assert_eq!(rank(&ranks, stalemate), 0);
+        assert_eq!(ranks.iter().filter(|&&r| r == MAX_DTZ).count(), 1);
+        assert!(ranks.iter().all(|&r| r >= 0));
+
+        let ranks = tablebases
+            .rank_root_moves_wdl(&mut board, &mg, &moves)
+            .unwrap();
+        assert_eq!(rank(&ranks, mate), MAX_DTZ);
+        assert_eq!(rank(&ranks, stalemate), 0);
+    }
+}
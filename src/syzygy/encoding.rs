@@ -0,0 +1,243 @@
+// Tables used to turn a position into the index of its value in a Syzygy table. The index of a
+// table with pawns starts with the leading pawns; without pawns it starts with the kings, or
+// with three unique pieces. In both cases the board is mirrored first, so that the leading
+// piece is in a small part of the board.
+
+/// The maximum number of pieces in a table, including the kings.
+pub const TB_PIECES: usize = 7;
+
+/// `BINOMIAL[k][n]`: the number of ways to choose `k` squares out of `n`.
+pub const BINOMIAL: [[u64; 64]; TB_PIECES] = init_binomial();
+
+/// The number of squares available to the other pawns when the leading pawn is on a square.
+/// The leading pawn is the one with the highest value: closest to the edge, and on the lowest
+/// rank among pawns on the same file.
+pub const MAP_PAWNS: [usize; 64] = init_map_pawns();
+
+/// The index of the leading pawn group, by the number of leading pawns and the square of the
+/// leading pawn.
+pub const LEAD_PAWN_IDX: [[u64; 64]; TB_PIECES] = init_lead_pawns().0;
+
+/// The number of indices of the leading pawn group, by the number of leading pawns and the file
+/// (a to d) of the leading pawn.
+pub const LEAD_PAWNS_SIZE: [[u64; 4]; TB_PIECES] = init_lead_pawns().1;
+
+/// Numbers the 28 squares below the a1-h8 diagonal.
+pub const MAP_B1H1H7: [usize; 64] = init_map_b1h1h7();
+
+/// Numbers the 10 squares of the a1-d1-d4 triangle: first the 6 squares below the diagonal,
+/// then the 4 squares on it.
+pub const MAP_A1D1D4: [usize; 64] = init_map_a1d1d4();
+
+/// The index of the two kings, by the triangle number of the first king and the square of the
+/// second. There are 462 ways to place them; the ones with both kings on the diagonal are last.
+pub const MAP_KK: [[u64; 64]; 10] = init_map_kk();
+
+/// The number of ways to place the two kings.
+pub const KK_SIZE: u64 = 462;
+
+/// The number of ways to place three unique pieces, the first in the a1-d1-d4 triangle.
+pub const UNIQUE_SIZE: u64 = 31_332;
+
+/// The distance of a square above the a1-h8 diagonal: positive above, negative below.
+///
+/// * `square`: The square.
+pub const fn off_a1h8(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+/// Mirrors a square in the a1-h8 diagonal.
+///
+/// * `square`: The square.
+pub const fn flip_diagonal(square: usize) -> usize {
+    ((square >> 3) | (square << 3)) & 63
+}
+
+const fn init_binomial() -> [[u64; 64]; TB_PIECES] {
+    let mut binomial = [[0; 64]; TB_PIECES];
+    binomial[0][0] = 1;
+
+    let mut n = 1;
+    while n < 64 {
+        let mut k = 0;
+        while k < TB_PIECES && k <= n {
+            let with = if k > 0 { binomial[k - 1][n - 1] } else { 0 };
+            let without = if k < n { binomial[k][n - 1] } else { 0 };
+            binomial[k][n] = with + without;
+            k += 1;
+        }
+        n += 1;
+    }
+
+    binomial
+}
+
+const fn init_map_pawns() -> [usize; 64] {
+    let mut map = [0; 64];
+    let mut available: usize = 47;
+
+    let mut file = 0;
+    while file < 4 {
+        let mut rank = 1;
+        while rank < 7 {
+            let square = rank * 8 + file;
+            map[square] = available;
+            map[square ^ 7] = available - 1;
+            available = available.saturating_sub(2);
+            rank += 1;
+        }
+        file += 1;
+    }
+
+    map
+}
+
+const fn init_lead_pawns() -> ([[u64; 64]; TB_PIECES], [[u64; 4]; TB_PIECES]) {
+    let mut index = [[0; 64]; TB_PIECES];
+    let mut size = [[0; 4]; TB_PIECES];
+
+    let mut count = 1;
+    while count < TB_PIECES {
+        let mut file = 0;
+        while file < 4 {
+            let mut idx = 0;
+            let mut rank = 1;
+            while rank < 7 {
+                let square = rank * 8 + file;
+                index[count][square] = idx;
+                idx += BINOMIAL[count - 1][MAP_PAWNS[square]];
+                rank += 1;
+            }
+            size[count][file] = idx;
+            file += 1;
+        }
+        count += 1;
+    }
+
+    (index, size)
+}
+
+const fn init_map_b1h1h7() -> [usize; 64] {
+    let mut map = [0; 64];
+    let mut code = 0;
+    let mut square = 0;
+    while square < 64 {
+        if off_a1h8(square) < 0 {
+            map[square] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+    map
+}
+
+const fn init_map_a1d1d4() -> [usize; 64] {
+    let mut map = [0; 64];
+    let mut code = 0;
+
+    let mut square = 0;
+    while square <= 27 {
+        if off_a1h8(square) < 0 && square % 8 <= 3 {
+            map[square] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+
+    let mut square = 0;
+    while square <= 27 {
+        if off_a1h8(square) == 0 && square % 8 <= 3 {
+            map[square] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+
+    map
+}
+
+/// True when two squares are the same or next to each other.
+const fn kings_touch(a: usize, b: usize) -> bool {
+    (a % 8).abs_diff(b % 8) <= 1 && (a / 8).abs_diff(b / 8) <= 1
+}
+
+const fn init_map_kk() -> [[u64; 64]; 10] {
+    let mut map = [[0; 64]; 10];
+    let mut on_diagonal = [(0, 0); 64];
+    let mut diagonal_count = 0;
+    let mut code = 0;
+
+    let mut idx = 0;
+    while idx < 10 {
+        let mut first = 0;
+        while first <= 27 {
+            // Squares outside the triangle are also mapped to 0, so only b1 counts for it.
+            if MAP_A1D1D4[first] == idx && (idx != 0 || first == 1) {
+                let mut second = 0;
+                while second < 64 {
+                    if kings_touch(first, second) || (off_a1h8(first) == 0 && off_a1h8(second) > 0)
+                    {
+                        // Not legal, or a mirror of a position with the second king below.
+                    } else if off_a1h8(first) == 0 && off_a1h8(second) == 0 {
+                        on_diagonal[diagonal_count] = (idx, second);
+                        diagonal_count += 1;
+                    } else {
+                        map[idx][second] = code;
+                        code += 1;
+                    }
+                    second += 1;
+                }
+            }
+            first += 1;
+        }
+        idx += 1;
+    }
+
+    let mut i = 0;
+    while i < diagonal_count {
+        let (idx, second) = on_diagonal[i];
+        map[idx][second] = code;
+        code += 1;
+        i += 1;
+    }
+
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BINOMIAL, KK_SIZE, LEAD_PAWNS_SIZE, MAP_A1D1D4, MAP_B1H1H7, MAP_KK, MAP_PAWNS};
+
+    #[test]
+    fn test_encoding_tables() {
+        assert_eq!(BINOMIAL[2][62], 62 * 61 / 2);
+        assert_eq!(BINOMIAL[3][10], 120);
+
+        // a2 and h2 are the most valuable leading pawn squares, d7 and e7 the least.
+        assert_eq!((MAP_PAWNS[8], MAP_PAWNS[15]), (47, 46));
+        assert_eq!((MAP_PAWNS[51], MAP_PAWNS[52]), (1, 0));
+        let single: u64 = (0..4).map(|file| LEAD_PAWNS_SIZE[1][file]).sum();
+        assert_eq!(single, 24);
+
+        assert_eq!(MAP_B1H1H7[55], 27);
+        assert_eq!((MAP_A1D1D4[3], MAP_A1D1D4[0], MAP_A1D1D4[27]), (2, 6, 9));
+
+        // Every legal pair of kings has its own index.
+        let mut seen = vec![false; KK_SIZE as usize];
+        for (idx, row) in MAP_KK.iter().enumerate() {
+            let first = (0..28)
+                .find(|&square| MAP_A1D1D4[square] == idx && (idx != 0 || square == 1))
+                .unwrap();
+            for (second, &code) in row.iter().enumerate() {
+                let touching =
+                    (first % 8).abs_diff(second % 8) <= 1 && (first / 8).abs_diff(second / 8) <= 1;
+                let mirrored = first / 8 == first % 8 && second / 8 > second % 8;
+                if !touching && !mirrored {
+                    assert!(!seen[code as usize]);
+                    seen[code as usize] = true;
+                }
+            }
+        }
+        assert!(seen.iter().all(|&found| found));
+    }
+}
@@ -0,0 +1,1149 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    board::{
+        defs::{BitBoard, NrOf, Pieces, Sides},
+        Board,
+    },
+    utils::bits,
+};
+
+use super::{
+    encoding::{
+        flip_diagonal, off_a1h8, BINOMIAL, KK_SIZE, LEAD_PAWNS_SIZE, LEAD_PAWN_IDX, MAP_A1D1D4,
+        MAP_B1H1H7, MAP_KK, MAP_PAWNS, TB_PIECES, UNIQUE_SIZE,
+    },
+    Wdl,
+};
+
+/// The first bytes of a WDL and of a DTZ file.
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+/// The flags of the values of a table, for one side to move and one file of the leading pawn.
+///
+/// * `STM`: The side to move of a DTZ table: set for Black.
+/// * `MAPPED`: DTZ values are stored as indices into a list of distances per [`Wdl`] value.
+/// * `WIN_PLIES`, `LOSS_PLIES`: Distances of wins or losses are stored in plies, not moves.
+/// * `WIDE`: The lists of distances have 16-bit entries.
+/// * `SINGLE_VALUE`: All positions have the same value, and nothing else is stored.
+struct Flags;
+impl Flags {
+    const STM: u8 = 1;
+    const MAPPED: u8 = 2;
+    const WIN_PLIES: u8 = 4;
+    const LOSS_PLIES: u8 = 8;
+    const WIDE: u8 = 16;
+    const SINGLE_VALUE: u8 = 128;
+}
+
+/// The value a symbol in the binary tree has when it is a leaf.
+const LEAF: u16 = 0xfff;
+
+/// The size of an entry of the sparse index: a block number and an offset into the block.
+const SPARSE_ENTRY_SIZE: usize = 6;
+
+/// The lists of DTZ values for each [`Wdl`] value, by `Wdl as i8 + 2`. Wins and draws share
+/// the first list, as DTZ tables do not store draws.
+const WDL_TO_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+
+/// The two kinds of Syzygy files: `.rtbw` files have the win/draw/loss value of each position,
+/// `.rtbz` files the distance to a capture or pawn move (DTZ).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/// The pieces of a table, derived from its name, such as `KRPvKR`. The first side is called
+/// White in the table, but may be either side on the board.
+///
+/// * `counts`: The number of pieces of each type, for both sides of the name.
+/// * `piece_count`: The number of pieces, including the kings.
+/// * `has_pawns`: At least one side has pawns.
+/// * `has_unique_pieces`: A side has a piece (other than the king) that it has only once; then
+///   three pieces are encoded together.
+/// * `pawn_count`: The number of pawns of the side with the leading pawns, which is the side
+///   with fewer pawns, and of the other side.
+/// * `symmetric`: Both sides have the same pieces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Material {
+    pub counts: [[u8; NrOf::PIECE_TYPES]; Sides::BOTH],
+    pub piece_count: usize,
+    pub has_pawns: bool,
+    pub has_unique_pieces: bool,
+    pub pawn_count: [usize; 2],
+    pub symmetric: bool,
+}
+
+impl Material {
+    /// Reads the name of a table. Returns None if it is not a valid name: two groups of pieces
+    /// separated by `v`, each with one king first, at most [`TB_PIECES`] pieces in total.
+    ///
+    /// * `name`: The name, such as `KQvK`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; NrOf::PIECE_TYPES]; Sides::BOTH];
+
+        for (side, part) in [white, black].into_iter().enumerate() {
+            if !part.starts_with('K') {
+                return None;
+            }
+            for c in part.chars() {
+                let piece = "KQRBNP".find(c)?;
+                counts[side][piece] += 1;
+            }
+            if counts[side][Pieces::KING] != 1 {
+                return None;
+            }
+        }
+
+        let piece_count: usize = counts.iter().flatten().map(|&n| n as usize).sum();
+        if piece_count > TB_PIECES {
+            return None;
+        }
+
+        let pawns = [
+            counts[Sides::WHITE][Pieces::PAWN] as usize,
+            counts[Sides::BLACK][Pieces::PAWN] as usize,
+        ];
+        let white_leads = pawns[1] == 0 || (pawns[0] > 0 && pawns[1] >= pawns[0]);
+        let has_unique_pieces = counts.iter().any(|side| side[Pieces::QUEEN..].contains(&1));
+
+        Some(Self {
+            counts,
+            piece_count,
+            has_pawns: pawns[0] + pawns[1] > 0,
+            has_unique_pieces,
+            pawn_count: if white_leads {
+                pawns
+            } else {
+                [pawns[1], pawns[0]]
+            },
+            symmetric: counts[0] == counts[1],
+        })
+    }
+}
+
+/// The information needed to find a value in one of the subtables of a file: one for each side
+/// to move, and for tables with pawns one for each file (a to d) of the leading pawn.
+///
+/// The values are compressed with Huffman codes of symbols, where a symbol stands for a single
+/// value or for a pair of other symbols (Re-Pair compression). The codes are stored in blocks of
+/// a fixed size, and the sparse index points to a block for every `span` values.
+///
+/// * `flags`: See [`Flags`].
+/// * `min_sym_len`: The length of the shortest code; for a single value table, the value.
+/// * `block_size`: The size of a block, in bytes.
+/// * `span`: The number of values between two entries of the sparse index.
+/// * `num_blocks`: The number of blocks.
+/// * `lowest_sym`: Where the lowest symbol of each code length is stored, from the shortest.
+/// * `btree`: Where the two symbols that each symbol stands for are stored.
+/// * `block_length`: Where the number of values in each block (minus one) is stored.
+/// * `block_length_size`: The number of entries of `block_length`.
+/// * `sparse_index`: Where the sparse index is stored.
+/// * `sparse_index_size`: The number of entries of the sparse index.
+/// * `data`: Where the blocks start.
+/// * `base64`: The lowest code of each length, from the shortest, aligned to 64 bits.
+/// * `symlen`: The number of values each symbol stands for, minus one.
+/// * `pieces`: The pieces in the order in which they are encoded, as numbered in the file.
+/// * `group_idx`: The factor of each group of pieces in the index; the last one is the size.
+/// * `group_len`: The number of pieces in each group, ending with 0.
+/// * `map_idx`: Where the DTZ values for each [`Wdl`] value start (see [`WDL_TO_MAP`]).
+#[derive(Clone, Debug, Default)]
+struct PairsData {
+    flags: u8,
+    min_sym_len: u8,
+    block_size: usize,
+    span: u64,
+    num_blocks: usize,
+    lowest_sym: usize,
+    btree: usize,
+    block_length: usize,
+    block_length_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u32>,
+    pieces: [u8; TB_PIECES],
+    group_idx: [u64; TB_PIECES + 1],
+    group_len: [usize; TB_PIECES + 1],
+    map_idx: [usize; 4],
+}
+
+/// What a probe of a table found.
+///
+/// * `Value`: For a WDL table the [`Wdl`] value as a number from -2 to 2; for a DTZ table the
+///   distance to zeroing in plies, which may be one too high (see [`super::Tablebases`]).
+/// * `ChangeStm`: The DTZ table only has the positions with the other side to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableProbe {
+    Value(i32),
+    ChangeStm,
+}
+
+/// A Syzygy table file, read into memory.
+///
+/// * `kind`: WDL or DTZ.
+/// * `material`: The pieces of the table.
+/// * `bytes`: The contents of the file.
+/// * `items`: The subtables, by side to move and file of the leading pawn.
+pub struct Table {
+    kind: TableKind,
+    material: Material,
+    bytes: Vec<u8>,
+    items: Vec<Vec<PairsData>>,
+}
+
+impl Table {
+    /// Reads a table file.
+    ///
+    /// * `path`: The path of the file.
+    /// * `kind`: The kind of table the file holds.
+    /// * `material`: The pieces of the table, from the name of the file.
+    pub fn open(path: &Path, kind: TableKind, material: Material) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        Self::from_bytes(bytes, kind, material).ok_or_else(|| {
+            let message = format!("Invalid tablebase file: {}", path.display());
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })
+    }
+
+    /// Reads a table from the contents of its file. Returns None when the file is not valid.
+    ///
+    /// * `bytes`: The contents of the file.
+    /// * `kind`: The kind of table the file holds.
+    /// * `material`: The pieces of the table.
+    pub fn from_bytes(bytes: Vec<u8>, kind: TableKind, material: Material) -> Option<Self> {
+        let mut table = Self {
+            kind,
+            material,
+            bytes,
+            items: Vec::new(),
+        };
+        table.items = table.read_layout()?;
+        Some(table)
+    }
+
+    /// Finds the value of a position. The side of the board that has the first group of pieces
+    /// of the table name is the table's White; for a symmetric table that is the side to move.
+    ///
+    /// * `board`: The position, with exactly the pieces of the table.
+    /// * `black_stronger`: Black has the first group of pieces of the name.
+    /// * `wdl`: For a DTZ table, the WDL value of the position, used to decode the distance.
+    pub fn probe(&self, board: &Board, black_stronger: bool, wdl: Wdl) -> Option<TableProbe> {
+        let (stm, file, idx) = match self.encode(board, black_stronger)? {
+            Encoded::Index { stm, file, idx } => (stm, file, idx),
+            Encoded::ChangeStm => return Some(TableProbe::ChangeStm),
+        };
+        let value = self.decompress(&self.items[stm][file], idx)?;
+
+        Some(TableProbe::Value(match self.kind {
+            TableKind::Wdl => i32::from(value) - 2,
+            TableKind::Dtz => self.map_dtz(file, i32::from(value), wdl)?,
+        }))
+    }
+
+    /// The subtable for a side to move and file, the one for White if there is only one.
+    fn item(&self, stm: usize, file: usize) -> &PairsData {
+        &self.items[stm % self.items.len()][file]
+    }
+
+    /// Computes the subtable and the index of a position.
+    ///
+    /// * `board`: The position.
+    /// * `black_stronger`: Black has the first group of pieces of the name.
+    fn encode(&self, board: &Board, black_stronger: bool) -> Option<Encoded> {
+        let material = &self.material;
+        let symmetric_black = material.symmetric && board.current_side() == Sides::BLACK;
+        let flip = symmetric_black || black_stronger;
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = usize::from(flip) ^ board.current_side();
+
+        let mut squares = [0; TB_PIECES];
+        let mut pieces = [0; TB_PIECES];
+        let mut size = 0;
+        let mut lead_pawns: BitBoard = 0;
+        let mut lead_count = 0;
+        let mut file = 0;
+
+        // The leading pawns are those of the color of the first piece of the table. The one with
+        // the highest MAP_PAWNS value decides the subtable.
+        if material.has_pawns {
+            let color = usize::from((self.item(0, 0).pieces[0] ^ flip_color) >> 3);
+            lead_pawns = board.bb_pieces[color][Pieces::PAWN];
+            let mut bb = lead_pawns;
+            while bb > 0 && size < TB_PIECES {
+                squares[size] = bits::next(&mut bb) ^ flip_squares;
+                size += 1;
+            }
+            lead_count = size;
+
+            let lead = (0..lead_count).max_by_key(|&i| MAP_PAWNS[squares[i]])?;
+            squares.swap(0, lead);
+            file = (squares[0] % 8).min(7 - squares[0] % 8);
+        }
+
+        if self.kind == TableKind::Dtz {
+            let flags = self.item(stm, file).flags;
+            let stored = usize::from(flags & Flags::STM);
+            // A symmetric table without pawns was already turned to the stored side to move.
+            if stored != stm && (!material.symmetric || material.has_pawns) {
+                return Some(Encoded::ChangeStm);
+            }
+        }
+
+        let mut bb = (board.bb_side[Sides::WHITE] | board.bb_side[Sides::BLACK]) & !lead_pawns;
+        while bb > 0 {
+            let square = bits::next(&mut bb);
+            let (piece, side) = board.get_piece_on_square(square).ok()?;
+            if size == TB_PIECES {
+                return None;
+            }
+            squares[size] = square ^ flip_squares;
+            pieces[size] = ((NrOf::PIECE_TYPES - piece) as u8 | (side as u8) << 3) ^ flip_color;
+            size += 1;
+        }
+        if size != material.piece_count {
+            return None;
+        }
+
+        // Put the pieces in the order of the table.
+        let d = self.item(stm, file);
+        for i in lead_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| d.pieces[i] == pieces[j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // Mirror the board so that the leading piece is on files a to d.
+        if squares[0] % 8 > 3 {
+            squares[..size].iter_mut().for_each(|square| *square ^= 7);
+        }
+
+        let mut idx = if material.has_pawns {
+            let mut idx = LEAD_PAWN_IDX[lead_count][squares[0]];
+            squares[1..lead_count].sort_by_key(|&square| MAP_PAWNS[square]);
+            for (i, &square) in squares.iter().enumerate().take(lead_count).skip(1) {
+                idx += BINOMIAL[i][MAP_PAWNS[square]];
+            }
+            idx
+        } else {
+            // Without pawns, also mirror the ranks, and the diagonal if the first piece of the
+            // leading group that is not on the a1-h8 diagonal is above it.
+            if squares[0] / 8 > 3 {
+                squares[..size].iter_mut().for_each(|square| *square ^= 56);
+            }
+            if let Some(i) = (0..d.group_len[0]).find(|&i| off_a1h8(squares[i]) != 0) {
+                if off_a1h8(squares[i]) > 0 {
+                    squares[i..size]
+                        .iter_mut()
+                        .for_each(|square| *square = flip_diagonal(*square));
+                }
+            }
+
+            if material.has_unique_pieces {
+                encode_unique(&squares)
+            } else {
+                MAP_KK[MAP_A1D1D4[squares[0]]][squares[1]]
+            }
+        };
+
+        // The other groups: each is a combination of squares that are not taken by the pieces
+        // of the groups before it. Pawns can not be on the first and last rank.
+        idx *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut next = 1;
+
+        while d.group_len[next] > 0 {
+            let end = start + d.group_len[next];
+            squares[start..end].sort_unstable();
+
+            let mut n = 0;
+            for i in start..end {
+                let square = squares[i];
+                let taken = squares[..start].iter().filter(|&&s| square > s).count();
+                let free = (square - taken).checked_sub(if remaining_pawns { 8 } else { 0 })?;
+                n += BINOMIAL[i - start + 1][free];
+            }
+
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start = end;
+            next += 1;
+        }
+
+        Some(Encoded::Index { stm, file, idx })
+    }
+
+    /// Finds the value at an index of a subtable.
+    ///
+    /// * `d`: The subtable.
+    /// * `idx`: The index of the value.
+    fn decompress(&self, d: &PairsData, idx: u64) -> Option<u16> {
+        if d.flags & Flags::SINGLE_VALUE > 0 {
+            return Some(u16::from(d.min_sym_len));
+        }
+
+        // The sparse index points to the block and offset of the value in the middle of each
+        // span; from there, walk to the block that holds the value.
+        let entry = d.sparse_index + (idx / d.span) as usize * SPARSE_ENTRY_SIZE;
+        let mut block = i64::from(self.u32_le(entry)?);
+        let mut offset = i64::from(self.u16_le(entry + 4)?);
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+
+        let block_length = |block: i64| -> Option<i64> {
+            let block = usize::try_from(block)
+                .ok()
+                .filter(|&b| b < d.block_length_size)?;
+            Some(i64::from(self.u16_le(d.block_length + 2 * block)?))
+        };
+        while offset < 0 {
+            block -= 1;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        // Read the codes of the block until reaching the symbol that holds the value.
+        let mut ptr = d.data + block as usize * d.block_size;
+        let mut buffer = self.u64_be(ptr);
+        let mut buffer_size = 64;
+        ptr += 8;
+
+        let min_len = usize::from(d.min_sym_len);
+        let mut sym = loop {
+            let len = d.base64.iter().position(|&base| buffer >= base)?;
+            let code = ((buffer - d.base64[len]) >> (64 - len - min_len)) as usize;
+            let sym = code + usize::from(self.u16_le(d.lowest_sym + 2 * len)?);
+            let values = i64::from(*d.symlen.get(sym)?) + 1;
+
+            if offset < values {
+                break sym;
+            }
+            offset -= values;
+            buffer <<= len + min_len;
+            buffer_size -= len + min_len;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= u64::from(self.u32_be(ptr)) << (64 - buffer_size);
+                ptr += 4;
+            }
+        };
+
+        // Expand the symbol into the pair it stands for, until a single value is left.
+        while d.symlen[sym] > 0 {
+            let (left, right) = self.btree(d, sym)?;
+            let left_values = i64::from(*d.symlen.get(usize::from(left))?) + 1;
+            if offset < left_values {
+                sym = usize::from(left);
+            } else {
+                offset -= left_values;
+                sym = usize::from(right);
+            }
+        }
+
+        Some(self.btree(d, sym)?.0)
+    }
+
+    /// Turns a value of a DTZ table into a distance in plies.
+    ///
+    /// * `file`: The file of the leading pawn.
+    /// * `value`: The value in the table.
+    /// * `wdl`: The WDL value of the position.
+    fn map_dtz(&self, file: usize, mut value: i32, wdl: Wdl) -> Option<i32> {
+        let d = self.item(0, file);
+        if d.flags & Flags::MAPPED > 0 {
+            let start = d.map_idx[WDL_TO_MAP[(wdl as i8 + 2) as usize]];
+            value = if d.flags & Flags::WIDE > 0 {
+                i32::from(self.u16_le(start + 2 * value as usize)?)
+            } else {
+                i32::from(*self.bytes.get(start + value as usize)?)
+            };
+        }
+
+        let in_moves = match wdl {
+            Wdl::Win => d.flags & Flags::WIN_PLIES == 0,
+            Wdl::Loss => d.flags & Flags::LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+
+        Some(value + 1)
+    }
+
+    /// Reads where everything is stored in the file, and which pieces are in each subtable.
+    fn read_layout(&self) -> Option<Vec<Vec<PairsData>>> {
+        let material = &self.material;
+        let magic = match self.kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if self.bytes.get(..4)? != magic {
+            return None;
+        }
+
+        const HAS_PAWNS: u8 = 2;
+        if (self.byte(4)? & HAS_PAWNS > 0) != material.has_pawns {
+            return None;
+        }
+
+        let sides = match self.kind {
+            TableKind::Wdl if !material.symmetric => 2,
+            _ => 1,
+        };
+        let files = if material.has_pawns { 4 } else { 1 };
+        let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut items = vec![vec![PairsData::default(); files]; sides];
+        let mut pos = 5;
+
+        for file in 0..files {
+            let first = self.byte(pos)?;
+            let second = if both_pawns {
+                self.byte(pos + 1)?
+            } else {
+                0xff
+            };
+            let order = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            pos += 1 + usize::from(both_pawns);
+
+            for k in 0..material.piece_count {
+                let pieces = self.byte(pos)?;
+                for (side, item) in items.iter_mut().enumerate() {
+                    item[file].pieces[k] = if side == 1 { pieces >> 4 } else { pieces & 0xf };
+                }
+                pos += 1;
+            }
+
+            for (side, item) in items.iter_mut().enumerate() {
+                set_groups(material, &mut item[file], order[side], file)?;
+            }
+        }
+        pos += pos & 1;
+
+        for file in 0..files {
+            for item in items.iter_mut() {
+                pos = self.read_sizes(&mut item[file], pos)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            pos = self.read_dtz_map(&mut items[0], pos)?;
+        }
+
+        for file in 0..files {
+            for item in items.iter_mut() {
+                item[file].sparse_index = pos;
+                pos += item[file].sparse_index_size * SPARSE_ENTRY_SIZE;
+            }
+        }
+        for file in 0..files {
+            for item in items.iter_mut() {
+                item[file].block_length = pos;
+                pos += item[file].block_length_size * 2;
+            }
+        }
+        for file in 0..files {
+            for item in items.iter_mut() {
+                let d = &mut item[file];
+                pos = (pos + 0x3f) & !0x3f;
+                d.data = pos;
+                pos += d.num_blocks * d.block_size;
+                if d.num_blocks > 0 && pos > self.bytes.len() {
+                    return None;
+                }
+            }
+        }
+
+        Some(items)
+    }
+
+    /// Reads the sizes and the Huffman code of a subtable, and returns where the next subtable
+    /// starts.
+    ///
+    /// * `d`: The subtable.
+    /// * `pos`: Where its sizes are stored.
+    fn read_sizes(&self, d: &mut PairsData, mut pos: usize) -> Option<usize> {
+        d.flags = self.byte(pos)?;
+        if d.flags & Flags::SINGLE_VALUE > 0 {
+            d.min_sym_len = self.byte(pos + 1)?;
+            return Some(pos + 2);
+        }
+
+        let groups = d.group_len.iter().position(|&len| len == 0)?;
+        let size = d.group_idx[groups];
+        let block_bits = self.byte(pos + 1)?;
+        let span_bits = self.byte(pos + 2)?;
+        if block_bits > 31 || span_bits > 31 {
+            return None;
+        }
+        d.block_size = 1 << block_bits;
+        d.span = 1 << span_bits;
+        d.sparse_index_size = size.div_ceil(d.span) as usize;
+        let padding = usize::from(self.byte(pos + 3)?);
+        d.num_blocks = self.u32_le(pos + 4)? as usize;
+        d.block_length_size = d.num_blocks + padding;
+
+        let max_sym_len = self.byte(pos + 8)?;
+        d.min_sym_len = self.byte(pos + 9)?;
+        if d.min_sym_len == 0 || max_sym_len < d.min_sym_len || max_sym_len > 32 {
+            return None;
+        }
+        pos += 10;
+        d.lowest_sym = pos;
+
+        // The codes of the longest length start at 0. All codes of one length are consecutive,
+        // so the lowest code of the next shorter length follows from the number of codes.
+        let lengths = usize::from(max_sym_len - d.min_sym_len) + 1;
+        d.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = self.u16_le(d.lowest_sym + 2 * i)?;
+            let next = self.u16_le(d.lowest_sym + 2 * (i + 1))?;
+            let codes = u64::from(lowest).wrapping_sub(u64::from(next));
+            d.base64[i] = d.base64[i + 1].wrapping_add(codes) / 2;
+        }
+        for (i, base) in d.base64.iter_mut().enumerate() {
+            *base <<= 64 - i - usize::from(d.min_sym_len);
+        }
+        pos += 2 * lengths;
+
+        let symbols = usize::from(self.u16_le(pos)?);
+        pos += 2;
+        d.btree = pos;
+        d.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                d.symlen[sym] = self.symlen(d, sym, &mut visited)?;
+            }
+        }
+
+        Some(pos + 3 * symbols + (symbols & 1))
+    }
+
+    /// Computes how many values (minus one) a symbol stands for, and does the same for the
+    /// symbols it is made of.
+    fn symlen(&self, d: &mut PairsData, sym: usize, visited: &mut [bool]) -> Option<u32> {
+        visited[sym] = true;
+        let (left, right) = self.btree(d, sym)?;
+        if right == LEAF {
+            return Some(0);
+        }
+
+        let (left, right) = (usize::from(left), usize::from(right));
+        for child in [left, right] {
+            if !*visited.get(child)? {
+                d.symlen[child] = self.symlen(d, child, visited)?;
+            }
+        }
+        Some(d.symlen[left] + d.symlen[right] + 1)
+    }
+
+    /// Reads the lists that DTZ values are mapped to, and returns where the sparse indices
+    /// start.
+    ///
+    /// * `items`: The subtables, one for each file.
+    /// * `pos`: Where the lists start.
+    fn read_dtz_map(&self, items: &mut [PairsData], mut pos: usize) -> Option<usize> {
+        for d in items.iter_mut().filter(|d| d.flags & Flags::MAPPED > 0) {
+            if d.flags & Flags::WIDE > 0 {
+                pos += pos & 1;
+                for map_idx in d.map_idx.iter_mut() {
+                    *map_idx = pos + 2;
+                    pos += 2 * usize::from(self.u16_le(pos)?) + 2;
+                }
+            } else {
+                for map_idx in d.map_idx.iter_mut() {
+                    *map_idx = pos + 1;
+                    pos += usize::from(self.byte(pos)?) + 1;
+                }
+            }
+        }
+
+        Some(pos + (pos & 1))
+    }
+
+    /// The two symbols a symbol stands for; for a leaf, the value and [`LEAF`].
+    fn btree(&self, d: &PairsData, sym: usize) -> Option<(u16, u16)> {
+        let bytes = self.bytes.get(d.btree + 3 * sym..d.btree + 3 * sym + 3)?;
+        let left = u16::from(bytes[1] & 0xf) << 8 | u16::from(bytes[0]);
+        let right = u16::from(bytes[2]) << 4 | u16::from(bytes[1] >> 4);
+        Some((left, right))
+    }
+
+    fn byte(&self, pos: usize) -> Option<u8> {
+        self.bytes.get(pos).copied()
+    }
+
+    fn u16_le(&self, pos: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(
+            self.bytes.get(pos..pos + 2)?.try_into().ok()?,
+        ))
+    }
+
+    fn u32_le(&self, pos: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(
+            self.bytes.get(pos..pos + 4)?.try_into().ok()?,
+        ))
+    }
+
+    /// Reads compressed data, which may run past the end of the file in the last block.
+    fn u32_be(&self, pos: usize) -> u32 {
+        (0..4).fold(0, |value, i| {
+            value << 8 | u32::from(self.byte(pos + i).unwrap_or(0))
+        })
+    }
+
+    fn u64_be(&self, pos: usize) -> u64 {
+        u64::from(self.u32_be(pos)) << 32 | u64::from(self.u32_be(pos + 4))
+    }
+}
+
+/// Where a position is stored in a table.
+///
+/// * `Index`: The side to move and file of the subtable, and the index in it.
+/// * `ChangeStm`: The DTZ table only has the positions with the other side to move.
+enum Encoded {
+    Index { stm: usize, file: usize, idx: u64 },
+    ChangeStm,
+}
+
+/// Encodes three unique pieces together, after mirroring has put the first one in the a1-d1-d4
+/// triangle and the first one that is not on the a1-h8 diagonal below it.
+///
+/// * `squares`: The squares of the pieces in the order of the table.
+fn encode_unique(squares: &[usize; TB_PIECES]) -> u64 {
+    let [s0, s1, s2] = [squares[0], squares[1], squares[2]];
+    let adjust1 = usize::from(s1 > s0);
+    let adjust2 = usize::from(s2 > s0) + usize::from(s2 > s1);
+    let idx = if off_a1h8(s0) != 0 {
+        (MAP_A1D1D4[s0] * 63 + s1 - adjust1) * 62 + s2 - adjust2
+    } else if off_a1h8(s1) != 0 {
+        (6 * 63 + (s0 / 8) * 28 + MAP_B1H1H7[s1]) * 62 + s2 - adjust2
+    } else if off_a1h8(s2) != 0 {
+        6 * 63 * 62 + 4 * 28 * 62 + (s0 / 8) * 7 * 28 + (s1 / 8 - adjust1) * 28 + MAP_B1H1H7[s2]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + (s0 / 8) * 7 * 6
+            + (s1 / 8 - adjust1) * 6
+            + (s2 / 8 - adjust2)
+    };
+    idx as u64
+}
+
+/// Divides the pieces of a subtable into groups that are encoded together, and computes the
+/// factor of each group in the index. The leading group is the leading pawns, or without pawns
+/// three unique pieces or the two kings; the other groups are pieces of the same type and color.
+///
+/// * `material`: The pieces of the table.
+/// * `d`: The subtable, with its pieces set.
+/// * `order`: The position of the leading group and of the other side's pawns in the index.
+/// * `file`: The file of the leading pawn.
+fn set_groups(material: &Material, d: &mut PairsData, order: [u8; 2], file: usize) -> Option<()> {
+    let mut first_len = match (material.has_pawns, material.has_unique_pieces) {
+        (true, _) => 0,
+        (false, true) => 3,
+        (false, false) => 2,
+    };
+    let mut n = 0;
+    d.group_len[0] = 1;
+    for i in 1..material.piece_count {
+        first_len -= 1;
+        if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+            d.group_len[n] += 1;
+        } else {
+            n += 1;
+            d.group_len[n] = 1;
+        }
+    }
+    n += 1;
+    d.group_len[n] = 0;
+
+    let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+    let mut next = if both_pawns { 2 } else { 1 };
+    let mut free = 64 - d.group_len[0] - if both_pawns { d.group_len[1] } else { 0 };
+    let mut idx: u64 = 1;
+
+    let mut k = 0;
+    while next < n || k == order[0] || k == order[1] {
+        if k == order[0] {
+            d.group_idx[0] = idx;
+            idx *= match (material.has_pawns, material.has_unique_pieces) {
+                (true, _) => LEAD_PAWNS_SIZE[d.group_len[0]][file],
+                (false, true) => UNIQUE_SIZE,
+                (false, false) => KK_SIZE,
+            };
+        } else if k == order[1] {
+            d.group_idx[1] = idx;
+            idx *= BINOMIAL[d.group_len[1]][48 - d.group_len[0]];
+        } else {
+            d.group_idx[next] = idx;
+            idx *= BINOMIAL[d.group_len[next]][free];
+            free -= d.group_len[next];
+            next += 1;
+        }
+
+        k += 1;
+        if k > 0xf {
+            return None;
+        }
+    }
+    d.group_idx[n] = idx;
+
+    Some(())
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::{cmp::Reverse, collections::BinaryHeap};
+
+    use crate::board::{
+        defs::{Pieces, Sides},
+        Board,
+    };
+
+    use super::{Encoded, Material, Table, TableKind, TableProbe, DTZ_MAGIC, WDL_MAGIC};
+    use crate::syzygy::Wdl;
+
+    /// The values of a subtable to write, and its flags. A single value is written as a
+    /// single value table.
+    pub struct Subtable {
+        pub flags: u8,
+        pub values: Vec<u16>,
+    }
+
+    /// The index of a position in a table, with the side to move of the subtable.
+    pub fn index(table: &Table, board: &Board, black_stronger: bool) -> Option<(usize, u64)> {
+        file_index(table, board, black_stronger).map(|(stm, _, idx)| (stm, idx))
+    }
+
+    /// The index of a position in a table, with the side to move and the file of the leading
+    /// pawn of the subtable.
+    pub fn file_index(
+        table: &Table,
+        board: &Board,
+        black_stronger: bool,
+    ) -> Option<(usize, usize, u64)> {
+        match table.encode(board, black_stronger)? {
+            Encoded::Index { stm, file, idx } => Some((stm, file, idx)),
+            Encoded::ChangeStm => None,
+        }
+    }
+
+    /// The number of values of a subtable.
+    pub fn size(table: &Table, stm: usize) -> usize {
+        file_size(table, stm, 0)
+    }
+
+    /// The number of values of a subtable of a table with pawns.
+    pub fn file_size(table: &Table, stm: usize, file: usize) -> usize {
+        let d = table.item(stm, file);
+        let groups = d.group_len.iter().position(|&len| len == 0).unwrap();
+        d.group_idx[groups] as usize
+    }
+
+    /// Writes a table with the pieces in the given order for all subtables and the leading
+    /// group first. The values are Huffman coded without pairs, in blocks of 32 bytes.
+    ///
+    /// * `kind`: WDL or DTZ.
+    /// * `material`: The pieces of the table; only the pieces of one side can be pawns.
+    /// * `pieces`: The pieces as numbered in the file, in the order of the index.
+    /// * `subtables`: One subtable for each side to move, or one for a DTZ table, and with
+    ///   pawns that for each file of the leading pawn in turn.
+    /// * `dtz_map`: For a mapped DTZ table, the four lists of distances.
+    pub fn write_table(
+        kind: TableKind,
+        material: &Material,
+        pieces: &[u8],
+        subtables: &[Subtable],
+        dtz_map: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let mut out = match kind {
+            TableKind::Wdl => WDL_MAGIC.to_vec(),
+            TableKind::Dtz => DTZ_MAGIC.to_vec(),
+        };
+        out.push(u8::from(!material.symmetric) | u8::from(material.has_pawns) << 1);
+        let files = if material.has_pawns { 4 } else { 1 };
+        for _ in 0..files {
+            out.push(0);
+            out.extend(pieces.iter().map(|&piece| piece | piece << 4));
+        }
+        pad(&mut out, 2);
+
+        let compressed: Vec<_> = subtables.iter().map(compress).collect();
+        for (header, ..) in &compressed {
+            out.extend(header);
+        }
+        if kind == TableKind::Dtz {
+            for list in dtz_map {
+                out.push(list.len() as u8);
+                out.extend(list);
+            }
+            pad(&mut out, 2);
+        }
+        for (_, sparse, ..) in &compressed {
+            out.extend(sparse);
+        }
+        for (_, _, lengths, _) in &compressed {
+            out.extend(lengths);
+        }
+        for (.., blocks) in &compressed {
+            pad(&mut out, 64);
+            out.extend(blocks);
+        }
+        out
+    }
+
+    /// Reads a table that was written with [`write_table`].
+    pub fn read_table(
+        kind: TableKind,
+        material: &Material,
+        pieces: &[u8],
+        subtables: &[Subtable],
+        dtz_map: &[Vec<u8>],
+    ) -> Table {
+        let bytes = write_table(kind, material, pieces, subtables, dtz_map);
+        Table::from_bytes(bytes, kind, *material).unwrap()
+    }
+
+    fn pad(out: &mut Vec<u8>, alignment: usize) {
+        out.resize(out.len().next_multiple_of(alignment), 0);
+    }
+
+    /// Compresses the values of a subtable into its header, sparse index, block lengths and
+    /// blocks.
+    fn compress(subtable: &Subtable) -> (Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>) {
+        const BLOCK_BITS: u8 = 5;
+        const SPAN_BITS: u8 = 6;
+        let values = &subtable.values;
+
+        let mut symbols: Vec<u16> = values.clone();
+        symbols.sort_unstable();
+        symbols.dedup();
+        if symbols.len() == 1 {
+            let header = vec![subtable.flags | 0x80, symbols[0] as u8];
+            return (header, Vec::new(), Vec::new(), Vec::new());
+        }
+
+        // Huffman code lengths, from a tree built by merging the two rarest nodes.
+        let count = |value: u16| values.iter().filter(|&&v| v == value).count() as u64;
+        let mut parents = vec![usize::MAX; symbols.len()];
+        let mut heap: BinaryHeap<_> = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| Reverse((count(value), i)))
+            .collect();
+        while let (Some(Reverse((a, i))), Some(Reverse((b, j)))) = (heap.pop(), heap.pop()) {
+            let node = parents.len();
+            parents.push(usize::MAX);
+            parents[i] = node;
+            parents[j] = node;
+            heap.push(Reverse((a + b, node)));
+        }
+        let depth = |mut node: usize| {
+            let mut depth = 0;
+            while parents[node] != usize::MAX {
+                node = parents[node];
+                depth += 1;
+            }
+            depth
+        };
+        let mut lengths: Vec<(usize, u16)> = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| (depth(i), value))
+            .collect();
+
+        // Symbols are numbered from the longest codes; codes of one length are consecutive, and
+        // the longest start at 0.
+        lengths.sort_by_key(|&(len, value)| (Reverse(len), value));
+        let max = lengths[0].0;
+        let min = lengths[lengths.len() - 1].0;
+        let mut lowest = vec![0u16; max + 1];
+        let mut base = vec![0u64; max + 1];
+        for len in (min..max).rev() {
+            let longer = lengths.iter().filter(|&&(l, _)| l == len + 1).count();
+            lowest[len] = lowest[len + 1] + longer as u16;
+            base[len] = (base[len + 1] + longer as u64) / 2;
+        }
+        let code = |sym: usize| {
+            let len = lengths[sym].0;
+            (base[len] + (sym - lowest[len] as usize) as u64, len)
+        };
+
+        let mut header = vec![subtable.flags, BLOCK_BITS, SPAN_BITS, 0];
+        let mut blocks: Vec<Vec<(u64, usize)>> = vec![Vec::new()];
+        let mut bits = 0;
+        for value in values {
+            let sym = lengths.iter().position(|&(_, v)| v == *value).unwrap();
+            let (code, len) = code(sym);
+            if bits + len > 8 << BLOCK_BITS {
+                blocks.push(Vec::new());
+                bits = 0;
+            }
+            blocks.last_mut().unwrap().push((code, len));
+            bits += len;
+        }
+
+        header.extend((blocks.len() as u32).to_le_bytes());
+        header.extend([max as u8, min as u8]);
+        for lowest in &lowest[min..=max] {
+            header.extend(lowest.to_le_bytes());
+        }
+        header.extend((lengths.len() as u16).to_le_bytes());
+        for &(_, value) in &lengths {
+            header.extend([value as u8, (value >> 8) as u8 | 0xf0, 0xff]);
+        }
+        pad(&mut header, 2);
+
+        let mut data = Vec::new();
+        let mut lengths_out = Vec::new();
+        let mut starts = Vec::new();
+        let mut start = 0;
+        for block in &blocks {
+            let mut bytes = vec![0u8; 1 << BLOCK_BITS];
+            let mut bit = 0;
+            for &(code, len) in block {
+                for i in (0..len).rev() {
+                    if code >> i & 1 == 1 {
+                        bytes[bit / 8] |= 0x80 >> (bit % 8);
+                    }
+                    bit += 1;
+                }
+            }
+            data.extend(bytes);
+            lengths_out.extend((block.len() as u16 - 1).to_le_bytes());
+            starts.push(start);
+            start += block.len();
+        }
+
+        let span = 1 << SPAN_BITS;
+        let mut sparse = Vec::new();
+        for k in 0..values.len().div_ceil(span) {
+            let middle = k * span + span / 2;
+            let block = starts.partition_point(|&s| s <= middle.min(values.len() - 1)) - 1;
+            sparse.extend((block as u32).to_le_bytes());
+            sparse.extend(((middle - starts[block]) as u16).to_le_bytes());
+        }
+
+        (header, sparse, lengths_out, data)
+    }
+
+    /// A position with the given pieces, as (side, piece, square).
+    pub fn position(pieces: &[(usize, usize, usize)], side_to_move: usize) -> Board {
+        let mut board = Board::new();
+        for &(side, piece, square) in pieces {
+            board.put_piece(side, piece, square);
+        }
+        board.state.active_side = side_to_move as u8;
+        board.init();
+        board
+    }
+
+    #[test]
+    fn test_material() {
+        let krpvkr = Material::parse("KRPvKR").unwrap();
+        assert_eq!(krpvkr.piece_count, 5);
+        assert!(krpvkr.has_pawns && krpvkr.has_unique_pieces && !krpvkr.symmetric);
+        assert_eq!(krpvkr.pawn_count, [1, 0]);
+
+        let kpvkp = Material::parse("KPvKP").unwrap();
+        assert!(kpvkp.symmetric && kpvkp.has_unique_pieces);
+        assert!(!Material::parse("KNNvK").unwrap().has_unique_pieces);
+        assert_eq!(Material::parse("KvKPP").unwrap().pawn_count, [2, 0]);
+
+        for invalid in ["KQK", "QvK", "KQvKK", "KXvK", "KQQQvKQQQ"] {
+            assert_eq!(Material::parse(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_decode_dtz_table() {
+        // A DTZ table for White to move with made-up values, mapped through the lists.
+        let material = Material::parse("KRvK").unwrap();
+        let pieces = [6, 4, 14];
+        let layout = Subtable {
+            flags: 0,
+            values: vec![0],
+        };
+        let layout = read_table(TableKind::Dtz, &material, &pieces, &[layout], &[]);
+        let values: Vec<u16> = (0..size(&layout, 0)).map(|i| (i % 97 % 5) as u16).collect();
+        let map = [
+            vec![10, 20, 30, 40, 50],
+            vec![1, 2, 3, 4, 5],
+            vec![60, 61, 62, 63, 64],
+            vec![7, 8, 9, 10, 11],
+        ];
+        let subtable = Subtable {
+            flags: super::Flags::MAPPED | super::Flags::LOSS_PLIES,
+            values: values.clone(),
+        };
+        let table = read_table(TableKind::Dtz, &material, &pieces, &[subtable], &map);
+
+        let mut probed = 0;
+        for (king, rook, other) in [
+            (0, 7, 63),
+            (4, 36, 20),
+            (61, 2, 45),
+            (27, 9, 57),
+            (50, 40, 16),
+        ] {
+            let white = [
+                (Sides::WHITE, Pieces::KING, king),
+                (Sides::WHITE, Pieces::ROOK, rook),
+            ];
+            let board = position(
+                &[white[0], white[1], (Sides::BLACK, Pieces::KING, other)],
+                0,
+            );
+            let (_, idx) = index(&table, &board, false).unwrap();
+            let value = usize::from(values[idx as usize]);
+
+            // Wins are stored in moves, losses in plies.
+            let win = table.probe(&board, false, Wdl::Win);
+            assert_eq!(
+                win,
+                Some(TableProbe::Value(i32::from(map[0][value]) * 2 + 1))
+            );
+            let loss = table.probe(&board, false, Wdl::Loss);
+            assert_eq!(loss, Some(TableProbe::Value(i32::from(map[1][value]) + 1)));
+            let cursed = table.probe(&board, false, Wdl::CursedWin);
+            assert_eq!(
+                cursed,
+                Some(TableProbe::Value(i32::from(map[2][value]) * 2 + 1))
+            );
+            probed += 1;
+
+            // The table only has White to move.
+            let mut black = board.clone();
+            black.state.active_side = Sides::BLACK as u8;
+            assert_eq!(
+                table.probe(&black, false, Wdl::Win),
+                Some(TableProbe::ChangeStm)
+            );
+        }
+        assert_eq!(probed, 5);
+
+        // Positions that mirror each other have the same index.
+        let a = position(&[(0, 0, 1), (0, 2, 20), (1, 0, 40)], 0);
+        let b = position(&[(0, 0, 6), (0, 2, 19), (1, 0, 47)], 0);
+        let c = position(&[(0, 0, 57), (0, 2, 44), (1, 0, 16)], 0);
+        let d = position(&[(0, 0, 8), (0, 2, 34), (1, 0, 5)], 0);
+        let idx = index(&table, &a, false);
+        assert!(idx.is_some());
+        assert_eq!(index(&table, &b, false), idx);
+        assert_eq!(index(&table, &c, false), idx);
+        assert_eq!(index(&table, &d, false), idx);
+    }
+}